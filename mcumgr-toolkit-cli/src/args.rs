@@ -1,8 +1,64 @@
-use clap::{Args, Parser};
+use clap::{ArgGroup, Args, Parser};
+use mcumgr_toolkit::{
+    client::ConnectionUrl,
+    transport::{SmpVersion, serial::SERIAL_TRANSPORT_MIN_MTU},
+};
 
 use crate::groups::Group;
 
-#[derive(Debug, Args)]
+/// The maximum line length of serial based backends
+#[derive(Debug, Clone, Copy)]
+pub enum SerialMtu {
+    /// Learn the MTU from the device
+    Auto,
+    /// Use a fixed MTU
+    Fixed(usize),
+}
+
+fn parse_serial_mtu(s: &str) -> Result<SerialMtu, String> {
+    if s == "auto" {
+        return Ok(SerialMtu::Auto);
+    }
+
+    let mtu = s.parse::<usize>().map_err(|e| e.to_string())?;
+    if mtu < SERIAL_TRANSPORT_MIN_MTU {
+        return Err(format!("must be at least {SERIAL_TRANSPORT_MIN_MTU}"));
+    }
+
+    Ok(SerialMtu::Fixed(mtu))
+}
+
+/// The number of requests data transfers keep in flight
+#[derive(Debug, Clone, Copy)]
+pub enum WindowSize {
+    /// Learn the buffer count from the device
+    Auto,
+    /// Use a fixed window size
+    Fixed(usize),
+}
+
+fn parse_window_size(s: &str) -> Result<WindowSize, String> {
+    if s == "auto" {
+        return Ok(WindowSize::Auto);
+    }
+
+    let window_size = s.parse::<usize>().map_err(|e| e.to_string())?;
+    if window_size < 1 {
+        return Err("must be at least 1".to_string());
+    }
+
+    Ok(WindowSize::Fixed(window_size))
+}
+
+fn parse_smp_version(s: &str) -> Result<SmpVersion, String> {
+    match s {
+        "1" => Ok(SmpVersion::V1),
+        "2" => Ok(SmpVersion::V2),
+        _ => Err("must be 1 or 2".to_string()),
+    }
+}
+
+#[derive(Debug, Clone, Args)]
 pub struct CommonArgs {
     /// Hide progress bar for data transfer commands
     #[arg(short, long)]
@@ -21,9 +77,12 @@ pub struct CommonArgs {
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 #[command(disable_help_subcommand = true)]
+#[command(group(ArgGroup::new("backend").args(["serial", "usb_serial", "udp", "tcp", "unix", "connection", "replay"])))]
 pub struct App {
     /// Use the given serial port as backend
     ///
+    /// Use `rfc2217://host:port` for a port behind an RFC 2217 server,
+    /// with optional parameters like `?data_bits=7&parity=even&dtr=off&rts=on`.
     /// If no argument provided, list all available ports and exit.
     #[arg(short, long, verbatim_doc_comment, num_args = 0..=1, default_missing_value = "")]
     pub serial: Option<String>,
@@ -35,14 +94,98 @@ pub struct App {
     #[arg(short, long, verbatim_doc_comment, num_args = 0..=1, default_missing_value = "")]
     pub usb_serial: Option<String>,
 
+    /// Use the given UDP address as backend
+    ///
+    /// Must be of the form `host:port`, for example `192.168.1.42:1337`
+    /// or `[fe80::1%eth0]:1337`. Zephyr listens on port 1337 by default.
+    #[arg(long, verbatim_doc_comment, value_name = "HOST:PORT")]
+    pub udp: Option<String>,
+
+    /// Use the given TCP serial bridge as backend
+    ///
+    /// For example ser2net in raw mode.
+    /// Must be of the form `host:port`, for example `192.168.1.42:4000`.
+    #[arg(long, verbatim_doc_comment, value_name = "HOST:PORT")]
+    pub tcp: Option<String>,
+
+    /// Use the given Unix socket as serial backend
+    ///
+    /// For example the UART of Zephyr's `native_sim`.
+    /// Only available on Unix platforms.
+    #[arg(long, verbatim_doc_comment, value_name = "PATH")]
+    pub unix: Option<std::path::PathBuf>,
+
+    /// Use the given connection string as backend
+    ///
+    /// For example `serial:///dev/ttyUSB0?baud=115200`, `usb://2fe3:0100:1`,
+    /// `rfc2217://host:port`, `udp://[fe80::1]:1337`, `tcp://host:port`, `unix:///path`
+    /// or `replay://session.jsonl`.
+    /// Read from `MCUMGR_CONNECTION` if no backend is selected.
+    #[arg(short, long, verbatim_doc_comment, value_name = "URL")]
+    pub connection: Option<ConnectionUrl>,
+
+    /// Play back a recorded session as backend
+    ///
+    /// The recording must have been created with `--record`.
+    #[arg(long, verbatim_doc_comment, value_name = "FILE")]
+    pub replay: Option<std::path::PathBuf>,
+
+    /// Record all frames of the session to the given file
+    ///
+    /// The file can be played back later with `--replay`,
+    /// for example to reproduce a bug without the device.
+    #[arg(long, verbatim_doc_comment, value_name = "FILE")]
+    pub record: Option<std::path::PathBuf>,
+
     /// Serial port baud rate
     #[arg(short, long, default_value_t = 115200)]
     pub baud: u32,
 
     /// Communication timeout (in ms)
-    #[arg(short, long, default_value_t = 10000)]
+    ///
+    /// Commands that take long on the device, like erasing flash,
+    /// wait at least 60 seconds.
+    #[arg(short, long, default_value_t = 10000, verbatim_doc_comment)]
     pub timeout: u64,
 
+    /// How often to retransmit requests that timed out or got a corrupted response
+    #[arg(long, default_value_t = 0, value_name = "COUNT")]
+    pub retries: u32,
+
+    /// Maximum line length of serial based backends
+    ///
+    /// Must not exceed `MCUMGR_SERIAL_MAX_FRAME` of the device.
+    /// Use `auto` to learn it from the device. Defaults to 127.
+    #[arg(long, verbatim_doc_comment, value_name = "BYTES|auto", value_parser = parse_serial_mtu)]
+    pub serial_mtu: Option<SerialMtu>,
+
+    /// Number of requests that data transfers keep in flight
+    ///
+    /// Must not exceed `MCUMGR_TRANSPORT_NETBUF_COUNT` of the device.
+    /// Use `auto` to learn it from the device. Defaults to 1.
+    #[arg(long, verbatim_doc_comment, value_name = "COUNT|auto", value_parser = parse_window_size)]
+    pub window: Option<WindowSize>,
+
+    /// Probe the SMP frame size if the device does not report it
+    ///
+    /// Sends echo requests of increasing size, which takes a few seconds.
+    /// The result is cached per device and firmware.
+    #[arg(long, verbatim_doc_comment)]
+    pub probe_frame_size: bool,
+
+    /// SMP protocol version of requests
+    ///
+    /// Negotiated with the device if omitted, falling back from 2 to 1.
+    #[arg(long, verbatim_doc_comment, value_name = "1|2", value_parser = parse_smp_version)]
+    pub smp_version: Option<SmpVersion>,
+
+    /// Print connection statistics after the command
+    ///
+    /// Counts frames, bytes, CRC errors, timeouts and retries,
+    /// and shows the round-trip times of all executed commands.
+    #[arg(long, verbatim_doc_comment)]
+    pub stats: bool,
+
     /// Settings that customize runtime behaviour
     #[command(flatten)]
     pub common: CommonArgs,
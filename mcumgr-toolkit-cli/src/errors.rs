@@ -3,7 +3,7 @@ use thiserror::Error;
 
 use mcumgr_toolkit::{
     Errno,
-    client::{FirmwareUpdateError, MCUmgrClientError, UsbSerialError},
+    client::{ClientBuilderError, ConnectionUrlError, FirmwareUpdateError, MCUmgrClientError},
     mcuboot::ImageParseError,
 };
 
@@ -13,11 +13,23 @@ pub enum CliError {
     #[error("Failed to list serial ports")]
     #[diagnostic(code(mcumgrctl::list_serial_ports_failed))]
     ListSerialPortsFailed(#[source] serialport::Error),
-    #[error("Failed to open serial port")]
-    #[diagnostic(code(mcumgrctl::open_serial_failed))]
-    OpenSerialFailed(#[source] serialport::Error),
+    #[error("Failed to connect to the device")]
+    #[diagnostic(code(mcumgrctl::connect_failed))]
+    ConnectFailed(#[from] ClientBuilderError),
+    #[error("Invalid connection string in MCUMGR_CONNECTION")]
+    #[diagnostic(code(mcumgrctl::connection_env))]
+    InvalidConnectionEnv(#[source] ConnectionUrlError),
+    #[error("Invalid serial port name")]
+    #[diagnostic(code(mcumgrctl::serial_name))]
+    InvalidSerialName(#[source] ConnectionUrlError),
+    #[error("Failed to create recording")]
+    #[diagnostic(code(mcumgrctl::create_recording_failed))]
+    CreateRecordingFailed(#[source] std::io::Error),
     #[error("No backend selected")]
-    #[diagnostic(code(mcumgrctl::no_backend))]
+    #[diagnostic(
+        code(mcumgrctl::no_backend),
+        help("Select a backend, for example with `--serial`, or set MCUMGR_CONNECTION")
+    )]
     NoBackendSelected,
     // #[error("Setting the timeout failed")]
     // #[diagnostic(code(mcumgrctl::set_timeout_failed))]
@@ -43,13 +55,22 @@ pub enum CliError {
     #[error("Failed to parse datetime string")]
     #[diagnostic(code(mcumgrctl::chrono_parse))]
     ChronoParseFailed(#[from] chrono::ParseError),
-    #[error("Failed to open USB serial port")]
-    #[diagnostic(code(mcumgrctl::usb_serial))]
-    UsbSerialOpenFailed(#[from] UsbSerialError),
     #[error("Failed to parse MCUboot image")]
     #[diagnostic(code(mcumgrctl::image_parse))]
     ImageParseFailed(#[from] ImageParseError),
+    #[error("The monitor can only be started from the command line")]
+    #[diagnostic(code(mcumgrctl::nested_monitor))]
+    NestedMonitor,
     #[error("Firmware update failed")]
     #[diagnostic(code(mcumgrctl::firmware_update))]
     FirmwareUpdateFailed(#[from] FirmwareUpdateError),
+    #[error("Invalid {format} value: '{value}'")]
+    #[diagnostic(code(mcumgrctl::invalid_setting_value))]
+    InvalidSettingValue { format: &'static str, value: String },
+    #[error("Setting value of {length} bytes is not a valid {format}")]
+    #[diagnostic(
+        code(mcumgrctl::setting_value_format),
+        help("Use `--format hex` to show the raw value")
+    )]
+    SettingValueFormatMismatch { format: &'static str, length: usize },
 }
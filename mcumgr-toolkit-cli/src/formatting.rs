@@ -1,7 +1,42 @@
 use console::style;
+use mcumgr_toolkit::commands::ExtraFields;
 
 use crate::errors::CliError;
 
+/// Converts a CBOR value to JSON, with byte strings as hex
+fn cbor_to_json(value: &ciborium::Value) -> serde_json::Value {
+    use ciborium::Value;
+
+    match value {
+        Value::Integer(val) => {
+            let val = i128::from(*val);
+            u64::try_from(val)
+                .map(Into::into)
+                .or_else(|_| i64::try_from(val).map(Into::into))
+                .unwrap_or_else(|_| val.to_string().into())
+        }
+        Value::Bytes(data) => hex::encode(data).into(),
+        Value::Float(val) => serde_json::Number::from_f64(*val)
+            .map(Into::into)
+            .unwrap_or_default(),
+        Value::Text(s) => s.as_str().into(),
+        Value::Bool(val) => (*val).into(),
+        Value::Tag(_, val) => cbor_to_json(val),
+        Value::Array(values) => values.iter().map(cbor_to_json).collect(),
+        Value::Map(entries) => entries
+            .iter()
+            .map(|(key, val)| {
+                let key = match key {
+                    Value::Text(key) => key.clone(),
+                    key => cbor_to_json(key).to_string(),
+                };
+                (key, cbor_to_json(val))
+            })
+            .collect(),
+        _ => serde_json::Value::Null,
+    }
+}
+
 enum Entry {
     Value(serde_json::Value),
     Sublist(StructuredPrint),
@@ -33,6 +68,13 @@ impl StructuredPrint {
         }
     }
 
+    /// Adds response fields that the library does not know about
+    pub fn extra_fields(&mut self, extra: &ExtraFields) {
+        for (key, value) in extra {
+            self.key_value(key, cbor_to_json(value));
+        }
+    }
+
     pub fn unaligned(&mut self) {
         self.no_align = true;
     }
@@ -0,0 +1,109 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use mcumgr_toolkit::{
+    MCUmgrClient,
+    client::{ConnectionUrl, MCUmgrClientError},
+};
+
+/// The largest frame size to probe.
+const MAX_PROBED_FRAME_SIZE: usize = 4096;
+
+/// The timeout of a single probe.
+///
+/// Every probe that is too large waits for it, so it is shorter than the usual timeout.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The file that stores the probed frame sizes, if a cache directory is known.
+fn cache_file() -> Option<PathBuf> {
+    let cache_dir = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    };
+
+    cache_dir.map(|dir| dir.join("mcumgrctl").join("frame_sizes.json"))
+}
+
+/// Identifies a device by its connection and the firmware it runs,
+/// as a firmware update may change the frame size.
+fn device_identity(client: &MCUmgrClient, url: &ConnectionUrl) -> Option<String> {
+    let images = client
+        .image_get_state()
+        .inspect_err(|e| log::debug!("Unable to identify device: {e}"))
+        .ok()?;
+
+    let images = images
+        .iter()
+        .filter_map(|image| {
+            let hash = hex::encode(image.hash?);
+            let active = if image.active { "*" } else { "" };
+            Some(format!("{}/{}:{hash}{active}", image.image, image.slot))
+        })
+        .collect::<Vec<_>>();
+
+    if images.is_empty() {
+        return None;
+    }
+
+    Some(format!("{url} {}", images.join(",")))
+}
+
+fn load_cache(file: &Path) -> serde_json::Map<String, serde_json::Value> {
+    std::fs::read(file)
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+fn store_cache(
+    file: &Path,
+    cache: &serde_json::Map<String, serde_json::Value>,
+) -> std::io::Result<()> {
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(file, serde_json::to_vec_pretty(cache)?)
+}
+
+/// Configures the frame size by probing the device.
+///
+/// Results are cached per device, so that later runs skip the probing.
+/// Devices that cannot be identified get probed every time.
+pub fn probe_frame_size(
+    client: &MCUmgrClient,
+    url: Option<&ConnectionUrl>,
+    timeout: Duration,
+) -> Result<usize, MCUmgrClientError> {
+    let file = cache_file();
+    let identity = url.and_then(|url| device_identity(client, url));
+
+    let mut cache = file.as_deref().map(load_cache).unwrap_or_default();
+    if let Some(identity) = &identity {
+        if let Some(frame_size) = cache.get(identity).and_then(|size| size.as_u64()) {
+            log::debug!("Using cached frame size {frame_size}.");
+            let frame_size = frame_size as usize;
+            client.set_frame_size(frame_size);
+            return Ok(frame_size);
+        }
+    }
+
+    log::info!("Probing SMP frame size ...");
+    let frame_size = client.with_timeout(timeout.min(PROBE_TIMEOUT), || {
+        client.probe_frame_size(MAX_PROBED_FRAME_SIZE)
+    })?;
+    log::info!("Using SMP frame size {frame_size}.");
+
+    if let (Some(file), Some(identity)) = (file, identity) {
+        cache.insert(identity, frame_size.into());
+        if let Err(e) = store_cache(&file, &cache) {
+            log::debug!("Failed to cache frame size: {e}");
+        }
+    }
+
+    Ok(frame_size)
+}
@@ -0,0 +1,127 @@
+use console::style;
+use mcumgr_toolkit::transport::codec::{SerialItem, SmpFrame, decode_serial_capture};
+
+use crate::{args::CommonArgs, errors::CliError, file_read_write::read_input_file};
+
+#[derive(Debug, clap::Args)]
+pub struct DecodeCommand {
+    /// The raw bytes of a serial connection, like a logic analyzer or terminal capture
+    ///
+    /// Use '-' to read from stdin.
+    #[arg(verbatim_doc_comment)]
+    pub capture: String,
+}
+
+fn direction(frame: &SmpFrame) -> &'static str {
+    if frame.header.is_response() {
+        "response"
+    } else {
+        "request"
+    }
+}
+
+fn operation(frame: &SmpFrame) -> &'static str {
+    if frame.header.is_write_operation() {
+        "write"
+    } else {
+        "read"
+    }
+}
+
+fn command_name(frame: &SmpFrame) -> String {
+    frame
+        .command_name()
+        .map(ToString::to_string)
+        .unwrap_or_else(|| format!("COMMAND({})", frame.header.command_id))
+}
+
+fn frame_to_json(frame: &SmpFrame) -> serde_json::Value {
+    let body = frame.body();
+
+    serde_json::json!({
+        "type": "frame",
+        "direction": direction(frame),
+        "operation": operation(frame),
+        "version": frame.header.version().to_string(),
+        "sequence_num": frame.header.sequence_num,
+        "group_id": frame.header.group_id,
+        "group": frame.group_name(),
+        "command_id": frame.header.command_id,
+        "command": frame.command_name(),
+        "body": body.as_ref().ok(),
+        "body_error": body.as_ref().err().map(ToString::to_string),
+        "device_error": frame.device_error().map(|e| e.to_string()),
+        "payload": hex::encode(&frame.payload),
+    })
+}
+
+fn print_frame(frame: &SmpFrame, verbose: bool) -> Result<(), CliError> {
+    println!(
+        "{} {} {} {} (seq {}, {}, {} bytes)",
+        style(format!("{:<8}", direction(frame))).bold(),
+        format_args!("{:<5}", operation(frame)),
+        frame.group_name(),
+        command_name(frame),
+        frame.header.sequence_num,
+        frame.header.version(),
+        frame.payload.len(),
+    );
+
+    match frame.body() {
+        Ok(body) => println!(
+            "    {}",
+            serde_json::to_string(&body).map_err(CliError::JsonEncodeError)?
+        ),
+        Err(e) => println!("    {}", style(format!("Invalid body: {e}")).red()),
+    }
+
+    if let Some(err) = frame.device_error() {
+        println!("    {}", style(format!("Device error: {err}")).red());
+    }
+
+    if verbose {
+        println!("    {}", style(hex::encode(&frame.payload)).dim());
+    }
+
+    Ok(())
+}
+
+pub fn run(args: CommonArgs, command: DecodeCommand) -> Result<(), CliError> {
+    let (capture, _) = read_input_file(&command.capture)?;
+    let items = decode_serial_capture(&capture);
+
+    if args.json {
+        let items = items
+            .iter()
+            .map(|item| match item {
+                SerialItem::Frame(frame) => frame_to_json(frame),
+                SerialItem::Console(line) => serde_json::json!({
+                    "type": "console",
+                    "line": String::from_utf8_lossy(line),
+                }),
+                SerialItem::Invalid(e) => serde_json::json!({
+                    "type": "invalid",
+                    "error": e.to_string(),
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&items).map_err(CliError::JsonEncodeError)?
+        );
+        return Ok(());
+    }
+
+    for item in items {
+        match item {
+            SerialItem::Frame(frame) => print_frame(&frame, args.verbose)?,
+            SerialItem::Console(line) => {
+                println!("{}", style(String::from_utf8_lossy(&line)).dim())
+            }
+            SerialItem::Invalid(e) => println!("{}", style(format!("Invalid frame: {e}")).red()),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use indicatif::MultiProgress;
+use mcumgr_toolkit::MCUmgrGroup;
+
+use crate::{args::CommonArgs, client::Client, errors::CliError, formatting::structured_print};
+
+#[derive(Debug, clap::Subcommand)]
+pub enum EnumCommand {
+    /// Show the number of management groups the device supports
+    Count,
+    /// List the IDs of the management groups the device supports
+    List,
+    /// Show the names and handler counts of the supported management groups
+    Details {
+        /// The IDs of the groups to show, all groups if empty
+        groups: Vec<u16>,
+    },
+}
+
+pub fn run(
+    client: &Client,
+    _multiprogress: &MultiProgress,
+    args: CommonArgs,
+    command: EnumCommand,
+) -> Result<(), CliError> {
+    let client = client.get()?;
+
+    match command {
+        EnumCommand::Count => {
+            let count = client.enum_group_count()?;
+            println!("{count}");
+        }
+        EnumCommand::List => {
+            let groups = client.enum_group_list()?;
+
+            if args.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&groups).map_err(CliError::JsonEncodeError)?
+                );
+            } else {
+                for group in groups {
+                    println!("{group:>3}  {}", MCUmgrGroup::group_id_to_string(group));
+                }
+            }
+        }
+        EnumCommand::Details { groups } => {
+            let details = client.enum_group_details(&groups)?;
+
+            structured_print(None, args.json, |s| {
+                for entry in details {
+                    s.sublist(entry.group, |s| {
+                        s.key_value_maybe("name", entry.name);
+                        s.key_value_maybe("handlers", entry.handlers);
+                        if args.json || args.verbose {
+                            s.extra_fields(&entry.extra);
+                        }
+                    });
+                }
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn run_capabilities(
+    client: &Client,
+    _multiprogress: &MultiProgress,
+    args: CommonArgs,
+) -> Result<(), CliError> {
+    let client = client.get()?;
+
+    let capabilities = client.capabilities()?;
+
+    structured_print(None, args.json, |s| {
+        s.key_value("probed", capabilities.probed);
+        s.sublist("groups", |s| {
+            for group in capabilities.groups {
+                s.sublist(group.group_id, |s| {
+                    s.key_value("id", MCUmgrGroup::group_id_to_string(group.group_id));
+                    s.key_value_maybe("name", group.name);
+                    s.key_value_maybe("handlers", group.handlers);
+                });
+            }
+        });
+    })?;
+
+    Ok(())
+}
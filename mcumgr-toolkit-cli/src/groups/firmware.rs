@@ -147,7 +147,7 @@ pub fn run(
             };
 
             if args.quiet {
-                client.firmware_update(firmware, checksum, params, None)
+                client.firmware_update(firmware, checksum, params, None, None)
             } else {
                 let mut progress_handler = FirmwareUpgradeProgressHandler::new(multiprogress);
                 client.firmware_update(
@@ -157,6 +157,7 @@ pub fn run(
                     Some(&mut move |msg, progress| {
                         progress_handler.update(&msg.to_string(), progress)
                     }),
+                    None,
                 )
             }?;
 
@@ -64,7 +64,7 @@ pub fn run(
         FsCommand::Download { remote, local } => {
             let mut data = vec![];
             with_progress_bar(multiprogress, !args.quiet, Some(&remote), |progress| {
-                client.fs_file_download(remote.as_str(), &mut data, progress)
+                client.fs_file_download(remote.as_str(), &mut data, progress, None)
             })?;
 
             let filename = remote.rsplit('/').next().filter(|s| !s.is_empty());
@@ -81,13 +81,16 @@ pub fn run(
             }
 
             with_progress_bar(multiprogress, !args.quiet, Some(&remote), |progress| {
-                client.fs_file_upload(remote.as_str(), &*data, data.len() as u64, progress)
+                client.fs_file_upload(remote.as_str(), &*data, data.len() as u64, progress, None)
             })?;
         }
         FsCommand::Status { name } => {
             let status = client.fs_file_status(&name)?;
             structured_print(Some(name), args.json, |s| {
                 s.key_value("length", status.len);
+                if args.json || args.verbose {
+                    s.extra_fields(&status.extra);
+                }
             })?;
         }
         FsCommand::Checksum {
@@ -104,6 +107,9 @@ pub fn run(
                     s.key_value("type", checksum.r#type);
                     s.key_value("data offset", checksum.off);
                     s.key_value("data length", checksum.len);
+                    if args.json || args.verbose {
+                        s.extra_fields(&checksum.extra);
+                    }
                 })?;
             } else {
                 println!("{}  {}", checksum.output.hex(), name);
@@ -122,6 +128,9 @@ pub fn run(
                         s.sublist(algo, |s| {
                             s.key_value("format", properties.format.to_string());
                             s.key_value("size", properties.size);
+                            if args.json || args.verbose {
+                                s.extra_fields(&properties.extra);
+                            }
                         });
                     }
                 })?;
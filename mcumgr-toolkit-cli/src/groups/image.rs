@@ -59,6 +59,9 @@ fn print_current_image_state(images: &[ImageState], args: CommonArgs) -> Result<
                     s.key_value("confirmed", image.confirmed);
                     s.key_value("active", image.active);
                     s.key_value("permanent", image.permanent);
+                    if args.json || args.verbose {
+                        s.extra_fields(&image.extra);
+                    }
                 });
             }
         })?;
@@ -101,7 +104,9 @@ pub fn run(
                 multiprogress,
                 !args.quiet,
                 source_filename.as_deref(),
-                |progress| client.image_upload(&data, image_id, checksum, upgrade_only, progress),
+                |progress| {
+                    client.image_upload(&data, image_id, checksum, upgrade_only, progress, None)
+                },
             )?;
         }
         ImageCommand::Erase { slot } => client.image_erase(slot)?,
@@ -121,9 +126,15 @@ pub fn run(
                                     s.unaligned();
                                     s.key_value("size", slot.size);
                                     s.key_value_maybe("upload_image_id", slot.upload_image_id);
+                                    if args.json || args.verbose {
+                                        s.extra_fields(&slot.extra);
+                                    }
                                 });
                             }
                             s.key_value_maybe("max_image_size", image.max_image_size);
+                            if args.json || args.verbose {
+                                s.extra_fields(&image.extra);
+                            }
                         });
                     }
                 })?;
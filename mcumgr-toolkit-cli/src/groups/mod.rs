@@ -2,12 +2,17 @@ use indicatif::MultiProgress;
 
 use crate::{args::CommonArgs, client::Client, errors::CliError};
 
+pub mod decode;
+mod enumeration;
 mod firmware;
 mod fs;
 mod image;
+pub mod monitor;
 mod os;
 mod raw;
+mod settings;
 mod shell;
+mod stat;
 mod zephyr;
 
 #[derive(Debug, clap::Subcommand)]
@@ -27,6 +32,16 @@ pub enum Group {
         #[command(subcommand)]
         command: firmware::FirmwareCommand,
     },
+    /// Statistics Management
+    Stat {
+        #[command(subcommand)]
+        command: stat::StatCommand,
+    },
+    /// Settings Management
+    Settings {
+        #[command(subcommand)]
+        command: settings::SettingsCommand,
+    },
     /// File Management
     Fs {
         #[command(subcommand)]
@@ -38,13 +53,35 @@ pub enum Group {
         #[arg(required = true, trailing_var_arg = true)]
         argv: Vec<String>,
     },
+    /// Group Enumeration Management
+    Enum {
+        #[command(subcommand)]
+        command: enumeration::EnumCommand,
+    },
     /// Zephyr Management
     Zephyr {
         #[command(subcommand)]
         command: zephyr::ZephyrCommand,
     },
+    /// Show the management groups the device supports
+    ///
+    /// Uses the enumeration group, or probes the known groups if the device lacks it.
+    #[command(verbatim_doc_comment)]
+    Capabilities,
     /// Execute a raw SMP command
     Raw(#[command(flatten)] raw::RawCommand),
+    /// Print the device's console output live
+    ///
+    /// For devices that share the UART between the shell and SMP.
+    /// Commands typed into stdin, like `os echo hello`, get executed in between.
+    #[command(verbatim_doc_comment)]
+    Monitor,
+    /// Decode a capture of serial SMP traffic
+    ///
+    /// Prints all SMP frames with their decoded payload, and the console output in between.
+    /// Does not need a connection to the device.
+    #[command(verbatim_doc_comment)]
+    Decode(decode::DecodeCommand),
 }
 
 pub fn run(
@@ -57,10 +94,16 @@ pub fn run(
         Group::Os { command } => os::run(client, multiprogress, args, command),
         Group::Image { command } => image::run(client, multiprogress, args, command),
         Group::Firmware { command } => firmware::run(client, multiprogress, args, command),
+        Group::Stat { command } => stat::run(client, multiprogress, args, command),
+        Group::Settings { command } => settings::run(client, multiprogress, args, command),
         Group::Fs { command } => fs::run(client, multiprogress, args, command),
         Group::Shell { argv } => shell::run(client, multiprogress, args, argv),
+        Group::Enum { command } => enumeration::run(client, multiprogress, args, command),
+        Group::Capabilities => enumeration::run_capabilities(client, multiprogress, args),
         Group::Zephyr { command } => zephyr::run(client, multiprogress, args, command),
         Group::Raw(raw_command) => raw::run(client, multiprogress, args, raw_command),
+        Group::Monitor => Err(CliError::NestedMonitor),
+        Group::Decode(command) => decode::run(args, command),
     }
 }
 
@@ -0,0 +1,89 @@
+use std::{
+    sync::mpsc::{self, TryRecvError},
+    time::Duration,
+};
+
+use clap::Parser;
+use indicatif::MultiProgress;
+use mcumgr_toolkit::client::MCUmgrClientError;
+
+use crate::{args::CommonArgs, client::Client, errors::CliError, groups::Group};
+
+/// How long to wait for console output before checking for new commands
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A command typed into the monitor
+#[derive(Parser, Debug)]
+#[command(no_binary_name = true, disable_help_subcommand = true)]
+struct MonitorCommand {
+    #[command(subcommand)]
+    group: Group,
+}
+
+pub fn run(
+    client: &Client,
+    multiprogress: &MultiProgress,
+    args: CommonArgs,
+    timeout: Duration,
+) -> Result<(), CliError> {
+    let mcumgr_client = client.get()?;
+
+    let console_output = multiprogress.clone();
+    mcumgr_client.set_console_handler(Some(Box::new(move |line| {
+        console_output.suspend(|| println!("{}", String::from_utf8_lossy(line)));
+    })))?;
+
+    let (command_sender, commands) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            if command_sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    log::info!(
+        "Monitoring console output. Type commands like `os echo hello`, or press Ctrl+D to exit."
+    );
+
+    mcumgr_client.set_timeout(POLL_INTERVAL)?;
+
+    loop {
+        match mcumgr_client.poll_console() {
+            Ok(()) => {}
+            // Gets reopened by the next poll, once the device is back
+            Err(MCUmgrClientError::DeviceDisconnected(_)) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => Err(e)?,
+        }
+
+        let line = match commands.try_recv() {
+            Ok(line) => line.map_err(CliError::InputReadFailed)?,
+            Err(TryRecvError::Empty) => continue,
+            Err(TryRecvError::Disconnected) => break,
+        };
+
+        let argv = line.split_whitespace().collect::<Vec<_>>();
+        if argv.is_empty() {
+            continue;
+        }
+
+        let command = match MonitorCommand::try_parse_from(argv) {
+            Ok(command) => command,
+            Err(e) => {
+                multiprogress.suspend(|| e.print()).ok();
+                continue;
+            }
+        };
+
+        mcumgr_client.set_timeout(timeout)?;
+        if let Err(e) = super::run(client, multiprogress, args.clone(), command.group) {
+            log::error!("{:?}", miette::Report::new(e));
+        }
+        mcumgr_client.set_timeout(POLL_INTERVAL)?;
+    }
+
+    mcumgr_client.set_console_handler(None)?;
+    mcumgr_client.set_timeout(timeout)?;
+
+    Ok(())
+}
@@ -1,4 +1,7 @@
-use std::collections::HashSet;
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
 
 use indicatif::MultiProgress;
 use mcumgr_toolkit::{
@@ -10,6 +13,10 @@ use mcumgr_toolkit::{
 
 use crate::{args::CommonArgs, client::Client, errors::CliError, formatting::structured_print};
 
+/// How long Zephyr waits by default before executing a reset,
+/// see `CONFIG_MCUMGR_GRP_OS_RESET_MS`
+const RESET_DELAY: Duration = Duration::from_millis(250);
+
 #[derive(Debug, clap::Subcommand)]
 pub enum OsCommand {
     /// Executes an echo command on the device
@@ -19,6 +26,8 @@ pub enum OsCommand {
     },
     /// Queries live task statistics
     TaskStatistics,
+    /// Queries memory pool statistics
+    Mempool,
     /// Set the device's RTC datetime
     SetDatetime {
         /// The datetime value, as RFC3339; host time if omitted
@@ -43,6 +52,9 @@ pub enum OsCommand {
         #[arg(verbatim_doc_comment)]
         #[arg(long)]
         bootmode: Option<u8>,
+        /// Wait up to the given number of seconds until the device is reachable again
+        #[arg(long, value_name = "SECONDS")]
+        wait: Option<u64>,
     },
     /// Fetch parameters from the MCUmgr library
     McumgrParameters,
@@ -169,8 +181,7 @@ pub fn run(
                             if let (Some(stkuse), Some(stksiz)) = (stats.stkuse, stats.stksiz) {
                                 s.key_value(
                                     "Stack Usage",
-                                    if stksiz != 0 {
-                                        let pct = stkuse * 100 / stksiz;
+                                    if let Some(pct) = (stkuse * 100).checked_div(stksiz) {
                                         format!("{stkuse} / {stksiz} bytes ({pct} %)")
                                     } else {
                                         format!("{stkuse} / {stksiz} bytes")
@@ -183,6 +194,43 @@ pub fn run(
                             if let Some(runtime) = stats.runtime {
                                 s.key_value("Runtime", format!("{} ticks", runtime));
                             }
+                            if args.json || args.verbose {
+                                s.extra_fields(&stats.extra);
+                            }
+                        });
+                    }
+                })?;
+            }
+        }
+        OsCommand::Mempool => {
+            let pools = client
+                .os_memory_pool_statistics()?
+                .into_iter()
+                .collect::<BTreeMap<_, _>>();
+
+            if args.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&pools).map_err(CliError::JsonEncodeError)?
+                );
+            } else {
+                structured_print(None, args.json, |s| {
+                    for (name, stats) in pools {
+                        s.sublist(name, |s| {
+                            s.key_value("Block Size", format!("{} bytes", stats.blksiz));
+                            s.key_value("Blocks", stats.nblks);
+                            s.key_value(
+                                "Free",
+                                if let Some(pct) = (stats.nfree * 100).checked_div(stats.nblks) {
+                                    format!("{} ({pct} %)", stats.nfree)
+                                } else {
+                                    format!("{}", stats.nfree)
+                                },
+                            );
+                            s.key_value("Minimum Free", stats.min);
+                            if args.json || args.verbose {
+                                s.extra_fields(&stats.extra);
+                            }
                         });
                     }
                 })?;
@@ -225,8 +273,18 @@ pub fn run(
                 println!("{:?}", datetime);
             }
         }
-        OsCommand::SystemReset { force, bootmode } => {
+        OsCommand::SystemReset {
+            force,
+            bootmode,
+            wait,
+        } => {
             client.os_system_reset(force, bootmode)?;
+
+            if let Some(wait) = wait {
+                std::thread::sleep(2 * RESET_DELAY);
+                client.wait_for_reconnect(Duration::from_secs(wait))?;
+                log::info!("Device is back online.");
+            }
         }
         OsCommand::McumgrParameters => {
             let params = client.os_mcumgr_parameters()?;
@@ -234,6 +292,9 @@ pub fn run(
             structured_print(Some("MCUmgr Parameters".to_string()), args.json, |s| {
                 s.key_value("buf_size", params.buf_size);
                 s.key_value("buf_count", params.buf_count);
+                if args.json || args.verbose {
+                    s.extra_fields(&params.extra);
+                }
             })?;
         }
         OsCommand::ApplicationInfo(flags) => {
@@ -0,0 +1,271 @@
+use clap::ValueEnum;
+use indicatif::MultiProgress;
+
+use crate::{args::CommonArgs, client::Client, errors::CliError};
+
+/// How the value of a setting is written on the command line
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ValueFormat {
+    /// Raw bytes, as hex string
+    Hex,
+    /// UTF-8 string
+    Str,
+    /// Unsigned 8-bit integer
+    U8,
+    /// Unsigned 16-bit integer
+    U16,
+    /// Unsigned 32-bit integer
+    U32,
+    /// Unsigned 64-bit integer
+    U64,
+    /// Signed 8-bit integer
+    I8,
+    /// Signed 16-bit integer
+    I16,
+    /// Signed 32-bit integer
+    I32,
+    /// Signed 64-bit integer
+    I64,
+}
+
+impl ValueFormat {
+    fn name(self) -> &'static str {
+        match self {
+            ValueFormat::Hex => "hex",
+            ValueFormat::Str => "str",
+            ValueFormat::U8 => "u8",
+            ValueFormat::U16 => "u16",
+            ValueFormat::U32 => "u32",
+            ValueFormat::U64 => "u64",
+            ValueFormat::I8 => "i8",
+            ValueFormat::I16 => "i16",
+            ValueFormat::I32 => "i32",
+            ValueFormat::I64 => "i64",
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ValueArgs {
+    /// The format of the value
+    #[arg(short, long, value_enum, default_value_t = ValueFormat::Hex)]
+    format: ValueFormat,
+    /// Store integers as big endian instead of little endian
+    #[arg(long)]
+    big_endian: bool,
+}
+
+/// Splits a `0x` prefixed hexadecimal integer into its sign and digits
+fn split_hex(value: &str) -> Option<(&str, &str)> {
+    let (sign, unsigned) = match value.strip_prefix(['-', '+']) {
+        Some(rest) => (&value[..1], rest),
+        None => ("", value),
+    };
+    let digits = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))?;
+
+    // The sign must come before the prefix
+    if digits.starts_with(['-', '+']) {
+        None
+    } else {
+        Some((sign, digits))
+    }
+}
+
+/// Encodes a decimal or `0x` prefixed hexadecimal integer
+macro_rules! encode_int {
+    ($type:ty, $value:expr, $big_endian:expr) => {{
+        let value = $value;
+        let parsed = match split_hex(value) {
+            Some((sign, digits)) => <$type>::from_str_radix(&format!("{sign}{digits}"), 16),
+            None => value.parse::<$type>(),
+        };
+        parsed.ok().map(|val| {
+            if $big_endian {
+                val.to_be_bytes().to_vec()
+            } else {
+                val.to_le_bytes().to_vec()
+            }
+        })
+    }};
+}
+
+/// Decodes an integer, if the data has the matching size
+macro_rules! decode_int {
+    ($type:ty, $data:expr, $big_endian:expr) => {{
+        <[u8; size_of::<$type>()]>::try_from($data)
+            .ok()
+            .map(|bytes| {
+                if $big_endian {
+                    <$type>::from_be_bytes(bytes)
+                } else {
+                    <$type>::from_le_bytes(bytes)
+                }
+            })
+            .map(serde_json::Value::from)
+    }};
+}
+
+impl ValueArgs {
+    fn encode(&self, value: &str) -> Result<Vec<u8>, CliError> {
+        let big_endian = self.big_endian;
+        let data = match self.format {
+            ValueFormat::Hex => hex::decode(value).ok(),
+            ValueFormat::Str => Some(value.as_bytes().to_vec()),
+            ValueFormat::U8 => encode_int!(u8, value, big_endian),
+            ValueFormat::U16 => encode_int!(u16, value, big_endian),
+            ValueFormat::U32 => encode_int!(u32, value, big_endian),
+            ValueFormat::U64 => encode_int!(u64, value, big_endian),
+            ValueFormat::I8 => encode_int!(i8, value, big_endian),
+            ValueFormat::I16 => encode_int!(i16, value, big_endian),
+            ValueFormat::I32 => encode_int!(i32, value, big_endian),
+            ValueFormat::I64 => encode_int!(i64, value, big_endian),
+        };
+
+        data.ok_or_else(|| CliError::InvalidSettingValue {
+            format: self.format.name(),
+            value: value.to_string(),
+        })
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<serde_json::Value, CliError> {
+        let big_endian = self.big_endian;
+        let value = match self.format {
+            ValueFormat::Hex => Some(hex::encode(data).into()),
+            ValueFormat::Str => std::str::from_utf8(data).ok().map(Into::into),
+            ValueFormat::U8 => decode_int!(u8, data, big_endian),
+            ValueFormat::U16 => decode_int!(u16, data, big_endian),
+            ValueFormat::U32 => decode_int!(u32, data, big_endian),
+            ValueFormat::U64 => decode_int!(u64, data, big_endian),
+            ValueFormat::I8 => decode_int!(i8, data, big_endian),
+            ValueFormat::I16 => decode_int!(i16, data, big_endian),
+            ValueFormat::I32 => decode_int!(i32, data, big_endian),
+            ValueFormat::I64 => decode_int!(i64, data, big_endian),
+        };
+
+        value.ok_or(CliError::SettingValueFormatMismatch {
+            format: self.format.name(),
+            length: data.len(),
+        })
+    }
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum SettingsCommand {
+    /// Read the value of a setting
+    Read {
+        /// The name of the setting
+        name: String,
+        #[command(flatten)]
+        value_args: ValueArgs,
+        /// The maximum size of the value to read
+        #[arg(long)]
+        max_size: Option<u32>,
+    },
+    /// Write the value of a setting
+    ///
+    /// The value gets applied by `settings commit` and persisted by `settings save`.
+    #[command(verbatim_doc_comment)]
+    Write {
+        /// The name of the setting
+        name: String,
+        /// The new value, in the given format
+        ///
+        /// Integers can be decimal or hexadecimal with a `0x` prefix, like `-0x10`.
+        #[arg(verbatim_doc_comment, allow_hyphen_values = true)]
+        value: String,
+        #[command(flatten)]
+        value_args: ValueArgs,
+    },
+    /// Delete a setting from the persistent storage
+    Delete {
+        /// The name of the setting
+        name: String,
+    },
+    /// Apply the written settings
+    Commit,
+    /// Load the settings from the persistent storage
+    Load,
+    /// Save the settings to the persistent storage
+    Save,
+}
+
+pub fn run(
+    client: &Client,
+    _multiprogress: &MultiProgress,
+    args: CommonArgs,
+    command: SettingsCommand,
+) -> Result<(), CliError> {
+    let client = client.get()?;
+
+    match command {
+        SettingsCommand::Read {
+            name,
+            value_args,
+            max_size,
+        } => {
+            let data = client.settings_read(&name, max_size)?;
+            let value = value_args.decode(&data)?;
+
+            if args.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&serde_json::json!({
+                        "name": name,
+                        "value": value,
+                        "raw": hex::encode(&data),
+                    }))
+                    .map_err(CliError::JsonEncodeError)?
+                );
+            } else {
+                match value {
+                    serde_json::Value::String(value) => println!("{value}"),
+                    value => println!("{value}"),
+                }
+            }
+        }
+        SettingsCommand::Write {
+            name,
+            value,
+            value_args,
+        } => {
+            let data = value_args.encode(&value)?;
+            client.settings_write(&name, &data)?;
+        }
+        SettingsCommand::Delete { name } => client.settings_delete(&name)?,
+        SettingsCommand::Commit => client.settings_commit()?,
+        SettingsCommand::Load => client.settings_load()?,
+        SettingsCommand::Save => client.settings_save()?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(format: ValueFormat, value: &str) -> Option<Vec<u8>> {
+        ValueArgs {
+            format,
+            big_endian: true,
+        }
+        .encode(value)
+        .ok()
+    }
+
+    #[test]
+    fn encode_hex_integers() {
+        assert_eq!(encode(ValueFormat::U16, "0x1234"), Some(vec![0x12, 0x34]));
+        assert_eq!(encode(ValueFormat::U16, "0X1234"), Some(vec![0x12, 0x34]));
+        assert_eq!(encode(ValueFormat::I16, "+0x10"), Some(vec![0x00, 0x10]));
+        assert_eq!(encode(ValueFormat::I16, "-0x10"), Some(vec![0xff, 0xf0]));
+        assert_eq!(encode(ValueFormat::I8, "-0x80"), Some(vec![0x80]));
+        assert_eq!(encode(ValueFormat::I16, "-16"), Some(vec![0xff, 0xf0]));
+
+        assert_eq!(encode(ValueFormat::I16, "0x-10"), None);
+        assert_eq!(encode(ValueFormat::U16, "-0x10"), None);
+        assert_eq!(encode(ValueFormat::I8, "-0x81"), None);
+    }
+}
@@ -0,0 +1,51 @@
+use indicatif::MultiProgress;
+
+use crate::{args::CommonArgs, client::Client, errors::CliError, formatting::structured_print};
+
+#[derive(Debug, clap::Subcommand)]
+pub enum StatCommand {
+    /// List the statistics groups of the device
+    List,
+    /// Read the statistics of a group
+    Read {
+        /// The name of the statistics group
+        group: String,
+    },
+}
+
+pub fn run(
+    client: &Client,
+    _multiprogress: &MultiProgress,
+    args: CommonArgs,
+    command: StatCommand,
+) -> Result<(), CliError> {
+    let client = client.get()?;
+
+    match command {
+        StatCommand::List => {
+            let groups = client.stat_list()?;
+
+            if args.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&groups).map_err(CliError::JsonEncodeError)?
+                );
+            } else {
+                for group in groups {
+                    println!("{group}");
+                }
+            }
+        }
+        StatCommand::Read { group } => {
+            let fields = client.stat_read(&group)?;
+
+            structured_print(Some(group), args.json, |s| {
+                for (name, value) in fields {
+                    s.key_value(name, value);
+                }
+            })?;
+        }
+    }
+
+    Ok(())
+}
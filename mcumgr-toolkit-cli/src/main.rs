@@ -5,8 +5,10 @@ mod client;
 mod errors;
 mod file_read_write;
 mod formatting;
+mod frame_size_cache;
 mod groups;
 mod progress;
+mod stats;
 
 use client::Client;
 use indicatif::MultiProgress;
@@ -15,14 +17,23 @@ use indicatif_log_bridge::LogWrapper;
 use std::time::Duration;
 
 use clap::Parser;
-use mcumgr_toolkit::{MCUmgrClient, client::UsbSerialError};
+use mcumgr_toolkit::{
+    client::{ClientBuilder, ClientBuilderError, ConnectionUrl, UsbSerialError},
+    connection::RetryPolicy,
+    transport::recording::Recording,
+};
 
 use crate::errors::CliError;
 
 fn cli_main(multiprogress: &MultiProgress) -> Result<(), CliError> {
     let args = args::App::parse();
 
-    let client = if let Some(serial_name) = args.serial {
+    // Decoding captures works offline, don't connect to a device
+    if let Some(groups::Group::Decode(command)) = args.group {
+        return groups::decode::run(args.common, command);
+    }
+
+    let url = if let Some(serial_name) = args.serial {
         if serial_name.is_empty() {
             let ports = serialport::available_ports()
                 .map_err(CliError::ListSerialPortsFailed)?
@@ -50,19 +61,50 @@ fn cli_main(multiprogress: &MultiProgress) -> Result<(), CliError> {
             return Ok(());
         }
 
-        let serial = serialport::new(serial_name, args.baud)
-            .timeout(Duration::from_millis(args.timeout))
-            .open()
-            .map_err(CliError::OpenSerialFailed)?;
-        Client::new(MCUmgrClient::new_from_serial(serial))
+        Some(ConnectionUrl::serial(&serial_name).map_err(CliError::InvalidSerialName)?)
     } else if let Some(identifier) = args.usb_serial {
-        let result = MCUmgrClient::new_from_usb_serial(
+        Some(ConnectionUrl::UsbSerial {
             identifier,
-            args.baud,
-            Duration::from_millis(args.timeout),
-        );
+            baud_rate: None,
+        })
+    } else if let Some(addr) = args.udp {
+        Some(ConnectionUrl::Udp { addr })
+    } else if let Some(addr) = args.tcp {
+        Some(ConnectionUrl::Tcp { addr })
+    } else if let Some(path) = args.unix {
+        Some(ConnectionUrl::Unix { path })
+    } else if let Some(path) = args.replay {
+        Some(ConnectionUrl::Replay { path })
+    } else if let Some(url) = args.connection {
+        Some(url)
+    } else {
+        // Not a clap `env` default, that would conflict with the other backends
+        ClientBuilder::from_env()
+            .map_err(CliError::InvalidConnectionEnv)?
+            .map(|builder| builder.url().clone())
+    };
 
-        if let Err(UsbSerialError::IdentifierEmpty { ports }) = &result {
+    let client = if let Some(url) = &url {
+        let mut builder = ClientBuilder::new(url.clone())
+            .baud_rate(args.baud)
+            .timeout(Duration::from_millis(args.timeout))
+            .retry_policy(RetryPolicy {
+                max_retries: args.retries,
+                ..Default::default()
+            })
+            .smp_version(args.smp_version)
+            // With probing, the frame size gets read below instead
+            .auto_frame_size(!args.probe_frame_size);
+        if let Some(path) = args.record {
+            let recording = Recording::create(path).map_err(CliError::CreateRecordingFailed)?;
+            builder = builder.record(recording);
+        }
+        let result = builder.build();
+
+        if let Err(ClientBuilderError::UsbSerialOpenFailed(UsbSerialError::IdentifierEmpty {
+            ports,
+        })) = &result
+        {
             if args.common.json {
                 println!(
                     "{}",
@@ -83,25 +125,68 @@ fn cli_main(multiprogress: &MultiProgress) -> Result<(), CliError> {
 
         Client::new(result?)
     } else {
+        if args.record.is_some() {
+            return Err(CliError::NoBackendSelected);
+        }
         Client::default()
     };
 
-    if let Ok(client) = client.get() {
-        if let Err(e) = client.use_auto_frame_size() {
-            log::warn!("Failed to read SMP frame size from device, using slow default");
-            log::warn!("Reason: {e}");
-            log::warn!("Hint: Make sure that `CONFIG_MCUMGR_GRP_OS_MCUMGR_PARAMS` is enabled.");
+    if args.probe_frame_size {
+        if let Ok(client) = client.get() {
+            if let Err(e) = client.use_auto_frame_size() {
+                log::debug!("Failed to read SMP frame size from device: {e}");
+                let timeout = Duration::from_millis(args.timeout);
+                if let Err(e) = frame_size_cache::probe_frame_size(client, url.as_ref(), timeout) {
+                    log::warn!("Failed to probe SMP frame size, using slow default");
+                    log::warn!("Reason: {e}");
+                }
+            }
+        }
+    }
+
+    if let Some(serial_mtu) = args.serial_mtu {
+        let client = client.get()?;
+        match serial_mtu {
+            args::SerialMtu::Auto => client.use_auto_serial_mtu()?,
+            args::SerialMtu::Fixed(mtu) => client.set_serial_mtu(mtu)?,
+        }
+    }
+
+    if let Some(window) = args.window {
+        let client = client.get()?;
+        match window {
+            args::WindowSize::Auto => client.use_auto_window_size()?,
+            args::WindowSize::Fixed(window_size) => client.set_window_size(window_size),
         }
     }
 
-    if let Some(group) = args.group {
-        groups::run(&client, multiprogress, args.common, group)?;
+    let json = args.common.json;
+
+    let result = if let Some(groups::Group::Monitor) = args.group {
+        groups::monitor::run(
+            &client,
+            multiprogress,
+            args.common,
+            Duration::from_millis(args.timeout),
+        )
+    } else if let Some(group) = args.group {
+        groups::run(&client, multiprogress, args.common, group)
     } else {
-        client.get()?.check_connection()?;
-        println!("Device alive and responsive.");
+        client.get().and_then(|client| {
+            client.check_connection()?;
+            println!("Device alive and responsive ({}).", client.smp_version());
+            Ok(())
+        })
+    };
+
+    // Also print statistics if the command failed, to help diagnosing the link
+    if args.stats {
+        if let Ok(client) = client.get() {
+            stats::print_stats(&client.stats(), json)?;
+        }
     }
 
-    Ok(())
+    result
 }
 
 fn main() -> miette::Result<()> {
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use mcumgr_toolkit::connection::ConnectionStats;
+
+use crate::{errors::CliError, formatting::structured_print};
+
+fn millis(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1000.0 * 100.0).round() / 100.0
+}
+
+pub fn print_stats(stats: &ConnectionStats, json: bool) -> Result<(), CliError> {
+    structured_print(Some("Connection Statistics".to_string()), json, |s| {
+        s.key_value("Frames sent", stats.frames_sent);
+        s.key_value("Bytes sent", stats.bytes_sent);
+        s.key_value("Frames received", stats.frames_received);
+        s.key_value("Bytes received", stats.bytes_received);
+        s.key_value("CRC errors", stats.crc_errors);
+        s.key_value("Skipped frames", stats.skipped_frames);
+        s.key_value("Timeouts", stats.timeouts);
+        s.key_value("Retries", stats.retries);
+
+        s.sublist("Round-trip times (ms)", |s| {
+            for latency in &stats.latencies {
+                let histogram = &latency.histogram;
+                s.sublist(
+                    format!("Group {}, Command {}", latency.group_id, latency.command_id),
+                    |s| {
+                        s.key_value("Count", histogram.count);
+                        s.key_value("Min", millis(histogram.min));
+                        s.key_value_maybe("Mean", histogram.mean().map(millis));
+                        s.key_value_maybe("p50", histogram.percentile(50.0).map(millis));
+                        s.key_value_maybe("p90", histogram.percentile(90.0).map(millis));
+                        s.key_value_maybe("p99", histogram.percentile(99.0).map(millis));
+                        s.key_value("Max", millis(histogram.max));
+                    },
+                );
+            }
+        });
+    })
+}
@@ -0,0 +1,257 @@
+#![deny(missing_docs)]
+#![deny(unreachable_pub)]
+#![forbid(unsafe_code)]
+//! Derive macros for user-defined commands of the [`mcumgr-toolkit`](https://docs.rs/mcumgr-toolkit) crate.
+//!
+//! Use them through the `derive` feature of `mcumgr-toolkit`, which re-exports them in its `commands` module.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{quote, quote_spanned};
+use syn::{DeriveInput, Error, Expr, LitInt, Type, parse_macro_input, spanned::Spanned};
+
+/// Implements `McuMgrCommand` for a request type, with the request itself as payload.
+///
+/// # Attributes
+///
+/// `#[mcumgr(read|write, group = <group>, id = <id>, response = <type>[, long_operation])]`
+///
+/// - `read`/`write`: Whether the command is a read or a write operation
+/// - `group`: The group ID, either as number or as `MCUmgrGroup` variant; must fit into a `u16`
+/// - `id`: The command ID
+/// - `response`: The response type of the command
+/// - `long_operation`: Optional, waits at least `LONG_OPERATION_TIMEOUT` for the response
+///
+/// # Example
+///
+/// ```ignore
+/// use mcumgr_toolkit::commands::{DeserializeFromEmptyMap, McuMgrCommand};
+///
+/// #[derive(serde::Serialize, McuMgrCommand)]
+/// #[mcumgr(write, group = 64, id = 3, response = SetLedResponse)]
+/// struct SetLed {
+///     on: bool,
+/// }
+///
+/// #[derive(DeserializeFromEmptyMap)]
+/// struct SetLedResponse;
+/// ```
+#[proc_macro_derive(McuMgrCommand, attributes(mcumgr))]
+pub fn derive_mcumgr_command(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    mcumgr_command(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implements `Serialize` as an empty map, for requests without parameters.
+///
+/// Only works on unit structs.
+#[proc_macro_derive(SerializeAsEmptyMap)]
+pub fn derive_serialize_as_empty_map(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    serialize_as_empty_map(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implements `Deserialize` from a map and `Into<()>`, for responses without content.
+///
+/// Entries of the map are ignored, for compatibility with future versions of the command.
+/// Only works on unit structs.
+#[proc_macro_derive(DeserializeFromEmptyMap)]
+pub fn derive_deserialize_from_empty_map(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    deserialize_from_empty_map(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// The content of the `#[mcumgr(...)]` attribute
+struct CommandAttributes {
+    write: bool,
+    group: Expr,
+    id: u8,
+    response: Type,
+    long_operation: bool,
+}
+
+impl CommandAttributes {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut write = None;
+        let mut group = None;
+        let mut id = None;
+        let mut response = None;
+        let mut long_operation = false;
+
+        let mut found = false;
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("mcumgr"))
+        {
+            found = true;
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("read") || meta.path.is_ident("write") {
+                    if write.is_some() {
+                        return Err(meta.error("duplicate `read`/`write`"));
+                    }
+                    write = Some(meta.path.is_ident("write"));
+                } else if meta.path.is_ident("group") {
+                    group = Some(meta.value()?.parse::<Expr>()?);
+                } else if meta.path.is_ident("id") {
+                    id = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u8>()?);
+                } else if meta.path.is_ident("response") {
+                    response = Some(meta.value()?.parse::<Type>()?);
+                } else if meta.path.is_ident("long_operation") {
+                    long_operation = true;
+                } else {
+                    return Err(meta.error("unknown `mcumgr` attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let missing = |what: &str| {
+            let span = if found {
+                Span::call_site()
+            } else {
+                input.ident.span()
+            };
+            Error::new(
+                span,
+                format!("missing {what} in `#[mcumgr(...)]` attribute"),
+            )
+        };
+
+        Ok(Self {
+            write: write.ok_or_else(|| missing("`read` or `write`"))?,
+            group: group.ok_or_else(|| missing("`group = ...`"))?,
+            id: id.ok_or_else(|| missing("`id = ...`"))?,
+            response: response.ok_or_else(|| missing("`response = ...`"))?,
+            long_operation,
+        })
+    }
+}
+
+fn mcumgr_command(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let CommandAttributes {
+        write,
+        group,
+        id,
+        response,
+        long_operation,
+    } = CommandAttributes::parse(&input)?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let min_timeout = long_operation.then(|| {
+        quote! {
+            fn min_timeout(&self) -> ::core::option::Option<::core::time::Duration> {
+                ::core::option::Option::Some(::mcumgr_toolkit::commands::LONG_OPERATION_TIMEOUT)
+            }
+        }
+    });
+
+    // `as u16` below would silently truncate out-of-range group IDs
+    let group_check = quote_spanned! {group.span()=>
+        #[allow(clippy::unnecessary_cast)]
+        const _: () = ::core::assert!(
+            (#group) as u128 <= ::core::primitive::u16::MAX as u128,
+            "`group` of `#[mcumgr(...)]` must fit into a `u16`"
+        );
+    };
+
+    Ok(quote! {
+        #group_check
+
+        impl #impl_generics ::mcumgr_toolkit::commands::McuMgrCommand for #name #ty_generics #where_clause {
+            type Payload = Self;
+            type Response = #response;
+            fn is_write_operation(&self) -> bool {
+                #write
+            }
+            fn group_id(&self) -> u16 {
+                (#group) as u16
+            }
+            fn command_id(&self) -> u8 {
+                #id
+            }
+            fn data(&self) -> &Self {
+                self
+            }
+            #min_timeout
+        }
+    })
+}
+
+fn ensure_unit_struct(input: &DeriveInput, derive: &str) -> syn::Result<()> {
+    match &input.data {
+        syn::Data::Struct(data) if matches!(data.fields, syn::Fields::Unit) => Ok(()),
+        _ => Err(Error::new(
+            input.ident.span(),
+            format!("`{derive}` can only be derived for unit structs"),
+        )),
+    }
+}
+
+fn serialize_as_empty_map(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    ensure_unit_struct(&input, "SerializeAsEmptyMap")?;
+    let name = &input.ident;
+
+    Ok(quote! {
+        impl ::mcumgr_toolkit::__private::serde::Serialize for #name {
+            fn serialize<S>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                S: ::mcumgr_toolkit::__private::serde::Serializer,
+            {
+                use ::mcumgr_toolkit::__private::serde::ser::SerializeMap;
+                let map = serializer.serialize_map(::core::option::Option::Some(0))?;
+                map.end()
+            }
+        }
+    })
+}
+
+fn deserialize_from_empty_map(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    ensure_unit_struct(&input, "DeserializeFromEmptyMap")?;
+    let name = &input.ident;
+
+    Ok(quote! {
+        impl ::core::convert::From<#name> for () {
+            fn from(_: #name) -> () {}
+        }
+
+        impl<'de> ::mcumgr_toolkit::__private::serde::Deserialize<'de> for #name {
+            fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+            where
+                D: ::mcumgr_toolkit::__private::serde::Deserializer<'de>,
+            {
+                use ::mcumgr_toolkit::__private::serde::de::{IgnoredAny, MapAccess, Visitor};
+
+                struct InternalVisitor;
+
+                impl<'de> Visitor<'de> for InternalVisitor {
+                    type Value = #name;
+
+                    fn expecting(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                        f.write_str("an empty map/object (`{}`)")
+                    }
+
+                    fn visit_map<M>(self, mut map: M) -> ::core::result::Result<#name, M::Error>
+                    where
+                        M: MapAccess<'de>,
+                    {
+                        // Entries are ignored for future compatibility
+                        while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+
+                        ::core::result::Result::Ok(#name)
+                    }
+                }
+
+                deserializer.deserialize_map(InternalVisitor)
+            }
+        }
+    })
+}
@@ -7,6 +7,123 @@ import datetime
 import enum
 import typing
 
+@typing.final
+class CommandLatency:
+    r"""
+    Round-trip times of one command
+    """
+    @property
+    def group_id(self) -> builtins.int:
+        r"""
+        group ID of the command
+        """
+    @property
+    def command_id(self) -> builtins.int:
+        r"""
+        command ID
+        """
+    @property
+    def count(self) -> builtins.int:
+        r"""
+        number of successful round trips
+        """
+    @property
+    def min_ms(self) -> builtins.float:
+        r"""
+        fastest round trip (in milliseconds)
+        """
+    @property
+    def mean_ms(self) -> typing.Optional[builtins.float]:
+        r"""
+        average round trip (in milliseconds)
+        """
+    @property
+    def max_ms(self) -> builtins.float:
+        r"""
+        slowest round trip (in milliseconds)
+        """
+    @property
+    def bucket_counts(self) -> builtins.list[builtins.int]:
+        r"""
+        number of round trips per histogram bucket, see `bucket_bounds_ms`;
+        the last entry counts the round trips slower than the last bound
+        """
+    @property
+    def bucket_bounds_ms(self) -> builtins.list[builtins.float]:
+        r"""
+        upper bounds of the histogram buckets (in milliseconds)
+        """
+
+@typing.final
+class ConnectionStats:
+    r"""
+    Return value of `MCUmgrClient.stats`.
+    
+    Byte counts include the SMP header, but not the framing of the transport.
+    """
+    @property
+    def frames_sent(self) -> builtins.int:
+        r"""
+        number of SMP frames sent
+        """
+    @property
+    def bytes_sent(self) -> builtins.int:
+        r"""
+        number of bytes sent
+        """
+    @property
+    def frames_received(self) -> builtins.int:
+        r"""
+        number of SMP frames received, including skipped ones
+        """
+    @property
+    def bytes_received(self) -> builtins.int:
+        r"""
+        number of bytes received
+        """
+    @property
+    def crc_errors(self) -> builtins.int:
+        r"""
+        number of received frames with a CRC error
+        """
+    @property
+    def skipped_frames(self) -> builtins.int:
+        r"""
+        number of received frames that did not belong to a request in flight
+        """
+    @property
+    def timeouts(self) -> builtins.int:
+        r"""
+        number of times the device did not respond in time
+        """
+    @property
+    def retries(self) -> builtins.int:
+        r"""
+        number of retransmitted requests
+        """
+    @property
+    def latencies(self) -> 'builtins.list[CommandLatency]':
+        r"""
+        round-trip times per command, ordered by group and command ID
+        """
+
+@typing.final
+class DeviceCapabilities:
+    r"""
+    Return value of `MCUmgrClient.capabilities`.
+    """
+    @property
+    def probed(self) -> builtins.bool:
+        r"""
+        whether the groups were probed, because the device lacks the enumeration group;
+        probing only finds the groups known to this library, without names or handler counts
+        """
+    @property
+    def groups(self) -> 'builtins.list[GroupCapabilities]':
+        r"""
+        the supported groups, ordered by ID
+        """
+
 @typing.final
 class FileChecksum:
     r"""
@@ -32,6 +149,11 @@ class FileChecksum:
         r"""
         output hash/checksum
         """
+    @property
+    def extra(self) -> builtins.dict:
+        r"""
+        response fields that are not known to this library yet
+        """
 
 @typing.final
 class FileChecksumProperties:
@@ -48,6 +170,11 @@ class FileChecksumProperties:
         r"""
         size (in bytes) of output hash/checksum response
         """
+    @property
+    def extra(self) -> builtins.dict:
+        r"""
+        response fields that are not known to this library yet
+        """
 
 @typing.final
 class FileStatus:
@@ -59,6 +186,32 @@ class FileStatus:
         r"""
         length of file (in bytes)
         """
+    @property
+    def extra(self) -> builtins.dict:
+        r"""
+        response fields that are not known to this library yet
+        """
+
+@typing.final
+class GroupCapabilities:
+    r"""
+    A management group that the device supports
+    """
+    @property
+    def group_id(self) -> builtins.int:
+        r"""
+        ID of the group
+        """
+    @property
+    def name(self) -> typing.Optional[builtins.str]:
+        r"""
+        name of the group, if reported by the device
+        """
+    @property
+    def handlers(self) -> typing.Optional[builtins.int]:
+        r"""
+        number of command handlers of the group, if reported by the device
+        """
 
 @typing.final
 class ImageState:
@@ -114,6 +267,11 @@ class ImageState:
         r"""
         true if image is to stay in primary slot after the next boot
         """
+    @property
+    def extra(self) -> builtins.dict:
+        r"""
+        response fields that are not known to this library yet
+        """
 
 @typing.final
 class MCUmgrClient:
@@ -127,11 +285,25 @@ class MCUmgrClient:
         
         ### Arguments
         
-        * `serial` - The identifier of the serial device. (Windows: `COMxx`, Linux: `/dev/ttyXX`)
+        * `serial` - The identifier of the serial device. (Windows: `COMxx`, Linux: `/dev/ttyXX`, RFC 2217: `rfc2217://host:port`, optionally with parameters like `?parity=even&dtr=off`)
         * `baud_rate` - The baud rate of the serial port.
         * `timeout_ms` - The communication timeout, in ms.
         """
     @staticmethod
+    def connect(url: typing.Optional[builtins.str] = None, baud_rate: builtins.int = 115200, timeout_ms: builtins.int = 10000) -> 'MCUmgrClient':
+        r"""
+        Creates a Zephyr MCUmgr SMP client from a connection string.
+        
+        ### Arguments
+        
+        * `url` - The connection string, for example `serial:///dev/ttyUSB0?baud=115200`, `usb://2fe3:0100:1`,
+          `rfc2217://host:port`, `udp://[fe80::1]:1337`, `tcp://host:port`, `unix:///path`
+          or `replay://session.jsonl`.
+          Read from the `MCUMGR_CONNECTION` environment variable if missing.
+        * `baud_rate` - The baud rate of serial based connections, unless given in the connection string.
+        * `timeout_ms` - The communication timeout, in ms.
+        """
+    @staticmethod
     def usb_serial(identifier: builtins.str, baud_rate: builtins.int = 115200, timeout_ms: builtins.int = 10000) -> 'MCUmgrClient':
         r"""
         Creates a Zephyr MCUmgr SMP client based on a USB serial port identified by VID:PID.
@@ -164,6 +336,51 @@ class MCUmgrClient:
         by reading the value of [`MCUMGR_TRANSPORT_NETBUF_SIZE`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L40)
         from the device.
         """
+    def probe_frame_size(self, max_frame_size: builtins.int = 4096) -> builtins.int:
+        r"""
+        Configures the maximum SMP frame size by probing which frame sizes the device accepts.
+        
+        For devices that do not report their frame size, like MCUboot serial recovery.
+        Frames that time out count as too large, so consider a short timeout.
+        
+        ### Arguments
+        
+        * `max_frame_size` - The largest frame size to probe.
+        
+        ### Return
+        
+        The frame size that is used from now on.
+        """
+    def set_window_size(self, window_size: builtins.int) -> None:
+        r"""
+        Configures how many requests uploads and downloads keep in flight.
+        
+        Speeds up transfers over connections with high latency.
+        
+        Must not exceed [`MCUMGR_TRANSPORT_NETBUF_COUNT`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L28),
+        otherwise the device drops our requests.
+        """
+    def use_auto_window_size(self) -> None:
+        r"""
+        Configures how many requests uploads and downloads keep in flight automatically
+        by reading the value of [`MCUMGR_TRANSPORT_NETBUF_COUNT`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L28)
+        from the device.
+        """
+    def set_serial_mtu(self, mtu: builtins.int) -> None:
+        r"""
+        Configures the maximum line length of serial based transports.
+        
+        Must not exceed the device's [`MCUMGR_SERIAL_MAX_FRAME`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/include/zephyr/mgmt/mcumgr/transport/serial.h#L18),
+        otherwise the device drops our requests.
+        """
+    def use_auto_serial_mtu(self) -> None:
+        r"""
+        Configures the maximum line length of serial based transports automatically
+        by learning it from the line length of a long echo response.
+        
+        Call `use_auto_frame_size` first, otherwise the probe might be too short
+        to reveal large MTUs.
+        """
     def set_timeout_ms(self, timeout_ms: builtins.int) -> None:
         r"""
         Changes the communication timeout.
@@ -171,6 +388,36 @@ class MCUmgrClient:
         When the device does not respond to packets within the set
         duration, an error will be raised.
         """
+    def set_retry_policy(self, max_retries: builtins.int, backoff_ms: builtins.int = 100) -> None:
+        r"""
+        Configures the automatic retransmission of requests
+        that timed out or received a corrupted response.
+        
+        The delay between retransmissions starts at `backoff_ms` and doubles every time.
+        Retransmissions are disabled by default.
+        """
+    def retry_count(self) -> builtins.int:
+        r"""
+        The number of retransmitted requests since the client was created.
+        """
+    def stats(self) -> ConnectionStats:
+        r"""
+        Statistics of the connection since the client was created.
+        
+        Counts frames, bytes, CRC errors, skipped frames, timeouts and retries,
+        and keeps a histogram of round-trip times per command.
+        """
+    def set_smp_version(self, version: typing.Optional[builtins.int] = None) -> None:
+        r"""
+        Selects the SMP protocol version of requests, `1` or `2`.
+        
+        `None` negotiates the version automatically, which is the default:
+        requests start with SMP v2 and fall back to SMP v1 if the device rejects them.
+        """
+    def smp_version(self) -> builtins.int:
+        r"""
+        The SMP protocol version of requests, as negotiated with the device.
+        """
     def check_connection(self) -> None:
         r"""
         Checks if the device is alive and responding.
@@ -179,6 +426,15 @@ class MCUmgrClient:
         
         Raises an error if the device is not alive and responding.
         """
+    def wait_for_reconnect(self, timeout_ms: builtins.int) -> None:
+        r"""
+        Waits until the device responds again, for example after a reset.
+        
+        Reopens the connection as soon as the device reappears,
+        even under a different port name for USB serial devices.
+        
+        Raises an error if the device did not respond within `timeout_ms`.
+        """
     def firmware_update(self, firmware: bytes, checksum: typing.Optional[builtins.str | builtins.bytes] = None, bootloader_type: typing.Optional[typing.Literal['MCUboot']] = None, skip_reboot: builtins.bool = False, force_confirm: builtins.bool = False, upgrade_only: builtins.bool = False, progress: typing.Optional[collections.abc.Callable[[builtins.str, typing.Optional[builtins.tuple[builtins.int, builtins.int]]], None]] = None) -> None:
         r"""
         High-level firmware update routine.
@@ -192,6 +448,8 @@ class MCUmgrClient:
         * `force_confirm` - Skip test boot and confirm directly.
         * `upgrade_only` - Prevent firmware downgrades.
         * `progress` - A callback that receives progress updates.
+        
+        A `KeyboardInterrupt` cancels the update after the current step or chunk.
         """
     def os_echo(self, msg: builtins.str) -> builtins.str:
         r"""
@@ -212,6 +470,14 @@ class MCUmgrClient:
         
         A map of task names with their respective statistics
         """
+    def os_memory_pool_statistics(self) -> 'builtins.dict[builtins.str, MemoryPoolStatistics]':
+        r"""
+        Queries memory pool statistics
+        
+        ### Return
+        
+        A map of memory pool names with their respective statistics
+        """
     def os_set_datetime(self, datetime: datetime.datetime) -> None:
         r"""
         Sets the RTC of the device to the given datetime.
@@ -295,6 +561,8 @@ class MCUmgrClient:
         * `progress` - A callable object that takes (transmitted, total) values as parameters.
                        Any return value is ignored. Raising an exception aborts the operation.
         
+        A `KeyboardInterrupt` cancels the operation after the current chunk.
+        
         ### Performance
         
         Uploading files with Zephyr's default parameters is slow.
@@ -314,6 +582,63 @@ class MCUmgrClient:
         r"""
         Obtain a list of available image slots.
         """
+    def stat_list(self) -> builtins.list[builtins.str]:
+        r"""
+        List the statistics groups of the device.
+        """
+    def stat_read(self, group: builtins.str) -> builtins.dict[builtins.str, builtins.int]:
+        r"""
+        Read the statistics of a group.
+        
+        ### Arguments
+        
+        * `group` - The name of the statistics group, as returned by `stat_list`
+        
+        ### Return
+        
+        The values of the statistics, by name
+        """
+    def settings_read(self, name: builtins.str, max_size: typing.Optional[builtins.int] = None) -> bytes:
+        r"""
+        Read the value of a setting.
+        
+        ### Arguments
+        
+        * `name` - The name of the setting
+        * `max_size` - The maximum size of the value to read. Device default if None.
+        
+        ### Return
+        
+        The raw value of the setting
+        """
+    def settings_write(self, name: builtins.str, value: bytes) -> None:
+        r"""
+        Write the value of a setting.
+        
+        The new value is only applied after `settings_commit`,
+        and only persisted after `settings_save`.
+        
+        ### Arguments
+        
+        * `name` - The name of the setting
+        * `value` - The raw value of the setting
+        """
+    def settings_delete(self, name: builtins.str) -> None:
+        r"""
+        Delete a setting from the persistent storage.
+        """
+    def settings_commit(self) -> None:
+        r"""
+        Apply the written settings.
+        """
+    def settings_load(self) -> None:
+        r"""
+        Load the settings from the persistent storage.
+        """
+    def settings_save(self) -> None:
+        r"""
+        Save the settings to the persistent storage.
+        """
     def fs_file_download(self, name: builtins.str, progress: typing.Optional[collections.abc.Callable[[builtins.int, builtins.int], None]] = None) -> bytes:
         r"""
         Load a file from the device.
@@ -324,6 +649,8 @@ class MCUmgrClient:
         * `progress` - A callable object that takes (transmitted, total) values as parameters.
                        Any return value is ignored. Raising an exception aborts the operation.
         
+        A `KeyboardInterrupt` cancels the operation after the current chunk.
+        
         ### Return
         
         The file content
@@ -345,6 +672,8 @@ class MCUmgrClient:
         * `progress` - A callable object that takes (transmitted, total) values as parameters.
                        Any return value is ignored. Raising an exception aborts the operation.
         
+        A `KeyboardInterrupt` cancels the operation after the current chunk.
+        
         ### Performance
         
         Uploading files with Zephyr's default parameters is slow.
@@ -389,6 +718,41 @@ class MCUmgrClient:
         
         The command output
         """
+    def enum_group_count(self) -> builtins.int:
+        r"""
+        Query the number of management groups the device supports.
+        """
+    def enum_group_list(self) -> builtins.list[builtins.int]:
+        r"""
+        Query the IDs of the management groups the device supports.
+        """
+    def enum_group_id(self, index: builtins.int) -> builtins.tuple[builtins.int, builtins.bool]:
+        r"""
+        Query the ID of a single supported management group.
+        
+        ### Arguments
+        
+        * `index` - The index of the group, up to `enum_group_count`
+        
+        ### Return
+        
+        A tuple of (group ID, whether it is the last group)
+        """
+    def enum_group_details(self, groups: typing.Optional[typing.Sequence[builtins.int]] = None) -> typing.Any:
+        r"""
+        Query the names and handler counts of supported management groups.
+        
+        ### Arguments
+        
+        * `groups` - The IDs of the groups to query. All groups if empty or `None`.
+        """
+    def capabilities(self) -> DeviceCapabilities:
+        r"""
+        Determine which management groups the device supports.
+        
+        Uses the enumeration group, if available. Otherwise, probes the groups known
+        to this library with requests that have no side effects.
+        """
     def zephyr_erase_storage(self) -> None:
         r"""
         Erase the `storage_partition` flash partition.
@@ -439,6 +803,11 @@ class MCUmgrParameters:
         r"""
         Number of SMP buffers supported
         """
+    @property
+    def extra(self) -> builtins.dict:
+        r"""
+        response fields that are not known to this library yet
+        """
 
 @typing.final
 class McubootImageInfo:
@@ -460,6 +829,37 @@ class McubootImageInfo:
         verification purposes.
         """
 
+@typing.final
+class MemoryPoolStatistics:
+    r"""
+    Statistics of a memory pool/heap
+    """
+    @property
+    def blksiz(self) -> builtins.int:
+        r"""
+        size of the memory blocks in the pool
+        """
+    @property
+    def nblks(self) -> builtins.int:
+        r"""
+        number of blocks in the pool
+        """
+    @property
+    def nfree(self) -> builtins.int:
+        r"""
+        number of free blocks
+        """
+    @property
+    def min(self) -> builtins.int:
+        r"""
+        lowest number of free blocks the pool ever had
+        """
+    @property
+    def extra(self) -> builtins.dict:
+        r"""
+        response fields that are not known to this library yet
+        """
+
 @typing.final
 class SlotInfoImage:
     r"""
@@ -480,6 +880,11 @@ class SlotInfoImage:
         r"""
         maximum size of an application that can be uploaded to that image number
         """
+    @property
+    def extra(self) -> builtins.dict:
+        r"""
+        response fields that are not known to this library yet
+        """
 
 @typing.final
 class SlotInfoImageSlot:
@@ -501,6 +906,11 @@ class SlotInfoImageSlot:
         r"""
         specifies the image ID that can be used by external tools to upload an image to that slot
         """
+    @property
+    def extra(self) -> builtins.dict:
+        r"""
+        response fields that are not known to this library yet
+        """
 
 @typing.final
 class TaskStatistics:
@@ -542,6 +952,11 @@ class TaskStatistics:
         r"""
         task’s/thread’s runtime in “ticks”
         """
+    @property
+    def extra(self) -> builtins.dict:
+        r"""
+        response fields that are not known to this library yet
+        """
 
 @typing.final
 class FileChecksumDataFormat(enum.Enum):
@@ -1,19 +1,22 @@
 #![forbid(unsafe_code)]
 #![allow(clippy::too_many_arguments)]
 
-use miette::IntoDiagnostic;
 use pyo3::types::PyDateTime;
 use pyo3::{prelude::*, types::PyBytes};
 
-use pyo3::exceptions::PyRuntimeError;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
 use pyo3_stub_gen::{derive::*, *};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use ::mcumgr_toolkit::bootloader::BootloaderType;
-use ::mcumgr_toolkit::client::{FirmwareUpdateParams, FirmwareUpdateStep};
+use ::mcumgr_toolkit::client::{
+    ClientBuilder, ConnectionUrl, FirmwareUpdateParams, FirmwareUpdateStep, MCUMGR_CONNECTION_ENV,
+};
+use ::mcumgr_toolkit::connection::{CancellationToken, RetryPolicy};
+use ::mcumgr_toolkit::transport::SmpVersion;
 
 use crate::errors::McubootPythonError;
 use crate::raw_py_any_command::RawPyAnyCommand;
@@ -41,7 +44,49 @@ fn err_to_pyerr<E: Into<miette::Report>>(err: E) -> PyErr {
     PyRuntimeError::new_err(format!("{e:?}"))
 }
 
+/// Cancels the running operation if a signal handler raised an exception,
+/// like `KeyboardInterrupt` on Ctrl+C.
+fn interrupt_to_cancel(py: Python<'_>, cancel: &CancellationToken) -> PyResult<()> {
+    py.check_signals().inspect_err(|_| cancel.cancel())
+}
+
+/// The progress callback of a transfer.
+///
+/// Forwards to the Python callback and cancels the transfer on `KeyboardInterrupt`;
+/// the raised exception is stored in `cb_error`.
+fn progress_callback<'a, 'py>(
+    py: Python<'py>,
+    cancel: &'a CancellationToken,
+    progress: Option<&'a Bound<'py, PyAny>>,
+    cb_error: &'a mut Option<PyErr>,
+) -> impl FnMut(u64, u64) -> bool + 'a {
+    move |current, total| {
+        if let Err(e) = interrupt_to_cancel(py, cancel) {
+            *cb_error = Some(e);
+            return true;
+        }
+        match progress.map(|progress| progress.call((current, total), None)) {
+            Some(Err(e)) => {
+                *cb_error = Some(e);
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
 impl MCUmgrClient {
+    fn build(builder: ClientBuilder, baud_rate: u32, timeout_ms: u64) -> PyResult<Self> {
+        let client = builder
+            .baud_rate(baud_rate)
+            .timeout(Duration::from_millis(timeout_ms))
+            .build()
+            .map_err(err_to_pyerr)?;
+        Ok(MCUmgrClient {
+            client: Mutex::new(Some(Arc::new(client))),
+        })
+    }
+
     fn get_client(&self) -> PyResult<Arc<::mcumgr_toolkit::MCUmgrClient>> {
         let locked_client = self.client.lock().unwrap();
         locked_client
@@ -58,22 +103,45 @@ impl MCUmgrClient {
     ///
     /// ### Arguments
     ///
-    /// * `serial` - The identifier of the serial device. (Windows: `COMxx`, Linux: `/dev/ttyXX`)
+    /// * `serial` - The identifier of the serial device. (Windows: `COMxx`, Linux: `/dev/ttyXX`, RFC 2217: `rfc2217://host:port`, optionally with parameters like `?parity=even&dtr=off`)
     /// * `baud_rate` - The baud rate of the serial port.
     /// * `timeout_ms` - The communication timeout, in ms.
     ///
     #[staticmethod]
     #[pyo3(signature = (serial, baud_rate=115200, timeout_ms=10000))]
     fn serial(serial: &str, baud_rate: u32, timeout_ms: u64) -> PyResult<Self> {
-        let serial = serialport::new(serial, baud_rate)
-            .timeout(Duration::from_millis(timeout_ms))
-            .open()
-            .into_diagnostic()
-            .map_err(err_to_pyerr)?;
-        let client = ::mcumgr_toolkit::MCUmgrClient::new_from_serial(serial);
-        Ok(MCUmgrClient {
-            client: Mutex::new(Some(Arc::new(client))),
-        })
+        Self::build(
+            ClientBuilder::new(ConnectionUrl::serial(serial).map_err(err_to_pyerr)?),
+            baud_rate,
+            timeout_ms,
+        )
+    }
+
+    /// Creates a Zephyr MCUmgr SMP client from a connection string.
+    ///
+    /// ### Arguments
+    ///
+    /// * `url` - The connection string, for example `serial:///dev/ttyUSB0?baud=115200`, `usb://2fe3:0100:1`,
+    ///   `rfc2217://host:port`, `udp://[fe80::1]:1337`, `tcp://host:port`, `unix:///path`
+    ///   or `replay://session.jsonl`.
+    ///   Read from the `MCUMGR_CONNECTION` environment variable if missing.
+    /// * `baud_rate` - The baud rate of serial based connections, unless given in the connection string.
+    /// * `timeout_ms` - The communication timeout, in ms.
+    ///
+    #[staticmethod]
+    #[pyo3(signature = (url=None, baud_rate=115200, timeout_ms=10000))]
+    fn connect(url: Option<&str>, baud_rate: u32, timeout_ms: u64) -> PyResult<Self> {
+        let builder = match url {
+            Some(url) => ClientBuilder::from_url(url).map_err(err_to_pyerr)?,
+            None => ClientBuilder::from_env()
+                .map_err(err_to_pyerr)?
+                .ok_or_else(|| {
+                    PyValueError::new_err(format!(
+                        "No connection string given and {MCUMGR_CONNECTION_ENV} is not set"
+                    ))
+                })?,
+        };
+        Self::build(builder, baud_rate, timeout_ms)
     }
 
     /// Creates a Zephyr MCUmgr SMP client based on a USB serial port identified by VID:PID.
@@ -96,15 +164,11 @@ impl MCUmgrClient {
     #[staticmethod]
     #[pyo3(signature = (identifier, baud_rate=115200, timeout_ms=10000))]
     fn usb_serial(identifier: &str, baud_rate: u32, timeout_ms: u64) -> PyResult<Self> {
-        let client = ::mcumgr_toolkit::MCUmgrClient::new_from_usb_serial(
-            identifier,
-            baud_rate,
-            Duration::from_millis(timeout_ms),
-        )
-        .map_err(err_to_pyerr)?;
-        Ok(MCUmgrClient {
-            client: Mutex::new(Some(Arc::new(client))),
-        })
+        let url = ConnectionUrl::UsbSerial {
+            identifier: identifier.to_string(),
+            baud_rate: None,
+        };
+        Self::build(ClientBuilder::new(url), baud_rate, timeout_ms)
     }
 
     /// Configures the maximum SMP frame size that we can send to the device.
@@ -125,6 +189,64 @@ impl MCUmgrClient {
             .map_err(err_to_pyerr)
     }
 
+    /// Configures the maximum SMP frame size by probing which frame sizes the device accepts.
+    ///
+    /// For devices that do not report their frame size, like MCUboot serial recovery.
+    /// Frames that time out count as too large, so consider a short timeout.
+    ///
+    /// ### Arguments
+    ///
+    /// * `max_frame_size` - The largest frame size to probe.
+    ///
+    /// ### Return
+    ///
+    /// The frame size that is used from now on.
+    #[pyo3(signature = (max_frame_size=4096))]
+    pub fn probe_frame_size(&self, max_frame_size: usize) -> PyResult<usize> {
+        self.get_client()?
+            .probe_frame_size(max_frame_size)
+            .map_err(err_to_pyerr)
+    }
+
+    /// Configures how many requests uploads and downloads keep in flight.
+    ///
+    /// Speeds up transfers over connections with high latency.
+    ///
+    /// Must not exceed [`MCUMGR_TRANSPORT_NETBUF_COUNT`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L28),
+    /// otherwise the device drops our requests.
+    pub fn set_window_size(&self, window_size: usize) -> PyResult<()> {
+        self.get_client()?.set_window_size(window_size);
+        Ok(())
+    }
+
+    /// Configures how many requests uploads and downloads keep in flight automatically
+    /// by reading the value of [`MCUMGR_TRANSPORT_NETBUF_COUNT`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L28)
+    /// from the device.
+    pub fn use_auto_window_size(&self) -> PyResult<()> {
+        self.get_client()?
+            .use_auto_window_size()
+            .map_err(err_to_pyerr)
+    }
+
+    /// Configures the maximum line length of serial based transports.
+    ///
+    /// Must not exceed the device's [`MCUMGR_SERIAL_MAX_FRAME`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/include/zephyr/mgmt/mcumgr/transport/serial.h#L18),
+    /// otherwise the device drops our requests.
+    pub fn set_serial_mtu(&self, mtu: usize) -> PyResult<()> {
+        self.get_client()?.set_serial_mtu(mtu).map_err(err_to_pyerr)
+    }
+
+    /// Configures the maximum line length of serial based transports automatically
+    /// by learning it from the line length of a long echo response.
+    ///
+    /// Call `use_auto_frame_size` first, otherwise the probe might be too short
+    /// to reveal large MTUs.
+    pub fn use_auto_serial_mtu(&self) -> PyResult<()> {
+        self.get_client()?
+            .use_auto_serial_mtu()
+            .map_err(err_to_pyerr)
+    }
+
     /// Changes the communication timeout.
     ///
     /// When the device does not respond to packets within the set
@@ -135,6 +257,58 @@ impl MCUmgrClient {
             .map_err(err_to_pyerr)
     }
 
+    /// Configures the automatic retransmission of requests
+    /// that timed out or received a corrupted response.
+    ///
+    /// The delay between retransmissions starts at `backoff_ms` and doubles every time.
+    /// Retransmissions are disabled by default.
+    #[pyo3(signature = (max_retries, backoff_ms=100))]
+    pub fn set_retry_policy(&self, max_retries: u32, backoff_ms: u64) -> PyResult<()> {
+        self.get_client()?.set_retry_policy(RetryPolicy {
+            max_retries,
+            backoff: Duration::from_millis(backoff_ms),
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// The number of retransmitted requests since the client was created.
+    pub fn retry_count(&self) -> PyResult<u64> {
+        Ok(self.get_client()?.retry_count())
+    }
+
+    /// Statistics of the connection since the client was created.
+    ///
+    /// Counts frames, bytes, CRC errors, skipped frames, timeouts and retries,
+    /// and keeps a histogram of round-trip times per command.
+    pub fn stats<'py>(&self, py: Python<'py>) -> PyResult<ConnectionStats> {
+        ConnectionStats::from_stats(py, self.get_client()?.stats())
+    }
+
+    /// Selects the SMP protocol version of requests, `1` or `2`.
+    ///
+    /// `None` negotiates the version automatically, which is the default:
+    /// requests start with SMP v2 and fall back to SMP v1 if the device rejects them.
+    #[pyo3(signature = (version=None))]
+    pub fn set_smp_version(&self, version: Option<u8>) -> PyResult<()> {
+        let version = match version {
+            None => None,
+            Some(1) => Some(SmpVersion::V1),
+            Some(2) => Some(SmpVersion::V2),
+            Some(_) => return Err(PyValueError::new_err("SMP version must be 1 or 2")),
+        };
+        self.get_client()?.set_smp_version(version);
+        Ok(())
+    }
+
+    /// The SMP protocol version of requests, as negotiated with the device.
+    pub fn smp_version(&self) -> PyResult<u8> {
+        Ok(match self.get_client()?.smp_version() {
+            SmpVersion::V1 => 1,
+            SmpVersion::V2 => 2,
+        })
+    }
+
     /// Checks if the device is alive and responding.
     ///
     /// Runs a simple echo with random data and checks if the response matches.
@@ -144,6 +318,18 @@ impl MCUmgrClient {
         self.get_client()?.check_connection().map_err(err_to_pyerr)
     }
 
+    /// Waits until the device responds again, for example after a reset.
+    ///
+    /// Reopens the connection as soon as the device reappears,
+    /// even under a different port name for USB serial devices.
+    ///
+    /// Raises an error if the device did not respond within `timeout_ms`.
+    pub fn wait_for_reconnect(&self, timeout_ms: u64) -> PyResult<()> {
+        self.get_client()?
+            .wait_for_reconnect(Duration::from_millis(timeout_ms))
+            .map_err(err_to_pyerr)
+    }
+
     /// High-level firmware update routine.
     ///
     /// ### Arguments
@@ -156,9 +342,12 @@ impl MCUmgrClient {
     /// * `upgrade_only` - Prevent firmware downgrades.
     /// * `progress` - A callback that receives progress updates.
     ///
+    /// A `KeyboardInterrupt` cancels the update after the current step or chunk.
+    ///
     #[pyo3(signature = (firmware, checksum=None, bootloader_type=None, skip_reboot=false, force_confirm=false, upgrade_only=false, progress=None))]
     pub fn firmware_update<'py>(
         &self,
+        py: Python<'py>,
         firmware: &Bound<'py, PyBytes>,
         checksum: Option<Sha256>,
         #[gen_stub(override_type(type_repr="typing.Optional[typing.Literal['MCUboot']]", imports=("typing")))]
@@ -188,24 +377,32 @@ impl MCUmgrClient {
             upgrade_only,
         };
 
+        let cancel = CancellationToken::new();
         let mut cb_error = None;
 
-        let res = if let Some(progress) = progress {
-            let mut cb = |msg: FirmwareUpdateStep, prog| match progress
-                .call((msg.to_string(), prog), None)
+        let mut cb = |msg: FirmwareUpdateStep, prog| {
+            if let Err(e) = interrupt_to_cancel(py, &cancel) {
+                cb_error = Some(e);
+                return true;
+            }
+            match progress
+                .as_ref()
+                .map(|progress| progress.call((msg.to_string(), prog), None))
             {
-                Ok(_) => true,
-                Err(e) => {
+                Some(Err(e)) => {
                     cb_error = Some(e);
                     false
                 }
-            };
-            self.get_client()?
-                .firmware_update(firmware_bytes, checksum, params, Some(&mut cb))
-        } else {
-            self.get_client()?
-                .firmware_update(firmware_bytes, checksum, params, None)
+                _ => true,
+            }
         };
+        let res = self.get_client()?.firmware_update(
+            firmware_bytes,
+            checksum,
+            params,
+            Some(&mut cb),
+            Some(&cancel),
+        );
 
         if let Some(cb_error) = cb_error {
             return Err(cb_error);
@@ -231,16 +428,39 @@ impl MCUmgrClient {
     /// ### Return
     ///
     /// A map of task names with their respective statistics
-    fn os_task_statistics(&self) -> PyResult<HashMap<String, TaskStatistics>> {
-        self.get_client()?
+    fn os_task_statistics<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<HashMap<String, TaskStatistics>> {
+        let tasks = self
+            .get_client()?
             .os_task_statistics()
-            .map(|tasks| {
-                tasks
-                    .into_iter()
-                    .map(|(name, stats)| (name, stats.into()))
-                    .collect()
-            })
-            .map_err(err_to_pyerr)
+            .map_err(err_to_pyerr)?;
+
+        tasks
+            .into_iter()
+            .map(|(name, stats)| Ok((name, TaskStatistics::from_response(py, stats)?)))
+            .collect()
+    }
+
+    /// Queries memory pool statistics
+    ///
+    /// ### Return
+    ///
+    /// A map of memory pool names with their respective statistics
+    fn os_memory_pool_statistics<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<HashMap<String, MemoryPoolStatistics>> {
+        let pools = self
+            .get_client()?
+            .os_memory_pool_statistics()
+            .map_err(err_to_pyerr)?;
+
+        pools
+            .into_iter()
+            .map(|(name, stats)| Ok((name, MemoryPoolStatistics::from_response(py, stats)?)))
+            .collect()
     }
 
     /// Sets the RTC of the device to the given datetime.
@@ -285,11 +505,13 @@ impl MCUmgrClient {
     }
 
     /// Fetch parameters from the MCUmgr library
-    pub fn os_mcumgr_parameters(&self) -> PyResult<MCUmgrParameters> {
-        self.get_client()?
+    pub fn os_mcumgr_parameters<'py>(&self, py: Python<'py>) -> PyResult<MCUmgrParameters> {
+        let parameters = self
+            .get_client()?
             .os_mcumgr_parameters()
-            .map(Into::into)
-            .map_err(err_to_pyerr)
+            .map_err(err_to_pyerr)?;
+
+        MCUmgrParameters::from_response(py, parameters)
     }
 
     /// Fetch information on the running image
@@ -323,10 +545,10 @@ impl MCUmgrClient {
     pub fn image_get_state<'py>(&self, py: Python<'py>) -> PyResult<Vec<ImageState>> {
         let images = self.get_client()?.image_get_state().map_err(err_to_pyerr)?;
 
-        Ok(images
+        images
             .into_iter()
             .map(|val| ImageState::from_response(py, val))
-            .collect())
+            .collect()
     }
 
     /// Modify the current image state and return the new state
@@ -357,10 +579,10 @@ impl MCUmgrClient {
             .image_set_state(hash.map(|val| val.0), confirm)
             .map_err(err_to_pyerr)?;
 
-        Ok(images
+        images
             .into_iter()
             .map(|val| ImageState::from_response(py, val))
-            .collect())
+            .collect()
     }
 
     /// Upload a firmware image to an image slot.
@@ -374,6 +596,8 @@ impl MCUmgrClient {
     /// * `progress` - A callable object that takes (transmitted, total) values as parameters.
     ///                Any return value is ignored. Raising an exception aborts the operation.
     ///
+    /// A `KeyboardInterrupt` cancels the operation after the current chunk.
+    ///
     /// ### Performance
     ///
     /// Uploading files with Zephyr's default parameters is slow.
@@ -384,6 +608,7 @@ impl MCUmgrClient {
     #[pyo3(signature = (data, image=None, checksum=None, upgrade_only=false, progress=None))]
     pub fn image_upload<'py>(
         &self,
+        py: Python<'py>,
         data: &Bound<'py, PyBytes>,
         image: Option<u32>,
         checksum: Option<Sha256>,
@@ -397,19 +622,17 @@ impl MCUmgrClient {
 
         let checksum = checksum.map(|val| val.0);
 
-        let res = if let Some(progress) = progress {
-            let mut cb = |current, total| match progress.call((current, total), None) {
-                Ok(_) => true,
-                Err(e) => {
-                    cb_error = Some(e);
-                    false
-                }
-            };
-            self.get_client()?
-                .image_upload(bytes, image, checksum, upgrade_only, Some(&mut cb))
-        } else {
-            self.get_client()?
-                .image_upload(bytes, image, checksum, upgrade_only, None)
+        let cancel = CancellationToken::new();
+        let res = {
+            let mut cb = progress_callback(py, &cancel, progress.as_ref(), &mut cb_error);
+            self.get_client()?.image_upload(
+                bytes,
+                image,
+                checksum,
+                upgrade_only,
+                Some(&mut cb),
+                Some(&cancel),
+            )
         };
 
         if let Some(cb_error) = cb_error {
@@ -440,6 +663,87 @@ impl MCUmgrClient {
             .collect::<PyResult<_>>()
     }
 
+    /// List the statistics groups of the device.
+    pub fn stat_list(&self) -> PyResult<Vec<String>> {
+        self.get_client()?.stat_list().map_err(err_to_pyerr)
+    }
+
+    /// Read the statistics of a group.
+    ///
+    /// ### Arguments
+    ///
+    /// * `group` - The name of the statistics group, as returned by `stat_list`
+    ///
+    /// ### Return
+    ///
+    /// The values of the statistics, by name
+    pub fn stat_read(&self, group: &str) -> PyResult<BTreeMap<String, u64>> {
+        self.get_client()?.stat_read(group).map_err(err_to_pyerr)
+    }
+
+    /// Read the value of a setting.
+    ///
+    /// ### Arguments
+    ///
+    /// * `name` - The name of the setting
+    /// * `max_size` - The maximum size of the value to read. Device default if None.
+    ///
+    /// ### Return
+    ///
+    /// The raw value of the setting
+    #[pyo3(signature = (name, max_size=None))]
+    pub fn settings_read<'py>(
+        &self,
+        py: Python<'py>,
+        name: &str,
+        max_size: Option<u32>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let data = self
+            .get_client()?
+            .settings_read(name, max_size)
+            .map_err(err_to_pyerr)?;
+
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Write the value of a setting.
+    ///
+    /// The new value is only applied after `settings_commit`,
+    /// and only persisted after `settings_save`.
+    ///
+    /// ### Arguments
+    ///
+    /// * `name` - The name of the setting
+    /// * `value` - The raw value of the setting
+    pub fn settings_write<'py>(&self, name: &str, value: &Bound<'py, PyBytes>) -> PyResult<()> {
+        let bytes: &[u8] = value.extract()?;
+        self.get_client()?
+            .settings_write(name, bytes)
+            .map_err(err_to_pyerr)
+    }
+
+    /// Delete a setting from the persistent storage.
+    pub fn settings_delete(&self, name: &str) -> PyResult<()> {
+        self.get_client()?
+            .settings_delete(name)
+            .map_err(err_to_pyerr)
+    }
+
+    /// Apply the written settings.
+    pub fn settings_commit(&self) -> PyResult<()> {
+        self.get_client()?.settings_commit().map_err(err_to_pyerr)
+    }
+
+    /// Load the settings from the persistent storage.
+    pub fn settings_load(&self) -> PyResult<()> {
+        self.get_client()?.settings_load().map_err(err_to_pyerr)
+    }
+
+    /// Save the settings to the persistent storage.
+    pub fn settings_save(&self) -> PyResult<()> {
+        self.get_client()?.settings_save().map_err(err_to_pyerr)
+    }
+
     /// Load a file from the device.
     ///
     /// ### Arguments
@@ -448,6 +752,8 @@ impl MCUmgrClient {
     /// * `progress` - A callable object that takes (transmitted, total) values as parameters.
     ///                Any return value is ignored. Raising an exception aborts the operation.
     ///
+    /// A `KeyboardInterrupt` cancels the operation after the current chunk.
+    ///
     /// ### Return
     ///
     /// The file content
@@ -469,18 +775,11 @@ impl MCUmgrClient {
 
         let mut cb_error = None;
 
-        let res = if let Some(progress) = progress {
-            let mut cb = |current, total| match progress.call((current, total), None) {
-                Ok(_) => true,
-                Err(e) => {
-                    cb_error = Some(e);
-                    false
-                }
-            };
+        let cancel = CancellationToken::new();
+        let res = {
+            let mut cb = progress_callback(py, &cancel, progress.as_ref(), &mut cb_error);
             self.get_client()?
-                .fs_file_download(name, &mut data, Some(&mut cb))
-        } else {
-            self.get_client()?.fs_file_download(name, &mut data, None)
+                .fs_file_download(name, &mut data, Some(&mut cb), Some(&cancel))
         };
 
         if let Some(cb_error) = cb_error {
@@ -500,6 +799,8 @@ impl MCUmgrClient {
     /// * `progress` - A callable object that takes (transmitted, total) values as parameters.
     ///                Any return value is ignored. Raising an exception aborts the operation.
     ///
+    /// A `KeyboardInterrupt` cancels the operation after the current chunk.
+    ///
     /// ### Performance
     ///
     /// Uploading files with Zephyr's default parameters is slow.
@@ -509,6 +810,7 @@ impl MCUmgrClient {
     #[pyo3(signature = (name, data, progress=None))]
     pub fn fs_file_upload<'py>(
         &self,
+        py: Python<'py>,
         name: &str,
         data: &Bound<'py, PyBytes>,
         #[gen_stub(override_type(type_repr="typing.Optional[collections.abc.Callable[[builtins.int, builtins.int], None]]", imports=("builtins", "collections.abc", "typing")))]
@@ -518,19 +820,16 @@ impl MCUmgrClient {
 
         let mut cb_error = None;
 
-        let res = if let Some(progress) = progress {
-            let mut cb = |current, total| match progress.call((current, total), None) {
-                Ok(_) => true,
-                Err(e) => {
-                    cb_error = Some(e);
-                    false
-                }
-            };
-            self.get_client()?
-                .fs_file_upload(name, bytes, bytes.len() as u64, Some(&mut cb))
-        } else {
-            self.get_client()?
-                .fs_file_upload(name, bytes, bytes.len() as u64, None)
+        let cancel = CancellationToken::new();
+        let res = {
+            let mut cb = progress_callback(py, &cancel, progress.as_ref(), &mut cb_error);
+            self.get_client()?.fs_file_upload(
+                name,
+                bytes,
+                bytes.len() as u64,
+                Some(&mut cb),
+                Some(&cancel),
+            )
         };
 
         if let Some(cb_error) = cb_error {
@@ -541,11 +840,13 @@ impl MCUmgrClient {
     }
 
     /// Queries the file status
-    pub fn fs_file_status(&self, name: &str) -> PyResult<FileStatus> {
-        self.get_client()?
+    pub fn fs_file_status<'py>(&self, py: Python<'py>, name: &str) -> PyResult<FileStatus> {
+        let status = self
+            .get_client()?
             .fs_file_status(name)
-            .map(Into::into)
-            .map_err(err_to_pyerr)
+            .map_err(err_to_pyerr)?;
+
+        FileStatus::from_response(py, status)
     }
 
     /// Computes the hash/checksum of a file
@@ -568,23 +869,28 @@ impl MCUmgrClient {
         offset: u64,
         length: Option<u64>,
     ) -> PyResult<FileChecksum> {
-        self.get_client()?
+        let checksum = self
+            .get_client()?
             .fs_file_checksum(name, algorithm, offset, length)
-            .map(|val| FileChecksum::from_response(py, val))
-            .map_err(err_to_pyerr)
+            .map_err(err_to_pyerr)?;
+
+        FileChecksum::from_response(py, checksum)
     }
 
     /// Queries which hash/checksum algorithms are available on the target
-    pub fn fs_supported_checksum_types(&self) -> PyResult<HashMap<String, FileChecksumProperties>> {
-        self.get_client()?
+    pub fn fs_supported_checksum_types<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<HashMap<String, FileChecksumProperties>> {
+        let types = self
+            .get_client()?
             .fs_supported_checksum_types()
-            .map(|val| {
-                let iter = val
-                    .into_iter()
-                    .map(|(key, value)| (key, FileChecksumProperties::from(value)));
-                iter.collect()
-            })
-            .map_err(err_to_pyerr)
+            .map_err(err_to_pyerr)?;
+
+        types
+            .into_iter()
+            .map(|(key, value)| Ok((key, FileChecksumProperties::from_response(py, value)?)))
+            .collect()
     }
 
     /// Close all device files MCUmgr has currently open
@@ -619,6 +925,58 @@ impl MCUmgrClient {
         Ok(data)
     }
 
+    /// Query the number of management groups the device supports.
+    pub fn enum_group_count(&self) -> PyResult<u16> {
+        self.get_client()?.enum_group_count().map_err(err_to_pyerr)
+    }
+
+    /// Query the IDs of the management groups the device supports.
+    pub fn enum_group_list(&self) -> PyResult<Vec<u16>> {
+        self.get_client()?.enum_group_list().map_err(err_to_pyerr)
+    }
+
+    /// Query the ID of a single supported management group.
+    ///
+    /// ### Arguments
+    ///
+    /// * `index` - The index of the group, up to `enum_group_count`
+    ///
+    /// ### Return
+    ///
+    /// A tuple of (group ID, whether it is the last group)
+    pub fn enum_group_id(&self, index: u16) -> PyResult<(u16, bool)> {
+        self.get_client()?
+            .enum_group_id(index)
+            .map_err(err_to_pyerr)
+    }
+
+    /// Query the names and handler counts of supported management groups.
+    ///
+    /// ### Arguments
+    ///
+    /// * `groups` - The IDs of the groups to query. All groups if empty or `None`.
+    #[pyo3(signature = (groups=None))]
+    pub fn enum_group_details<'py>(
+        &self,
+        py: Python<'py>,
+        groups: Option<Vec<u16>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.get_client()?
+            .enum_group_details(&groups.unwrap_or_default())
+            .map_err(err_to_pyerr)
+            .map(|details| serde_pyobject::to_pyobject(py, &details))?
+            .map_err(Into::into)
+    }
+
+    /// Determine which management groups the device supports.
+    ///
+    /// Uses the enumeration group, if available. Otherwise, probes the groups known
+    /// to this library with requests that have no side effects.
+    pub fn capabilities<'py>(&self, py: Python<'py>) -> PyResult<DeviceCapabilities> {
+        let capabilities = self.get_client()?.capabilities().map_err(err_to_pyerr)?;
+        DeviceCapabilities::from_capabilities(py, capabilities)
+    }
+
     /// Erase the `storage_partition` flash partition.
     pub fn zephyr_erase_storage(&self) -> PyResult<()> {
         self.get_client()?
@@ -700,6 +1058,12 @@ mod mcumgr_toolkit {
     #[pymodule_export]
     use super::MCUmgrClient;
     #[pymodule_export]
+    use super::return_types::CommandLatency;
+    #[pymodule_export]
+    use super::return_types::ConnectionStats;
+    #[pymodule_export]
+    use super::return_types::DeviceCapabilities;
+    #[pymodule_export]
     use super::return_types::FileChecksum;
     #[pymodule_export]
     use super::return_types::FileChecksumDataFormat;
@@ -708,10 +1072,14 @@ mod mcumgr_toolkit {
     #[pymodule_export]
     use super::return_types::FileStatus;
     #[pymodule_export]
+    use super::return_types::GroupCapabilities;
+    #[pymodule_export]
     use super::return_types::ImageState;
     #[pymodule_export]
     use super::return_types::MCUmgrParameters;
     #[pymodule_export]
+    use super::return_types::MemoryPoolStatistics;
+    #[pymodule_export]
     use super::return_types::SlotInfoImage;
     #[pymodule_export]
     use super::return_types::SlotInfoImageSlot;
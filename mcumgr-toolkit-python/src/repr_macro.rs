@@ -1,8 +1,8 @@
 use pyo3::{
     Py, Python,
-    types::{PyBytes, PyBytesMethods},
+    types::{PyBytes, PyBytesMethods, PyDict},
 };
-use serde::Serializer;
+use serde::{Serialize, Serializer, ser::Error};
 
 /// Makes the struct `print`able by converting it
 /// to a python dict and then printing that
@@ -49,4 +49,16 @@ where
     }
 }
 
+pub fn serialize_pydict<S>(pydict: &Py<PyDict>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    Python::attach(|py| {
+        let value: ciborium::Value =
+            serde_pyobject::from_pyobject(pydict.bind(py).clone().into_any())
+                .map_err(S::Error::custom)?;
+        value.serialize(serializer)
+    })
+}
+
 pub(crate) use generate_repr_from_serialize;
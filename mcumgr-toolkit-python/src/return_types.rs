@@ -1,11 +1,21 @@
-use pyo3::{PyClass, prelude::*, types::PyBytes};
+use pyo3::{
+    PyClass,
+    prelude::*,
+    types::{PyBytes, PyDict},
+};
 use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyclass_enum};
 
-use ::mcumgr_toolkit::commands;
+use ::mcumgr_toolkit::commands::{self, ExtraFields};
 use serde::{Serialize, ser::SerializeSeq};
 
 use crate::repr_macro::generate_repr_from_serialize;
 
+fn extra_to_pydict(py: Python<'_>, extra: &ExtraFields) -> PyResult<Py<PyDict>> {
+    Ok(serde_pyobject::to_pyobject(py, extra)?
+        .cast_into::<PyDict>()?
+        .unbind())
+}
+
 /// Return value of `MCUmgrClient.fs_file_status`.
 #[gen_stub_pyclass]
 #[pyclass(frozen)]
@@ -14,11 +24,21 @@ pub struct FileStatus {
     /// length of file (in bytes)
     #[pyo3(get)]
     pub length: u64,
+    /// response fields that are not known to this library yet
+    #[pyo3(get)]
+    #[serde(serialize_with = "crate::repr_macro::serialize_pydict")]
+    pub extra: Py<PyDict>,
 }
 generate_repr_from_serialize!(FileStatus);
-impl From<commands::fs::FileStatusResponse> for FileStatus {
-    fn from(value: commands::fs::FileStatusResponse) -> Self {
-        Self { length: value.len }
+impl FileStatus {
+    pub(crate) fn from_response<'py>(
+        py: Python<'py>,
+        value: commands::fs::FileStatusResponse,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            length: value.len,
+            extra: extra_to_pydict(py, &value.extra)?,
+        })
     }
 }
 
@@ -33,14 +53,22 @@ pub struct MCUmgrParameters {
     /// Number of SMP buffers supported
     #[pyo3(get)]
     pub buf_count: u32,
+    /// response fields that are not known to this library yet
+    #[pyo3(get)]
+    #[serde(serialize_with = "crate::repr_macro::serialize_pydict")]
+    pub extra: Py<PyDict>,
 }
 generate_repr_from_serialize!(MCUmgrParameters);
-impl From<commands::os::MCUmgrParametersResponse> for MCUmgrParameters {
-    fn from(value: commands::os::MCUmgrParametersResponse) -> Self {
-        Self {
+impl MCUmgrParameters {
+    pub(crate) fn from_response<'py>(
+        py: Python<'py>,
+        value: commands::os::MCUmgrParametersResponse,
+    ) -> PyResult<Self> {
+        Ok(Self {
             buf_size: value.buf_size,
             buf_count: value.buf_count,
-        }
+            extra: extra_to_pydict(py, &value.extra)?,
+        })
     }
 }
 
@@ -62,6 +90,10 @@ pub struct FileChecksum {
     #[pyo3(get)]
     #[serde(serialize_with = "crate::repr_macro::serialize_pybytes_as_hex")]
     pub output: Py<PyBytes>,
+    /// response fields that are not known to this library yet
+    #[pyo3(get)]
+    #[serde(serialize_with = "crate::repr_macro::serialize_pydict")]
+    pub extra: Py<PyDict>,
 }
 generate_repr_from_serialize!(FileChecksum);
 
@@ -69,19 +101,20 @@ impl FileChecksum {
     pub(crate) fn from_response<'py>(
         py: Python<'py>,
         value: commands::fs::FileChecksumResponse,
-    ) -> Self {
+    ) -> PyResult<Self> {
         let output = match value.output {
             commands::fs::FileChecksumData::Hash(data) => PyBytes::new(py, &data).unbind(),
             commands::fs::FileChecksumData::Checksum(data) => {
                 PyBytes::new(py, &data.to_be_bytes()).unbind()
             }
         };
-        Self {
+        Ok(Self {
             r#type: value.r#type,
             offset: value.off,
             length: value.len,
             output,
-        }
+            extra: extra_to_pydict(py, &value.extra)?,
+        })
     }
 }
 
@@ -107,12 +140,19 @@ pub struct FileChecksumProperties {
     /// size (in bytes) of output hash/checksum response
     #[pyo3(get)]
     pub size: u32,
+    /// response fields that are not known to this library yet
+    #[pyo3(get)]
+    #[serde(serialize_with = "crate::repr_macro::serialize_pydict")]
+    pub extra: Py<PyDict>,
 }
 generate_repr_from_serialize!(FileChecksumProperties);
 
-impl From<commands::fs::FileChecksumProperties> for FileChecksumProperties {
-    fn from(value: commands::fs::FileChecksumProperties) -> Self {
-        Self {
+impl FileChecksumProperties {
+    pub(crate) fn from_response<'py>(
+        py: Python<'py>,
+        value: commands::fs::FileChecksumProperties,
+    ) -> PyResult<Self> {
+        Ok(Self {
             format: match value.format {
                 commands::fs::FileChecksumDataFormat::Numerical => {
                     FileChecksumDataFormat::Numerical
@@ -122,7 +162,8 @@ impl From<commands::fs::FileChecksumProperties> for FileChecksumProperties {
                 }
             },
             size: value.size,
-        }
+            extra: extra_to_pydict(py, &value.extra)?,
+        })
     }
 }
 
@@ -152,12 +193,19 @@ pub struct TaskStatistics {
     /// task’s/thread’s runtime in “ticks”
     #[pyo3(get)]
     pub runtime: Option<u64>,
+    /// response fields that are not known to this library yet
+    #[pyo3(get)]
+    #[serde(serialize_with = "crate::repr_macro::serialize_pydict")]
+    pub extra: Py<PyDict>,
 }
 generate_repr_from_serialize!(TaskStatistics);
 
-impl From<commands::os::TaskStatisticsEntry> for TaskStatistics {
-    fn from(value: commands::os::TaskStatisticsEntry) -> Self {
-        Self {
+impl TaskStatistics {
+    pub(crate) fn from_response<'py>(
+        py: Python<'py>,
+        value: commands::os::TaskStatisticsEntry,
+    ) -> PyResult<Self> {
+        Ok(Self {
             prio: value.prio,
             tid: value.tid,
             state: value.state,
@@ -165,7 +213,47 @@ impl From<commands::os::TaskStatisticsEntry> for TaskStatistics {
             stksiz: value.stksiz,
             cswcnt: value.cswcnt,
             runtime: value.runtime,
-        }
+            extra: extra_to_pydict(py, &value.extra)?,
+        })
+    }
+}
+
+/// Statistics of a memory pool/heap
+#[gen_stub_pyclass]
+#[pyclass(frozen)]
+#[derive(Serialize)]
+pub struct MemoryPoolStatistics {
+    /// size of the memory blocks in the pool
+    #[pyo3(get)]
+    pub blksiz: u64,
+    /// number of blocks in the pool
+    #[pyo3(get)]
+    pub nblks: u64,
+    /// number of free blocks
+    #[pyo3(get)]
+    pub nfree: u64,
+    /// lowest number of free blocks the pool ever had
+    #[pyo3(get)]
+    pub min: u64,
+    /// response fields that are not known to this library yet
+    #[pyo3(get)]
+    #[serde(serialize_with = "crate::repr_macro::serialize_pydict")]
+    pub extra: Py<PyDict>,
+}
+generate_repr_from_serialize!(MemoryPoolStatistics);
+
+impl MemoryPoolStatistics {
+    pub(crate) fn from_response<'py>(
+        py: Python<'py>,
+        value: commands::os::MemoryPoolStatisticsEntry,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            blksiz: value.blksiz,
+            nblks: value.nblks,
+            nfree: value.nfree,
+            min: value.min,
+            extra: extra_to_pydict(py, &value.extra)?,
+        })
     }
 }
 
@@ -206,12 +294,19 @@ pub struct ImageState {
     /// true if image is to stay in primary slot after the next boot
     #[pyo3(get)]
     pub permanent: bool,
+    /// response fields that are not known to this library yet
+    #[pyo3(get)]
+    #[serde(serialize_with = "crate::repr_macro::serialize_pydict")]
+    pub extra: Py<PyDict>,
 }
 generate_repr_from_serialize!(ImageState);
 
 impl ImageState {
-    pub(crate) fn from_response<'py>(py: Python<'py>, value: commands::image::ImageState) -> Self {
-        Self {
+    pub(crate) fn from_response<'py>(
+        py: Python<'py>,
+        value: commands::image::ImageState,
+    ) -> PyResult<Self> {
+        Ok(Self {
             image: value.image,
             slot: value.slot,
             version: value.version,
@@ -221,7 +316,8 @@ impl ImageState {
             confirmed: value.confirmed,
             active: value.active,
             permanent: value.permanent,
-        }
+            extra: extra_to_pydict(py, &value.extra)?,
+        })
     }
 }
 
@@ -255,6 +351,10 @@ pub struct SlotInfoImage {
     /// maximum size of an application that can be uploaded to that image number
     #[pyo3(get)]
     pub max_image_size: Option<u64>,
+    /// response fields that are not known to this library yet
+    #[pyo3(get)]
+    #[serde(serialize_with = "crate::repr_macro::serialize_pydict")]
+    pub extra: Py<PyDict>,
 }
 generate_repr_from_serialize!(SlotInfoImage);
 
@@ -272,6 +372,10 @@ pub struct SlotInfoImageSlot {
     /// specifies the image ID that can be used by external tools to upload an image to that slot
     #[pyo3(get)]
     pub upload_image_id: Option<u32>,
+    /// response fields that are not known to this library yet
+    #[pyo3(get)]
+    #[serde(serialize_with = "crate::repr_macro::serialize_pydict")]
+    pub extra: Py<PyDict>,
 }
 generate_repr_from_serialize!(SlotInfoImageSlot);
 
@@ -292,11 +396,186 @@ impl SlotInfoImage {
                             slot: slot.slot,
                             size: slot.size,
                             upload_image_id: slot.upload_image_id,
+                            extra: extra_to_pydict(py, &slot.extra)?,
                         },
                     )
                 })
                 .collect::<PyResult<_>>()?,
             max_image_size: value.max_image_size,
+            extra: extra_to_pydict(py, &value.extra)?,
+        })
+    }
+}
+
+/// Return value of `MCUmgrClient.stats`.
+///
+/// Byte counts include the SMP header, but not the framing of the transport.
+#[gen_stub_pyclass]
+#[pyclass(frozen)]
+#[derive(Serialize)]
+pub struct ConnectionStats {
+    /// number of SMP frames sent
+    #[pyo3(get)]
+    pub frames_sent: u64,
+    /// number of bytes sent
+    #[pyo3(get)]
+    pub bytes_sent: u64,
+    /// number of SMP frames received, including skipped ones
+    #[pyo3(get)]
+    pub frames_received: u64,
+    /// number of bytes received
+    #[pyo3(get)]
+    pub bytes_received: u64,
+    /// number of received frames with a CRC error
+    #[pyo3(get)]
+    pub crc_errors: u64,
+    /// number of received frames that did not belong to a request in flight
+    #[pyo3(get)]
+    pub skipped_frames: u64,
+    /// number of times the device did not respond in time
+    #[pyo3(get)]
+    pub timeouts: u64,
+    /// number of retransmitted requests
+    #[pyo3(get)]
+    pub retries: u64,
+    /// round-trip times per command, ordered by group and command ID
+    #[pyo3(get)]
+    #[serde(serialize_with = "serialize_pyvec")]
+    pub latencies: Vec<Py<CommandLatency>>,
+}
+generate_repr_from_serialize!(ConnectionStats);
+
+/// Round-trip times of one command
+#[gen_stub_pyclass]
+#[pyclass(frozen)]
+#[derive(Serialize)]
+pub struct CommandLatency {
+    /// group ID of the command
+    #[pyo3(get)]
+    pub group_id: u16,
+    /// command ID
+    #[pyo3(get)]
+    pub command_id: u8,
+    /// number of successful round trips
+    #[pyo3(get)]
+    pub count: u64,
+    /// fastest round trip (in milliseconds)
+    #[pyo3(get)]
+    pub min_ms: f64,
+    /// average round trip (in milliseconds)
+    #[pyo3(get)]
+    pub mean_ms: Option<f64>,
+    /// slowest round trip (in milliseconds)
+    #[pyo3(get)]
+    pub max_ms: f64,
+    /// number of round trips per histogram bucket, see `bucket_bounds_ms`;
+    /// the last entry counts the round trips slower than the last bound
+    #[pyo3(get)]
+    pub bucket_counts: Vec<u64>,
+    /// upper bounds of the histogram buckets (in milliseconds)
+    #[pyo3(get)]
+    pub bucket_bounds_ms: Vec<f64>,
+}
+generate_repr_from_serialize!(CommandLatency);
+
+impl ConnectionStats {
+    pub(crate) fn from_stats<'py>(
+        py: Python<'py>,
+        value: ::mcumgr_toolkit::connection::ConnectionStats,
+    ) -> PyResult<Self> {
+        let millis = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+
+        Ok(Self {
+            frames_sent: value.frames_sent,
+            bytes_sent: value.bytes_sent,
+            frames_received: value.frames_received,
+            bytes_received: value.bytes_received,
+            crc_errors: value.crc_errors,
+            skipped_frames: value.skipped_frames,
+            timeouts: value.timeouts,
+            retries: value.retries,
+            latencies: value
+                .latencies
+                .into_iter()
+                .map(|latency| {
+                    let histogram = latency.histogram;
+                    Py::new(
+                        py,
+                        CommandLatency {
+                            group_id: latency.group_id,
+                            command_id: latency.command_id,
+                            count: histogram.count,
+                            min_ms: millis(histogram.min),
+                            mean_ms: histogram.mean().map(millis),
+                            max_ms: millis(histogram.max),
+                            bucket_counts: histogram.buckets.to_vec(),
+                            bucket_bounds_ms: ::mcumgr_toolkit::connection::LATENCY_BUCKETS
+                                .iter()
+                                .copied()
+                                .map(millis)
+                                .collect(),
+                        },
+                    )
+                })
+                .collect::<PyResult<_>>()?,
+        })
+    }
+}
+
+/// Return value of `MCUmgrClient.capabilities`.
+#[gen_stub_pyclass]
+#[pyclass(frozen)]
+#[derive(Serialize)]
+pub struct DeviceCapabilities {
+    /// whether the groups were probed, because the device lacks the enumeration group;
+    /// probing only finds the groups known to this library, without names or handler counts
+    #[pyo3(get)]
+    pub probed: bool,
+    /// the supported groups, ordered by ID
+    #[pyo3(get)]
+    #[serde(serialize_with = "serialize_pyvec")]
+    pub groups: Vec<Py<GroupCapabilities>>,
+}
+generate_repr_from_serialize!(DeviceCapabilities);
+
+/// A management group that the device supports
+#[gen_stub_pyclass]
+#[pyclass(frozen)]
+#[derive(Serialize)]
+pub struct GroupCapabilities {
+    /// ID of the group
+    #[pyo3(get)]
+    pub group_id: u16,
+    /// name of the group, if reported by the device
+    #[pyo3(get)]
+    pub name: Option<String>,
+    /// number of command handlers of the group, if reported by the device
+    #[pyo3(get)]
+    pub handlers: Option<u32>,
+}
+generate_repr_from_serialize!(GroupCapabilities);
+
+impl DeviceCapabilities {
+    pub(crate) fn from_capabilities<'py>(
+        py: Python<'py>,
+        value: ::mcumgr_toolkit::client::DeviceCapabilities,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            probed: value.probed,
+            groups: value
+                .groups
+                .into_iter()
+                .map(|group| {
+                    Py::new(
+                        py,
+                        GroupCapabilities {
+                            group_id: group.group_id,
+                            name: group.name,
+                            handlers: group.handlers,
+                        },
+                    )
+                })
+                .collect::<PyResult<_>>()?,
         })
     }
 }
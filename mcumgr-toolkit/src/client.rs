@@ -1,15 +1,40 @@
 /// High-level firmware update routine
 mod firmware_update;
 
+#[cfg(feature = "tokio")]
+pub use firmware_update::AsyncFirmwareUpdateProgressCallback;
 pub use firmware_update::{
     FirmwareUpdateError, FirmwareUpdateParams, FirmwareUpdateProgressCallback, FirmwareUpdateStep,
 };
 
+/// Creating clients from connection strings
+mod builder;
+
+/// Finding the largest frame size a device accepts
+mod frame_size_probe;
+use frame_size_probe::FrameSizeSearch;
+
+pub use builder::{
+    ClientBuilder, ClientBuilderError, ConnectionUrl, ConnectionUrlError, DEFAULT_BAUD_RATE,
+    DEFAULT_TIMEOUT, MCUMGR_CONNECTION_ENV,
+};
+
+/// Finding the management groups a device supports
+mod capabilities;
+pub use capabilities::{DeviceCapabilities, GroupCapabilities};
+
+/// Async client
+#[cfg(feature = "tokio")]
+mod async_client;
+#[cfg(feature = "tokio")]
+pub use async_client::AsyncMCUmgrClient;
+
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, VecDeque},
     io::{self, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
     sync::atomic::AtomicUsize,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use miette::Diagnostic;
@@ -21,10 +46,19 @@ use thiserror::Error;
 use crate::{
     bootloader::BootloaderInfo,
     commands::{
-        self, fs::file_upload_max_data_chunk_size, image::image_upload_max_data_chunk_size,
+        self, McuMgrCommand, fs::file_upload_max_data_chunk_size,
+        image::image_upload_max_data_chunk_size,
+    },
+    connection::{
+        CancellationToken, CommandInterceptor, Connection, ConnectionStats, ExecuteError, Reopen,
+        RetryPolicy, TransportWrapper,
+    },
+    transport::{
+        ConsoleHandler, SmpVersion, Transport,
+        rfc2217::{Rfc2217Port, Rfc2217Settings},
+        serial::{ConfigurableTimeout, SerialMtuTooSmall, SerialTransport, SerialTransportConfig},
+        udp::UdpTransport,
     },
-    connection::{Connection, ExecuteError},
-    transport::serial::{ConfigurableTimeout, SerialTransport},
 };
 
 /// The default SMP frame size of Zephyr.
@@ -32,12 +66,21 @@ use crate::{
 /// Matches Zephyr default value of [MCUMGR_TRANSPORT_NETBUF_SIZE](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L40).
 const ZEPHYR_DEFAULT_SMP_FRAME_SIZE: usize = 384;
 
+/// Size of an echo request or response without its message.
+///
+/// Leaves enough room for the SMP header and the CBOR map.
+const ECHO_FRAME_OVERHEAD: usize = 16;
+
+/// How often [`MCUmgrClient::wait_for_reconnect`] checks whether the device is back.
+const RECONNECT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// A high-level client for Zephyr's MCUmgr SMP protocol.
 ///
 /// This struct is the central entry point of this crate.
 pub struct MCUmgrClient {
     connection: Connection,
     smp_frame_size: AtomicUsize,
+    window_size: AtomicUsize,
 }
 
 /// Possible error values of [`MCUmgrClient`].
@@ -46,7 +89,14 @@ pub enum MCUmgrClientError {
     /// The command failed in the SMP protocol layer.
     #[error("Command execution failed")]
     #[diagnostic(code(mcumgr_toolkit::client::execute))]
-    ExecuteError(#[from] ExecuteError),
+    ExecuteError(ExecuteError),
+    /// The device disconnected, for example because of a reset, and could not be reopened yet.
+    ///
+    /// Clients that know how to reopen their transport do so with the next command;
+    /// see [`MCUmgrClient::wait_for_reconnect`].
+    #[error("Device disconnected")]
+    #[diagnostic(code(mcumgr_toolkit::client::device_disconnected))]
+    DeviceDisconnected(#[source] io::Error),
     /// A device response contained an unexpected offset value.
     #[error("Received an unexpected offset value")]
     #[diagnostic(code(mcumgr_toolkit::client::unexpected_offset))]
@@ -87,6 +137,28 @@ pub enum MCUmgrClientError {
     #[error("Failed to set the device timeout")]
     #[diagnostic(code(mcumgr_toolkit::client::set_timeout))]
     SetTimeoutFailed(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// Setting the transport MTU failed
+    #[error("Failed to set the transport MTU")]
+    #[diagnostic(code(mcumgr_toolkit::client::set_mtu))]
+    SetMtuFailed(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// Setting the console handler failed
+    #[error("Failed to set the console handler")]
+    #[diagnostic(code(mcumgr_toolkit::client::set_console_handler))]
+    SetConsoleHandlerFailed(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// The operation was cancelled through its [`CancellationToken`].
+    #[error("Operation was cancelled")]
+    #[diagnostic(code(mcumgr_toolkit::client::cancelled))]
+    Cancelled,
+}
+
+impl From<ExecuteError> for MCUmgrClientError {
+    fn from(err: ExecuteError) -> Self {
+        match err {
+            ExecuteError::DeviceDisconnected(e) => Self::DeviceDisconnected(e),
+            ExecuteError::Cancelled => Self::Cancelled,
+            err => Self::ExecuteError(err),
+        }
+    }
 }
 
 impl MCUmgrClientError {
@@ -213,9 +285,114 @@ pub enum UsbSerialError {
     RegexError(#[from] regex::Error),
 }
 
+/// Connects to the first reachable address of a TCP serial bridge.
+fn connect_tcp(addrs: &[SocketAddr], timeout: Duration) -> io::Result<TcpStream> {
+    let mut last_error = None;
+
+    for addr in addrs {
+        let mut stream = match TcpStream::connect_timeout(addr, timeout) {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::debug!("Unable to connect to {addr}: {e}");
+                last_error = Some(e);
+                continue;
+            }
+        };
+
+        stream.set_nodelay(true)?;
+        ConfigurableTimeout::set_timeout(&mut stream, timeout).map_err(io::Error::other)?;
+
+        return Ok(stream);
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Could not resolve to any address",
+        )
+    }))
+}
+
+/// Finds the name of the USB serial port that matches the given identifier,
+/// see [`MCUmgrClient::new_from_usb_serial`].
+fn find_usb_serial_port(identifier: &str) -> Result<String, UsbSerialError> {
+    let ports = serialport::available_ports()?
+        .into_iter()
+        .filter_map(|port| {
+            if let serialport::SerialPortType::UsbPort(port_info) = port.port_type {
+                if let Some(interface) = port_info.interface {
+                    Some(UsbSerialPortInfo {
+                        identifier: format!(
+                            "{:04x}:{:04x}:{}",
+                            port_info.vid, port_info.pid, interface
+                        ),
+                        port_name: port.port_name,
+                        port_info,
+                    })
+                } else {
+                    Some(UsbSerialPortInfo {
+                        identifier: format!("{:04x}:{:04x}", port_info.vid, port_info.pid),
+                        port_name: port.port_name,
+                        port_info,
+                    })
+                }
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if identifier.is_empty() {
+        return Err(UsbSerialError::IdentifierEmpty {
+            ports: UsbSerialPorts(ports),
+        });
+    }
+
+    let port_regex = regex::RegexBuilder::new(identifier)
+        .case_insensitive(true)
+        .unicode(true)
+        .build()?;
+
+    let matches = ports
+        .iter()
+        .filter(|port| {
+            if let Some(m) = port_regex.find(&port.identifier) {
+                // Only accept if the regex matches at the beginning of the string
+                m.start() == 0
+            } else {
+                false
+            }
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if matches.len() > 1 {
+        return Err(UsbSerialError::MultipleMatchingPorts {
+            identifier: identifier.to_string(),
+            ports: UsbSerialPorts(matches),
+        });
+    }
+
+    let port_name = match matches.into_iter().next() {
+        Some(port) => port.port_name,
+        None => {
+            return Err(UsbSerialError::NoMatchingPort {
+                identifier: identifier.to_string(),
+                available: UsbSerialPorts(ports),
+            });
+        }
+    };
+
+    Ok(port_name)
+}
+
 impl MCUmgrClient {
     /// Creates a Zephyr MCUmgr SMP client based on a configured and opened serial port.
     ///
+    /// The client does not know the timeout of `serial`. Until it gets set through
+    /// [`set_timeout`](Self::set_timeout), commands that take long on the device
+    /// do not wait longer, and a [`CancellationToken`] does not interrupt pending receives.
+    ///
     /// ```no_run
     /// # use mcumgr_toolkit::MCUmgrClient;
     /// # fn main() {
@@ -233,9 +410,48 @@ impl MCUmgrClient {
         Self {
             connection: Connection::new(SerialTransport::new(serial)),
             smp_frame_size: ZEPHYR_DEFAULT_SMP_FRAME_SIZE.into(),
+            window_size: 1.into(),
         }
     }
 
+    /// Creates a Zephyr MCUmgr SMP client based on a configured and opened serial port,
+    /// with custom serial line length and buffer sizes.
+    ///
+    /// Like [`new_from_serial`](Self::new_from_serial), the timeout of `serial` is unknown.
+    ///
+    /// ```no_run
+    /// # use mcumgr_toolkit::{MCUmgrClient, transport::serial::SerialTransportConfig};
+    /// # fn main() {
+    /// let serial = serialport::new("COM42", 115200)
+    ///     .timeout(std::time::Duration::from_millis(10000))
+    ///     .open()
+    ///     .unwrap();
+    ///
+    /// let mut client = MCUmgrClient::new_from_serial_with_config(
+    ///     serial,
+    ///     SerialTransportConfig {
+    ///         mtu: 1024,
+    ///         ..Default::default()
+    ///     },
+    /// )
+    /// .unwrap();
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If `config.mtu` is smaller than [`SERIAL_TRANSPORT_MIN_MTU`](crate::transport::serial::SERIAL_TRANSPORT_MIN_MTU).
+    pub fn new_from_serial_with_config<T: Send + Read + Write + ConfigurableTimeout + 'static>(
+        serial: T,
+        config: SerialTransportConfig,
+    ) -> Result<Self, SerialMtuTooSmall> {
+        Ok(Self {
+            connection: Connection::new(SerialTransport::with_config(serial, config)?),
+            smp_frame_size: ZEPHYR_DEFAULT_SMP_FRAME_SIZE.into(),
+            window_size: 1.into(),
+        })
+    }
+
     /// Creates a Zephyr MCUmgr SMP client based on a USB serial port identified by VID:PID.
     ///
     /// Useful for programming many devices in rapid succession, as Windows usually
@@ -258,80 +474,203 @@ impl MCUmgrClient {
         baud_rate: u32,
         timeout: Duration,
     ) -> Result<Self, UsbSerialError> {
-        let identifier = identifier.as_ref();
-
-        let ports = serialport::available_ports()?
-            .into_iter()
-            .filter_map(|port| {
-                if let serialport::SerialPortType::UsbPort(port_info) = port.port_type {
-                    if let Some(interface) = port_info.interface {
-                        Some(UsbSerialPortInfo {
-                            identifier: format!(
-                                "{:04x}:{:04x}:{}",
-                                port_info.vid, port_info.pid, interface
-                            ),
-                            port_name: port.port_name,
-                            port_info,
-                        })
-                    } else {
-                        Some(UsbSerialPortInfo {
-                            identifier: format!("{:04x}:{:04x}", port_info.vid, port_info.pid),
-                            port_name: port.port_name,
-                            port_info,
-                        })
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
+        let identifier = identifier.as_ref().to_string();
+
+        let open = move || -> Result<_, UsbSerialError> {
+            let port_name = find_usb_serial_port(&identifier)?;
+            let serial = serialport::new(port_name, baud_rate)
+                .timeout(timeout)
+                .open()?;
+            Ok(SerialTransport::new(serial))
+        };
 
-        if identifier.is_empty() {
-            return Err(UsbSerialError::IdentifierEmpty {
-                ports: UsbSerialPorts(ports),
-            });
-        }
+        let transport = open()?;
+        Ok(Self::new_reopenable(
+            transport,
+            Box::new(move || Ok(Box::new(open().map_err(io::Error::other)?))),
+        )
+        .with_known_timeout(timeout))
+    }
 
-        let port_regex = regex::RegexBuilder::new(identifier)
-            .case_insensitive(true)
-            .unicode(true)
-            .build()?;
-
-        let matches = ports
-            .iter()
-            .filter(|port| {
-                if let Some(m) = port_regex.find(&port.identifier) {
-                    // Only accept if the regex matches at the beginning of the string
-                    m.start() == 0
-                } else {
-                    false
-                }
-            })
-            .cloned()
-            .collect::<Vec<_>>();
+    /// Creates a Zephyr MCUmgr SMP client that talks to a device over UDP.
+    ///
+    /// Requires [`MCUMGR_TRANSPORT_UDP`](https://docs.zephyrproject.org/latest/kconfig.html#CONFIG_MCUMGR_TRANSPORT_UDP)
+    /// on the device. Zephyr listens on port [`1337`](crate::transport::udp::ZEPHYR_DEFAULT_UDP_PORT) by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the device, for example `192.168.1.42:1337` or `[fe80::1]:1337`.
+    /// * `timeout` - The communication timeout.
+    ///
+    /// ```no_run
+    /// # use mcumgr_toolkit::MCUmgrClient;
+    /// # fn main() {
+    /// let client = MCUmgrClient::new_from_udp(
+    ///     "192.168.1.42:1337",
+    ///     std::time::Duration::from_millis(10000),
+    /// )
+    /// .unwrap();
+    /// # }
+    /// ```
+    pub fn new_from_udp(addr: impl ToSocketAddrs, timeout: Duration) -> io::Result<Self> {
+        Ok(Self {
+            connection: Connection::new(UdpTransport::new(addr, timeout)?)
+                .with_known_timeout(timeout),
+            smp_frame_size: ZEPHYR_DEFAULT_SMP_FRAME_SIZE.into(),
+            window_size: 1.into(),
+        })
+    }
 
-        if matches.len() > 1 {
-            return Err(UsbSerialError::MultipleMatchingPorts {
-                identifier: identifier.to_string(),
-                ports: UsbSerialPorts(matches),
-            });
-        }
+    /// Creates a Zephyr MCUmgr SMP client that talks to a serial port behind a TCP bridge,
+    /// like [ser2net](https://github.com/cminyard/ser2net).
+    ///
+    /// Uses the same framing as [`new_from_serial`](Self::new_from_serial);
+    /// the bridge has to forward the raw bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the bridge, for example `192.168.1.42:4000`.
+    /// * `timeout` - The connection and communication timeout.
+    ///
+    /// ```no_run
+    /// # use mcumgr_toolkit::MCUmgrClient;
+    /// # fn main() {
+    /// let client = MCUmgrClient::new_from_tcp(
+    ///     "192.168.1.42:4000",
+    ///     std::time::Duration::from_millis(10000),
+    /// )
+    /// .unwrap();
+    /// # }
+    /// ```
+    pub fn new_from_tcp(addr: impl ToSocketAddrs, timeout: Duration) -> io::Result<Self> {
+        let addrs = addr.to_socket_addrs()?.collect::<Vec<_>>();
 
-        let port_name = match matches.into_iter().next() {
-            Some(port) => port.port_name,
-            None => {
-                return Err(UsbSerialError::NoMatchingPort {
-                    identifier: identifier.to_string(),
-                    available: UsbSerialPorts(ports),
-                });
-            }
+        let open = move || connect_tcp(&addrs, timeout).map(SerialTransport::new);
+
+        Ok(
+            Self::new_reopenable(open()?, Box::new(move || Ok(Box::new(open()?))))
+                .with_known_timeout(timeout),
+        )
+    }
+
+    /// Creates a Zephyr MCUmgr SMP client that talks to a serial port behind an
+    /// [RFC 2217](https://www.rfc-editor.org/rfc/rfc2217) terminal server.
+    ///
+    /// Unlike [`new_from_tcp`](Self::new_from_tcp), this configures the
+    /// serial port settings on the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the server, for example `192.168.1.42:4000`.
+    /// * `settings` - The serial port settings, applied again after reconnecting.
+    /// * `timeout` - The connection and communication timeout.
+    ///
+    /// ```no_run
+    /// # use mcumgr_toolkit::{MCUmgrClient, transport::rfc2217::Rfc2217Settings};
+    /// # fn main() {
+    /// let client = MCUmgrClient::new_from_rfc2217(
+    ///     "192.168.1.42:4000",
+    ///     Rfc2217Settings::new(115200),
+    ///     std::time::Duration::from_millis(10000),
+    /// )
+    /// .unwrap();
+    /// # }
+    /// ```
+    pub fn new_from_rfc2217(
+        addr: impl ToSocketAddrs,
+        settings: Rfc2217Settings,
+        timeout: Duration,
+    ) -> io::Result<Self> {
+        let addrs = addr.to_socket_addrs()?.collect::<Vec<_>>();
+
+        let open = move || {
+            Rfc2217Port::open(addrs.as_slice(), settings, timeout).map(SerialTransport::new)
+        };
+
+        Ok(
+            Self::new_reopenable(open()?, Box::new(move || Ok(Box::new(open()?))))
+                .with_known_timeout(timeout),
+        )
+    }
+
+    /// Creates a Zephyr MCUmgr SMP client that talks to a serial port exposed as a Unix socket,
+    /// like the UART of Zephyr's `native_sim`.
+    ///
+    /// Uses the same framing as [`new_from_serial`](Self::new_from_serial).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the socket.
+    /// * `timeout` - The communication timeout.
+    ///
+    #[cfg(unix)]
+    pub fn new_from_unix(path: impl AsRef<std::path::Path>, timeout: Duration) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let open = move || {
+            let mut stream = std::os::unix::net::UnixStream::connect(&path)?;
+            ConfigurableTimeout::set_timeout(&mut stream, timeout).map_err(io::Error::other)?;
+            Ok::<_, io::Error>(SerialTransport::new(stream))
         };
 
-        let serial = serialport::new(port_name, baud_rate)
-            .timeout(timeout)
-            .open()?;
+        Ok(
+            Self::new_reopenable(open()?, Box::new(move || Ok(Box::new(open()?))))
+                .with_known_timeout(timeout),
+        )
+    }
+
+    /// Creates a Zephyr MCUmgr SMP client based on an arbitrary transport.
+    ///
+    /// Like [`new_from_serial`](Self::new_from_serial), the timeout of `transport` is unknown.
+    ///
+    /// ```no_run
+    /// # use mcumgr_toolkit::{MCUmgrClient, transport::recording::ReplayTransport};
+    /// # fn main() {
+    /// let replay = ReplayTransport::open("session.jsonl").unwrap();
+    ///
+    /// let mut client = MCUmgrClient::new_from_transport(replay);
+    /// # }
+    /// ```
+    pub fn new_from_transport(transport: impl Transport + Send + 'static) -> Self {
+        Self {
+            connection: Connection::new(transport),
+            smp_frame_size: ZEPHYR_DEFAULT_SMP_FRAME_SIZE.into(),
+            window_size: 1.into(),
+        }
+    }
+
+    /// Creates a client that reopens its transport after the device disconnected.
+    fn new_reopenable(transport: impl Transport + Send + 'static, reopen: Reopen) -> Self {
+        Self {
+            connection: Connection::new_reopenable(transport, reopen),
+            smp_frame_size: ZEPHYR_DEFAULT_SMP_FRAME_SIZE.into(),
+            window_size: 1.into(),
+        }
+    }
 
-        Ok(Self::new_from_serial(serial))
+    /// Records the communication timeout the transport was created with.
+    fn with_known_timeout(self, timeout: Duration) -> Self {
+        Self {
+            connection: self.connection.with_known_timeout(timeout),
+            ..self
+        }
+    }
+
+    /// Consumes the client and returns its transport.
+    ///
+    /// To wrap the transport without losing the ability to reconnect,
+    /// use [`wrap_transport`](Self::wrap_transport) instead.
+    pub fn into_transport(self) -> Box<dyn Transport + Send> {
+        self.connection.into_transport()
+    }
+
+    /// Wraps the transport, for example in a
+    /// [`RecordingTransport`](crate::transport::recording::RecordingTransport).
+    ///
+    /// Transports that get reopened after the device disconnected are wrapped as well,
+    /// see [`Recording`](crate::transport::recording::Recording).
+    pub fn wrap_transport(&self, wrap: TransportWrapper) {
+        self.connection.wrap_transport(wrap)
     }
 
     /// Configures the maximum SMP frame size that we can send to the device.
@@ -361,6 +700,165 @@ impl MCUmgrClient {
         Ok(())
     }
 
+    /// Configures the maximum SMP frame size by probing which frame sizes the device accepts.
+    ///
+    /// For devices that do not report their frame size to [`use_auto_frame_size`](Self::use_auto_frame_size)
+    /// because `CONFIG_MCUMGR_GRP_OS_MCUMGR_PARAMS` is disabled, like MCUboot serial recovery.
+    ///
+    /// Sends echo requests that double in size, starting at Zephyr's default,
+    /// and then bisects between the largest accepted and the smallest rejected size.
+    /// The result may be up to 1/16 below the actual limit.
+    ///
+    /// Requests that time out or get rejected with `ENOMEM` count as too large.
+    /// Every rejected probe waits for the timeout, so consider a shorter one
+    /// through [`with_timeout`](Self::with_timeout).
+    ///
+    /// # Arguments
+    ///
+    /// * `max_frame_size` - The largest frame size to probe.
+    ///
+    /// # Return
+    ///
+    /// The frame size that is used from now on.
+    pub fn probe_frame_size(&self, max_frame_size: usize) -> Result<usize, MCUmgrClientError> {
+        let mut search = FrameSizeSearch::new(max_frame_size);
+
+        while let Some(size) = search.next_size() {
+            let message = frame_size_probe::echo_message(size);
+            match self.os_echo(&message) {
+                Ok(response) if response == message => search.accepted(size),
+                Ok(_) => {
+                    return Err(ExecuteError::ReceiveFailed(
+                        crate::transport::ReceiveError::UnexpectedResponse,
+                    )
+                    .into());
+                }
+                Err(e) if frame_size_probe::is_too_large(&e) => {
+                    log::debug!("Frame size {size} too large: {e}");
+                    search.rejected(size, e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let frame_size = search.finish()?;
+        log::debug!("Using probed frame size {frame_size}.");
+        self.set_frame_size(frame_size);
+
+        Ok(frame_size)
+    }
+
+    /// Configures how many requests uploads and downloads keep in flight.
+    ///
+    /// Speeds up transfers over connections with high latency, like UDP or TCP serial bridges.
+    /// Defaults to `1`.
+    ///
+    /// Must not exceed [`MCUMGR_TRANSPORT_NETBUF_COUNT`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L28),
+    /// otherwise the device drops our requests.
+    pub fn set_window_size(&self, window_size: usize) {
+        self.window_size
+            .store(window_size.max(1), std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Configures how many requests uploads and downloads keep in flight automatically
+    /// by reading the value of [`MCUMGR_TRANSPORT_NETBUF_COUNT`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L28)
+    /// from the device.
+    pub fn use_auto_window_size(&self) -> Result<(), MCUmgrClientError> {
+        let mcumgr_params = self
+            .connection
+            .execute_command(&commands::os::MCUmgrParameters)?;
+
+        log::debug!("Using window size {}.", mcumgr_params.buf_count);
+
+        self.set_window_size(mcumgr_params.buf_count as usize);
+
+        Ok(())
+    }
+
+    /// Configures the maximum line length of serial based transports.
+    ///
+    /// Must not exceed the device's [`MCUMGR_SERIAL_MAX_FRAME`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/include/zephyr/mgmt/mcumgr/transport/serial.h#L18),
+    /// otherwise the device drops our requests.
+    ///
+    /// Fails for transports without lines, like UDP.
+    pub fn set_serial_mtu(&self, mtu: usize) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .set_mtu(Some(mtu))
+            .map_err(MCUmgrClientError::SetMtuFailed)
+    }
+
+    /// Configures the maximum line length of serial based transports automatically.
+    ///
+    /// Zephyr chunks its responses with the same `MCUMGR_SERIAL_MAX_FRAME` that limits
+    /// its requests. This sends an echo request that fills the current SMP frame size
+    /// and adopts the length of the longest line of the response.
+    ///
+    /// Call [`use_auto_frame_size`](Self::use_auto_frame_size) first,
+    /// otherwise the probe might be too short to reveal large MTUs.
+    pub fn use_auto_serial_mtu(&self) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .set_mtu(None)
+            .map_err(MCUmgrClientError::SetMtuFailed)?;
+
+        let probe_size = self
+            .smp_frame_size
+            .load(std::sync::atomic::Ordering::SeqCst)
+            .saturating_sub(ECHO_FRAME_OVERHEAD);
+        let probe = "0".repeat(probe_size);
+
+        if self.os_echo(&probe)? == probe {
+            Ok(())
+        } else {
+            Err(
+                ExecuteError::ReceiveFailed(crate::transport::ReceiveError::UnexpectedResponse)
+                    .into(),
+            )
+        }
+    }
+
+    /// Sets a handler that receives the device's console output.
+    ///
+    /// For devices that share a UART between SMP and the Zephyr shell,
+    /// as allowed by [`MCUMGR_TRANSPORT_SHELL`](https://docs.zephyrproject.org/latest/kconfig.html#CONFIG_MCUMGR_TRANSPORT_SHELL).
+    /// The handler receives every line that is not part of an SMP frame, without line ending.
+    ///
+    /// Console output only gets read while waiting for a response or in
+    /// [`poll_console`](Self::poll_console).
+    ///
+    /// Fails for transports without a console, like UDP.
+    ///
+    /// ```no_run
+    /// # use mcumgr_toolkit::MCUmgrClient;
+    /// # fn main() {
+    /// # let client = MCUmgrClient::new_from_tcp("192.168.1.42:4000", std::time::Duration::from_millis(100)).unwrap();
+    /// client
+    ///     .set_console_handler(Some(Box::new(|line| {
+    ///         println!("{}", String::from_utf8_lossy(line));
+    ///     })))
+    ///     .unwrap();
+    ///
+    /// loop {
+    ///     client.poll_console().unwrap();
+    /// }
+    /// # }
+    /// ```
+    pub fn set_console_handler(
+        &self,
+        handler: Option<ConsoleHandler>,
+    ) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .set_console_handler(handler)
+            .map_err(MCUmgrClientError::SetConsoleHandlerFailed)
+    }
+
+    /// Reads the device's console output without sending a command.
+    ///
+    /// Returns after receiving data or when the communication timeout expires.
+    /// See [`set_console_handler`](Self::set_console_handler).
+    pub fn poll_console(&self) -> Result<(), MCUmgrClientError> {
+        self.connection.poll_console().map_err(Into::into)
+    }
+
     /// Changes the communication timeout.
     ///
     /// When the device does not respond to packets within the set
@@ -371,6 +869,94 @@ impl MCUmgrClient {
             .map_err(MCUmgrClientError::SetTimeoutFailed)
     }
 
+    /// Runs `f` with a different timeout for all commands,
+    /// for example for a single call that needs longer or must fail fast.
+    ///
+    /// Without an override, commands that take long on the device, like erasing flash
+    /// or the first chunk of an image upload, wait at least
+    /// [`LONG_OPERATION_TIMEOUT`](commands::LONG_OPERATION_TIMEOUT).
+    /// The communication timeout is restored after each command.
+    ///
+    /// The override also applies to commands that other threads send through this client meanwhile.
+    ///
+    /// ```no_run
+    /// # use mcumgr_toolkit::MCUmgrClient;
+    /// # use std::time::Duration;
+    /// # let client = MCUmgrClient::new_from_usb_serial("2fe3:0100", 115200, Duration::from_secs(5)).unwrap();
+    /// client.with_timeout(Duration::from_millis(200), || client.check_connection())?;
+    /// # Ok::<(), mcumgr_toolkit::client::MCUmgrClientError>(())
+    /// ```
+    pub fn with_timeout<T>(&self, timeout: Duration, f: impl FnOnce() -> T) -> T {
+        let previous = self.connection.set_timeout_override(Some(timeout));
+        let result = f();
+        self.connection.set_timeout_override(previous);
+        result
+    }
+
+    /// Configures the automatic retransmission of requests
+    /// that timed out or received a corrupted response.
+    ///
+    /// Retransmissions are disabled by default.
+    ///
+    /// ```no_run
+    /// # use mcumgr_toolkit::{MCUmgrClient, connection::RetryPolicy};
+    /// # fn main() {
+    /// # let serial = serialport::new("COM42", 115200).open().unwrap();
+    /// let client = MCUmgrClient::new_from_serial(serial);
+    ///
+    /// client.set_retry_policy(RetryPolicy {
+    ///     max_retries: 3,
+    ///     ..Default::default()
+    /// });
+    /// # }
+    /// ```
+    pub fn set_retry_policy(&self, policy: RetryPolicy) {
+        self.connection.set_retry_policy(policy)
+    }
+
+    /// The number of retransmitted requests since the client was created.
+    pub fn retry_count(&self) -> u64 {
+        self.connection.retry_count()
+    }
+
+    /// Frame, error and latency statistics since the client was created.
+    ///
+    /// Useful for diagnosing slow or unreliable connections.
+    pub fn stats(&self) -> ConnectionStats {
+        self.connection.stats()
+    }
+
+    /// Adds a [`CommandInterceptor`] that observes, modifies or rejects all following commands,
+    /// for example for audit logging or to block dangerous commands.
+    ///
+    /// Interceptors get called in the order they were added,
+    /// also for [`raw_command`](Self::raw_command) and for uploads and downloads.
+    pub fn add_interceptor(&self, interceptor: impl CommandInterceptor + 'static) {
+        self.connection
+            .add_interceptor(std::sync::Arc::new(interceptor));
+    }
+
+    /// Removes all interceptors.
+    pub fn clear_interceptors(&self) {
+        self.connection.clear_interceptors();
+    }
+
+    /// Selects the SMP protocol version of requests.
+    ///
+    /// `None` negotiates the version automatically, which is the default:
+    /// requests start with SMP v2 and fall back to SMP v1 if the device rejects them,
+    /// as older MCUmgr versions and MCUboot serial recovery do.
+    pub fn set_smp_version(&self, version: Option<SmpVersion>) {
+        self.connection.set_smp_version(version)
+    }
+
+    /// The SMP protocol version of requests, as negotiated with the device.
+    ///
+    /// Only meaningful after the first command, for example [`MCUmgrClient::check_connection`].
+    pub fn smp_version(&self) -> SmpVersion {
+        self.connection.smp_version()
+    }
+
     /// Checks if the device is alive and responding.
     ///
     /// Runs a simple echo with random data and checks if the response matches.
@@ -391,6 +977,31 @@ impl MCUmgrClient {
         }
     }
 
+    /// Waits until the device responds again, for example after a reset.
+    ///
+    /// Reopens the transport as soon as the device reappears, even under a different
+    /// port name, if the client was created with [`new_from_usb_serial`](Self::new_from_usb_serial),
+    /// [`new_from_tcp`](Self::new_from_tcp), [`new_from_rfc2217`](Self::new_from_rfc2217)
+    /// or `new_from_unix`.
+    ///
+    /// # Return
+    ///
+    /// The last error if the device did not respond within `timeout`.
+    pub fn wait_for_reconnect(&self, timeout: Duration) -> Result<(), MCUmgrClientError> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match self.check_connection() {
+                Ok(()) => return Ok(()),
+                Err(e) if Instant::now() < deadline => {
+                    log::debug!("Device not reachable yet: {e}");
+                    std::thread::sleep(RECONNECT_POLL_INTERVAL);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     /// High-level firmware update routine.
     ///
     /// # Arguments
@@ -399,6 +1010,7 @@ impl MCUmgrClient {
     /// * `checksum` - SHA256 of the firmware image. Optional.
     /// * `params` - Configurable parameters.
     /// * `progress` - A callback that receives progress updates.
+    /// * `cancel` - Aborts the update once cancelled.
     ///
     pub fn firmware_update(
         &self,
@@ -406,8 +1018,9 @@ impl MCUmgrClient {
         checksum: Option<[u8; 32]>,
         params: FirmwareUpdateParams,
         progress: Option<&mut FirmwareUpdateProgressCallback>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<(), FirmwareUpdateError> {
-        firmware_update::firmware_update(self, firmware, checksum, params, progress)
+        firmware_update::firmware_update(self, firmware, checksum, params, progress, cancel)
     }
 
     /// Sends a message to the device and expects the same message back as response.
@@ -446,6 +1059,20 @@ impl MCUmgrClient {
             .map_err(Into::into)
     }
 
+    /// Queries memory pool statistics
+    ///
+    /// # Return
+    ///
+    /// A map of memory pool names with their respective statistics
+    pub fn os_memory_pool_statistics(
+        &self,
+    ) -> Result<HashMap<String, commands::os::MemoryPoolStatisticsEntry>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::os::MemoryPoolStatistics)
+            .map(|resp| resp.mpools)
+            .map_err(Into::into)
+    }
+
     /// Sets the RTC of the device to the given datetime.
     pub fn os_set_datetime(
         &self,
@@ -587,6 +1214,7 @@ impl MCUmgrClient {
     /// * `checksum` - The SHA256 checksum of the image. If missing, will be computed from the image data.
     /// * `upgrade_only` - If true, allow firmware upgrades only and reject downgrades.
     /// * `progress` - A callback that receives a pair of (transferred, total) bytes and returns false on error.
+    /// * `cancel` - Aborts the upload once cancelled. A later upload starts over.
     ///
     pub fn image_upload(
         &self,
@@ -595,6 +1223,7 @@ impl MCUmgrClient {
         checksum: Option<[u8; 32]>,
         upgrade_only: bool,
         mut progress: Option<&mut dyn FnMut(u64, u64) -> bool>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<(), MCUmgrClientError> {
         let chunk_size_max = image_upload_max_data_chunk_size(
             self.smp_frame_size
@@ -611,36 +1240,49 @@ impl MCUmgrClient {
             }
         }
 
+        let window_size = self.window_size.load(std::sync::atomic::Ordering::SeqCst);
+        let mut pipeline = self
+            .connection
+            .pipeline(window_size)
+            .with_cancellation(cancel);
+        let mut chunk_ends = VecDeque::new();
+
         let mut offset = 0;
+        let mut next_offset = 0;
         let size = data.len();
 
         let mut checksum_matched = None;
 
         while offset < size {
-            let current_chunk_size = (size - offset).min(chunk_size_max);
-            let chunk_data = &data[offset..offset + current_chunk_size];
+            while pipeline.can_send() && next_offset < size {
+                let current_chunk_size = (size - next_offset).min(chunk_size_max);
+                let chunk_data = &data[next_offset..next_offset + current_chunk_size];
 
-            let upload_response = if offset == 0 {
-                self.connection
-                    .execute_command(&commands::image::ImageUpload {
+                if next_offset == 0 {
+                    pipeline.send(&commands::image::ImageUpload {
                         image,
                         len: Some(size as u64),
-                        off: offset as u64,
+                        off: next_offset as u64,
                         sha: Some(&actual_checksum),
                         data: chunk_data,
                         upgrade: Some(upgrade_only),
-                    })?
-            } else {
-                self.connection
-                    .execute_command(&commands::image::ImageUpload {
+                    })?;
+                } else {
+                    pipeline.send(&commands::image::ImageUpload {
                         image: None,
                         len: None,
-                        off: offset as u64,
+                        off: next_offset as u64,
                         sha: None,
                         data: chunk_data,
                         upgrade: None,
-                    })?
-            };
+                    })?;
+                }
+
+                next_offset += current_chunk_size;
+                chunk_ends.push_back(next_offset);
+            }
+
+            let upload_response = pipeline.receive()?;
 
             offset = upload_response
                 .off
@@ -651,6 +1293,17 @@ impl MCUmgrClient {
                 return Err(MCUmgrClientError::UnexpectedOffset);
             }
 
+            // The device continues at a different offset, for example when resuming
+            // a previous upload; the chunks in flight are of no use.
+            if chunk_ends.pop_front() != Some(offset) {
+                pipeline = self
+                    .connection
+                    .pipeline(window_size)
+                    .with_cancellation(cancel);
+                chunk_ends.clear();
+                next_offset = offset;
+            }
+
             if let Some(progress) = &mut progress {
                 if !progress(offset as u64, size as u64) {
                     return Err(MCUmgrClientError::ProgressCallbackError);
@@ -696,6 +1349,97 @@ impl MCUmgrClient {
             .map_err(Into::into)
     }
 
+    /// List the statistics groups of the device.
+    pub fn stat_list(&self) -> Result<Vec<String>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::stat::ListGroups)
+            .map(|val| val.stat_list)
+            .map_err(Into::into)
+    }
+
+    /// Read the statistics of a group.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The name of the statistics group, as returned by [`stat_list`](Self::stat_list).
+    ///
+    /// # Return
+    ///
+    /// The values of the statistics, by name.
+    pub fn stat_read(&self, group: &str) -> Result<BTreeMap<String, u64>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::stat::GroupData { name: group })
+            .map(|val| val.fields)
+            .map_err(Into::into)
+    }
+
+    /// Read the value of a setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the setting.
+    /// * `max_size` - The maximum size of the value to read. Device default if omitted.
+    ///
+    pub fn settings_read(
+        &self,
+        name: &str,
+        max_size: Option<u32>,
+    ) -> Result<Vec<u8>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::ReadSetting { name, max_size })
+            .map(|val| val.val)
+            .map_err(Into::into)
+    }
+
+    /// Write the value of a setting.
+    ///
+    /// The new value is only applied after [`settings_commit`](Self::settings_commit),
+    /// and only persisted after [`settings_save`](Self::settings_save).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the setting.
+    /// * `value` - The raw value of the setting.
+    ///
+    pub fn settings_write(&self, name: &str, value: &[u8]) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::WriteSetting { name, val: value })
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Delete a setting from the persistent storage.
+    pub fn settings_delete(&self, name: &str) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::DeleteSetting { name })
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Apply the written settings.
+    pub fn settings_commit(&self) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::CommitSettings)
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Load the settings from the persistent storage.
+    pub fn settings_load(&self) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::LoadSettings)
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Save the settings to the persistent storage.
+    pub fn settings_save(&self) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::SaveSettings)
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
     /// Load a file from the device.
     ///
     /// # Arguments
@@ -703,22 +1447,44 @@ impl MCUmgrClient {
     /// * `name` - The full path of the file on the device.
     /// * `writer` - A [`Write`] object that the file content will be written to.
     /// * `progress` - A callback that receives a pair of (transferred, total) bytes.
+    /// * `cancel` - Aborts the download once cancelled.
+    ///
+    /// If the download gets aborted, either by `cancel` or by `progress`, the file
+    /// gets closed on the device through [`fs_file_close`](Self::fs_file_close).
     ///
     /// # Performance
     ///
     /// Downloading files with Zephyr's default parameters is slow.
     /// You want to increase [`MCUMGR_TRANSPORT_NETBUF_SIZE`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L40)
     /// to maybe `4096` or larger.
+    ///
+    /// Over connections with high latency, [`MCUmgrClient::set_window_size`] helps as well.
     pub fn fs_file_download<T: Write>(
         &self,
         name: impl AsRef<str>,
+        writer: T,
+        progress: Option<&mut dyn FnMut(u64, u64) -> bool>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(), MCUmgrClientError> {
+        let result = self.fs_file_download_chunks(name.as_ref(), writer, progress, cancel);
+        self.close_aborted_file(result)
+    }
+
+    /// Downloads a file chunk by chunk, see [`fs_file_download`](Self::fs_file_download).
+    fn fs_file_download_chunks<T: Write>(
+        &self,
+        name: &str,
         mut writer: T,
         mut progress: Option<&mut dyn FnMut(u64, u64) -> bool>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<(), MCUmgrClientError> {
-        let name = name.as_ref();
-        let response = self
-            .connection
-            .execute_command(&commands::fs::FileDownload { name, off: 0 })?;
+        let request = commands::fs::FileDownload { name, off: 0 };
+        let response = match cancel {
+            Some(cancel) => self
+                .connection
+                .execute_cancellable_command(&request, cancel)?,
+            None => self.connection.execute_command(&request)?,
+        };
 
         let file_len = response.len.ok_or(MCUmgrClientError::MissingSize)?;
         if response.off != 0 {
@@ -744,10 +1510,26 @@ impl MCUmgrClient {
             };
         }
 
+        // Assume that the device fills all responses like the first one
+        let chunk_size = (response.data.len() as u64).max(1);
+
+        let window_size = self.window_size.load(std::sync::atomic::Ordering::SeqCst);
+        let mut pipeline = self
+            .connection
+            .pipeline(window_size)
+            .with_cancellation(cancel);
+        let mut next_offset = offset;
+
         while offset < file_len {
-            let response = self
-                .connection
-                .execute_command(&commands::fs::FileDownload { name, off: offset })?;
+            while pipeline.can_send() && next_offset < file_len {
+                pipeline.send(&commands::fs::FileDownload {
+                    name,
+                    off: next_offset,
+                })?;
+                next_offset += chunk_size;
+            }
+
+            let response = pipeline.receive()?;
 
             if response.off != offset {
                 return Err(MCUmgrClientError::UnexpectedOffset);
@@ -758,6 +1540,15 @@ impl MCUmgrClient {
                 .map_err(MCUmgrClientError::WriterError)?;
             offset += response.data.len() as u64;
 
+            // The requests in flight are for the wrong offsets if the device sent less
+            if response.data.len() as u64 != chunk_size {
+                pipeline = self
+                    .connection
+                    .pipeline(window_size)
+                    .with_cancellation(cancel);
+                next_offset = offset;
+            }
+
             if let Some(progress) = &mut progress {
                 if !progress(offset, file_len) {
                     return Err(MCUmgrClientError::ProgressCallbackError);
@@ -780,6 +1571,10 @@ impl MCUmgrClient {
     /// * `reader` - A [`Read`] object that contains the file content.
     /// * `size` - The file size.
     /// * `progress` - A callback that receives a pair of (transferred, total) bytes and returns false on error.
+    /// * `cancel` - Aborts the upload once cancelled.
+    ///
+    /// If the upload gets aborted, either by `cancel` or by `progress`, the file
+    /// gets closed on the device through [`fs_file_close`](Self::fs_file_close).
     ///
     /// # Performance
     ///
@@ -787,15 +1582,29 @@ impl MCUmgrClient {
     /// You want to increase [`MCUMGR_TRANSPORT_NETBUF_SIZE`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L40)
     /// to maybe `4096` and then enable larger chunking through either [`MCUmgrClient::set_frame_size`]
     /// or [`MCUmgrClient::use_auto_frame_size`].
+    ///
+    /// Over connections with high latency, [`MCUmgrClient::set_window_size`] helps as well.
     pub fn fs_file_upload<T: Read>(
         &self,
         name: impl AsRef<str>,
+        reader: T,
+        size: u64,
+        progress: Option<&mut dyn FnMut(u64, u64) -> bool>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(), MCUmgrClientError> {
+        let result = self.fs_file_upload_chunks(name.as_ref(), reader, size, progress, cancel);
+        self.close_aborted_file(result)
+    }
+
+    /// Uploads a file chunk by chunk, see [`fs_file_upload`](Self::fs_file_upload).
+    fn fs_file_upload_chunks<T: Read>(
+        &self,
+        name: &str,
         mut reader: T,
         size: u64,
         mut progress: Option<&mut dyn FnMut(u64, u64) -> bool>,
+        cancel: Option<&CancellationToken>,
     ) -> Result<(), MCUmgrClientError> {
-        let name = name.as_ref();
-
         let chunk_size_max = file_upload_max_data_chunk_size(
             self.smp_frame_size
                 .load(std::sync::atomic::Ordering::SeqCst),
@@ -804,24 +1613,44 @@ impl MCUmgrClient {
         .map_err(MCUmgrClientError::FrameSizeTooSmall)?;
         let mut data_buffer = vec![0u8; chunk_size_max].into_boxed_slice();
 
+        let window_size = self.window_size.load(std::sync::atomic::Ordering::SeqCst);
+        let mut pipeline = self
+            .connection
+            .pipeline(window_size)
+            .with_cancellation(cancel);
+        let mut chunk_ends = VecDeque::new();
+
         let mut offset = 0;
+        let mut next_offset = 0;
 
         while offset < size {
-            let current_chunk_size = (size - offset).min(data_buffer.len() as u64) as usize;
+            while pipeline.can_send() && next_offset < size {
+                let current_chunk_size =
+                    (size - next_offset).min(data_buffer.len() as u64) as usize;
+
+                let chunk_buffer = &mut data_buffer[..current_chunk_size];
+                reader
+                    .read_exact(chunk_buffer)
+                    .map_err(MCUmgrClientError::ReaderError)?;
+
+                pipeline.send(&commands::fs::FileUpload {
+                    off: next_offset,
+                    data: chunk_buffer,
+                    name,
+                    len: if next_offset == 0 { Some(size) } else { None },
+                })?;
+
+                next_offset += current_chunk_size as u64;
+                chunk_ends.push_back(next_offset);
+            }
 
-            let chunk_buffer = &mut data_buffer[..current_chunk_size];
-            reader
-                .read_exact(chunk_buffer)
-                .map_err(MCUmgrClientError::ReaderError)?;
+            let upload_response = pipeline.receive()?;
 
-            self.connection.execute_command(&commands::fs::FileUpload {
-                off: offset,
-                data: chunk_buffer,
-                name,
-                len: if offset == 0 { Some(size) } else { None },
-            })?;
+            offset = chunk_ends.pop_front().unwrap_or_default();
 
-            offset += chunk_buffer.len() as u64;
+            if upload_response.off != offset {
+                return Err(MCUmgrClientError::UnexpectedOffset);
+            }
 
             if let Some(progress) = &mut progress {
                 if !progress(offset, size) {
@@ -833,6 +1662,22 @@ impl MCUmgrClient {
         Ok(())
     }
 
+    /// Closes the file of an aborted transfer, so that the device does not keep it open.
+    fn close_aborted_file(
+        &self,
+        result: Result<(), MCUmgrClientError>,
+    ) -> Result<(), MCUmgrClientError> {
+        if let Err(MCUmgrClientError::Cancelled | MCUmgrClientError::ProgressCallbackError) =
+            &result
+        {
+            if let Err(e) = self.fs_file_close() {
+                log::warn!("Failed to close the file of the aborted transfer: {e}");
+            }
+        }
+
+        result
+    }
+
     /// Queries the file status
     pub fn fs_file_status(
         &self,
@@ -907,6 +1752,88 @@ impl MCUmgrClient {
             .map_err(Into::into)
     }
 
+    /// Query the number of management groups the device supports.
+    pub fn enum_group_count(&self) -> Result<u16, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::enumeration::GroupCount)
+            .map(|resp| resp.count)
+            .map_err(Into::into)
+    }
+
+    /// Query the IDs of the management groups the device supports.
+    pub fn enum_group_list(&self) -> Result<Vec<u16>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::enumeration::GroupList)
+            .map(|resp| resp.groups)
+            .map_err(Into::into)
+    }
+
+    /// Query the ID of a single supported management group.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the group, up to [`enum_group_count`](Self::enum_group_count).
+    ///
+    /// # Return
+    ///
+    /// A tuple of (group ID, whether it is the last group).
+    pub fn enum_group_id(&self, index: u16) -> Result<(u16, bool), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::enumeration::GroupId { index })
+            .map(|resp| (resp.group, resp.end))
+            .map_err(Into::into)
+    }
+
+    /// Query the names and handler counts of supported management groups.
+    ///
+    /// # Arguments
+    ///
+    /// * `groups` - The IDs of the groups to query. All groups if empty.
+    pub fn enum_group_details(
+        &self,
+        groups: &[u16],
+    ) -> Result<Vec<commands::enumeration::GroupDetailsEntry>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::enumeration::GroupDetails { groups })
+            .map(|resp| resp.groups)
+            .map_err(Into::into)
+    }
+
+    /// Determine which management groups the device supports.
+    ///
+    /// Uses the enumeration group, if available. Otherwise, probes the groups known
+    /// to this crate with requests that have no side effects, and checks
+    /// which of them the device reports as unsupported.
+    pub fn capabilities(&self) -> Result<DeviceCapabilities, MCUmgrClientError> {
+        let group_ids = match self.enum_group_list() {
+            Ok(group_ids) => group_ids,
+            Err(e) if e.command_not_supported() => {
+                log::debug!("Enumeration group not supported, probing groups.");
+
+                let mut group_ids = vec![];
+                for probe in capabilities::group_probes() {
+                    if group_ids.contains(&probe.group_id()) {
+                        continue;
+                    }
+                    if capabilities::probe_succeeded(self.raw_command(&probe))? {
+                        group_ids.push(probe.group_id());
+                    }
+                }
+
+                return Ok(DeviceCapabilities::from_probes(group_ids));
+            }
+            Err(e) => return Err(e),
+        };
+
+        let details = match self.enum_group_details(&[]) {
+            Ok(details) => Some(details),
+            Err(e) if e.command_not_supported() => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(DeviceCapabilities::from_enumeration(group_ids, details))
+    }
+
     /// Erase the `storage_partition` flash partition.
     pub fn zephyr_erase_storage(&self) -> Result<(), MCUmgrClientError> {
         self.connection
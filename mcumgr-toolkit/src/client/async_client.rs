@@ -0,0 +1,1087 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io,
+    sync::atomic::AtomicUsize,
+    time::Duration,
+};
+
+use rand::distr::SampleString;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    bootloader::BootloaderInfo,
+    commands::{
+        self, McuMgrCommand, fs::file_upload_max_data_chunk_size,
+        image::image_upload_max_data_chunk_size,
+    },
+    connection::{
+        AsyncConnection, CancellationToken, CommandInterceptor, ConnectionStats, ExecuteError,
+        RetryPolicy,
+    },
+    transport::{
+        SmpVersion,
+        async_serial::AsyncSerialTransport,
+        async_udp::AsyncUdpTransport,
+        serial::{SerialMtuTooSmall, SerialTransportConfig},
+    },
+};
+
+use super::{
+    AsyncFirmwareUpdateProgressCallback, DeviceCapabilities, ECHO_FRAME_OVERHEAD,
+    FirmwareUpdateError, FirmwareUpdateParams, FrameSizeSearch, MCUmgrClientError,
+    ZEPHYR_DEFAULT_SMP_FRAME_SIZE, capabilities, firmware_update, frame_size_probe,
+};
+
+/// An async client for Zephyr's MCUmgr SMP protocol.
+///
+/// Async counterpart of [`MCUmgrClient`](crate::MCUmgrClient), based on tokio.
+/// Every method behaves exactly like its blocking counterpart.
+pub struct AsyncMCUmgrClient {
+    connection: AsyncConnection,
+    smp_frame_size: AtomicUsize,
+    window_size: AtomicUsize,
+}
+
+impl AsyncMCUmgrClient {
+    /// Creates a Zephyr MCUmgr SMP client based on an opened async serial port.
+    ///
+    /// ```no_run
+    /// # use mcumgr_toolkit::AsyncMCUmgrClient;
+    /// # async fn connect() {
+    /// let serial = tokio::net::TcpStream::connect("192.168.1.42:4000")
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let client = AsyncMCUmgrClient::new_from_serial(
+    ///     serial,
+    ///     std::time::Duration::from_millis(10000),
+    /// );
+    /// # }
+    /// ```
+    pub fn new_from_serial<T: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+        serial: T,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            connection: AsyncConnection::new(AsyncSerialTransport::new(serial, timeout))
+                .with_known_timeout(timeout),
+            smp_frame_size: ZEPHYR_DEFAULT_SMP_FRAME_SIZE.into(),
+            window_size: 1.into(),
+        }
+    }
+
+    /// Creates a Zephyr MCUmgr SMP client based on an opened async serial port,
+    /// with custom serial line length and buffer sizes.
+    ///
+    /// See [`MCUmgrClient::new_from_serial_with_config`](crate::MCUmgrClient::new_from_serial_with_config).
+    pub fn new_from_serial_with_config<T: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+        serial: T,
+        timeout: Duration,
+        config: SerialTransportConfig,
+    ) -> Result<Self, SerialMtuTooSmall> {
+        Ok(Self {
+            connection: AsyncConnection::new(AsyncSerialTransport::with_config(
+                serial, timeout, config,
+            )?)
+            .with_known_timeout(timeout),
+            smp_frame_size: ZEPHYR_DEFAULT_SMP_FRAME_SIZE.into(),
+            window_size: 1.into(),
+        })
+    }
+
+    /// Creates a Zephyr MCUmgr SMP client that talks to a device over UDP.
+    ///
+    /// See [`MCUmgrClient::new_from_udp`](crate::MCUmgrClient::new_from_udp).
+    pub async fn new_from_udp(
+        addr: impl tokio::net::ToSocketAddrs,
+        timeout: Duration,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            connection: AsyncConnection::new(AsyncUdpTransport::new(addr, timeout).await?)
+                .with_known_timeout(timeout),
+            smp_frame_size: ZEPHYR_DEFAULT_SMP_FRAME_SIZE.into(),
+            window_size: 1.into(),
+        })
+    }
+
+    /// Configures the maximum SMP frame size that we can send to the device.
+    ///
+    /// See [`MCUmgrClient::set_frame_size`](crate::MCUmgrClient::set_frame_size).
+    pub fn set_frame_size(&self, smp_frame_size: usize) {
+        self.smp_frame_size
+            .store(smp_frame_size, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Configures the maximum SMP frame size that we can send to the device automatically.
+    ///
+    /// See [`MCUmgrClient::use_auto_frame_size`](crate::MCUmgrClient::use_auto_frame_size).
+    pub async fn use_auto_frame_size(&self) -> Result<(), MCUmgrClientError> {
+        let mcumgr_params = self
+            .connection
+            .execute_command(&commands::os::MCUmgrParameters)
+            .await?;
+
+        log::debug!("Using frame size {}.", mcumgr_params.buf_size);
+
+        self.smp_frame_size.store(
+            mcumgr_params.buf_size as usize,
+            std::sync::atomic::Ordering::SeqCst,
+        );
+
+        Ok(())
+    }
+
+    /// Configures the maximum SMP frame size by probing which frame sizes the device accepts.
+    ///
+    /// See [`MCUmgrClient::probe_frame_size`](crate::MCUmgrClient::probe_frame_size).
+    pub async fn probe_frame_size(
+        &self,
+        max_frame_size: usize,
+    ) -> Result<usize, MCUmgrClientError> {
+        let mut search = FrameSizeSearch::new(max_frame_size);
+
+        while let Some(size) = search.next_size() {
+            let message = frame_size_probe::echo_message(size);
+            match self.os_echo(&message).await {
+                Ok(response) if response == message => search.accepted(size),
+                Ok(_) => {
+                    return Err(ExecuteError::ReceiveFailed(
+                        crate::transport::ReceiveError::UnexpectedResponse,
+                    )
+                    .into());
+                }
+                Err(e) if frame_size_probe::is_too_large(&e) => {
+                    log::debug!("Frame size {size} too large: {e}");
+                    search.rejected(size, e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let frame_size = search.finish()?;
+        log::debug!("Using probed frame size {frame_size}.");
+        self.set_frame_size(frame_size);
+
+        Ok(frame_size)
+    }
+
+    /// Configures how many requests uploads and downloads keep in flight.
+    ///
+    /// See [`MCUmgrClient::set_window_size`](crate::MCUmgrClient::set_window_size).
+    pub fn set_window_size(&self, window_size: usize) {
+        self.window_size
+            .store(window_size.max(1), std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Configures how many requests uploads and downloads keep in flight automatically.
+    ///
+    /// See [`MCUmgrClient::use_auto_window_size`](crate::MCUmgrClient::use_auto_window_size).
+    pub async fn use_auto_window_size(&self) -> Result<(), MCUmgrClientError> {
+        let mcumgr_params = self
+            .connection
+            .execute_command(&commands::os::MCUmgrParameters)
+            .await?;
+
+        log::debug!("Using window size {}.", mcumgr_params.buf_count);
+
+        self.set_window_size(mcumgr_params.buf_count as usize);
+
+        Ok(())
+    }
+
+    /// Configures the maximum line length of serial based transports.
+    ///
+    /// See [`MCUmgrClient::set_serial_mtu`](crate::MCUmgrClient::set_serial_mtu).
+    pub async fn set_serial_mtu(&self, mtu: usize) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .set_mtu(Some(mtu))
+            .await
+            .map_err(MCUmgrClientError::SetMtuFailed)
+    }
+
+    /// Configures the maximum line length of serial based transports automatically.
+    ///
+    /// See [`MCUmgrClient::use_auto_serial_mtu`](crate::MCUmgrClient::use_auto_serial_mtu).
+    pub async fn use_auto_serial_mtu(&self) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .set_mtu(None)
+            .await
+            .map_err(MCUmgrClientError::SetMtuFailed)?;
+
+        let probe_size = self
+            .smp_frame_size
+            .load(std::sync::atomic::Ordering::SeqCst)
+            .saturating_sub(ECHO_FRAME_OVERHEAD);
+        let probe = "0".repeat(probe_size);
+
+        if self.os_echo(&probe).await? == probe {
+            Ok(())
+        } else {
+            Err(
+                ExecuteError::ReceiveFailed(crate::transport::ReceiveError::UnexpectedResponse)
+                    .into(),
+            )
+        }
+    }
+
+    /// Changes the communication timeout.
+    ///
+    /// When the device does not respond to packets within the set
+    /// duration, an error will be raised.
+    pub async fn set_timeout(&self, timeout: Duration) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .set_timeout(timeout)
+            .await
+            .map_err(MCUmgrClientError::SetTimeoutFailed)
+    }
+
+    /// Awaits `f` with a different timeout for all commands.
+    ///
+    /// See [`MCUmgrClient::with_timeout`](crate::MCUmgrClient::with_timeout).
+    pub async fn with_timeout<F: Future>(&self, timeout: Duration, f: F) -> F::Output {
+        let previous = self.connection.set_timeout_override(Some(timeout)).await;
+        let result = f.await;
+        self.connection.set_timeout_override(previous).await;
+        result
+    }
+
+    /// Configures the automatic retransmission of requests.
+    ///
+    /// See [`MCUmgrClient::set_retry_policy`](crate::MCUmgrClient::set_retry_policy).
+    pub async fn set_retry_policy(&self, policy: RetryPolicy) {
+        self.connection.set_retry_policy(policy).await
+    }
+
+    /// The number of retransmitted requests since the client was created.
+    pub fn retry_count(&self) -> u64 {
+        self.connection.retry_count()
+    }
+
+    /// Frame, error and latency statistics since the client was created.
+    ///
+    /// Useful for diagnosing slow or unreliable connections.
+    pub fn stats(&self) -> ConnectionStats {
+        self.connection.stats()
+    }
+
+    /// Adds a [`CommandInterceptor`] that observes, modifies or rejects all following commands,
+    /// for example for audit logging or to block dangerous commands.
+    ///
+    /// Interceptors get called in the order they were added,
+    /// also for [`raw_command`](Self::raw_command) and for uploads and downloads.
+    pub fn add_interceptor(&self, interceptor: impl CommandInterceptor + 'static) {
+        self.connection
+            .add_interceptor(std::sync::Arc::new(interceptor));
+    }
+
+    /// Removes all interceptors.
+    pub fn clear_interceptors(&self) {
+        self.connection.clear_interceptors();
+    }
+
+    /// Selects the SMP protocol version of requests.
+    ///
+    /// See [`MCUmgrClient::set_smp_version`](crate::MCUmgrClient::set_smp_version).
+    pub async fn set_smp_version(&self, version: Option<SmpVersion>) {
+        self.connection.set_smp_version(version).await
+    }
+
+    /// The SMP protocol version of requests, as negotiated with the device.
+    pub async fn smp_version(&self) -> SmpVersion {
+        self.connection.smp_version().await
+    }
+
+    /// Checks if the device is alive and responding.
+    ///
+    /// See [`MCUmgrClient::check_connection`](crate::MCUmgrClient::check_connection).
+    pub async fn check_connection(&self) -> Result<(), MCUmgrClientError> {
+        let random_message = rand::distr::Alphanumeric.sample_string(&mut rand::rng(), 16);
+        let response = self.os_echo(&random_message).await?;
+        if random_message == response {
+            Ok(())
+        } else {
+            Err(
+                ExecuteError::ReceiveFailed(crate::transport::ReceiveError::UnexpectedResponse)
+                    .into(),
+            )
+        }
+    }
+
+    /// High-level firmware update routine.
+    ///
+    /// See [`MCUmgrClient::firmware_update`](crate::MCUmgrClient::firmware_update).
+    pub async fn firmware_update(
+        &self,
+        firmware: impl AsRef<[u8]>,
+        checksum: Option<[u8; 32]>,
+        params: FirmwareUpdateParams,
+        progress: Option<&mut AsyncFirmwareUpdateProgressCallback<'_>>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(), FirmwareUpdateError> {
+        firmware_update::firmware_update_async(self, firmware, checksum, params, progress, cancel)
+            .await
+    }
+
+    /// Sends a message to the device and expects the same message back as response.
+    pub async fn os_echo(&self, msg: impl AsRef<str>) -> Result<String, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::os::Echo { d: msg.as_ref() })
+            .await
+            .map(|resp| resp.r)
+            .map_err(Into::into)
+    }
+
+    /// Queries live task statistics
+    ///
+    /// See [`MCUmgrClient::os_task_statistics`](crate::MCUmgrClient::os_task_statistics).
+    pub async fn os_task_statistics(
+        &self,
+    ) -> Result<HashMap<String, commands::os::TaskStatisticsEntry>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::os::TaskStatistics)
+            .await
+            .map(|resp| {
+                let mut tasks = resp.tasks;
+                for (_, stats) in tasks.iter_mut() {
+                    stats.stkuse = stats.stkuse.map(|val| val * 4);
+                    stats.stksiz = stats.stksiz.map(|val| val * 4);
+                }
+                tasks
+            })
+            .map_err(Into::into)
+    }
+
+    /// Queries memory pool statistics
+    ///
+    /// See [`MCUmgrClient::os_memory_pool_statistics`](crate::MCUmgrClient::os_memory_pool_statistics).
+    pub async fn os_memory_pool_statistics(
+        &self,
+    ) -> Result<HashMap<String, commands::os::MemoryPoolStatisticsEntry>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::os::MemoryPoolStatistics)
+            .await
+            .map(|resp| resp.mpools)
+            .map_err(Into::into)
+    }
+
+    /// Sets the RTC of the device to the given datetime.
+    pub async fn os_set_datetime(
+        &self,
+        datetime: chrono::NaiveDateTime,
+    ) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::os::DateTimeSet { datetime })
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Retrieves the device RTC's datetime.
+    pub async fn os_get_datetime(&self) -> Result<chrono::NaiveDateTime, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::os::DateTimeGet)
+            .await
+            .map(|val| val.datetime)
+            .map_err(Into::into)
+    }
+
+    /// Issues a system reset.
+    ///
+    /// See [`MCUmgrClient::os_system_reset`](crate::MCUmgrClient::os_system_reset).
+    pub async fn os_system_reset(
+        &self,
+        force: bool,
+        boot_mode: Option<u8>,
+    ) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::os::SystemReset { force, boot_mode })
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Fetch parameters from the MCUmgr library
+    pub async fn os_mcumgr_parameters(
+        &self,
+    ) -> Result<commands::os::MCUmgrParametersResponse, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::os::MCUmgrParameters)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Fetch information on the running image
+    ///
+    /// See [`MCUmgrClient::os_application_info`](crate::MCUmgrClient::os_application_info).
+    pub async fn os_application_info(
+        &self,
+        format: Option<&str>,
+    ) -> Result<String, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::os::ApplicationInfo { format })
+            .await
+            .map(|resp| resp.output)
+            .map_err(Into::into)
+    }
+
+    /// Fetch information on the device's bootloader
+    pub async fn os_bootloader_info(&self) -> Result<BootloaderInfo, MCUmgrClientError> {
+        let bootloader = self
+            .connection
+            .execute_command(&commands::os::BootloaderInfo)
+            .await?
+            .bootloader;
+
+        Ok(match bootloader.as_str() {
+            "MCUboot" => {
+                let mode_data = self
+                    .connection
+                    .execute_command(&commands::os::BootloaderInfoMcubootMode {})
+                    .await?;
+                BootloaderInfo::MCUboot {
+                    mode: mode_data.mode,
+                    no_downgrade: mode_data.no_downgrade,
+                }
+            }
+            name => BootloaderInfo::Unknown {
+                name: name.to_string(),
+            },
+        })
+    }
+
+    /// Obtain a list of images with their current state.
+    pub async fn image_get_state(
+        &self,
+    ) -> Result<Vec<commands::image::ImageState>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::image::GetImageState)
+            .await
+            .map(|val| val.images)
+            .map_err(Into::into)
+    }
+
+    /// Modify the current image state
+    ///
+    /// See [`MCUmgrClient::image_set_state`](crate::MCUmgrClient::image_set_state).
+    pub async fn image_set_state(
+        &self,
+        hash: Option<[u8; 32]>,
+        confirm: bool,
+    ) -> Result<Vec<commands::image::ImageState>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::image::SetImageState {
+                hash: hash.as_ref(),
+                confirm,
+            })
+            .await
+            .map(|val| val.images)
+            .map_err(Into::into)
+    }
+
+    /// Upload a firmware image to an image slot.
+    ///
+    /// See [`MCUmgrClient::image_upload`](crate::MCUmgrClient::image_upload).
+    pub async fn image_upload(
+        &self,
+        data: impl AsRef<[u8]>,
+        image: Option<u32>,
+        checksum: Option<[u8; 32]>,
+        upgrade_only: bool,
+        mut progress: Option<&mut (dyn FnMut(u64, u64) -> bool + Send)>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(), MCUmgrClientError> {
+        let chunk_size_max = image_upload_max_data_chunk_size(
+            self.smp_frame_size
+                .load(std::sync::atomic::Ordering::SeqCst),
+        )
+        .map_err(MCUmgrClientError::FrameSizeTooSmall)?;
+
+        let data = data.as_ref();
+
+        let actual_checksum: [u8; 32] = Sha256::digest(data).into();
+        if let Some(checksum) = checksum {
+            if actual_checksum != checksum {
+                return Err(MCUmgrClientError::ChecksumMismatch);
+            }
+        }
+
+        let window_size = self.window_size.load(std::sync::atomic::Ordering::SeqCst);
+        let mut pipeline = self
+            .connection
+            .pipeline(window_size)
+            .with_cancellation(cancel);
+        let mut chunk_ends = VecDeque::new();
+
+        let mut offset = 0;
+        let mut next_offset = 0;
+        let size = data.len();
+
+        let mut checksum_matched = None;
+
+        while offset < size {
+            while pipeline.can_send() && next_offset < size {
+                let current_chunk_size = (size - next_offset).min(chunk_size_max);
+                let chunk_data = &data[next_offset..next_offset + current_chunk_size];
+
+                if next_offset == 0 {
+                    pipeline
+                        .send(&commands::image::ImageUpload {
+                            image,
+                            len: Some(size as u64),
+                            off: next_offset as u64,
+                            sha: Some(&actual_checksum),
+                            data: chunk_data,
+                            upgrade: Some(upgrade_only),
+                        })
+                        .await?;
+                } else {
+                    pipeline
+                        .send(&commands::image::ImageUpload {
+                            image: None,
+                            len: None,
+                            off: next_offset as u64,
+                            sha: None,
+                            data: chunk_data,
+                            upgrade: None,
+                        })
+                        .await?;
+                }
+
+                next_offset += current_chunk_size;
+                chunk_ends.push_back(next_offset);
+            }
+
+            let upload_response = pipeline.receive().await?;
+
+            offset = upload_response
+                .off
+                .try_into()
+                .map_err(|_| MCUmgrClientError::UnexpectedOffset)?;
+
+            if offset > size {
+                return Err(MCUmgrClientError::UnexpectedOffset);
+            }
+
+            // The device continues at a different offset, for example when resuming
+            // a previous upload; the chunks in flight are of no use.
+            if chunk_ends.pop_front() != Some(offset) {
+                pipeline = self
+                    .connection
+                    .pipeline(window_size)
+                    .with_cancellation(cancel);
+                chunk_ends.clear();
+                next_offset = offset;
+            }
+
+            if let Some(progress) = &mut progress {
+                if !progress(offset as u64, size as u64) {
+                    return Err(MCUmgrClientError::ProgressCallbackError);
+                };
+            }
+
+            if let Some(is_match) = upload_response.r#match {
+                checksum_matched = Some(is_match);
+            }
+        }
+
+        if let Some(checksum_matched) = checksum_matched {
+            if !checksum_matched {
+                return Err(MCUmgrClientError::ChecksumMismatchOnDevice);
+            }
+        } else {
+            log::warn!("Device did not perform image checksum verification");
+        }
+
+        Ok(())
+    }
+
+    /// Erase image slot on target device.
+    ///
+    /// See [`MCUmgrClient::image_erase`](crate::MCUmgrClient::image_erase).
+    pub async fn image_erase(&self, slot: Option<u32>) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::image::ImageErase { slot })
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Obtain a list of available image slots.
+    pub async fn image_slot_info(
+        &self,
+    ) -> Result<Vec<commands::image::SlotInfoImage>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::image::SlotInfo)
+            .await
+            .map(|val| val.images)
+            .map_err(Into::into)
+    }
+
+    /// List the statistics groups of the device.
+    pub async fn stat_list(&self) -> Result<Vec<String>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::stat::ListGroups)
+            .await
+            .map(|val| val.stat_list)
+            .map_err(Into::into)
+    }
+
+    /// Read the statistics of a group.
+    ///
+    /// See [`MCUmgrClient::stat_read`](crate::MCUmgrClient::stat_read).
+    pub async fn stat_read(&self, group: &str) -> Result<BTreeMap<String, u64>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::stat::GroupData { name: group })
+            .await
+            .map(|val| val.fields)
+            .map_err(Into::into)
+    }
+
+    /// Read the value of a setting.
+    ///
+    /// See [`MCUmgrClient::settings_read`](crate::MCUmgrClient::settings_read).
+    pub async fn settings_read(
+        &self,
+        name: &str,
+        max_size: Option<u32>,
+    ) -> Result<Vec<u8>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::ReadSetting { name, max_size })
+            .await
+            .map(|val| val.val)
+            .map_err(Into::into)
+    }
+
+    /// Write the value of a setting.
+    ///
+    /// See [`MCUmgrClient::settings_write`](crate::MCUmgrClient::settings_write).
+    pub async fn settings_write(&self, name: &str, value: &[u8]) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::WriteSetting { name, val: value })
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Delete a setting from the persistent storage.
+    pub async fn settings_delete(&self, name: &str) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::DeleteSetting { name })
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Apply the written settings.
+    pub async fn settings_commit(&self) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::CommitSettings)
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Load the settings from the persistent storage.
+    pub async fn settings_load(&self) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::LoadSettings)
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Save the settings to the persistent storage.
+    pub async fn settings_save(&self) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::settings::SaveSettings)
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Load a file from the device.
+    ///
+    /// See [`MCUmgrClient::fs_file_download`](crate::MCUmgrClient::fs_file_download).
+    pub async fn fs_file_download<T: AsyncWrite + Unpin>(
+        &self,
+        name: impl AsRef<str>,
+        writer: T,
+        progress: Option<&mut (dyn FnMut(u64, u64) -> bool + Send)>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(), MCUmgrClientError> {
+        let result = self
+            .fs_file_download_chunks(name.as_ref(), writer, progress, cancel)
+            .await;
+        self.close_aborted_file(result).await
+    }
+
+    /// Downloads a file chunk by chunk, see [`fs_file_download`](Self::fs_file_download).
+    async fn fs_file_download_chunks<T: AsyncWrite + Unpin>(
+        &self,
+        name: &str,
+        mut writer: T,
+        mut progress: Option<&mut (dyn FnMut(u64, u64) -> bool + Send)>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(), MCUmgrClientError> {
+        let request = commands::fs::FileDownload { name, off: 0 };
+        let response = match cancel {
+            Some(cancel) => {
+                self.connection
+                    .execute_cancellable_command(&request, cancel)
+                    .await?
+            }
+            None => self.connection.execute_command(&request).await?,
+        };
+
+        let file_len = response.len.ok_or(MCUmgrClientError::MissingSize)?;
+        if response.off != 0 {
+            return Err(MCUmgrClientError::UnexpectedOffset);
+        }
+
+        let mut offset = 0;
+
+        if let Some(progress) = &mut progress {
+            if !progress(offset, file_len) {
+                return Err(MCUmgrClientError::ProgressCallbackError);
+            };
+        }
+
+        writer
+            .write_all(&response.data)
+            .await
+            .map_err(MCUmgrClientError::WriterError)?;
+        offset += response.data.len() as u64;
+
+        if let Some(progress) = &mut progress {
+            if !progress(offset, file_len) {
+                return Err(MCUmgrClientError::ProgressCallbackError);
+            };
+        }
+
+        // Assume that the device fills all responses like the first one
+        let chunk_size = (response.data.len() as u64).max(1);
+
+        let window_size = self.window_size.load(std::sync::atomic::Ordering::SeqCst);
+        let mut pipeline = self
+            .connection
+            .pipeline(window_size)
+            .with_cancellation(cancel);
+        let mut next_offset = offset;
+
+        while offset < file_len {
+            while pipeline.can_send() && next_offset < file_len {
+                pipeline
+                    .send(&commands::fs::FileDownload {
+                        name,
+                        off: next_offset,
+                    })
+                    .await?;
+                next_offset += chunk_size;
+            }
+
+            let response = pipeline.receive().await?;
+
+            if response.off != offset {
+                return Err(MCUmgrClientError::UnexpectedOffset);
+            }
+
+            writer
+                .write_all(&response.data)
+                .await
+                .map_err(MCUmgrClientError::WriterError)?;
+            offset += response.data.len() as u64;
+
+            // The requests in flight are for the wrong offsets if the device sent less
+            if response.data.len() as u64 != chunk_size {
+                pipeline = self
+                    .connection
+                    .pipeline(window_size)
+                    .with_cancellation(cancel);
+                next_offset = offset;
+            }
+
+            if let Some(progress) = &mut progress {
+                if !progress(offset, file_len) {
+                    return Err(MCUmgrClientError::ProgressCallbackError);
+                };
+            }
+        }
+
+        if offset != file_len {
+            return Err(MCUmgrClientError::SizeMismatch);
+        }
+
+        writer
+            .flush()
+            .await
+            .map_err(MCUmgrClientError::WriterError)?;
+
+        Ok(())
+    }
+
+    /// Write a file to the device.
+    ///
+    /// See [`MCUmgrClient::fs_file_upload`](crate::MCUmgrClient::fs_file_upload).
+    pub async fn fs_file_upload<T: AsyncRead + Unpin>(
+        &self,
+        name: impl AsRef<str>,
+        reader: T,
+        size: u64,
+        progress: Option<&mut (dyn FnMut(u64, u64) -> bool + Send)>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(), MCUmgrClientError> {
+        let result = self
+            .fs_file_upload_chunks(name.as_ref(), reader, size, progress, cancel)
+            .await;
+        self.close_aborted_file(result).await
+    }
+
+    /// Uploads a file chunk by chunk, see [`fs_file_upload`](Self::fs_file_upload).
+    async fn fs_file_upload_chunks<T: AsyncRead + Unpin>(
+        &self,
+        name: &str,
+        mut reader: T,
+        size: u64,
+        mut progress: Option<&mut (dyn FnMut(u64, u64) -> bool + Send)>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(), MCUmgrClientError> {
+        let chunk_size_max = file_upload_max_data_chunk_size(
+            self.smp_frame_size
+                .load(std::sync::atomic::Ordering::SeqCst),
+            name,
+        )
+        .map_err(MCUmgrClientError::FrameSizeTooSmall)?;
+        let mut data_buffer = vec![0u8; chunk_size_max].into_boxed_slice();
+
+        let window_size = self.window_size.load(std::sync::atomic::Ordering::SeqCst);
+        let mut pipeline = self
+            .connection
+            .pipeline(window_size)
+            .with_cancellation(cancel);
+        let mut chunk_ends = VecDeque::new();
+
+        let mut offset = 0;
+        let mut next_offset = 0;
+
+        while offset < size {
+            while pipeline.can_send() && next_offset < size {
+                let current_chunk_size =
+                    (size - next_offset).min(data_buffer.len() as u64) as usize;
+
+                let chunk_buffer = &mut data_buffer[..current_chunk_size];
+                reader
+                    .read_exact(chunk_buffer)
+                    .await
+                    .map_err(MCUmgrClientError::ReaderError)?;
+
+                pipeline
+                    .send(&commands::fs::FileUpload {
+                        off: next_offset,
+                        data: chunk_buffer,
+                        name,
+                        len: if next_offset == 0 { Some(size) } else { None },
+                    })
+                    .await?;
+
+                next_offset += current_chunk_size as u64;
+                chunk_ends.push_back(next_offset);
+            }
+
+            let upload_response = pipeline.receive().await?;
+
+            offset = chunk_ends.pop_front().unwrap_or_default();
+
+            if upload_response.off != offset {
+                return Err(MCUmgrClientError::UnexpectedOffset);
+            }
+
+            if let Some(progress) = &mut progress {
+                if !progress(offset, size) {
+                    return Err(MCUmgrClientError::ProgressCallbackError);
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Closes the file of an aborted transfer, so that the device does not keep it open.
+    async fn close_aborted_file(
+        &self,
+        result: Result<(), MCUmgrClientError>,
+    ) -> Result<(), MCUmgrClientError> {
+        if let Err(MCUmgrClientError::Cancelled | MCUmgrClientError::ProgressCallbackError) =
+            &result
+        {
+            if let Err(e) = self.fs_file_close().await {
+                log::warn!("Failed to close the file of the aborted transfer: {e}");
+            }
+        }
+
+        result
+    }
+
+    /// Queries the file status
+    pub async fn fs_file_status(
+        &self,
+        name: impl AsRef<str>,
+    ) -> Result<commands::fs::FileStatusResponse, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::fs::FileStatus {
+                name: name.as_ref(),
+            })
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Computes the hash/checksum of a file
+    ///
+    /// See [`MCUmgrClient::fs_file_checksum`](crate::MCUmgrClient::fs_file_checksum).
+    pub async fn fs_file_checksum(
+        &self,
+        name: impl AsRef<str>,
+        algorithm: Option<impl AsRef<str>>,
+        offset: u64,
+        length: Option<u64>,
+    ) -> Result<commands::fs::FileChecksumResponse, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::fs::FileChecksum {
+                name: name.as_ref(),
+                r#type: algorithm.as_ref().map(AsRef::as_ref),
+                off: offset,
+                len: length,
+            })
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Queries which hash/checksum algorithms are available on the target
+    pub async fn fs_supported_checksum_types(
+        &self,
+    ) -> Result<HashMap<String, commands::fs::FileChecksumProperties>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::fs::SupportedFileChecksumTypes)
+            .await
+            .map(|val| val.types)
+            .map_err(Into::into)
+    }
+
+    /// Close all device files MCUmgr has currently open
+    pub async fn fs_file_close(&self) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::fs::FileClose)
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Run a shell command.
+    ///
+    /// See [`MCUmgrClient::shell_execute`](crate::MCUmgrClient::shell_execute).
+    pub async fn shell_execute(&self, argv: &[String]) -> Result<(i32, String), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::shell::ShellCommandLineExecute { argv })
+            .await
+            .map(|ret| (ret.ret, ret.o))
+            .map_err(Into::into)
+    }
+
+    /// Query the number of management groups the device supports.
+    pub async fn enum_group_count(&self) -> Result<u16, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::enumeration::GroupCount)
+            .await
+            .map(|resp| resp.count)
+            .map_err(Into::into)
+    }
+
+    /// Query the IDs of the management groups the device supports.
+    pub async fn enum_group_list(&self) -> Result<Vec<u16>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::enumeration::GroupList)
+            .await
+            .map(|resp| resp.groups)
+            .map_err(Into::into)
+    }
+
+    /// Query the ID of a single supported management group.
+    ///
+    /// See [`MCUmgrClient::enum_group_id`](crate::MCUmgrClient::enum_group_id).
+    pub async fn enum_group_id(&self, index: u16) -> Result<(u16, bool), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::enumeration::GroupId { index })
+            .await
+            .map(|resp| (resp.group, resp.end))
+            .map_err(Into::into)
+    }
+
+    /// Query the names and handler counts of supported management groups.
+    ///
+    /// See [`MCUmgrClient::enum_group_details`](crate::MCUmgrClient::enum_group_details).
+    pub async fn enum_group_details(
+        &self,
+        groups: &[u16],
+    ) -> Result<Vec<commands::enumeration::GroupDetailsEntry>, MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::enumeration::GroupDetails { groups })
+            .await
+            .map(|resp| resp.groups)
+            .map_err(Into::into)
+    }
+
+    /// Determine which management groups the device supports.
+    ///
+    /// See [`MCUmgrClient::capabilities`](crate::MCUmgrClient::capabilities).
+    pub async fn capabilities(&self) -> Result<DeviceCapabilities, MCUmgrClientError> {
+        let group_ids = match self.enum_group_list().await {
+            Ok(group_ids) => group_ids,
+            Err(e) if e.command_not_supported() => {
+                log::debug!("Enumeration group not supported, probing groups.");
+
+                let mut group_ids = vec![];
+                for probe in capabilities::group_probes() {
+                    if group_ids.contains(&probe.group_id()) {
+                        continue;
+                    }
+                    if capabilities::probe_succeeded(self.raw_command(&probe).await)? {
+                        group_ids.push(probe.group_id());
+                    }
+                }
+
+                return Ok(DeviceCapabilities::from_probes(group_ids));
+            }
+            Err(e) => return Err(e),
+        };
+
+        let details = match self.enum_group_details(&[]).await {
+            Ok(details) => Some(details),
+            Err(e) if e.command_not_supported() => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(DeviceCapabilities::from_enumeration(group_ids, details))
+    }
+
+    /// Erase the `storage_partition` flash partition.
+    pub async fn zephyr_erase_storage(&self) -> Result<(), MCUmgrClientError> {
+        self.connection
+            .execute_command(&commands::zephyr::EraseStorage)
+            .await
+            .map(Into::into)
+            .map_err(Into::into)
+    }
+
+    /// Execute a raw [`commands::McuMgrCommand`].
+    ///
+    /// See [`MCUmgrClient::raw_command`](crate::MCUmgrClient::raw_command).
+    pub async fn raw_command<T: commands::McuMgrCommand + Sync>(
+        &self,
+        command: &T,
+    ) -> Result<T::Response, MCUmgrClientError> {
+        self.connection
+            .execute_command(command)
+            .await
+            .map_err(Into::into)
+    }
+}
@@ -0,0 +1,525 @@
+use std::{fmt, num::ParseIntError, path::PathBuf, str::FromStr, time::Duration};
+
+use miette::Diagnostic;
+use serialport::{DataBits, Parity};
+use thiserror::Error;
+
+use crate::{
+    connection::RetryPolicy,
+    transport::{
+        SmpVersion,
+        recording::{Recording, ReplayTransport},
+        rfc2217::{RFC2217_URL_PREFIX, Rfc2217Settings},
+    },
+};
+
+use super::{MCUmgrClient, UsbSerialError};
+
+/// The environment variable that [`ClientBuilder::from_env`] reads the connection string from.
+pub const MCUMGR_CONNECTION_ENV: &str = "MCUMGR_CONNECTION";
+
+/// The default baud rate of serial based connections.
+pub const DEFAULT_BAUD_RATE: u32 = 115200;
+
+/// The default communication timeout.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Describes how to connect to a device.
+///
+/// Can be parsed from and formatted as a connection string:
+///
+/// | Connection string                   | Backend                                        |
+/// |-------------------------------------|------------------------------------------------|
+/// | `serial:///dev/ttyUSB0?baud=115200` | Serial port, `serial://COM3` on Windows        |
+/// | `usb://2fe3:0100:1?baud=115200`     | USB serial port, see [`MCUmgrClient::new_from_usb_serial`] |
+/// | `rfc2217://host:port?baud=115200`   | RFC 2217 terminal server                       |
+/// | `udp://[fe80::1]:1337`              | UDP                                            |
+/// | `tcp://host:port`                   | TCP serial bridge                              |
+/// | `unix:///path/to/socket`            | Unix socket                                    |
+/// | `replay://session.jsonl`            | Recorded session, see [`ReplayTransport`]      |
+///
+/// The `baud` parameter is optional; without it, the baud rate of
+/// the [`ClientBuilder`] is used.
+///
+/// RFC 2217 connections additionally accept `data_bits=5..8`, `parity=none|odd|even`
+/// and `dtr=on|off`/`rts=on|off`, for example `rfc2217://host:port?parity=even&dtr=off`.
+/// Without them, the port uses 8 data bits, no parity and keeps the modem lines untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionUrl {
+    /// A serial port, like `/dev/ttyUSB0` or `COM3`
+    Serial {
+        /// The name of the serial port
+        port: String,
+        /// The baud rate, if given in the connection string
+        baud_rate: Option<u32>,
+    },
+    /// A USB serial port identified by VID:PID
+    UsbSerial {
+        /// A regex that identifies the device, see [`MCUmgrClient::new_from_usb_serial`]
+        identifier: String,
+        /// The baud rate, if given in the connection string
+        baud_rate: Option<u32>,
+    },
+    /// A serial port behind an RFC 2217 terminal server
+    Rfc2217 {
+        /// The address of the server, as `host:port`
+        addr: String,
+        /// The baud rate, if given in the connection string
+        baud_rate: Option<u32>,
+        /// The number of data bits, if given in the connection string
+        data_bits: Option<DataBits>,
+        /// The parity, if given in the connection string
+        parity: Option<Parity>,
+        /// The state of the DTR line, if given in the connection string
+        dtr: Option<bool>,
+        /// The state of the RTS line, if given in the connection string
+        rts: Option<bool>,
+    },
+    /// A device that talks SMP over UDP
+    Udp {
+        /// The address of the device, as `host:port`
+        addr: String,
+    },
+    /// A serial port behind a TCP bridge
+    Tcp {
+        /// The address of the bridge, as `host:port`
+        addr: String,
+    },
+    /// A serial port exposed as a Unix socket
+    Unix {
+        /// The path of the socket
+        path: PathBuf,
+    },
+    /// A recorded session that gets played back
+    Replay {
+        /// The path of the recording
+        path: PathBuf,
+    },
+}
+
+/// Possible error values of parsing a [`ConnectionUrl`].
+#[derive(Error, Debug, Diagnostic)]
+pub enum ConnectionUrlError {
+    /// The connection string does not start with `scheme://`
+    #[error("Connection string '{0}' is missing a scheme, like 'serial://'")]
+    #[diagnostic(code(mcumgr_toolkit::connection_url::missing_scheme))]
+    MissingScheme(String),
+    /// The scheme is not supported
+    #[error("Unknown connection scheme '{0}'")]
+    #[diagnostic(
+        code(mcumgr_toolkit::connection_url::unknown_scheme),
+        help("Supported schemes: serial, usb, rfc2217, udp, tcp, unix, replay")
+    )]
+    UnknownScheme(String),
+    /// Nothing follows the scheme
+    #[error("Connection string is missing a port, address or path")]
+    #[diagnostic(code(mcumgr_toolkit::connection_url::missing_target))]
+    MissingTarget,
+    /// The parameter is not supported by the scheme
+    #[error("Unknown connection parameter '{0}'")]
+    #[diagnostic(code(mcumgr_toolkit::connection_url::unknown_parameter))]
+    UnknownParameter(String),
+    /// The baud rate is not a number
+    #[error("Invalid baud rate")]
+    #[diagnostic(code(mcumgr_toolkit::connection_url::invalid_baud_rate))]
+    InvalidBaudRate(#[source] ParseIntError),
+    /// The value of a parameter is not supported
+    #[error("Invalid value of connection parameter '{0}'")]
+    #[diagnostic(
+        code(mcumgr_toolkit::connection_url::invalid_parameter),
+        help("Supported values: data_bits=5..8, parity=none|odd|even, dtr=on|off, rts=on|off")
+    )]
+    InvalidParameter(String),
+    /// The environment variable does not contain valid unicode
+    #[error("Environment variable {MCUMGR_CONNECTION_ENV} is not valid unicode")]
+    #[diagnostic(code(mcumgr_toolkit::connection_url::not_unicode))]
+    NotUnicode,
+}
+
+impl ConnectionUrl {
+    /// Selects a serial port by name, or an RFC 2217 server if the name
+    /// starts with [`RFC2217_URL_PREFIX`].
+    ///
+    /// RFC 2217 names are parsed as connection strings, so they may contain parameters.
+    pub fn serial(name: &str) -> Result<Self, ConnectionUrlError> {
+        if name.starts_with(RFC2217_URL_PREFIX) {
+            name.parse()
+        } else {
+            Ok(Self::Serial {
+                port: name.to_string(),
+                baud_rate: None,
+            })
+        }
+    }
+
+    fn baud_rate(&self) -> Option<u32> {
+        match self {
+            Self::Serial { baud_rate, .. }
+            | Self::UsbSerial { baud_rate, .. }
+            | Self::Rfc2217 { baud_rate, .. } => *baud_rate,
+            Self::Udp { .. } | Self::Tcp { .. } | Self::Unix { .. } | Self::Replay { .. } => None,
+        }
+    }
+}
+
+impl FromStr for ConnectionUrl {
+    type Err = ConnectionUrlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = s
+            .split_once("://")
+            .ok_or_else(|| ConnectionUrlError::MissingScheme(s.to_string()))?;
+
+        if !matches!(
+            scheme,
+            "serial" | "usb" | "rfc2217" | "udp" | "tcp" | "unix" | "replay"
+        ) {
+            return Err(ConnectionUrlError::UnknownScheme(scheme.to_string()));
+        }
+
+        let (target, query) = match rest.split_once('?') {
+            Some((target, query)) => (target, Some(query)),
+            None => (rest, None),
+        };
+        if target.is_empty() {
+            return Err(ConnectionUrlError::MissingTarget);
+        }
+        let target = target.to_string();
+
+        let mut baud_rate = None;
+        let mut data_bits = None;
+        let mut parity = None;
+        let mut dtr = None;
+        let mut rts = None;
+        for param in query.into_iter().flat_map(|query| query.split('&')) {
+            let invalid = || ConnectionUrlError::InvalidParameter(param.to_string());
+            match param.split_once('=') {
+                Some(("baud", value)) if matches!(scheme, "serial" | "usb" | "rfc2217") => {
+                    baud_rate = Some(value.parse().map_err(ConnectionUrlError::InvalidBaudRate)?);
+                }
+                Some(("data_bits", value)) if scheme == "rfc2217" => {
+                    let bits = value.parse::<u8>().map_err(|_| invalid())?;
+                    data_bits = Some(DataBits::try_from(bits).map_err(|_| invalid())?);
+                }
+                Some(("parity", value)) if scheme == "rfc2217" => {
+                    parity = Some(match value {
+                        "none" => Parity::None,
+                        "odd" => Parity::Odd,
+                        "even" => Parity::Even,
+                        _ => return Err(invalid()),
+                    });
+                }
+                Some(("dtr", value)) if scheme == "rfc2217" => {
+                    dtr = Some(parse_line_state(value).ok_or_else(invalid)?);
+                }
+                Some(("rts", value)) if scheme == "rfc2217" => {
+                    rts = Some(parse_line_state(value).ok_or_else(invalid)?);
+                }
+                _ => return Err(ConnectionUrlError::UnknownParameter(param.to_string())),
+            }
+        }
+
+        Ok(match scheme {
+            "serial" => Self::Serial {
+                port: target,
+                baud_rate,
+            },
+            "usb" => Self::UsbSerial {
+                identifier: target,
+                baud_rate,
+            },
+            "rfc2217" => Self::Rfc2217 {
+                addr: target,
+                baud_rate,
+                data_bits,
+                parity,
+                dtr,
+                rts,
+            },
+            "udp" => Self::Udp { addr: target },
+            "tcp" => Self::Tcp { addr: target },
+            "unix" => Self::Unix {
+                path: target.into(),
+            },
+            "replay" => Self::Replay {
+                path: target.into(),
+            },
+            _ => unreachable!("Scheme was checked above"),
+        })
+    }
+}
+
+fn parse_line_state(value: &str) -> Option<bool> {
+    match value {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
+
+impl fmt::Display for ConnectionUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Serial { port, .. } => write!(f, "serial://{port}")?,
+            Self::UsbSerial { identifier, .. } => write!(f, "usb://{identifier}")?,
+            Self::Rfc2217 { addr, .. } => write!(f, "rfc2217://{addr}")?,
+            Self::Udp { addr } => write!(f, "udp://{addr}")?,
+            Self::Tcp { addr } => write!(f, "tcp://{addr}")?,
+            Self::Unix { path } => write!(f, "unix://{}", path.display())?,
+            Self::Replay { path } => write!(f, "replay://{}", path.display())?,
+        }
+
+        let mut params = vec![];
+        if let Some(baud_rate) = self.baud_rate() {
+            params.push(format!("baud={baud_rate}"));
+        }
+        if let Self::Rfc2217 {
+            data_bits,
+            parity,
+            dtr,
+            rts,
+            ..
+        } = self
+        {
+            if let Some(data_bits) = data_bits {
+                params.push(format!("data_bits={}", u8::from(*data_bits)));
+            }
+            if let Some(parity) = parity {
+                params.push(format!("parity={}", parity.to_string().to_lowercase()));
+            }
+            let line_state = |level: bool| if level { "on" } else { "off" };
+            if let Some(dtr) = dtr {
+                params.push(format!("dtr={}", line_state(*dtr)));
+            }
+            if let Some(rts) = rts {
+                params.push(format!("rts={}", line_state(*rts)));
+            }
+        }
+
+        if !params.is_empty() {
+            write!(f, "?{}", params.join("&"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Possible error values of [`ClientBuilder::build`].
+#[derive(Error, Debug, Diagnostic)]
+pub enum ClientBuilderError {
+    /// Opening the serial port failed
+    #[error("Failed to open serial port")]
+    #[diagnostic(code(mcumgr_toolkit::client_builder::serial))]
+    OpenSerialFailed(#[source] serialport::Error),
+    /// Opening the USB serial port failed
+    #[error("Failed to open USB serial port")]
+    #[diagnostic(code(mcumgr_toolkit::client_builder::usb_serial))]
+    UsbSerialOpenFailed(#[from] UsbSerialError),
+    /// Connecting to a network or socket based backend failed
+    #[error("Failed to connect to {0}")]
+    #[diagnostic(code(mcumgr_toolkit::client_builder::connect))]
+    ConnectFailed(String, #[source] std::io::Error),
+    /// Opening the recording of a replayed session failed
+    #[error("Failed to open recording")]
+    #[diagnostic(code(mcumgr_toolkit::client_builder::replay))]
+    OpenReplayFailed(#[source] std::io::Error),
+}
+
+/// Creates an [`MCUmgrClient`] from a [`ConnectionUrl`].
+///
+/// ```no_run
+/// # use mcumgr_toolkit::client::ClientBuilder;
+/// # fn main() {
+/// let client = ClientBuilder::from_url("usb://2fe3:0100:1?baud=115200")
+///     .unwrap()
+///     .timeout(std::time::Duration::from_secs(5))
+///     .auto_frame_size(true)
+///     .build()
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    url: ConnectionUrl,
+    baud_rate: u32,
+    timeout: Duration,
+    frame_size: Option<usize>,
+    auto_frame_size: bool,
+    retry_policy: RetryPolicy,
+    smp_version: Option<SmpVersion>,
+    recording: Option<Recording>,
+}
+
+impl ClientBuilder {
+    /// Creates a builder for the given connection.
+    pub fn new(url: ConnectionUrl) -> Self {
+        Self {
+            url,
+            baud_rate: DEFAULT_BAUD_RATE,
+            timeout: DEFAULT_TIMEOUT,
+            frame_size: None,
+            auto_frame_size: false,
+            retry_policy: RetryPolicy::default(),
+            smp_version: None,
+            recording: None,
+        }
+    }
+
+    /// Creates a builder from a connection string, see [`ConnectionUrl`].
+    pub fn from_url(url: &str) -> Result<Self, ConnectionUrlError> {
+        Ok(Self::new(url.parse()?))
+    }
+
+    /// Creates a builder from the connection string in the
+    /// [`MCUMGR_CONNECTION`](MCUMGR_CONNECTION_ENV) environment variable.
+    ///
+    /// # Return
+    ///
+    /// `None` if the environment variable is not set.
+    pub fn from_env() -> Result<Option<Self>, ConnectionUrlError> {
+        match std::env::var(MCUMGR_CONNECTION_ENV) {
+            Ok(url) => Self::from_url(&url).map(Some),
+            Err(std::env::VarError::NotPresent) => Ok(None),
+            Err(std::env::VarError::NotUnicode(_)) => Err(ConnectionUrlError::NotUnicode),
+        }
+    }
+
+    /// The connection the client will be created for.
+    pub fn url(&self) -> &ConnectionUrl {
+        &self.url
+    }
+
+    /// Sets the baud rate of serial based connections,
+    /// unless the connection string contains one.
+    ///
+    /// Defaults to [`DEFAULT_BAUD_RATE`].
+    pub fn baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// Sets the communication timeout.
+    ///
+    /// Defaults to [`DEFAULT_TIMEOUT`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the maximum SMP frame size, see [`MCUmgrClient::set_frame_size`].
+    pub fn frame_size(mut self, smp_frame_size: usize) -> Self {
+        self.frame_size = Some(smp_frame_size);
+        self
+    }
+
+    /// Reads the maximum SMP frame size from the device after connecting,
+    /// see [`MCUmgrClient::use_auto_frame_size`].
+    ///
+    /// If the device does not report it, a warning gets logged and the client keeps
+    /// the frame size of [`frame_size`](Self::frame_size) or Zephyr's default.
+    pub fn auto_frame_size(mut self, auto_frame_size: bool) -> Self {
+        self.auto_frame_size = auto_frame_size;
+        self
+    }
+
+    /// Sets the retransmission policy, see [`MCUmgrClient::set_retry_policy`].
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Selects the SMP protocol version of requests, see [`MCUmgrClient::set_smp_version`].
+    pub fn smp_version(mut self, version: Option<SmpVersion>) -> Self {
+        self.smp_version = version;
+        self
+    }
+
+    /// Records all frames of the session, including the ones sent while connecting.
+    ///
+    /// See [`MCUmgrClient::wrap_transport`].
+    pub fn record(mut self, recording: Recording) -> Self {
+        self.recording = Some(recording);
+        self
+    }
+
+    /// Connects to the device.
+    pub fn build(self) -> Result<MCUmgrClient, ClientBuilderError> {
+        let baud_rate = self.url.baud_rate().unwrap_or(self.baud_rate);
+        let timeout = self.timeout;
+        let connect_failed = |e| ClientBuilderError::ConnectFailed(self.url.to_string(), e);
+
+        let client = match &self.url {
+            ConnectionUrl::Serial { port, .. } => {
+                let serial = serialport::new(port, baud_rate)
+                    .timeout(timeout)
+                    .open()
+                    .map_err(ClientBuilderError::OpenSerialFailed)?;
+                MCUmgrClient::new_from_serial(serial).with_known_timeout(timeout)
+            }
+            ConnectionUrl::UsbSerial { identifier, .. } => {
+                MCUmgrClient::new_from_usb_serial(identifier, baud_rate, timeout)?
+            }
+            ConnectionUrl::Rfc2217 {
+                addr,
+                data_bits,
+                parity,
+                dtr,
+                rts,
+                ..
+            } => {
+                let defaults = Rfc2217Settings::new(baud_rate);
+                let settings = Rfc2217Settings {
+                    data_bits: data_bits.unwrap_or(defaults.data_bits),
+                    parity: parity.unwrap_or(defaults.parity),
+                    dtr: *dtr,
+                    rts: *rts,
+                    ..defaults
+                };
+                MCUmgrClient::new_from_rfc2217(addr.as_str(), settings, timeout)
+                    .map_err(connect_failed)?
+            }
+            ConnectionUrl::Udp { addr } => {
+                MCUmgrClient::new_from_udp(addr.as_str(), timeout).map_err(connect_failed)?
+            }
+            ConnectionUrl::Tcp { addr } => {
+                MCUmgrClient::new_from_tcp(addr.as_str(), timeout).map_err(connect_failed)?
+            }
+            #[cfg(unix)]
+            ConnectionUrl::Unix { path } => {
+                MCUmgrClient::new_from_unix(path, timeout).map_err(connect_failed)?
+            }
+            #[cfg(not(unix))]
+            ConnectionUrl::Unix { .. } => {
+                return Err(connect_failed(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "Unix sockets are not supported on this platform",
+                )));
+            }
+            ConnectionUrl::Replay { path } => MCUmgrClient::new_from_transport(
+                ReplayTransport::open(path).map_err(ClientBuilderError::OpenReplayFailed)?,
+            ),
+        };
+
+        if let Some(recording) = self.recording {
+            client.wrap_transport(Box::new(move |transport| {
+                Box::new(recording.record(transport))
+            }));
+        }
+
+        client.set_retry_policy(self.retry_policy);
+        client.set_smp_version(self.smp_version);
+
+        if let Some(frame_size) = self.frame_size {
+            client.set_frame_size(frame_size);
+        }
+
+        if self.auto_frame_size {
+            if let Err(e) = client.use_auto_frame_size() {
+                log::warn!("Failed to read SMP frame size from device, using slow default");
+                log::warn!("Reason: {e}");
+                log::warn!("Hint: Make sure that `CONFIG_MCUMGR_GRP_OS_MCUMGR_PARAMS` is enabled.");
+            }
+        }
+
+        Ok(client)
+    }
+}
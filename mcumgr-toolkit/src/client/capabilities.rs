@@ -0,0 +1,182 @@
+use serde::Serialize;
+
+use crate::{
+    MCUmgrGroup,
+    client::MCUmgrClientError,
+    commands::{McuMgrCommand, enumeration::GroupDetailsEntry},
+    connection::ExecuteError,
+};
+
+/// A management group that a device supports
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct GroupCapabilities {
+    /// ID of the group
+    pub group_id: u16,
+    /// name of the group, if reported by the device
+    pub name: Option<String>,
+    /// number of command handlers of the group, if reported by the device
+    pub handlers: Option<u32>,
+}
+
+/// The management groups that a device supports
+///
+/// Returned by [`MCUmgrClient::capabilities`](crate::MCUmgrClient::capabilities).
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct DeviceCapabilities {
+    /// Whether the groups were probed, because the device lacks the enumeration group.
+    ///
+    /// Probing only finds the groups known to this crate, without names or handler counts.
+    pub probed: bool,
+    /// The supported groups, ordered by ID
+    pub groups: Vec<GroupCapabilities>,
+}
+
+impl DeviceCapabilities {
+    /// Checks whether the device supports the given group
+    pub fn supports(&self, group: MCUmgrGroup) -> bool {
+        self.supports_group_id(group as u16)
+    }
+
+    /// Checks whether the device supports the group with the given ID
+    pub fn supports_group_id(&self, group_id: u16) -> bool {
+        self.groups.iter().any(|group| group.group_id == group_id)
+    }
+
+    /// Combines the groups listed by the device with their details, if available.
+    pub(super) fn from_enumeration(
+        group_ids: Vec<u16>,
+        details: Option<Vec<GroupDetailsEntry>>,
+    ) -> Self {
+        let details = details.unwrap_or_default();
+
+        let mut groups = group_ids
+            .into_iter()
+            .map(|group_id| {
+                let details = details.iter().find(|entry| entry.group == group_id);
+                GroupCapabilities {
+                    group_id,
+                    name: details.and_then(|entry| entry.name.clone()),
+                    handlers: details.and_then(|entry| entry.handlers),
+                }
+            })
+            .collect::<Vec<_>>();
+        groups.sort_by_key(|group| group.group_id);
+
+        Self {
+            probed: false,
+            groups,
+        }
+    }
+
+    pub(super) fn from_probes(mut group_ids: Vec<u16>) -> Self {
+        group_ids.sort();
+        group_ids.dedup();
+
+        Self {
+            probed: true,
+            groups: group_ids
+                .into_iter()
+                .map(|group_id| GroupCapabilities {
+                    group_id,
+                    name: None,
+                    handlers: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A request without side effects, to check whether the device supports its group.
+pub(super) struct GroupProbe {
+    group: MCUmgrGroup,
+    command_id: u8,
+    write: bool,
+    data: ciborium::Value,
+}
+
+impl McuMgrCommand for GroupProbe {
+    type Payload = ciborium::Value;
+    type Response = ciborium::Value;
+
+    fn is_write_operation(&self) -> bool {
+        self.write
+    }
+
+    fn group_id(&self) -> u16 {
+        self.group as u16
+    }
+
+    fn command_id(&self) -> u8 {
+        self.command_id
+    }
+
+    fn data(&self) -> &Self::Payload {
+        &self.data
+    }
+}
+
+/// The probes for all groups that can be checked without side effects.
+///
+/// Groups have several probes, as most of their commands can be disabled individually.
+/// The Zephyr management group is missing, its only command erases the storage.
+pub(super) fn group_probes() -> Vec<GroupProbe> {
+    use ciborium::Value;
+
+    let probe = |group, command_id, write, data: Vec<(&str, Value)>| GroupProbe {
+        group,
+        command_id,
+        write,
+        data: Value::Map(
+            data.into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        ),
+    };
+
+    vec![
+        probe(
+            MCUmgrGroup::MGMT_GROUP_ID_OS,
+            0,
+            false,
+            vec![("d", "".into())],
+        ),
+        probe(MCUmgrGroup::MGMT_GROUP_ID_OS, 6, false, vec![]),
+        probe(MCUmgrGroup::MGMT_GROUP_ID_IMAGE, 0, false, vec![]),
+        probe(MCUmgrGroup::MGMT_GROUP_ID_STAT, 1, false, vec![]),
+        probe(
+            MCUmgrGroup::MGMT_GROUP_ID_SETTINGS,
+            0,
+            false,
+            vec![("name", "".into())],
+        ),
+        probe(
+            MCUmgrGroup::MGMT_GROUP_ID_FS,
+            1,
+            false,
+            vec![("name", "/".into())],
+        ),
+        probe(MCUmgrGroup::MGMT_GROUP_ID_FS, 3, false, vec![]),
+        // Empty command lines get rejected without running anything
+        probe(
+            MCUmgrGroup::MGMT_GROUP_ID_SHELL,
+            0,
+            true,
+            vec![("argv", Value::Array(vec![]))],
+        ),
+    ]
+}
+
+/// Checks whether the outcome of a probe shows that the device supports the group.
+///
+/// Any error reported by the device, except for unsupported commands,
+/// comes from the group's handler and therefore counts as supported.
+pub(super) fn probe_succeeded(
+    result: Result<ciborium::Value, MCUmgrClientError>,
+) -> Result<bool, MCUmgrClientError> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(e) if e.command_not_supported() => Ok(false),
+        Err(MCUmgrClientError::ExecuteError(ExecuteError::ErrorResponse(_))) => Ok(true),
+        Err(e) => Err(e),
+    }
+}
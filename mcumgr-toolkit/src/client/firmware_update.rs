@@ -3,7 +3,13 @@ use std::fmt::Display;
 use miette::Diagnostic;
 use thiserror::Error;
 
-use crate::{MCUmgrClient, bootloader::BootloaderType, client::MCUmgrClientError, mcuboot};
+use crate::{
+    MCUmgrClient,
+    bootloader::BootloaderType,
+    client::MCUmgrClientError,
+    connection::{CancellationToken, check_cancelled},
+    mcuboot,
+};
 
 /// Possible error values of [`MCUmgrClient::firmware_update`].
 #[derive(Error, Debug, Diagnostic)]
@@ -12,6 +18,10 @@ pub enum FirmwareUpdateError {
     #[error("Progress callback returned an error")]
     #[diagnostic(code(mcumgr_toolkit::firmware_update::progress_cb_error))]
     ProgressCallbackError,
+    /// The update was cancelled through its [`CancellationToken`].
+    #[error("Firmware update was cancelled")]
+    #[diagnostic(code(mcumgr_toolkit::firmware_update::cancelled))]
+    Cancelled,
     /// An error occurred while trying to detect the bootloader.
     #[error("Failed to detect bootloader")]
     #[diagnostic(code(mcumgr_toolkit::firmware_update::detect_bootloader))]
@@ -148,8 +158,31 @@ impl Display for FirmwareUpdateStep {
 pub type FirmwareUpdateProgressCallback<'a> =
     dyn FnMut(FirmwareUpdateStep, Option<(u64, u64)>) -> bool + 'a;
 
+/// The progress callback type of [`AsyncMCUmgrClient::firmware_update`](crate::AsyncMCUmgrClient::firmware_update).
+///
+/// Same as [`FirmwareUpdateProgressCallback`], but [`Send`].
+#[cfg(feature = "tokio")]
+pub type AsyncFirmwareUpdateProgressCallback<'a> =
+    dyn FnMut(FirmwareUpdateStep, Option<(u64, u64)>) -> bool + Send + 'a;
+
 const SHOWN_HASH_DIGITS: usize = 4;
 
+/// Converts the error of the firmware upload.
+///
+/// The progress callback of the upload also fails once the update was cancelled.
+fn upload_error(err: MCUmgrClientError, cancel: Option<&CancellationToken>) -> FirmwareUpdateError {
+    if check_cancelled(cancel).is_err() {
+        return FirmwareUpdateError::Cancelled;
+    }
+
+    match err {
+        // Users expect this error when the progress callback errors
+        MCUmgrClientError::ProgressCallbackError => FirmwareUpdateError::ProgressCallbackError,
+        MCUmgrClientError::Cancelled => FirmwareUpdateError::Cancelled,
+        err => FirmwareUpdateError::ImageUploadFailed(err),
+    }
+}
+
 /// High-level firmware update routine
 ///
 /// # Arguments
@@ -159,6 +192,7 @@ const SHOWN_HASH_DIGITS: usize = 4;
 /// * `checksum` - SHA256 of the firmware image. Optional.
 /// * `params` - Configurable parameters.
 /// * `progress` - A callback that receives progress updates.
+/// * `cancel` - Aborts the update once cancelled.
 ///
 pub(crate) fn firmware_update(
     client: &MCUmgrClient,
@@ -166,6 +200,7 @@ pub(crate) fn firmware_update(
     checksum: Option<[u8; 32]>,
     params: FirmwareUpdateParams,
     mut progress: Option<&mut FirmwareUpdateProgressCallback>,
+    cancel: Option<&CancellationToken>,
 ) -> Result<(), FirmwareUpdateError> {
     // Might become a params member in the future
     let target_image: Option<u32> = Default::default();
@@ -175,6 +210,7 @@ pub(crate) fn firmware_update(
 
     let has_progress = progress.is_some();
     let mut progress = |state: FirmwareUpdateStep, prog| {
+        check_cancelled(cancel).map_err(|_| FirmwareUpdateError::Cancelled)?;
         if let Some(progress) = &mut progress {
             if !progress(state, prog) {
                 return Err(FirmwareUpdateError::ProgressCallbackError);
@@ -249,15 +285,9 @@ pub(crate) fn firmware_update(
             checksum,
             params.upgrade_only,
             has_progress.then_some(&mut upload_progress_cb),
+            cancel,
         )
-        .map_err(|err| {
-            if let MCUmgrClientError::ProgressCallbackError = err {
-                // Users expect this error when the progress callback errors
-                FirmwareUpdateError::ProgressCallbackError
-            } else {
-                FirmwareUpdateError::ImageUploadFailed(err)
-            }
-        })?;
+        .map_err(|err| upload_error(err, cancel))?;
 
     progress(FirmwareUpdateStep::ActivatingFirmware, None)?;
     let set_state_result = client.image_set_state(Some(image_id_hash), params.force_confirm);
@@ -295,3 +325,140 @@ pub(crate) fn firmware_update(
 
     Ok(())
 }
+
+/// Async version of [`firmware_update`].
+#[cfg(feature = "tokio")]
+pub(crate) async fn firmware_update_async(
+    client: &crate::AsyncMCUmgrClient,
+    firmware: impl AsRef<[u8]>,
+    checksum: Option<[u8; 32]>,
+    params: FirmwareUpdateParams,
+    mut progress: Option<&mut AsyncFirmwareUpdateProgressCallback<'_>>,
+    cancel: Option<&CancellationToken>,
+) -> Result<(), FirmwareUpdateError> {
+    // Might become a params member in the future
+    let target_image: Option<u32> = Default::default();
+    let actual_target_image = target_image.unwrap_or(0);
+
+    let firmware = firmware.as_ref();
+
+    let has_progress = progress.is_some();
+    let mut progress = |state: FirmwareUpdateStep, prog| {
+        check_cancelled(cancel).map_err(|_| FirmwareUpdateError::Cancelled)?;
+        if let Some(progress) = &mut progress {
+            if !progress(state, prog) {
+                return Err(FirmwareUpdateError::ProgressCallbackError);
+            }
+        }
+        Ok(())
+    };
+
+    let bootloader_type = if let Some(bootloader_type) = params.bootloader_type {
+        bootloader_type
+    } else {
+        progress(FirmwareUpdateStep::DetectingBootloader, None)?;
+
+        let bootloader_type = client
+            .os_bootloader_info()
+            .await
+            .map_err(FirmwareUpdateError::BootloaderDetectionFailed)?
+            .get_bootloader_type()
+            .map_err(FirmwareUpdateError::BootloaderNotSupported)?;
+
+        progress(FirmwareUpdateStep::BootloaderFound(bootloader_type), None)?;
+
+        bootloader_type
+    };
+
+    progress(FirmwareUpdateStep::ParsingFirmwareImage, None)?;
+    let (image_version, image_id_hash) = match bootloader_type {
+        BootloaderType::MCUboot => {
+            let info = mcuboot::get_image_info(std::io::Cursor::new(firmware))?;
+            (info.version, info.hash)
+        }
+    };
+
+    progress(FirmwareUpdateStep::QueryingDeviceState, None)?;
+    let image_state = client
+        .image_get_state()
+        .await
+        .map_err(FirmwareUpdateError::GetStateFailed)?;
+
+    let active_image = image_state
+        .iter()
+        .find(|img| img.image == actual_target_image && img.active)
+        .or_else(|| {
+            image_state
+                .iter()
+                .find(|img| img.image == actual_target_image && img.slot == 0)
+        });
+
+    progress(
+        FirmwareUpdateStep::UpdateInfo {
+            current_version: active_image.map(|img| (img.version.clone(), img.hash)),
+            new_version: (image_version.to_string(), image_id_hash),
+        },
+        None,
+    )?;
+
+    if active_image.and_then(|img| img.hash) == Some(image_id_hash) {
+        return Err(FirmwareUpdateError::AlreadyInstalled);
+    }
+
+    progress(FirmwareUpdateStep::UploadingFirmware, None)?;
+    let mut upload_progress_cb = |current, total| {
+        progress(
+            FirmwareUpdateStep::UploadingFirmware,
+            Some((current, total)),
+        )
+        .is_ok()
+    };
+
+    client
+        .image_upload(
+            firmware,
+            target_image,
+            checksum,
+            params.upgrade_only,
+            has_progress.then_some(&mut upload_progress_cb),
+            cancel,
+        )
+        .await
+        .map_err(|err| upload_error(err, cancel))?;
+
+    progress(FirmwareUpdateStep::ActivatingFirmware, None)?;
+    let set_state_result = client
+        .image_set_state(Some(image_id_hash), params.force_confirm)
+        .await;
+    if let Err(set_state_error) = set_state_result {
+        let mut image_already_active = false;
+
+        // See `firmware_update` for why this exception exists.
+        if bootloader_type == BootloaderType::MCUboot && set_state_error.command_not_supported() {
+            progress(FirmwareUpdateStep::QueryingDeviceState, None)?;
+            let image_state = client
+                .image_get_state()
+                .await
+                .map_err(FirmwareUpdateError::GetStateFailed)?;
+            if image_state.iter().any(|img| {
+                img.image == actual_target_image && img.slot == 0 && img.hash == Some(image_id_hash)
+            }) {
+                image_already_active = true;
+            }
+        }
+
+        if !image_already_active {
+            return Err(FirmwareUpdateError::SetStateFailed(set_state_error));
+        }
+    }
+
+    if !params.skip_reboot {
+        progress(FirmwareUpdateStep::TriggeringReboot, None)?;
+        client
+            .os_system_reset(false, None)
+            .await
+            .map_err(FirmwareUpdateError::RebootFailed)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use std::io;
+
+use crate::{
+    client::{MCUmgrClientError, ZEPHYR_DEFAULT_SMP_FRAME_SIZE},
+    connection::ExecuteError,
+    smp_errors::{DeviceError, MCUmgrErr},
+};
+
+/// The search stops once the gap between the largest accepted and the smallest
+/// rejected frame size is below this fraction of the accepted size.
+///
+/// Every rejected probe costs a timeout, and netbuf sizes are usually round numbers anyway.
+const PRECISION_DIVISOR: usize = 16;
+
+/// Size of an echo request without its message and the CBOR header of the message.
+///
+/// The SMP header, the CBOR map header and the `"d"` key.
+const ECHO_REQUEST_OVERHEAD: usize = 8 + 1 + 2;
+
+/// Size of the CBOR header of a text string with the given length.
+fn cbor_text_header_size(len: usize) -> usize {
+    match len {
+        0..24 => 1,
+        24..256 => 2,
+        256..65536 => 3,
+        _ => 5,
+    }
+}
+
+/// The echo message that fills a frame of the given size.
+///
+/// The frame is exact, except where the message length crosses a CBOR header
+/// size boundary; it is then one byte short.
+pub(super) fn echo_message(frame_size: usize) -> String {
+    let payload = frame_size.saturating_sub(ECHO_REQUEST_OVERHEAD);
+    let len = (payload.saturating_sub(5)..=payload)
+        .rev()
+        .find(|&len| len + cbor_text_header_size(len) <= payload)
+        .unwrap_or(0);
+
+    "0".repeat(len)
+}
+
+/// Checks whether a failed probe indicates that the frame was too large.
+///
+/// Devices drop frames that exceed their buffers, which results in a timeout,
+/// or reject them with `ENOMEM`.
+pub(super) fn is_too_large(error: &MCUmgrClientError) -> bool {
+    match error {
+        MCUmgrClientError::ExecuteError(ExecuteError::ReceiveFailed(e)) => e.is_timeout(),
+        MCUmgrClientError::ExecuteError(ExecuteError::ErrorResponse(DeviceError::V1 {
+            rc,
+            ..
+        })) => {
+            *rc == MCUmgrErr::MGMT_ERR_ENOMEM as i32 || *rc == MCUmgrErr::MGMT_ERR_EMSGSIZE as i32
+        }
+        _ => false,
+    }
+}
+
+/// Searches the largest frame size the device accepts.
+///
+/// Doubles the size, starting at Zephyr's default, until the device rejects it,
+/// then bisects between the largest accepted and the smallest rejected size.
+pub(super) struct FrameSizeSearch {
+    max_frame_size: usize,
+    accepted: usize,
+    rejected: Option<(usize, MCUmgrClientError)>,
+}
+
+impl FrameSizeSearch {
+    pub(super) fn new(max_frame_size: usize) -> Self {
+        Self {
+            max_frame_size,
+            accepted: 0,
+            rejected: None,
+        }
+    }
+
+    /// The frame size to probe next, or `None` if the search is done.
+    pub(super) fn next_size(&self) -> Option<usize> {
+        let size = match &self.rejected {
+            None if self.accepted == 0 => ZEPHYR_DEFAULT_SMP_FRAME_SIZE.min(self.max_frame_size),
+            None => self.accepted.saturating_mul(2).min(self.max_frame_size),
+            Some((rejected, _)) => {
+                let gap = rejected - self.accepted;
+                if gap <= (self.accepted / PRECISION_DIVISOR).max(1) {
+                    return None;
+                }
+                self.accepted + gap / 2
+            }
+        };
+
+        (size > self.accepted).then_some(size)
+    }
+
+    /// Records that the device accepted a frame of the given size.
+    pub(super) fn accepted(&mut self, size: usize) {
+        self.accepted = size;
+    }
+
+    /// Records that a frame of the given size was too large.
+    pub(super) fn rejected(&mut self, size: usize, error: MCUmgrClientError) {
+        self.rejected = Some((size, error));
+    }
+
+    /// The largest accepted frame size, or the last rejection if none was accepted.
+    pub(super) fn finish(self) -> Result<usize, MCUmgrClientError> {
+        match self.rejected {
+            _ if self.accepted > 0 => Ok(self.accepted),
+            Some((_, error)) => Err(error),
+            None => Err(MCUmgrClientError::FrameSizeTooSmall(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no frame size to probe",
+            ))),
+        }
+    }
+}
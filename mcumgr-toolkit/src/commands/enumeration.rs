@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ExtraFields, macros::impl_serialize_as_empty_map};
+
+/// [Count of supported groups](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_10.html#count-of-supported-groups-command) command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GroupCount;
+impl_serialize_as_empty_map!(GroupCount);
+
+/// Response for [`GroupCount`] command
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GroupCountResponse {
+    /// number of supported groups
+    pub count: u16,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// [List supported groups](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_10.html#list-supported-groups-command) command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GroupList;
+impl_serialize_as_empty_map!(GroupList);
+
+/// Response for [`GroupList`] command
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GroupListResponse {
+    /// IDs of the supported groups
+    pub groups: Vec<u16>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// [Fetch single group ID](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_10.html#fetch-single-group-id-command) command
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct GroupId {
+    /// index of the group to fetch
+    pub index: u16,
+}
+
+/// Response for [`GroupId`] command
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GroupIdResponse {
+    /// ID of the group at the requested index
+    pub group: u16,
+    /// true if this is the last supported group
+    #[serde(default)]
+    pub end: bool,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// [Details on supported groups](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_10.html#details-on-supported-groups-command) command
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct GroupDetails<'a> {
+    /// IDs of the groups to fetch details for, all groups if empty
+    #[serde(skip_serializing_if = "<[u16]>::is_empty")]
+    pub groups: &'a [u16],
+}
+
+/// Details of a group, as part of [`GroupDetailsResponse`]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GroupDetailsEntry {
+    /// ID of the group
+    pub group: u16,
+    /// name of the group
+    pub name: Option<String>,
+    /// number of command handlers of the group
+    pub handlers: Option<u32>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// Response for [`GroupDetails`] command
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GroupDetailsResponse {
+    /// details of the requested groups
+    pub groups: Vec<GroupDetailsEntry>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::macros::command_encode_decode_test;
+    use super::*;
+    use ciborium::cbor;
+
+    command_encode_decode_test! {
+        group_count,
+        (0, 10, 0),
+        GroupCount,
+        cbor!({}),
+        cbor!({"count" => 4}),
+        GroupCountResponse{
+            count: 4,
+            extra: Default::default(),
+        },
+    }
+
+    command_encode_decode_test! {
+        group_list,
+        (0, 10, 1),
+        GroupList,
+        cbor!({}),
+        cbor!({"groups" => [0, 1, 10, 64]}),
+        GroupListResponse{
+            groups: vec![0, 1, 10, 64],
+            extra: Default::default(),
+        },
+    }
+
+    command_encode_decode_test! {
+        group_id,
+        (0, 10, 2),
+        GroupId{index: 1},
+        cbor!({"index" => 1}),
+        cbor!({"group" => 1}),
+        GroupIdResponse{
+            group: 1,
+            end: false,
+            extra: Default::default(),
+        },
+    }
+
+    command_encode_decode_test! {
+        group_id_end,
+        (0, 10, 2),
+        GroupId{index: 3},
+        cbor!({"index" => 3}),
+        cbor!({"group" => 64, "end" => true}),
+        GroupIdResponse{
+            group: 64,
+            end: true,
+            extra: Default::default(),
+        },
+    }
+
+    command_encode_decode_test! {
+        group_details,
+        (0, 10, 3),
+        GroupDetails{groups: &[]},
+        cbor!({}),
+        cbor!({"groups" => [
+            {"group" => 0, "name" => "os", "handlers" => 6},
+            {"group" => 64},
+        ]}),
+        GroupDetailsResponse{
+            groups: vec![
+                GroupDetailsEntry{
+                    group: 0,
+                    name: Some("os".to_string()),
+                    handlers: Some(6),
+                    extra: Default::default(),
+                },
+                GroupDetailsEntry{
+                    group: 64,
+                    name: None,
+                    handlers: None,
+                    extra: Default::default(),
+                },
+            ],
+            extra: Default::default(),
+        },
+    }
+
+    command_encode_decode_test! {
+        group_details_selected,
+        (0, 10, 3),
+        GroupDetails{groups: &[1, 8]},
+        cbor!({"groups" => [1, 8]}),
+        cbor!({"groups" => [
+            {"group" => 1, "name" => "img", "handlers" => 4},
+        ]}),
+        GroupDetailsResponse{
+            groups: vec![
+                GroupDetailsEntry{
+                    group: 1,
+                    name: Some("img".to_string()),
+                    handlers: Some(4),
+                    extra: Default::default(),
+                },
+            ],
+            extra: Default::default(),
+        },
+    }
+}
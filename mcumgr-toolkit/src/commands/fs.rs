@@ -5,8 +5,8 @@ use serde_repr::Deserialize_repr;
 use strum::Display;
 
 use crate::commands::{
-    CountingWriter, data_too_large_error,
-    macros::{impl_deserialize_from_empty_map_and_into_unit, impl_serialize_as_empty_map},
+    CountingWriter, ExtraFields, data_too_large_error,
+    macros::{impl_into_unit, impl_serialize_as_empty_map},
 };
 
 use super::is_default;
@@ -21,7 +21,7 @@ pub struct FileDownload<'a> {
 }
 
 /// Response for [`FileDownload`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct FileDownloadResponse {
     /// offset the response is for
     pub off: u64,
@@ -29,6 +29,9 @@ pub struct FileDownloadResponse {
     pub data: Vec<u8>,
     /// length of file, this field is only mandatory when “off” is 0
     pub len: Option<u64>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// Computes how large [`FileUpload::data`] is allowed to be.
@@ -104,10 +107,13 @@ pub struct FileUpload<'a, 'b> {
 }
 
 /// Response for [`FileUpload`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct FileUploadResponse {
     /// offset of last successfully written data
     pub off: u64,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// [File Status](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_8.html#file-status) command
@@ -118,10 +124,13 @@ pub struct FileStatus<'a> {
 }
 
 /// Response for [`FileStatus`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct FileStatusResponse {
     /// length of file (in bytes)
     pub len: u64,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// [File Hash/Checksum](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_8.html#file-hash-checksum) command
@@ -141,7 +150,7 @@ pub struct FileChecksum<'a, 'b> {
 }
 
 /// Response for [`FileChecksum`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct FileChecksumResponse {
     /// type of hash/checksum that was performed
     pub r#type: String,
@@ -152,6 +161,9 @@ pub struct FileChecksumResponse {
     pub len: u64,
     /// output hash/checksum
     pub output: FileChecksumData,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// Hash data of [`FileChecksumResponse`]
@@ -181,10 +193,13 @@ pub struct SupportedFileChecksumTypes;
 impl_serialize_as_empty_map!(SupportedFileChecksumTypes);
 
 /// Response for [`SupportedFileChecksumTypes`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct SupportedFileChecksumTypesResponse {
     /// names and properties of the hash/checksum types
     pub r#types: HashMap<String, FileChecksumProperties>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// Data format of the hash/checksum type
@@ -199,12 +214,15 @@ pub enum FileChecksumDataFormat {
 }
 
 /// Properties of a hash/checksum algorithm
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct FileChecksumProperties {
     /// format that the hash/checksum returns
     pub format: FileChecksumDataFormat,
     /// size (in bytes) of output hash/checksum response
     pub size: u32,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// [File Close](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_8.html#file-close) command
@@ -213,9 +231,13 @@ pub struct FileClose;
 impl_serialize_as_empty_map!(FileClose);
 
 /// Response for [`FileClose`] command
-#[derive(Clone, Default, Debug, Eq, PartialEq)]
-pub struct FileCloseResponse;
-impl_deserialize_from_empty_map_and_into_unit!(FileCloseResponse);
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
+pub struct FileCloseResponse {
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+impl_into_unit!(FileCloseResponse);
 
 #[cfg(test)]
 mod tests {
@@ -282,6 +304,7 @@ mod tests {
             off: 42,
             data: vec![1,2,3,4,5],
             len: Some(100),
+            extra: Default::default(),
         },
     }
 
@@ -304,6 +327,7 @@ mod tests {
             off: 50,
             data: vec![10],
             len: None,
+            extra: Default::default(),
         },
     }
 
@@ -321,7 +345,8 @@ mod tests {
             "off" => 58,
         }),
         FileUploadResponse{
-            off: 58
+            off: 58,
+            extra: Default::default(),
         }
     }
 
@@ -338,7 +363,8 @@ mod tests {
             "off" => 0,
         }),
         FileUploadResponse{
-            off: 0
+            off: 0,
+            extra: Default::default(),
         }
     }
 
@@ -354,6 +380,7 @@ mod tests {
         }),
         FileStatusResponse{
             len: 123,
+            extra: Default::default(),
         }
     }
 
@@ -383,6 +410,7 @@ mod tests {
             off: 69,
             len: 42,
             output: FileChecksumData::Checksum(100000),
+            extra: Default::default(),
         }
     }
 
@@ -408,6 +436,7 @@ mod tests {
             off: 0,
             len: 42,
             output: FileChecksumData::Hash(vec![1,2,3,4].into_boxed_slice()),
+            extra: Default::default(),
         }
     }
 
@@ -435,6 +464,7 @@ mod tests {
                     FileChecksumProperties{
                         format: FileChecksumDataFormat::Numerical,
                         size: 4,
+                        extra: Default::default(),
                     }
                 ),
                 (
@@ -442,9 +472,11 @@ mod tests {
                     FileChecksumProperties{
                         format: FileChecksumDataFormat::ByteArray,
                         size: 32,
+                        extra: Default::default(),
                     }
                 ),
-            ])
+            ]),
+            extra: Default::default(),
         }
     }
 
@@ -454,6 +486,6 @@ mod tests {
         FileClose,
         cbor!({}),
         cbor!({}),
-        FileCloseResponse,
+        FileCloseResponse::default(),
     }
 }
@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 
 use crate::commands::{
-    CountingWriter, data_too_large_error,
-    macros::{impl_deserialize_from_empty_map_and_into_unit, impl_serialize_as_empty_map},
+    CountingWriter, ExtraFields, data_too_large_error,
+    macros::{impl_into_unit, impl_serialize_as_empty_map},
 };
 
 fn serialize_option_hex<S, T>(data: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
@@ -16,7 +16,7 @@ where
 }
 
 /// The state of an image slot
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct ImageState {
     /// image number
     #[serde(default)]
@@ -47,6 +47,9 @@ pub struct ImageState {
     /// true if image is to stay in primary slot after the next boot
     #[serde(default)]
     pub permanent: bool,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// [Get Image State](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_1.html#get-state-of-images-request) command
@@ -55,12 +58,14 @@ pub struct GetImageState;
 impl_serialize_as_empty_map!(GetImageState);
 
 /// Response for [`GetImageState`] and [`SetImageState`] commands
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct ImageStateResponse {
     /// List of all images and their state
     pub images: Vec<ImageState>,
-    // splitStatus field is missing
-    // because it is unused by Zephyr
+    // splitStatus is unused by Zephyr and ends up in `extra`
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// [Set Image State](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_1.html#set-state-of-image-request) command
@@ -119,12 +124,15 @@ pub struct ImageUpload<'a, 'b> {
 }
 
 /// Response for [`ImageUpload`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct ImageUploadResponse {
     /// offset of last successfully written byte of update.
     pub off: u64,
     /// indicates if the uploaded data successfully matches the provided SHA256 hash or not
     pub r#match: Option<bool>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// Computes how large [`ImageUpload::data`] is allowed to be.
@@ -192,9 +200,13 @@ pub struct ImageErase {
 }
 
 /// Response for [`ImageErase`] command
-#[derive(Clone, Default, Debug, Eq, PartialEq)]
-pub struct ImageEraseResponse;
-impl_deserialize_from_empty_map_and_into_unit!(ImageEraseResponse);
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
+pub struct ImageEraseResponse {
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+impl_into_unit!(ImageEraseResponse);
 
 /// [Slot Info](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_1.html#slot-info) command
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -202,7 +214,7 @@ pub struct SlotInfo;
 impl_serialize_as_empty_map!(SlotInfo);
 
 /// Information about a firmware image type returned by [`SlotInfo`]
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct SlotInfoImage {
     /// The number of the image
     pub image: u32,
@@ -211,10 +223,13 @@ pub struct SlotInfoImage {
     /// Maximum size of an application that can be uploaded to that image number
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_image_size: Option<u64>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// Information about a slot that can hold a firmware image
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct SlotInfoImageSlot {
     /// The slot inside the image being enumerated
     pub slot: u32,
@@ -223,13 +238,19 @@ pub struct SlotInfoImageSlot {
     /// Specifies the image ID that can be used by external tools to upload an image to that slot
     #[serde(skip_serializing_if = "Option::is_none")]
     pub upload_image_id: Option<u32>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// Response for [`SlotInfo`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct SlotInfoResponse {
     /// List of all image slot collections on the device
     pub images: Vec<SlotInfoImage>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[cfg(test)]
@@ -285,6 +306,7 @@ mod tests {
                     confirmed: true,
                     active: true,
                     permanent: true,
+                    extra: Default::default(),
                 },
                 ImageState{
                     image: 4,
@@ -296,6 +318,7 @@ mod tests {
                     confirmed: false,
                     active: false,
                     permanent: false,
+                    extra: Default::default(),
                 },
                 ImageState{
                     image: 0,
@@ -307,8 +330,12 @@ mod tests {
                     confirmed: false,
                     active: false,
                     permanent: false,
+                    extra: Default::default(),
                 }
             ],
+            extra: ExtraFields::from([
+                ("splitStatus".to_string(), ciborium::Value::from(42)),
+            ]),
         },
     }
 
@@ -328,6 +355,7 @@ mod tests {
         }),
         ImageStateResponse{
             images: vec![],
+            extra: Default::default(),
         },
     }
 
@@ -346,6 +374,7 @@ mod tests {
         }),
         ImageStateResponse{
             images: vec![],
+            extra: Default::default(),
         },
     }
 
@@ -374,6 +403,7 @@ mod tests {
         ImageUploadResponse {
             off: 4,
             r#match: None,
+            extra: Default::default(),
         },
     }
 
@@ -399,6 +429,7 @@ mod tests {
         ImageUploadResponse {
             off: 123456789123,
             r#match: Some(false),
+            extra: Default::default(),
         },
     }
 
@@ -410,7 +441,7 @@ mod tests {
         },
         cbor!({}),
         cbor!({}),
-        ImageEraseResponse,
+        ImageEraseResponse::default(),
     }
 
     command_encode_decode_test! {
@@ -423,7 +454,7 @@ mod tests {
             "slot" => 42,
         }),
         cbor!({}),
-        ImageEraseResponse,
+        ImageEraseResponse::default(),
     }
 
     command_encode_decode_test! {
@@ -464,21 +495,26 @@ mod tests {
                             slot: 0,
                             size: 42,
                             upload_image_id: Some(2),
+                            extra: Default::default(),
                         },
                         SlotInfoImageSlot {
                             slot: 1,
                             size: 123456789012,
                             upload_image_id: None,
+                            extra: Default::default(),
                         }
                     ],
-                    max_image_size: Some(123456789987)
+                    max_image_size: Some(123456789987),
+                    extra: Default::default(),
                 },
                 SlotInfoImage {
                     image: 1,
                     slots: vec![],
                     max_image_size: None,
+                    extra: Default::default(),
                 }
             ],
+            extra: Default::default(),
         },
     }
 
@@ -6,10 +6,11 @@
 /// - `$iswrite`: Boolean literal indicating if this is a write operation
 /// - `$groupid`: The MCUmgr group
 /// - `$commandid`: The MCUmgr command ID (u8)
+/// - `$min_timeout`: Optional, the minimum timeout of the request `$req`
 macro_rules! impl_mcumgr_command {
     (@direction read) => {false};
     (@direction write) => {true};
-    (($direction:tt, $groupid:ident, $commandid:literal): $request:ty => $response:ty) => {
+    (($direction:tt, $groupid:ident, $commandid:literal $(, min_timeout($req:ident) = $min_timeout:expr)?): $request:ty => $response:ty) => {
         impl McuMgrCommand for $request {
             type Payload = Self;
             type Response = $response;
@@ -25,6 +26,12 @@ macro_rules! impl_mcumgr_command {
             fn data(&self) -> &Self {
                 self
             }
+            $(
+                fn min_timeout(&self) -> Option<std::time::Duration> {
+                    let $req = self;
+                    $min_timeout
+                }
+            )?
         }
     };
 }
@@ -99,57 +106,13 @@ macro_rules! impl_serialize_as_empty_map {
     };
 }
 
-macro_rules! impl_deserialize_from_empty_map_and_into_unit {
+macro_rules! impl_into_unit {
     ($type:ty) => {
         impl From<$type> for () {
-            fn from(_: $type) -> () {
-                const _: () = [()][size_of::<$type>()]; // Fails if type is not a unit struct
-            }
-        }
-
-        impl<'de> ::serde::Deserialize<'de> for $type {
-            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-            where
-                D: ::serde::Deserializer<'de>,
-            {
-                struct InternalVisitor;
-
-                impl<'de> ::serde::de::Visitor<'de> for InternalVisitor {
-                    type Value = $type;
-
-                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-                        write!(f, "an empty map/object (`{{}}`)")
-                    }
-
-                    fn visit_map<M>(self, mut map: M) -> Result<$type, M::Error>
-                    where
-                        M: ::serde::de::MapAccess<'de>,
-                    {
-                        let mut had_entries = false;
-
-                        while map
-                            .next_entry::<::serde::de::IgnoredAny, ::serde::de::IgnoredAny>()?
-                            .is_some()
-                        {
-                            had_entries = true;
-                        }
-
-                        // Do not error when there are entries; we also accept non-empty maps for future compatibility.
-                        if had_entries {
-                            ::log::debug!(
-                                "Ignoring unexpected entries in unit-type deserialization"
-                            );
-                        }
-
-                        Ok(<$type>::default())
-                    }
-                }
-
-                deserializer.deserialize_map(InternalVisitor)
-            }
+            fn from(_: $type) -> () {}
         }
     };
 }
 
-pub(super) use impl_deserialize_from_empty_map_and_into_unit;
+pub(super) use impl_into_unit;
 pub(super) use impl_serialize_as_empty_map;
@@ -1,19 +1,36 @@
+/// [Enumeration management](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_10.html) group commands
+pub mod enumeration;
 /// [File management](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_8.html) group commands
 pub mod fs;
 /// [Application/software image management](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_1.html) group commands
 pub mod image;
 /// [Default/OS management](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_0.html) group commands
 pub mod os;
+/// [Settings management](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_3.html) group commands
+pub mod settings;
 /// [Shell management](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_9.html) group commands
 pub mod shell;
+/// [Statistics management](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_2.html) group commands
+pub mod stat;
 /// [Zephyr management](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_63.html) group commands
 pub mod zephyr;
 
 mod macros;
 use macros::impl_mcumgr_command;
 
+use std::{collections::BTreeMap, time::Duration};
+
 use serde::{Deserialize, Serialize};
 
+use crate::smp_errors::{DeviceError, MCUmgrErr};
+
+/// The minimum timeout of commands that can take long on the device,
+/// like erasing flash or hashing large files.
+pub const LONG_OPERATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Response fields that are not known to this crate, like fields added by newer Zephyr versions
+pub type ExtraFields = BTreeMap<String, ciborium::Value>;
+
 /// SMP version 2 group based error message
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
 pub struct ErrResponseV2 {
@@ -34,6 +51,22 @@ pub struct ErrResponse {
     pub err: Option<ErrResponseV2>,
 }
 
+impl ErrResponse {
+    /// The error reported by the device, if any
+    pub fn into_device_error(self) -> Option<DeviceError> {
+        if let Some(ErrResponseV2 { rc, group }) = self.err {
+            return Some(DeviceError::V2 { group, rc });
+        }
+
+        match self.rc {
+            Some(rc) if rc != MCUmgrErr::MGMT_ERR_EOK as i32 => {
+                Some(DeviceError::V1 { rc, rsn: self.rsn })
+            }
+            _ => None,
+        }
+    }
+}
+
 /// An MCUmgr command that can be executed through [`Connection::execute_command`](crate::connection::Connection::execute_command).
 pub trait McuMgrCommand {
     /// the data payload type
@@ -48,8 +81,53 @@ pub trait McuMgrCommand {
     fn command_id(&self) -> u8;
     /// the data
     fn data(&self) -> &Self::Payload;
+    /// the minimum time to wait for the response, for commands that take long on the device;
+    /// `None` uses the communication timeout
+    fn min_timeout(&self) -> Option<Duration> {
+        None
+    }
 }
 
+/// Derive macros for user-defined commands, like the ones of `MGMT_GROUP_ID_PERUSER` groups.
+///
+/// ```
+/// use mcumgr_toolkit::commands::{DeserializeFromEmptyMap, McuMgrCommand, SerializeAsEmptyMap};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, McuMgrCommand)]
+/// #[mcumgr(write, group = 64, id = 3, response = SetLedResponse)]
+/// struct SetLed {
+///     on: bool,
+/// }
+///
+/// #[derive(DeserializeFromEmptyMap)]
+/// struct SetLedResponse;
+///
+/// #[derive(SerializeAsEmptyMap, McuMgrCommand)]
+/// #[mcumgr(read, group = mcumgr_toolkit::MCUmgrGroup::MGMT_GROUP_ID_PERUSER, id = 4, response = GetLedResponse)]
+/// struct GetLed;
+///
+/// #[derive(Deserialize)]
+/// struct GetLedResponse {
+///     on: bool,
+/// }
+///
+/// assert!(SetLed { on: true }.is_write_operation());
+/// assert_eq!(GetLed.group_id(), 64);
+/// ```
+///
+/// Group IDs that do not fit into a `u16` are rejected at compile time:
+///
+/// ```compile_fail
+/// use mcumgr_toolkit::commands::{McuMgrCommand, SerializeAsEmptyMap};
+///
+/// #[derive(SerializeAsEmptyMap, McuMgrCommand)]
+/// #[mcumgr(read, group = 65600, id = 0, response = ())]
+/// struct Invalid;
+/// ```
+#[cfg(feature = "derive")]
+pub use mcumgr_toolkit_derive::{DeserializeFromEmptyMap, McuMgrCommand, SerializeAsEmptyMap};
+
 /// Checks if a value is the default value
 fn is_default<T: Default + PartialEq>(val: &T) -> bool {
     val == &T::default()
@@ -87,6 +165,7 @@ impl std::io::Write for CountingWriter {
 
 impl_mcumgr_command!((read,  MGMT_GROUP_ID_OS, 0): os::Echo<'_> => os::EchoResponse);
 impl_mcumgr_command!((read,  MGMT_GROUP_ID_OS, 2): os::TaskStatistics => os::TaskStatisticsResponse);
+impl_mcumgr_command!((read,  MGMT_GROUP_ID_OS, 3): os::MemoryPoolStatistics => os::MemoryPoolStatisticsResponse);
 impl_mcumgr_command!((read,  MGMT_GROUP_ID_OS, 4): os::DateTimeGet => os::DateTimeGetResponse);
 impl_mcumgr_command!((write, MGMT_GROUP_ID_OS, 4): os::DateTimeSet => os::DateTimeSetResponse);
 impl_mcumgr_command!((write, MGMT_GROUP_ID_OS, 5): os::SystemReset => os::SystemResetResponse);
@@ -97,20 +176,36 @@ impl_mcumgr_command!((read,  MGMT_GROUP_ID_OS, 8): os::BootloaderInfoMcubootMode
 
 impl_mcumgr_command!((read,  MGMT_GROUP_ID_IMAGE, 0): image::GetImageState => image::ImageStateResponse);
 impl_mcumgr_command!((write,  MGMT_GROUP_ID_IMAGE, 0): image::SetImageState<'_> => image::ImageStateResponse);
-impl_mcumgr_command!((write,  MGMT_GROUP_ID_IMAGE, 1): image::ImageUpload<'_, '_> => image::ImageUploadResponse);
-impl_mcumgr_command!((write,  MGMT_GROUP_ID_IMAGE, 5): image::ImageErase => image::ImageEraseResponse);
+// The first chunk of an upload erases the slot
+impl_mcumgr_command!((write,  MGMT_GROUP_ID_IMAGE, 1, min_timeout(req) = (req.off == 0).then_some(LONG_OPERATION_TIMEOUT)): image::ImageUpload<'_, '_> => image::ImageUploadResponse);
+impl_mcumgr_command!((write,  MGMT_GROUP_ID_IMAGE, 5, min_timeout(_req) = Some(LONG_OPERATION_TIMEOUT)): image::ImageErase => image::ImageEraseResponse);
 impl_mcumgr_command!((read,  MGMT_GROUP_ID_IMAGE, 6): image::SlotInfo => image::SlotInfoResponse);
 
+impl_mcumgr_command!((read,  MGMT_GROUP_ID_STAT, 0): stat::GroupData<'_> => stat::GroupDataResponse);
+impl_mcumgr_command!((read,  MGMT_GROUP_ID_STAT, 1): stat::ListGroups => stat::ListGroupsResponse);
+
+impl_mcumgr_command!((read,  MGMT_GROUP_ID_SETTINGS, 0): settings::ReadSetting<'_> => settings::ReadSettingResponse);
+impl_mcumgr_command!((write, MGMT_GROUP_ID_SETTINGS, 0): settings::WriteSetting<'_, '_> => settings::WriteSettingResponse);
+impl_mcumgr_command!((write, MGMT_GROUP_ID_SETTINGS, 1): settings::DeleteSetting<'_> => settings::DeleteSettingResponse);
+impl_mcumgr_command!((write, MGMT_GROUP_ID_SETTINGS, 2): settings::CommitSettings => settings::CommitSettingsResponse);
+impl_mcumgr_command!((read,  MGMT_GROUP_ID_SETTINGS, 3): settings::LoadSettings => settings::LoadSettingsResponse);
+impl_mcumgr_command!((write, MGMT_GROUP_ID_SETTINGS, 3, min_timeout(_req) = Some(LONG_OPERATION_TIMEOUT)): settings::SaveSettings => settings::SaveSettingsResponse);
+
 impl_mcumgr_command!((write, MGMT_GROUP_ID_FS, 0): fs::FileUpload<'_, '_> => fs::FileUploadResponse);
 impl_mcumgr_command!((read,  MGMT_GROUP_ID_FS, 0): fs::FileDownload<'_> => fs::FileDownloadResponse);
 impl_mcumgr_command!((read,  MGMT_GROUP_ID_FS, 1): fs::FileStatus<'_> => fs::FileStatusResponse);
-impl_mcumgr_command!((read,  MGMT_GROUP_ID_FS, 2): fs::FileChecksum<'_, '_> => fs::FileChecksumResponse);
+impl_mcumgr_command!((read,  MGMT_GROUP_ID_FS, 2, min_timeout(_req) = Some(LONG_OPERATION_TIMEOUT)): fs::FileChecksum<'_, '_> => fs::FileChecksumResponse);
 impl_mcumgr_command!((read,  MGMT_GROUP_ID_FS, 3): fs::SupportedFileChecksumTypes => fs::SupportedFileChecksumTypesResponse);
 impl_mcumgr_command!((write, MGMT_GROUP_ID_FS, 4): fs::FileClose => fs::FileCloseResponse);
 
 impl_mcumgr_command!((write, MGMT_GROUP_ID_SHELL, 0): shell::ShellCommandLineExecute<'_> => shell::ShellCommandLineExecuteResponse);
 
-impl_mcumgr_command!((write, ZEPHYR_MGMT_GRP_BASIC, 0): zephyr::EraseStorage => zephyr::EraseStorageResponse);
+impl_mcumgr_command!((read,  MGMT_GROUP_ID_ENUM, 0): enumeration::GroupCount => enumeration::GroupCountResponse);
+impl_mcumgr_command!((read,  MGMT_GROUP_ID_ENUM, 1): enumeration::GroupList => enumeration::GroupListResponse);
+impl_mcumgr_command!((read,  MGMT_GROUP_ID_ENUM, 2): enumeration::GroupId => enumeration::GroupIdResponse);
+impl_mcumgr_command!((read,  MGMT_GROUP_ID_ENUM, 3): enumeration::GroupDetails<'_> => enumeration::GroupDetailsResponse);
+
+impl_mcumgr_command!((write, ZEPHYR_MGMT_GRP_BASIC, 0, min_timeout(_req) = Some(LONG_OPERATION_TIMEOUT)): zephyr::EraseStorage => zephyr::EraseStorageResponse);
 
 #[cfg(test)]
 mod tests {
@@ -214,4 +309,24 @@ mod tests {
         assert!(super::is_default(&0));
         assert!(!super::is_default(&5));
     }
+
+    #[test]
+    fn min_timeout() {
+        let upload = |off| image::ImageUpload {
+            image: None,
+            len: None,
+            off,
+            sha: None,
+            data: &[],
+            upgrade: None,
+        };
+
+        assert_eq!(upload(0).min_timeout(), Some(LONG_OPERATION_TIMEOUT));
+        assert_eq!(upload(128).min_timeout(), None);
+        assert_eq!(
+            image::ImageErase { slot: None }.min_timeout(),
+            Some(LONG_OPERATION_TIMEOUT)
+        );
+        assert_eq!(os::Echo { d: "" }.min_timeout(), None);
+    }
 }
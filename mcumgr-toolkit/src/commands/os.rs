@@ -4,8 +4,8 @@ use chrono::Timelike;
 use serde::{Deserialize, Serialize};
 
 use super::{
-    is_default,
-    macros::{impl_deserialize_from_empty_map_and_into_unit, impl_serialize_as_empty_map},
+    ExtraFields, is_default,
+    macros::{impl_into_unit, impl_serialize_as_empty_map},
 };
 
 /// [Echo](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_0.html#echo-command) command
@@ -16,10 +16,13 @@ pub struct Echo<'a> {
 }
 
 /// Response for [`Echo`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct EchoResponse {
     /// replying echo string
     pub r: String,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// [Task statistics](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_0.html#task-statistics-command) command
@@ -28,7 +31,7 @@ pub struct TaskStatistics;
 impl_serialize_as_empty_map!(TaskStatistics);
 
 /// Statistics of an MCU task/thread
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct TaskStatisticsEntry {
     /// task priority
     pub prio: i32,
@@ -44,6 +47,9 @@ pub struct TaskStatisticsEntry {
     pub cswcnt: Option<u64>,
     /// task’s/thread’s runtime in “ticks”
     pub runtime: Option<u64>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// Flags inside of [`TaskStatisticsEntry::state`]
@@ -93,10 +99,44 @@ impl ThreadStateFlags {
 }
 
 /// Response for [`TaskStatistics`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct TaskStatisticsResponse {
     /// Dictionary of task names with their respective statistics
     pub tasks: HashMap<String, TaskStatisticsEntry>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// [Memory pool statistics](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_0.html#memory-pool-statistics) command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MemoryPoolStatistics;
+impl_serialize_as_empty_map!(MemoryPoolStatistics);
+
+/// Statistics of a memory pool/heap
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MemoryPoolStatisticsEntry {
+    /// size of the memory blocks in the pool
+    pub blksiz: u64,
+    /// number of blocks in the pool
+    pub nblks: u64,
+    /// number of free blocks
+    pub nfree: u64,
+    /// lowest number of free blocks the pool ever had
+    pub min: u64,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// Response for [`MemoryPoolStatistics`] command
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct MemoryPoolStatisticsResponse {
+    /// Dictionary of pool names with their respective statistics
+    pub mpools: HashMap<String, MemoryPoolStatisticsEntry>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// Parses a [`chrono::NaiveDateTime`] object with optional timezone specifiers
@@ -141,11 +181,14 @@ pub struct DateTimeGet;
 impl_serialize_as_empty_map!(DateTimeGet);
 
 /// Response for [`DateTimeGet`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct DateTimeGetResponse {
     /// String in format: `yyyy-MM-dd'T'HH:mm:ss.SSS`.
     #[serde(deserialize_with = "deserialize_datetime_and_ignore_timezone")]
     pub datetime: chrono::NaiveDateTime,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// [Date-Time Set](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_0.html#date-time-set) command
@@ -157,9 +200,13 @@ pub struct DateTimeSet {
 }
 
 /// Response for [`DateTimeSet`] command
-#[derive(Clone, Default, Debug, Eq, PartialEq)]
-pub struct DateTimeSetResponse;
-impl_deserialize_from_empty_map_and_into_unit!(DateTimeSetResponse);
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
+pub struct DateTimeSetResponse {
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+impl_into_unit!(DateTimeSetResponse);
 
 /// [System Reset](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_0.html#system-reset) command
 #[derive(Clone, Serialize, Debug, Eq, PartialEq)]
@@ -177,9 +224,13 @@ pub struct SystemReset {
 }
 
 /// Response for [`SystemReset`] command
-#[derive(Clone, Default, Debug, Eq, PartialEq)]
-pub struct SystemResetResponse;
-impl_deserialize_from_empty_map_and_into_unit!(SystemResetResponse);
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
+pub struct SystemResetResponse {
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+impl_into_unit!(SystemResetResponse);
 
 /// [MCUmgr Parameters](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_0.html#mcumgr-parameters) command
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -187,12 +238,15 @@ pub struct MCUmgrParameters;
 impl_serialize_as_empty_map!(MCUmgrParameters);
 
 /// Response for [`MCUmgrParameters`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct MCUmgrParametersResponse {
     /// Single SMP buffer size, this includes SMP header and CBOR payload
     pub buf_size: u32,
     /// Number of SMP buffers supported
     pub buf_count: u32,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// [OS/Application Info](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_0.html#os-application-info) command
@@ -206,10 +260,13 @@ pub struct ApplicationInfo<'a> {
 }
 
 /// Response for [`ApplicationInfo`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct ApplicationInfoResponse {
     /// Text response including requested parameters
     pub output: String,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// [Bootloader Information](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_0.html#bootloader-information) command
@@ -218,10 +275,13 @@ pub struct BootloaderInfo;
 impl_serialize_as_empty_map!(BootloaderInfo);
 
 /// Response for [`BootloaderInfo`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct BootloaderInfoResponse {
     /// String representing bootloader name
     pub bootloader: String,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// [Bootloader Information MCUboot Mode](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_0.html#bootloader-information-mcuboot) subcommand
@@ -230,13 +290,16 @@ pub struct BootloaderInfoResponse {
 pub struct BootloaderInfoMcubootMode {}
 
 /// Response for [`BootloaderInfoMcubootMode`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct BootloaderInfoMcubootModeResponse {
     /// The bootloader mode
     pub mode: i32,
     /// MCUboot has downgrade prevention enabled
     #[serde(default, rename = "no-downgrade")]
     pub no_downgrade: bool,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[cfg(test)]
@@ -271,7 +334,7 @@ mod tests {
         Echo{d: "Hello World!"},
         cbor!({"d" => "Hello World!"}),
         cbor!({"r" => "Hello World!"}),
-        EchoResponse{r: "Hello World!".to_string()},
+        EchoResponse{r: "Hello World!".to_string(), extra: Default::default()},
     }
 
     command_encode_decode_test! {
@@ -280,7 +343,7 @@ mod tests {
         TaskStatistics,
         cbor!({}),
         cbor!({"tasks" => {}}),
-        TaskStatisticsResponse{ tasks: HashMap::new() },
+        TaskStatisticsResponse{ tasks: HashMap::new(), extra: Default::default()},
     }
 
     command_encode_decode_test! {
@@ -317,6 +380,7 @@ mod tests {
                     stksiz: None,
                     cswcnt: None,
                     runtime: None,
+                    extra: Default::default(),
                 },
             ), (
                 "task_b".to_string(),
@@ -328,9 +392,40 @@ mod tests {
                     stksiz: Some(34),
                     cswcnt: Some(35),
                     runtime: Some(36),
+                    extra: ExtraFields::from([
+                        ("last_checkin".to_string(), ciborium::Value::from(0)),
+                        ("next_checkin".to_string(), ciborium::Value::from(0)),
+                    ]),
+                },
+            ),
+        ]), extra: Default::default() },
+    }
+
+    command_encode_decode_test! {
+        memory_pool_statistics,
+        (0, 0, 3),
+        MemoryPoolStatistics,
+        cbor!({}),
+        cbor!({"mpools" => {
+            "heap" => {
+                "blksiz" => 8,
+                "nblks"  => 512,
+                "nfree"  => 300,
+                "min"    => 120,
+            },
+        }}),
+        MemoryPoolStatisticsResponse{ mpools: HashMap::from([
+            (
+                "heap".to_string(),
+                MemoryPoolStatisticsEntry{
+                    blksiz: 8,
+                    nblks: 512,
+                    nfree: 300,
+                    min: 120,
+                    extra: Default::default(),
                 },
             ),
-        ]) },
+        ]), extra: Default::default()},
     }
 
     command_encode_decode_test! {
@@ -343,6 +438,7 @@ mod tests {
         }),
         DateTimeGetResponse{
             datetime: NaiveDateTime::new(NaiveDate::from_ymd_opt(2025, 11, 20).unwrap(), NaiveTime::from_hms_micro_opt(11,56,5,366345).unwrap()),
+            extra: Default::default(),
         },
     }
 
@@ -356,6 +452,7 @@ mod tests {
         }),
         DateTimeGetResponse{
             datetime: NaiveDateTime::new(NaiveDate::from_ymd_opt(2025, 11, 20).unwrap(), NaiveTime::from_hms_milli_opt(11,56,5,366).unwrap()),
+            extra: Default::default(),
         },
     }
 
@@ -369,6 +466,7 @@ mod tests {
         }),
         DateTimeGetResponse{
             datetime: NaiveDateTime::new(NaiveDate::from_ymd_opt(2025, 11, 20).unwrap(), NaiveTime::from_hms_opt(11,56,5).unwrap()),
+            extra: Default::default(),
         },
     }
 
@@ -382,7 +480,7 @@ mod tests {
             "datetime" => "2025-11-20T12:03:56.642"
         }),
         cbor!({}),
-        DateTimeSetResponse,
+        DateTimeSetResponse::default(),
     }
 
     command_encode_decode_test! {
@@ -395,7 +493,7 @@ mod tests {
             "datetime" => "2025-11-20T12:03:56"
         }),
         cbor!({}),
-        DateTimeSetResponse,
+        DateTimeSetResponse::default(),
     }
 
     command_encode_decode_test! {
@@ -407,7 +505,7 @@ mod tests {
         },
         cbor!({}),
         cbor!({}),
-        SystemResetResponse,
+        SystemResetResponse::default(),
     }
 
     command_encode_decode_test! {
@@ -422,7 +520,7 @@ mod tests {
             "boot_mode" => 42,
         }),
         cbor!({}),
-        SystemResetResponse,
+        SystemResetResponse::default(),
     }
 
     command_encode_decode_test! {
@@ -431,7 +529,7 @@ mod tests {
         MCUmgrParameters,
         cbor!({}),
         cbor!({"buf_size" => 42, "buf_count" => 69}),
-        MCUmgrParametersResponse{buf_size: 42, buf_count: 69 },
+        MCUmgrParametersResponse{buf_size: 42, buf_count: 69, extra: Default::default()},
     }
 
     command_encode_decode_test! {
@@ -446,6 +544,7 @@ mod tests {
         }),
         ApplicationInfoResponse{
             output: "foo".to_string(),
+            extra: Default::default(),
         }
     }
 
@@ -463,6 +562,7 @@ mod tests {
         }),
         ApplicationInfoResponse{
             output: "bar".to_string(),
+            extra: Default::default(),
         }
     }
 
@@ -476,6 +576,7 @@ mod tests {
         }),
         BootloaderInfoResponse{
             bootloader: "MCUboot".to_string(),
+            extra: Default::default(),
         }
     }
 
@@ -493,6 +594,7 @@ mod tests {
         BootloaderInfoMcubootModeResponse{
             mode: 5,
             no_downgrade: true,
+            extra: Default::default(),
         }
     }
 
@@ -509,6 +611,7 @@ mod tests {
         BootloaderInfoMcubootModeResponse{
             mode: -1,
             no_downgrade: false,
+            extra: Default::default(),
         }
     }
 }
@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ExtraFields,
+    macros::{impl_into_unit, impl_serialize_as_empty_map},
+};
+
+/// [Read setting](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_3.html#read-setting-request) command
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct ReadSetting<'a> {
+    /// name of the setting
+    pub name: &'a str,
+    /// maximum size of the value to return
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u32>,
+}
+
+/// Response for [`ReadSetting`] command
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ReadSettingResponse {
+    /// value of the setting
+    pub val: Vec<u8>,
+    /// maximum value size supported by the device, if smaller than the requested one
+    pub max_size: Option<u32>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// [Write setting](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_3.html#write-setting-request) command
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct WriteSetting<'a, 'b> {
+    /// name of the setting
+    pub name: &'a str,
+    /// value to write
+    #[serde(with = "serde_bytes")]
+    pub val: &'b [u8],
+}
+
+/// Response for [`WriteSetting`] command
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
+pub struct WriteSettingResponse {
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+impl_into_unit!(WriteSettingResponse);
+
+/// [Delete setting](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_3.html#delete-setting-command) command
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct DeleteSetting<'a> {
+    /// name of the setting
+    pub name: &'a str,
+}
+
+/// Response for [`DeleteSetting`] command
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
+pub struct DeleteSettingResponse {
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+impl_into_unit!(DeleteSettingResponse);
+
+/// [Commit settings](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_3.html#commit-settings-command) command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CommitSettings;
+impl_serialize_as_empty_map!(CommitSettings);
+
+/// Response for [`CommitSettings`] command
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
+pub struct CommitSettingsResponse {
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+impl_into_unit!(CommitSettingsResponse);
+
+/// [Load settings](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_3.html#load-settings-command) command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoadSettings;
+impl_serialize_as_empty_map!(LoadSettings);
+
+/// Response for [`LoadSettings`] command
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
+pub struct LoadSettingsResponse {
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+impl_into_unit!(LoadSettingsResponse);
+
+/// [Save settings](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_3.html#save-settings-command) command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SaveSettings;
+impl_serialize_as_empty_map!(SaveSettings);
+
+/// Response for [`SaveSettings`] command
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
+pub struct SaveSettingsResponse {
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+impl_into_unit!(SaveSettingsResponse);
+
+#[cfg(test)]
+mod tests {
+    use super::super::macros::command_encode_decode_test;
+    use super::*;
+    use ciborium::cbor;
+
+    command_encode_decode_test! {
+        read_setting,
+        (0, 3, 0),
+        ReadSetting{name: "app/calibration", max_size: None},
+        cbor!({"name" => "app/calibration"}),
+        cbor!({"val" => ciborium::Value::Bytes(vec![1, 2, 3])}),
+        ReadSettingResponse{
+            val: vec![1, 2, 3],
+            max_size: None,
+            extra: Default::default(),
+        },
+    }
+
+    command_encode_decode_test! {
+        read_setting_with_max_size,
+        (0, 3, 0),
+        ReadSetting{name: "app/calibration", max_size: Some(1024)},
+        cbor!({"name" => "app/calibration", "max_size" => 1024}),
+        cbor!({"val" => ciborium::Value::Bytes(vec![1, 2, 3, 4]), "max_size" => 4}),
+        ReadSettingResponse{
+            val: vec![1, 2, 3, 4],
+            max_size: Some(4),
+            extra: Default::default(),
+        },
+    }
+
+    command_encode_decode_test! {
+        write_setting,
+        (2, 3, 0),
+        WriteSetting{name: "app/id", val: &[0xde, 0xad]},
+        cbor!({"name" => "app/id", "val" => ciborium::Value::Bytes(vec![0xde, 0xad])}),
+        cbor!({}),
+        WriteSettingResponse::default(),
+    }
+
+    command_encode_decode_test! {
+        delete_setting,
+        (2, 3, 1),
+        DeleteSetting{name: "app/id"},
+        cbor!({"name" => "app/id"}),
+        cbor!({}),
+        DeleteSettingResponse::default(),
+    }
+
+    command_encode_decode_test! {
+        commit_settings,
+        (2, 3, 2),
+        CommitSettings,
+        cbor!({}),
+        cbor!({}),
+        CommitSettingsResponse::default(),
+    }
+
+    command_encode_decode_test! {
+        load_settings,
+        (0, 3, 3),
+        LoadSettings,
+        cbor!({}),
+        cbor!({}),
+        LoadSettingsResponse::default(),
+    }
+
+    command_encode_decode_test! {
+        save_settings,
+        (2, 3, 3),
+        SaveSettings,
+        cbor!({}),
+        cbor!({}),
+        SaveSettingsResponse::default(),
+    }
+}
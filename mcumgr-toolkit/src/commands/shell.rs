@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::ExtraFields;
+
 /// [Shell command line execute](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_9.html#shell-command-line-execute) command
 #[derive(Clone, Debug, Serialize)]
 pub struct ShellCommandLineExecute<'a> {
@@ -8,12 +10,15 @@ pub struct ShellCommandLineExecute<'a> {
 }
 
 /// Response for [`ShellCommandLineExecute`] command
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct ShellCommandLineExecuteResponse {
     /// command output
     pub o: String,
     /// return code from shell command execution
     pub ret: i32,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[cfg(test)]
@@ -41,6 +46,7 @@ mod tests {
         ShellCommandLineExecuteResponse{
             o: "some_zephyr_version".to_string(),
             ret: -4,
+            extra: Default::default(),
         },
     }
 }
@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{ExtraFields, macros::impl_serialize_as_empty_map};
+
+/// [Statistics: group data](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_2.html#statistics-group-data) command
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct GroupData<'a> {
+    /// name of the statistics group
+    pub name: &'a str,
+}
+
+/// Response for [`GroupData`] command
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GroupDataResponse {
+    /// name of the statistics group
+    pub name: String,
+    /// values of the statistics in the group, by name
+    pub fields: BTreeMap<String, u64>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// [Statistics: list of groups](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_2.html#statistics-list-of-groups) command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListGroups;
+impl_serialize_as_empty_map!(ListGroups);
+
+/// Response for [`ListGroups`] command
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ListGroupsResponse {
+    /// names of the statistics groups
+    pub stat_list: Vec<String>,
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::macros::command_encode_decode_test;
+    use super::*;
+    use ciborium::cbor;
+
+    command_encode_decode_test! {
+        group_data,
+        (0, 2, 0),
+        GroupData{name: "radio"},
+        cbor!({"name" => "radio"}),
+        cbor!({
+            "name" => "radio",
+            "fields" => {
+                "tx_errors" => 3,
+                "rx_packets" => 1234,
+            },
+        }),
+        GroupDataResponse{
+            name: "radio".to_string(),
+            fields: BTreeMap::from([
+                ("tx_errors".to_string(), 3),
+                ("rx_packets".to_string(), 1234),
+            ]),
+            extra: Default::default(),
+        },
+    }
+
+    command_encode_decode_test! {
+        list_groups,
+        (0, 2, 1),
+        ListGroups,
+        cbor!({}),
+        cbor!({"stat_list" => ["smp_svr_stats", "radio"]}),
+        ListGroupsResponse{
+            stat_list: vec!["smp_svr_stats".to_string(), "radio".to_string()],
+            extra: Default::default(),
+        },
+    }
+}
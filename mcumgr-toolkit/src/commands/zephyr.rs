@@ -1,5 +1,8 @@
-use crate::commands::macros::{
-    impl_deserialize_from_empty_map_and_into_unit, impl_serialize_as_empty_map,
+use serde::Deserialize;
+
+use crate::commands::{
+    ExtraFields,
+    macros::{impl_into_unit, impl_serialize_as_empty_map},
 };
 
 /// [Erase Storage](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_groups/smp_group_63.html#erase-storage-command) command
@@ -8,9 +11,13 @@ pub struct EraseStorage;
 impl_serialize_as_empty_map!(EraseStorage);
 
 /// Response for [`EraseStorage`] command
-#[derive(Clone, Default, Debug, Eq, PartialEq)]
-pub struct EraseStorageResponse;
-impl_deserialize_from_empty_map_and_into_unit!(EraseStorageResponse);
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
+pub struct EraseStorageResponse {
+    /// Fields that are not known to this crate yet
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+impl_into_unit!(EraseStorageResponse);
 
 #[cfg(test)]
 mod tests {
@@ -24,6 +31,6 @@ mod tests {
         EraseStorage,
         cbor!({}),
         cbor!({}),
-        EraseStorageResponse,
+        EraseStorageResponse::default(),
     }
 }
@@ -1,18 +1,74 @@
-use std::{io::Cursor, sync::Mutex, time::Duration};
+use std::{
+    io::{self, Cursor},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use crate::{
-    commands::{ErrResponse, ErrResponseV2, McuMgrCommand},
+    commands::{ErrResponse, McuMgrCommand},
     smp_errors::{DeviceError, MCUmgrErr},
-    transport::{ReceiveError, SendError, Transport},
+    transport::{
+        self, ConsoleHandler, ReceiveError, SMP_HEADER_SIZE, SendError, SmpVersion, Transport,
+    },
 };
 
 use miette::{Diagnostic, IntoDiagnostic};
+use serde::de::DeserializeOwned;
 use thiserror::Error;
 
+/// Async SMP protocol layer
+#[cfg(feature = "tokio")]
+mod async_connection;
+#[cfg(feature = "tokio")]
+pub use async_connection::{AsyncConnection, AsyncPipeline};
+
+/// Cooperative cancellation of long running operations
+mod cancel;
+use cancel::CancellableWait;
+pub use cancel::CancellationToken;
+pub(crate) use cancel::check_cancelled;
+
+/// Hooks that observe, modify or reject commands
+mod interceptor;
+use interceptor::Interceptors;
+pub use interceptor::{CommandInterceptor, InterceptError, InterceptedRequest};
+
+/// SMP protocol version negotiation
+mod negotiation;
+use negotiation::VersionNegotiation;
+
+/// Multiple requests in flight
+mod pipeline;
+pub use pipeline::Pipeline;
+
+/// Reopening the transport after the device disconnected
+mod reconnect;
+use reconnect::TransportSettings;
+pub use reconnect::{Reopen, TransportWrapper};
+
+/// Automatic retransmission of requests
+mod retry;
+pub use retry::RetryPolicy;
+
+/// Timeouts of commands that take long
+mod timeout;
+
+/// Frame, error and latency statistics
+mod stats;
+use stats::Stats;
+pub use stats::{CommandLatency, ConnectionStats, LATENCY_BUCKETS, LatencyHistogram};
+
 struct Inner {
     transport: Box<dyn Transport + Send>,
     next_seqnum: u8,
     transport_buffer: Box<[u8; u16::MAX as usize]>,
+    retry_policy: RetryPolicy,
+    negotiation: VersionNegotiation,
+    reopen: Option<Reopen>,
+    wrap: Option<TransportWrapper>,
+    disconnected: bool,
+    settings: TransportSettings,
+    timeout_override: Option<Duration>,
 }
 
 /// An SMP protocol layer connection to a device.
@@ -21,6 +77,8 @@ struct Inner {
 /// but instead it is used indirectly through [`MCUmgrClient`](crate::MCUmgrClient).
 pub struct Connection {
     inner: Mutex<Inner>,
+    stats: Stats,
+    interceptors: Interceptors,
 }
 
 /// Errors that can happen on SMP protocol level
@@ -46,6 +104,18 @@ pub enum ExecuteError {
     #[error("Device returned error code: {0}")]
     #[diagnostic(code(mcumgr_toolkit::connection::execute::device_error))]
     ErrorResponse(DeviceError),
+    /// The device disconnected, for example because of a reset, and could not be reopened yet
+    #[error("Device disconnected")]
+    #[diagnostic(code(mcumgr_toolkit::connection::execute::disconnected))]
+    DeviceDisconnected(#[source] io::Error),
+    /// A [`CommandInterceptor`] rejected the request or its response
+    #[error("Rejected by interceptor")]
+    #[diagnostic(code(mcumgr_toolkit::connection::execute::rejected))]
+    Rejected(#[source] InterceptError),
+    /// The [`CancellationToken`] of the command was cancelled
+    #[error("Cancelled")]
+    #[diagnostic(code(mcumgr_toolkit::connection::execute::cancelled))]
+    Cancelled,
 }
 
 impl ExecuteError {
@@ -59,6 +129,148 @@ impl ExecuteError {
     }
 }
 
+/// CBOR encodes the payload of a request.
+fn encode_request<R: McuMgrCommand>(request: &R) -> Result<Vec<u8>, ExecuteError> {
+    let mut data = vec![];
+    ciborium::into_writer(request.data(), &mut data)
+        .into_diagnostic()
+        .map_err(Into::into)
+        .map_err(ExecuteError::EncodeFailed)?;
+
+    log::debug!("TX data: {}", hex::encode(&data));
+
+    Ok(data)
+}
+
+/// Decodes the CBOR payload of a response, including device errors.
+fn decode_response<T: DeserializeOwned>(response: &[u8]) -> Result<T, ExecuteError> {
+    log::debug!("RX data: {}", hex::encode(response));
+
+    let err: ErrResponse = ciborium::from_reader(Cursor::new(response))
+        .into_diagnostic()
+        .map_err(Into::into)
+        .map_err(ExecuteError::DecodeFailed)?;
+
+    if let Some(err) = err.into_device_error() {
+        return Err(ExecuteError::ErrorResponse(err));
+    }
+
+    ciborium::from_reader(Cursor::new(response))
+        .into_diagnostic()
+        .map_err(Into::into)
+        .map_err(ExecuteError::DecodeFailed)
+}
+
+impl Inner {
+    /// Sends a request and receives its response, without retransmissions.
+    fn transceive_once(
+        &mut self,
+        stats: &Stats,
+        version: SmpVersion,
+        request: &InterceptedRequest,
+        timeout: Option<Duration>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<&[u8], ExecuteError> {
+        let InterceptedRequest {
+            write_operation,
+            group_id,
+            command_id,
+            ref data,
+        } = *request;
+
+        let sequence_num = self.next_seqnum;
+        self.next_seqnum = self.next_seqnum.wrapping_add(1);
+
+        self.transport.send_frame(
+            version,
+            write_operation,
+            sequence_num,
+            group_id,
+            command_id,
+            data,
+        )?;
+        stats.frame_sent(data.len());
+        let sent_at = Instant::now();
+
+        let wait = CancellableWait::new(cancel, self.settings.timeout, timeout);
+        let (response_version, response_size) = loop {
+            let frame = timeout::recv_raw_frame(
+                self.transport.as_mut(),
+                &mut self.transport_buffer,
+                self.settings.timeout,
+                wait.next_timeout(),
+            );
+            if wait.slice_elapsed(&frame)? {
+                continue;
+            }
+            stats.frame_received(&frame);
+            let frame = frame?;
+
+            match transport::response_payload(
+                frame,
+                write_operation,
+                sequence_num,
+                group_id,
+                command_id,
+            )? {
+                Some(response) => {
+                    break (
+                        transport::frame_version(frame).unwrap_or_default(),
+                        response.len(),
+                    );
+                }
+                None => stats.frame_skipped(),
+            }
+        };
+
+        stats.round_trip(group_id, command_id, sent_at.elapsed());
+        self.negotiation.response_received(response_version);
+
+        Ok(&self.transport_buffer[SMP_HEADER_SIZE..SMP_HEADER_SIZE + response_size])
+    }
+
+    /// Sends a request and processes its response,
+    /// retransmitting the request as configured by the [`RetryPolicy`]
+    /// or if the device rejected its SMP version.
+    fn transceive<T>(
+        &mut self,
+        stats: &Stats,
+        request: &InterceptedRequest,
+        min_timeout: Option<Duration>,
+        cancel: Option<&CancellationToken>,
+        process_response: impl Fn(&[u8]) -> Result<T, ExecuteError>,
+    ) -> Result<T, ExecuteError> {
+        self.ensure_connected()?;
+
+        let timeout =
+            timeout::request_timeout(self.settings.timeout, self.timeout_override, min_timeout);
+        let policy = self.retry_policy;
+        let mut retry = 0;
+        let mut renegotiated = false;
+
+        loop {
+            let version = self.negotiation.version();
+
+            match self
+                .transceive_once(stats, version, request, timeout, cancel)
+                .and_then(&process_response)
+            {
+                Err(e) if !renegotiated && self.negotiation.request_rejected(version, &e) => {
+                    renegotiated = true;
+                }
+                Err(e) if retry < policy.max_retries && policy.is_retryable(&e) => {
+                    let delay = policy.backoff_delay(retry);
+                    retry += 1;
+                    stats.retry();
+                    retry::log_retry(&e, retry, policy.max_retries);
+                    std::thread::sleep(delay);
+                }
+                result => return result.map_err(|e| self.check_disconnect(e)),
+            }
+        }
+    }
+}
+
 impl Connection {
     /// Creates a new SMP
     pub fn new<T: Transport + Send + 'static>(transport: T) -> Self {
@@ -67,10 +279,88 @@ impl Connection {
                 transport: Box::new(transport),
                 next_seqnum: rand::random(),
                 transport_buffer: Box::new([0; u16::MAX as usize]),
+                retry_policy: RetryPolicy::default(),
+                negotiation: VersionNegotiation::new(),
+                reopen: None,
+                wrap: None,
+                disconnected: false,
+                settings: TransportSettings::default(),
+                timeout_override: None,
             }),
+            stats: Stats::default(),
+            interceptors: Interceptors::default(),
         }
     }
 
+    /// Creates a new SMP connection that reopens its transport after the device disconnected,
+    /// for example because of a reset or a USB re-enumeration.
+    ///
+    /// Errors that indicate a disconnect are reported as [`ExecuteError::DeviceDisconnected`];
+    /// the next command then calls `reopen` and restores the timeout, the MTU or MTU learning,
+    /// and the console handler.
+    pub fn new_reopenable(transport: impl Transport + Send + 'static, reopen: Reopen) -> Self {
+        let connection = Self::new(transport);
+        connection.inner.lock().unwrap().reopen = Some(reopen);
+        connection
+    }
+
+    /// Wraps the transport, for example in a
+    /// [`RecordingTransport`](crate::transport::recording::RecordingTransport).
+    ///
+    /// Transports that get reopened later are wrapped as well,
+    /// and the settings of the connection are kept.
+    pub fn wrap_transport(&self, wrap: TransportWrapper) {
+        self.inner.lock().unwrap().wrap_transport(wrap);
+    }
+
+    /// Configures the automatic retransmission of requests.
+    ///
+    /// Retransmissions are disabled by default.
+    pub fn set_retry_policy(&self, policy: RetryPolicy) {
+        self.inner.lock().unwrap().retry_policy = policy;
+    }
+
+    /// The number of retransmitted requests since the connection was created.
+    pub fn retry_count(&self) -> u64 {
+        self.stats.retries()
+    }
+
+    /// Frame, error and latency statistics since the connection was created.
+    pub fn stats(&self) -> ConnectionStats {
+        self.stats.snapshot()
+    }
+
+    /// Adds a [`CommandInterceptor`] that observes, modifies or rejects all following commands.
+    ///
+    /// Interceptors get called in the order they were added.
+    pub fn add_interceptor(&self, interceptor: Arc<dyn CommandInterceptor>) {
+        self.interceptors.add(interceptor);
+    }
+
+    /// Removes all interceptors.
+    pub fn clear_interceptors(&self) {
+        self.interceptors.clear();
+    }
+
+    /// Selects the SMP protocol version of requests.
+    ///
+    /// `None` negotiates the version automatically, which is the default:
+    /// requests start with [`SmpVersion::V2`] and fall back to [`SmpVersion::V1`]
+    /// if the device rejects them or responds with v1 headers.
+    pub fn set_smp_version(&self, version: Option<SmpVersion>) {
+        self.inner.lock().unwrap().negotiation.set(version);
+    }
+
+    /// The SMP protocol version of requests, as negotiated with the device.
+    pub fn smp_version(&self) -> SmpVersion {
+        self.inner.lock().unwrap().negotiation.version()
+    }
+
+    /// Consumes the connection and returns the underlying transport.
+    pub fn into_transport(self) -> Box<dyn Transport + Send> {
+        self.inner.into_inner().unwrap().transport
+    }
+
     /// Changes the communication timeout.
     ///
     /// When the device does not respond to packets within the set
@@ -79,76 +369,112 @@ impl Connection {
         &self,
         timeout: Duration,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.inner.lock().unwrap().transport.set_timeout(timeout)
+        let mut inner = self.inner.lock().unwrap();
+        inner.transport.set_timeout(timeout)?;
+        inner.settings.timeout = Some(timeout);
+        Ok(())
     }
 
-    /// Executes a given CBOR based SMP command.
-    pub fn execute_command<R: McuMgrCommand>(
-        &self,
-        request: &R,
-    ) -> Result<R::Response, ExecuteError> {
-        let mut lock_guard = self.inner.lock().unwrap();
-        let locked_self: &mut Inner = &mut lock_guard;
-
-        let mut cursor = Cursor::new(locked_self.transport_buffer.as_mut_slice());
-        ciborium::into_writer(request.data(), &mut cursor)
-            .into_diagnostic()
-            .map_err(Into::into)
-            .map_err(ExecuteError::EncodeFailed)?;
-        let data_size = cursor.position() as usize;
-        let data = &locked_self.transport_buffer[..data_size];
-
-        log::debug!("TX data: {}", hex::encode(data));
-
-        let sequence_num = locked_self.next_seqnum;
-        locked_self.next_seqnum = locked_self.next_seqnum.wrapping_add(1);
-
-        let write_operation = request.is_write_operation();
-        let group_id = request.group_id();
-        let command_id = request.command_id();
-
-        locked_self.transport.send_frame(
-            write_operation,
-            sequence_num,
-            group_id,
-            command_id,
-            data,
-        )?;
+    /// Records the communication timeout the transport was created with,
+    /// which is required to temporarily change it for commands that take long.
+    pub(crate) fn with_known_timeout(mut self, timeout: Duration) -> Self {
+        self.inner.get_mut().unwrap().settings.timeout = Some(timeout);
+        self
+    }
 
-        let response = locked_self.transport.receive_frame(
-            &mut locked_self.transport_buffer,
-            write_operation,
-            sequence_num,
-            group_id,
-            command_id,
-        )?;
+    /// Replaces the timeouts of all following commands, or restores them with `None`.
+    ///
+    /// Without an override, commands that take long on the device, like erasing flash,
+    /// wait at least for their [`min_timeout`](McuMgrCommand::min_timeout).
+    /// The communication timeout set by [`set_timeout`](Self::set_timeout) is restored
+    /// after each command.
+    ///
+    /// Requires the communication timeout to be known: it must either be set through
+    /// [`set_timeout`](Self::set_timeout), or the connection was created
+    /// by an [`MCUmgrClient`](crate::MCUmgrClient) constructor that takes a timeout.
+    ///
+    /// Returns the previous override.
+    pub fn set_timeout_override(&self, timeout: Option<Duration>) -> Option<Duration> {
+        std::mem::replace(&mut self.inner.lock().unwrap().timeout_override, timeout)
+    }
 
-        log::debug!("RX data: {}", hex::encode(response));
+    /// Changes the maximum length of a transmitted line.
+    ///
+    /// See [`Transport::set_mtu`].
+    pub fn set_mtu(
+        &self,
+        mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.transport.set_mtu(mtu)?;
+        inner.settings.mtu_changed(mtu);
+        Ok(())
+    }
 
-        let err: ErrResponse = ciborium::from_reader(Cursor::new(response))
-            .into_diagnostic()
-            .map_err(Into::into)
-            .map_err(ExecuteError::DecodeFailed)?;
+    /// Sets a handler that receives all data that is not part of an SMP frame.
+    ///
+    /// See [`Transport::set_console_handler`].
+    pub fn set_console_handler(
+        &self,
+        handler: Option<ConsoleHandler>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut inner = self.inner.lock().unwrap();
+        let handler = handler.map(|handler| Arc::new(Mutex::new(handler)));
+        inner
+            .transport
+            .set_console_handler(handler.as_ref().map(reconnect::forward_console))?;
+        inner.settings.console_handler = handler;
+        Ok(())
+    }
 
-        if let Some(ErrResponseV2 { rc, group }) = err.err {
-            return Err(ExecuteError::ErrorResponse(DeviceError::V2 { group, rc }));
-        }
+    /// Reads incoming data without waiting for an SMP response.
+    ///
+    /// See [`Transport::poll_console`].
+    pub fn poll_console(&self) -> Result<(), ExecuteError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.ensure_connected()?;
+        inner
+            .transport
+            .poll_console()
+            .map_err(|e| inner.check_disconnect(e.into()))
+    }
 
-        if let Some(rc) = err.rc {
-            if rc != MCUmgrErr::MGMT_ERR_EOK as i32 {
-                return Err(ExecuteError::ErrorResponse(DeviceError::V1 {
-                    rc,
-                    rsn: err.rsn,
-                }));
-            }
-        }
+    /// Executes a given CBOR based SMP command.
+    pub fn execute_command<R: McuMgrCommand>(
+        &self,
+        request: &R,
+    ) -> Result<R::Response, ExecuteError> {
+        self.execute(
+            InterceptedRequest::new(request)?,
+            request.min_timeout(),
+            None,
+            decode_response::<R::Response>,
+        )
+    }
 
-        let decoded_response: R::Response = ciborium::from_reader(Cursor::new(response))
-            .into_diagnostic()
-            .map_err(Into::into)
-            .map_err(ExecuteError::DecodeFailed)?;
+    /// Executes a given CBOR based SMP command that can be cancelled.
+    ///
+    /// Fails with [`ExecuteError::Cancelled`] if `cancel` gets cancelled
+    /// before or while waiting for the response.
+    pub fn execute_cancellable_command<R: McuMgrCommand>(
+        &self,
+        request: &R,
+        cancel: &CancellationToken,
+    ) -> Result<R::Response, ExecuteError> {
+        self.execute(
+            InterceptedRequest::new(request)?,
+            request.min_timeout(),
+            Some(cancel),
+            decode_response::<R::Response>,
+        )
+    }
 
-        Ok(decoded_response)
+    /// Creates a [`Pipeline`] that keeps up to `window` requests in flight.
+    ///
+    /// Must not exceed [`MCUMGR_TRANSPORT_NETBUF_COUNT`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig#L28)
+    /// of the device, as reported by [`MCUmgrParameters`](crate::commands::os::MCUmgrParameters).
+    pub fn pipeline<T: DeserializeOwned>(&self, window: usize) -> Pipeline<'_, T> {
+        Pipeline::new(self, window)
     }
 
     /// Executes a raw SMP command.
@@ -167,30 +493,37 @@ impl Connection {
         command_id: u8,
         data: &[u8],
     ) -> Result<Box<[u8]>, ExecuteError> {
-        let mut lock_guard = self.inner.lock().unwrap();
-        let locked_self: &mut Inner = &mut lock_guard;
-
-        let sequence_num = locked_self.next_seqnum;
-        locked_self.next_seqnum = locked_self.next_seqnum.wrapping_add(1);
-
-        locked_self.transport.send_frame(
+        let request = InterceptedRequest {
             write_operation,
-            sequence_num,
             group_id,
             command_id,
-            data,
-        )?;
+            data: data.to_vec(),
+        };
 
-        locked_self
-            .transport
-            .receive_frame(
-                &mut locked_self.transport_buffer,
-                write_operation,
-                sequence_num,
-                group_id,
-                command_id,
-            )
-            .map_err(Into::into)
-            .map(|val| val.into())
+        self.execute(request, None, None, |response| Ok(response.into()))
+    }
+
+    /// Passes a request through the interceptors and executes it.
+    fn execute<T>(
+        &self,
+        mut request: InterceptedRequest,
+        min_timeout: Option<Duration>,
+        cancel: Option<&CancellationToken>,
+        decode: impl Fn(&[u8]) -> Result<T, ExecuteError>,
+    ) -> Result<T, ExecuteError> {
+        check_cancelled(cancel)?;
+
+        let interceptors = self.interceptors.chain();
+        interceptors.request(&mut request)?;
+
+        let result = self.inner.lock().unwrap().transceive(
+            &self.stats,
+            &request,
+            min_timeout,
+            cancel,
+            |response| interceptors.response(&request, response, &decode),
+        );
+
+        interceptors.finish(&request, result)
     }
 }
@@ -0,0 +1,565 @@
+use std::{
+    collections::VecDeque,
+    marker::PhantomData,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::de::DeserializeOwned;
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::{
+    commands::McuMgrCommand,
+    transport::{self, AsyncTransport, SMP_HEADER_SIZE, SendError, SmpVersion},
+};
+
+use super::{
+    CancellableWait, CancellationToken, CommandInterceptor, ConnectionStats, ExecuteError,
+    InterceptedRequest, Interceptors, RetryPolicy, Stats, VersionNegotiation, check_cancelled,
+    decode_response,
+    interceptor::InterceptorChain,
+    pipeline::{PendingRequest, pending_timeout, store_response},
+    retry, timeout,
+};
+
+struct Inner {
+    transport: Box<dyn AsyncTransport + Send>,
+    next_seqnum: u8,
+    transport_buffer: Box<[u8; u16::MAX as usize]>,
+    retry_policy: RetryPolicy,
+    negotiation: VersionNegotiation,
+    timeout: Option<Duration>,
+    timeout_override: Option<Duration>,
+}
+
+/// An async SMP protocol layer connection to a device.
+///
+/// Async counterpart of [`Connection`](super::Connection).
+///
+/// In most cases this struct will not be used directly by the user,
+/// but instead it is used indirectly through [`AsyncMCUmgrClient`](crate::AsyncMCUmgrClient).
+pub struct AsyncConnection {
+    inner: Mutex<Inner>,
+    stats: Stats,
+    interceptors: Interceptors,
+}
+
+impl Inner {
+    /// Sends a request and receives its response, without retransmissions.
+    async fn transceive_once(
+        &mut self,
+        stats: &Stats,
+        version: SmpVersion,
+        request: &InterceptedRequest,
+        timeout: Option<Duration>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<&[u8], ExecuteError> {
+        let InterceptedRequest {
+            write_operation,
+            group_id,
+            command_id,
+            ref data,
+        } = *request;
+
+        let sequence_num = self.next_seqnum;
+        self.next_seqnum = self.next_seqnum.wrapping_add(1);
+
+        self.transport
+            .send_frame(
+                version,
+                write_operation,
+                sequence_num,
+                group_id,
+                command_id,
+                data,
+            )
+            .await?;
+        stats.frame_sent(data.len());
+        let sent_at = Instant::now();
+
+        let wait = CancellableWait::new(cancel, self.timeout, timeout);
+        let (response_version, response_size) = loop {
+            let frame = timeout::recv_raw_frame_async(
+                self.transport.as_mut(),
+                &mut self.transport_buffer,
+                self.timeout,
+                wait.next_timeout(),
+            )
+            .await;
+            if wait.slice_elapsed(&frame)? {
+                continue;
+            }
+            stats.frame_received(&frame);
+            let frame = frame?;
+
+            match transport::response_payload(
+                frame,
+                write_operation,
+                sequence_num,
+                group_id,
+                command_id,
+            )? {
+                Some(response) => {
+                    break (
+                        transport::frame_version(frame).unwrap_or_default(),
+                        response.len(),
+                    );
+                }
+                None => stats.frame_skipped(),
+            }
+        };
+
+        stats.round_trip(group_id, command_id, sent_at.elapsed());
+        self.negotiation.response_received(response_version);
+
+        Ok(&self.transport_buffer[SMP_HEADER_SIZE..SMP_HEADER_SIZE + response_size])
+    }
+
+    /// Sends a request with a new sequence number and the negotiated SMP version.
+    async fn send_pending(
+        &mut self,
+        stats: &Stats,
+        pending: &mut PendingRequest,
+    ) -> Result<(), SendError> {
+        pending.version = self.negotiation.version();
+        pending.sequence_num = self.next_seqnum;
+        self.next_seqnum = self.next_seqnum.wrapping_add(1);
+
+        let request = &pending.request;
+        self.transport
+            .send_frame(
+                pending.version,
+                request.write_operation,
+                pending.sequence_num,
+                request.group_id,
+                request.command_id,
+                &request.data,
+            )
+            .await?;
+        stats.frame_sent(request.data.len());
+        pending.sent_at = Instant::now();
+
+        Ok(())
+    }
+
+    /// Sends a request and processes its response,
+    /// retransmitting the request as configured by the [`RetryPolicy`]
+    /// or if the device rejected its SMP version.
+    async fn transceive<T>(
+        &mut self,
+        stats: &Stats,
+        request: &InterceptedRequest,
+        min_timeout: Option<Duration>,
+        cancel: Option<&CancellationToken>,
+        process_response: impl Fn(&[u8]) -> Result<T, ExecuteError>,
+    ) -> Result<T, ExecuteError> {
+        let timeout = timeout::request_timeout(self.timeout, self.timeout_override, min_timeout);
+        let policy = self.retry_policy;
+        let mut retry = 0;
+        let mut renegotiated = false;
+
+        loop {
+            let version = self.negotiation.version();
+
+            match self
+                .transceive_once(stats, version, request, timeout, cancel)
+                .await
+                .and_then(&process_response)
+            {
+                Err(e) if !renegotiated && self.negotiation.request_rejected(version, &e) => {
+                    renegotiated = true;
+                }
+                Err(e) if retry < policy.max_retries && policy.is_retryable(&e) => {
+                    let delay = policy.backoff_delay(retry);
+                    retry += 1;
+                    stats.retry();
+                    retry::log_retry(&e, retry, policy.max_retries);
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl AsyncConnection {
+    /// Creates a new async SMP connection
+    pub fn new<T: AsyncTransport + Send + 'static>(transport: T) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                transport: Box::new(transport),
+                next_seqnum: rand::random(),
+                transport_buffer: Box::new([0; u16::MAX as usize]),
+                retry_policy: RetryPolicy::default(),
+                negotiation: VersionNegotiation::new(),
+                timeout: None,
+                timeout_override: None,
+            }),
+            stats: Stats::default(),
+            interceptors: Interceptors::default(),
+        }
+    }
+
+    /// Changes the communication timeout.
+    ///
+    /// When the device does not respond to packets within the set
+    /// duration, an error will be raised.
+    pub async fn set_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut inner = self.inner.lock().await;
+        inner.transport.set_timeout(timeout)?;
+        inner.timeout = Some(timeout);
+        Ok(())
+    }
+
+    /// Records the communication timeout the transport was created with,
+    /// which is required to temporarily change it for commands that take long.
+    pub(crate) fn with_known_timeout(mut self, timeout: Duration) -> Self {
+        self.inner.get_mut().timeout = Some(timeout);
+        self
+    }
+
+    /// Replaces the timeouts of all following commands, or restores them with `None`.
+    ///
+    /// See [`Connection::set_timeout_override`](super::Connection::set_timeout_override).
+    pub async fn set_timeout_override(&self, timeout: Option<Duration>) -> Option<Duration> {
+        std::mem::replace(&mut self.inner.lock().await.timeout_override, timeout)
+    }
+
+    /// Changes the maximum length of a transmitted line.
+    ///
+    /// See [`AsyncTransport::set_mtu`].
+    pub async fn set_mtu(
+        &self,
+        mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.inner.lock().await.transport.set_mtu(mtu)
+    }
+
+    /// Configures the automatic retransmission of requests.
+    ///
+    /// See [`Connection::set_retry_policy`](super::Connection::set_retry_policy).
+    pub async fn set_retry_policy(&self, policy: RetryPolicy) {
+        self.inner.lock().await.retry_policy = policy;
+    }
+
+    /// The number of retransmitted requests since the connection was created.
+    pub fn retry_count(&self) -> u64 {
+        self.stats.retries()
+    }
+
+    /// Frame, error and latency statistics since the connection was created.
+    pub fn stats(&self) -> ConnectionStats {
+        self.stats.snapshot()
+    }
+
+    /// Adds a [`CommandInterceptor`] that observes, modifies or rejects all following commands.
+    ///
+    /// See [`Connection::add_interceptor`](super::Connection::add_interceptor).
+    pub fn add_interceptor(&self, interceptor: Arc<dyn CommandInterceptor>) {
+        self.interceptors.add(interceptor);
+    }
+
+    /// Removes all interceptors.
+    pub fn clear_interceptors(&self) {
+        self.interceptors.clear();
+    }
+
+    /// Selects the SMP protocol version of requests.
+    ///
+    /// See [`Connection::set_smp_version`](super::Connection::set_smp_version).
+    pub async fn set_smp_version(&self, version: Option<SmpVersion>) {
+        self.inner.lock().await.negotiation.set(version);
+    }
+
+    /// The SMP protocol version of requests, as negotiated with the device.
+    pub async fn smp_version(&self) -> SmpVersion {
+        self.inner.lock().await.negotiation.version()
+    }
+
+    /// Executes a given CBOR based SMP command.
+    pub async fn execute_command<R: McuMgrCommand + Sync>(
+        &self,
+        request: &R,
+    ) -> Result<R::Response, ExecuteError> {
+        self.execute(
+            InterceptedRequest::new(request)?,
+            request.min_timeout(),
+            None,
+            decode_response::<R::Response>,
+        )
+        .await
+    }
+
+    /// Executes a given CBOR based SMP command that can be cancelled.
+    ///
+    /// See [`Connection::execute_cancellable_command`](super::Connection::execute_cancellable_command).
+    pub async fn execute_cancellable_command<R: McuMgrCommand + Sync>(
+        &self,
+        request: &R,
+        cancel: &CancellationToken,
+    ) -> Result<R::Response, ExecuteError> {
+        self.execute(
+            InterceptedRequest::new(request)?,
+            request.min_timeout(),
+            Some(cancel),
+            decode_response::<R::Response>,
+        )
+        .await
+    }
+
+    /// Creates an [`AsyncPipeline`] that keeps up to `window` requests in flight.
+    ///
+    /// See [`Connection::pipeline`](super::Connection::pipeline).
+    pub fn pipeline<T: DeserializeOwned>(&self, window: usize) -> AsyncPipeline<'_, T> {
+        AsyncPipeline {
+            connection: self,
+            interceptors: self.interceptors.chain(),
+            guard: None,
+            window: window.max(1),
+            in_flight: VecDeque::new(),
+            retry: 0,
+            renegotiated: false,
+            cancel: None,
+            _response: PhantomData,
+        }
+    }
+
+    /// Executes a raw SMP command.
+    ///
+    /// See [`Connection::execute_raw_command`](super::Connection::execute_raw_command).
+    pub async fn execute_raw_command(
+        &self,
+        write_operation: bool,
+        group_id: u16,
+        command_id: u8,
+        data: &[u8],
+    ) -> Result<Box<[u8]>, ExecuteError> {
+        let request = InterceptedRequest {
+            write_operation,
+            group_id,
+            command_id,
+            data: data.to_vec(),
+        };
+
+        self.execute(request, None, None, |response| Ok(response.into()))
+            .await
+    }
+
+    /// Passes a request through the interceptors and executes it.
+    async fn execute<T>(
+        &self,
+        mut request: InterceptedRequest,
+        min_timeout: Option<Duration>,
+        cancel: Option<&CancellationToken>,
+        decode: impl Fn(&[u8]) -> Result<T, ExecuteError>,
+    ) -> Result<T, ExecuteError> {
+        check_cancelled(cancel)?;
+
+        let interceptors = self.interceptors.chain();
+        interceptors.request(&mut request)?;
+
+        let result = self
+            .inner
+            .lock()
+            .await
+            .transceive(&self.stats, &request, min_timeout, cancel, |response| {
+                interceptors.response(&request, response, &decode)
+            })
+            .await;
+
+        interceptors.finish(&request, result)
+    }
+}
+
+/// Keeps multiple requests in flight, to hide the latency of the connection.
+///
+/// Async counterpart of [`Pipeline`](super::Pipeline).
+///
+/// Created by [`AsyncConnection::pipeline`].
+pub struct AsyncPipeline<'a, T> {
+    connection: &'a AsyncConnection,
+    interceptors: InterceptorChain,
+    guard: Option<MutexGuard<'a, Inner>>,
+    window: usize,
+    in_flight: VecDeque<PendingRequest>,
+    retry: u32,
+    renegotiated: bool,
+    cancel: Option<CancellationToken>,
+    _response: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> AsyncPipeline<'_, T> {
+    /// Makes sending and receiving fail with [`ExecuteError::Cancelled`]
+    /// once `cancel` gets cancelled.
+    pub fn with_cancellation(mut self, cancel: Option<&CancellationToken>) -> Self {
+        self.cancel = cancel.cloned();
+        self
+    }
+
+    /// Checks whether another request fits into the window.
+    pub fn can_send(&self) -> bool {
+        self.in_flight.len() < self.window
+    }
+
+    /// Checks whether no requests are in flight.
+    pub fn is_empty(&self) -> bool {
+        self.in_flight.is_empty()
+    }
+
+    /// Sends a request without waiting for its response.
+    ///
+    /// # Panics
+    ///
+    /// If the window is full, see [`can_send`](Self::can_send).
+    pub async fn send<R: McuMgrCommand<Response = T>>(
+        &mut self,
+        request: &R,
+    ) -> Result<(), ExecuteError> {
+        assert!(self.can_send(), "Pipeline window is full");
+
+        check_cancelled(self.cancel.as_ref())?;
+
+        let mut pending = PendingRequest::new(&self.interceptors, request)?;
+
+        if self.guard.is_none() {
+            self.guard = Some(self.connection.inner.lock().await);
+        }
+        let inner = self.guard.as_mut().unwrap();
+        pending.timeout =
+            timeout::request_timeout(inner.timeout, inner.timeout_override, request.min_timeout());
+
+        if let Err(e) = inner
+            .send_pending(&self.connection.stats, &mut pending)
+            .await
+        {
+            return self.interceptors.finish(&pending.request, Err(e.into()));
+        }
+
+        self.in_flight.push_back(pending);
+
+        Ok(())
+    }
+
+    /// Waits for the response of the oldest request in flight.
+    ///
+    /// See [`Pipeline::receive`](super::Pipeline::receive).
+    ///
+    /// # Panics
+    ///
+    /// If no requests are in flight.
+    pub async fn receive(&mut self) -> Result<T, ExecuteError> {
+        assert!(!self.is_empty(), "No requests in flight");
+
+        if self.guard.is_none() {
+            self.guard = Some(self.connection.inner.lock().await);
+        }
+        let inner = self.guard.as_mut().unwrap();
+
+        let mut current_wait = None;
+
+        loop {
+            let front = self.in_flight.front_mut().unwrap();
+            if let Some(response) = front.response.take() {
+                let result =
+                    self.interceptors
+                        .response(&front.request, &response, decode_response::<T>);
+
+                if let Err(e) = &result {
+                    if !self.renegotiated && inner.negotiation.request_rejected(front.version, e) {
+                        self.renegotiated = true;
+                        let mut sent = Ok(());
+                        for pending in &mut self.in_flight {
+                            pending.response = None;
+                            sent = inner.send_pending(&self.connection.stats, pending).await;
+                            if sent.is_err() {
+                                break;
+                            }
+                        }
+                        if let Err(e) = sent {
+                            let front = self.in_flight.front().unwrap();
+                            return self.interceptors.finish(&front.request, Err(e.into()));
+                        }
+                        continue;
+                    }
+                }
+
+                let front = self.in_flight.pop_front().unwrap();
+                self.retry = 0;
+
+                if self.in_flight.is_empty() {
+                    self.guard = None;
+                }
+
+                return self.interceptors.finish(&front.request, result);
+            }
+
+            let Inner {
+                transport,
+                transport_buffer,
+                negotiation,
+                timeout: base_timeout,
+                ..
+            } = &mut **inner;
+
+            let wait = current_wait.get_or_insert_with(|| {
+                CancellableWait::new(
+                    self.cancel.as_ref(),
+                    *base_timeout,
+                    pending_timeout(&self.in_flight),
+                )
+            });
+
+            let stats = &self.connection.stats;
+            let frame = timeout::recv_raw_frame_async(
+                transport.as_mut(),
+                transport_buffer,
+                *base_timeout,
+                wait.next_timeout(),
+            )
+            .await;
+            match wait.slice_elapsed(&frame) {
+                Ok(true) => continue,
+                Ok(false) => current_wait = None,
+                Err(e) => {
+                    let front = self.in_flight.front().unwrap();
+                    return self.interceptors.finish(&front.request, Err(e));
+                }
+            }
+            stats.frame_received(&frame);
+            let result = frame
+                .and_then(|frame| store_response(&mut self.in_flight, negotiation, stats, frame))
+                .map_err(ExecuteError::from);
+
+            if let Err(e) = result {
+                let policy = inner.retry_policy;
+                if self.retry >= policy.max_retries || !policy.is_retryable(&e) {
+                    let front = self.in_flight.front().unwrap();
+                    return self.interceptors.finish(&front.request, Err(e));
+                }
+
+                let delay = policy.backoff_delay(self.retry);
+                self.retry += 1;
+                self.connection.stats.retry();
+                retry::log_retry(&e, self.retry, policy.max_retries);
+                tokio::time::sleep(delay).await;
+
+                let mut sent = Ok(());
+                for pending in &mut self.in_flight {
+                    if pending.response.is_none() {
+                        sent = inner.send_pending(&self.connection.stats, pending).await;
+                        if sent.is_err() {
+                            break;
+                        }
+                    }
+                }
+                if let Err(e) = sent {
+                    let front = self.in_flight.front().unwrap();
+                    return self.interceptors.finish(&front.request, Err(e.into()));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::transport::ReceiveError;
+
+use super::ExecuteError;
+
+/// How often a pending receive checks for cancellation.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The shortest timeout of a receive, as zero is not a valid timeout for all transports.
+const MIN_SLICE_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Cooperatively cancels long running operations, like uploads and downloads.
+///
+/// Clones share their state, so that the token can be cancelled from another thread
+/// while an operation is running. Once cancelled, a token stays cancelled.
+///
+/// Operations check the token between chunks. A pending receive gets interrupted
+/// within a fraction of a second if the communication timeout is known,
+/// otherwise once it timed out. Clients created with a timeout know it;
+/// for all others, it becomes known through [`MCUmgrClient::set_timeout`](crate::MCUmgrClient::set_timeout).
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels all operations that use this token or one of its clones.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Checks whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Fails with [`ExecuteError::Cancelled`] if the token was cancelled.
+pub(crate) fn check_cancelled(cancel: Option<&CancellationToken>) -> Result<(), ExecuteError> {
+    if cancel.is_some_and(CancellationToken::is_cancelled) {
+        Err(ExecuteError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// Waiting for a frame, in short slices to react to cancellation.
+///
+/// Slicing requires the communication timeout to be known, so that it can be restored.
+pub(super) struct CancellableWait<'a> {
+    cancel: Option<&'a CancellationToken>,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+impl<'a> CancellableWait<'a> {
+    /// Starts waiting for up to `timeout`, or the communication timeout `base` if `None`.
+    pub(super) fn new(
+        cancel: Option<&'a CancellationToken>,
+        base: Option<Duration>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        let timeout = timeout.or(base);
+        let deadline = cancel
+            .and(base)
+            .and(timeout)
+            .map(|timeout| Instant::now() + timeout);
+
+        Self {
+            cancel,
+            timeout,
+            deadline,
+        }
+    }
+
+    /// The timeout of the next receive.
+    pub(super) fn next_timeout(&self) -> Option<Duration> {
+        match self.deadline {
+            Some(deadline) => Some(
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .clamp(MIN_SLICE_TIMEOUT, CANCEL_POLL_INTERVAL),
+            ),
+            None => self.timeout,
+        }
+    }
+
+    /// Checks whether a receive only timed out at the end of a slice,
+    /// so that waiting should continue.
+    ///
+    /// Fails with [`ExecuteError::Cancelled`] if the token was cancelled in the meantime.
+    pub(super) fn slice_elapsed(
+        &self,
+        frame: &Result<&[u8], ReceiveError>,
+    ) -> Result<bool, ExecuteError> {
+        match frame {
+            Err(e) if e.is_timeout() => {
+                check_cancelled(self.cancel)?;
+                Ok(self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() < deadline))
+            }
+            _ => Ok(false),
+        }
+    }
+}
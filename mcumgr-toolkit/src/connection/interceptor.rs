@@ -0,0 +1,202 @@
+use std::sync::{Arc, RwLock};
+
+use crate::commands::McuMgrCommand;
+
+use super::{ExecuteError, encode_request};
+
+/// The error an interceptor returns to reject a command.
+pub type InterceptError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A request as seen by a [`CommandInterceptor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterceptedRequest {
+    /// Whether the request is a write operation
+    pub write_operation: bool,
+    /// The group ID of the command
+    pub group_id: u16,
+    /// The command ID
+    pub command_id: u8,
+    /// The payload of the request, usually CBOR encoded
+    pub data: Vec<u8>,
+}
+
+impl InterceptedRequest {
+    /// CBOR encodes a command.
+    pub(super) fn new<R: McuMgrCommand>(request: &R) -> Result<Self, ExecuteError> {
+        Ok(Self {
+            write_operation: request.is_write_operation(),
+            group_id: request.group_id(),
+            command_id: request.command_id(),
+            data: encode_request(request)?,
+        })
+    }
+
+    /// Checks whether this is a request of the given command.
+    pub fn is<R: McuMgrCommand>(&self, command: &R) -> bool {
+        self.group_id == command.group_id() && self.command_id == command.command_id()
+    }
+}
+
+/// Hooks that observe, modify or reject the commands of a connection.
+///
+/// Use cases are audit logging, blocking dangerous commands, collecting metrics
+/// or rewriting the payloads of vendor specific groups.
+///
+/// Interceptors get called in the order they were added, for all commands,
+/// including raw and pipelined ones. Retransmissions of a request are not intercepted again.
+///
+/// All hooks have a default implementation that does nothing.
+///
+/// # Example
+///
+/// ```no_run
+/// use mcumgr_toolkit::{
+///     MCUmgrClient,
+///     commands::zephyr::EraseStorage,
+///     connection::{CommandInterceptor, InterceptError, InterceptedRequest},
+/// };
+///
+/// struct ProductionMode;
+///
+/// impl CommandInterceptor for ProductionMode {
+///     fn on_request(&self, request: &mut InterceptedRequest) -> Result<(), InterceptError> {
+///         if request.is(&EraseStorage) {
+///             return Err("Erasing the storage is not allowed in production".into());
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let client = MCUmgrClient::new_from_usb_serial("2fe3:0100", 115200, std::time::Duration::from_secs(5)).unwrap();
+/// client.add_interceptor(ProductionMode);
+///
+/// assert!(client.zephyr_erase_storage().is_err());
+/// ```
+pub trait CommandInterceptor: Send + Sync {
+    /// Called before a request gets sent.
+    ///
+    /// Can modify the request, or reject it by returning an error;
+    /// the command then fails with [`ExecuteError::Rejected`] without being sent.
+    fn on_request(&self, request: &mut InterceptedRequest) -> Result<(), InterceptError> {
+        let _ = request;
+        Ok(())
+    }
+
+    /// Called with the payload of every response, before it gets decoded.
+    ///
+    /// Can modify the payload, or reject it by returning an error;
+    /// the command then fails with [`ExecuteError::Rejected`].
+    fn on_response(
+        &self,
+        request: &InterceptedRequest,
+        response: &mut Vec<u8>,
+    ) -> Result<(), InterceptError> {
+        let _ = (request, response);
+        Ok(())
+    }
+
+    /// Called once a command finished, with its decoded response or its error.
+    ///
+    /// Responses of raw commands that are not valid CBOR are passed as [`ciborium::Value::Bytes`].
+    fn on_result(
+        &self,
+        request: &InterceptedRequest,
+        result: Result<&ciborium::Value, &ExecuteError>,
+    ) {
+        let _ = (request, result);
+    }
+}
+
+/// The interceptors registered at a connection.
+#[derive(Default)]
+pub(super) struct Interceptors(RwLock<Vec<Arc<dyn CommandInterceptor>>>);
+
+impl Interceptors {
+    pub(super) fn add(&self, interceptor: Arc<dyn CommandInterceptor>) {
+        self.0.write().unwrap().push(interceptor);
+    }
+
+    pub(super) fn clear(&self) {
+        self.0.write().unwrap().clear();
+    }
+
+    /// The interceptors of one command.
+    ///
+    /// Taken when the command starts, so that interceptors added in the meantime
+    /// do not observe half of a command.
+    pub(super) fn chain(&self) -> InterceptorChain {
+        InterceptorChain(self.0.read().unwrap().clone())
+    }
+}
+
+/// The interceptors of one command, see [`Interceptors::chain`].
+pub(super) struct InterceptorChain(Vec<Arc<dyn CommandInterceptor>>);
+
+impl InterceptorChain {
+    /// Passes a request through all interceptors.
+    ///
+    /// If an interceptor rejects it, the rejection is reported as result to all interceptors.
+    pub(super) fn request(&self, request: &mut InterceptedRequest) -> Result<(), ExecuteError> {
+        for interceptor in &self.0 {
+            if let Err(e) = interceptor.on_request(request) {
+                return self.finish::<()>(request, Err(ExecuteError::Rejected(e)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Passes a response through all interceptors and decodes it.
+    ///
+    /// Returns the decoded response together with the intercepted payload,
+    /// which must be handed to [`finish`](Self::finish).
+    pub(super) fn response<T>(
+        &self,
+        request: &InterceptedRequest,
+        response: &[u8],
+        decode: impl Fn(&[u8]) -> Result<T, ExecuteError>,
+    ) -> Result<(T, Vec<u8>), ExecuteError> {
+        if self.0.is_empty() {
+            return Ok((decode(response)?, Vec::new()));
+        }
+
+        let mut response = response.to_vec();
+        for interceptor in &self.0 {
+            interceptor
+                .on_response(request, &mut response)
+                .map_err(ExecuteError::Rejected)?;
+        }
+
+        Ok((decode(&response)?, response))
+    }
+
+    /// Reports the result of a command to all interceptors.
+    pub(super) fn finish<T>(
+        &self,
+        request: &InterceptedRequest,
+        result: Result<(T, Vec<u8>), ExecuteError>,
+    ) -> Result<T, ExecuteError> {
+        if self.0.is_empty() {
+            return result.map(|(value, _)| value);
+        }
+
+        match result {
+            Ok((value, response)) => {
+                let decoded = match ciborium::from_reader(response.as_slice()) {
+                    Ok(decoded) => decoded,
+                    Err(_) => ciborium::Value::Bytes(response),
+                };
+                for interceptor in &self.0 {
+                    interceptor.on_result(request, Ok(&decoded));
+                }
+                Ok(value)
+            }
+            Err(e) => {
+                for interceptor in &self.0 {
+                    interceptor.on_result(request, Err(&e));
+                }
+                Err(e)
+            }
+        }
+    }
+}
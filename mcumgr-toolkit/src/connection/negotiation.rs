@@ -0,0 +1,83 @@
+use crate::{
+    smp_errors::{DeviceError, MCUmgrErr},
+    transport::SmpVersion,
+};
+
+use super::ExecuteError;
+
+/// Negotiates the SMP protocol version with the device.
+///
+/// Requests start with [`SmpVersion::V2`]; the version changes if the device
+/// rejects a request because of its version or responds with a different version.
+pub(super) struct VersionNegotiation {
+    version: SmpVersion,
+    automatic: bool,
+}
+
+impl VersionNegotiation {
+    pub(super) fn new() -> Self {
+        Self {
+            version: SmpVersion::V2,
+            automatic: true,
+        }
+    }
+
+    /// The version of the next request.
+    pub(super) fn version(&self) -> SmpVersion {
+        self.version
+    }
+
+    /// Uses a fixed version, or negotiates it automatically if `None`.
+    pub(super) fn set(&mut self, version: Option<SmpVersion>) {
+        self.version = version.unwrap_or_default();
+        self.automatic = version.is_none();
+    }
+
+    /// Adopts the version the device responded with.
+    pub(super) fn response_received(&mut self, version: SmpVersion) {
+        if self.automatic && self.version != version {
+            log::info!(
+                "Device responded with {version}, switching from {}",
+                self.version
+            );
+            self.version = version;
+        }
+    }
+
+    /// Switches the version if the device rejected a request because of its version.
+    ///
+    /// # Return
+    ///
+    /// Whether the request should be retransmitted with the new version.
+    ///
+    pub(super) fn request_rejected(
+        &mut self,
+        request_version: SmpVersion,
+        error: &ExecuteError,
+    ) -> bool {
+        if !self.automatic {
+            return false;
+        }
+
+        let ExecuteError::ErrorResponse(DeviceError::V1 { rc, .. }) = error else {
+            return false;
+        };
+
+        let version = if *rc == MCUmgrErr::MGMT_ERR_UNSUPPORTED_TOO_NEW as i32 {
+            SmpVersion::V1
+        } else if *rc == MCUmgrErr::MGMT_ERR_UNSUPPORTED_TOO_OLD as i32 {
+            SmpVersion::V2
+        } else {
+            return false;
+        };
+
+        if version == request_version {
+            return false;
+        }
+
+        log::info!("Device rejected {request_version}, falling back to {version}");
+        self.version = version;
+
+        true
+    }
+}
@@ -0,0 +1,342 @@
+use std::{
+    collections::VecDeque,
+    marker::PhantomData,
+    sync::MutexGuard,
+    time::{Duration, Instant},
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    commands::McuMgrCommand,
+    transport::{self, ReceiveError, SendError, SmpVersion},
+};
+
+use super::{
+    CancellableWait, CancellationToken, Connection, ExecuteError, Inner, InterceptedRequest, Stats,
+    VersionNegotiation, check_cancelled, decode_response, interceptor::InterceptorChain, retry,
+    timeout,
+};
+
+/// A request that was sent, but whose response was not consumed yet.
+pub(super) struct PendingRequest {
+    pub(super) version: SmpVersion,
+    pub(super) sequence_num: u8,
+    pub(super) request: InterceptedRequest,
+    pub(super) timeout: Option<Duration>,
+    pub(super) response: Option<Vec<u8>>,
+    pub(super) sent_at: Instant,
+}
+
+impl PendingRequest {
+    /// Encodes a request and passes it through the interceptors.
+    pub(super) fn new<R: McuMgrCommand>(
+        interceptors: &InterceptorChain,
+        request: &R,
+    ) -> Result<Self, ExecuteError> {
+        let mut request = InterceptedRequest::new(request)?;
+        interceptors.request(&mut request)?;
+
+        Ok(Self {
+            version: SmpVersion::default(),
+            sequence_num: 0,
+            request,
+            timeout: None,
+            response: None,
+            sent_at: Instant::now(),
+        })
+    }
+}
+
+/// Stores the payload of a received frame at the request it belongs to.
+///
+/// Frames that do not belong to any of the requests get ignored,
+/// for example late responses to retransmitted requests.
+pub(super) fn store_response(
+    in_flight: &mut VecDeque<PendingRequest>,
+    negotiation: &mut VersionNegotiation,
+    stats: &Stats,
+    frame: &[u8],
+) -> Result<(), ReceiveError> {
+    let sequence_num =
+        transport::frame_sequence_num(frame).ok_or(ReceiveError::UnexpectedResponse)?;
+
+    let Some(pending) = in_flight
+        .iter_mut()
+        .find(|pending| pending.sequence_num == sequence_num && pending.response.is_none())
+    else {
+        stats.frame_skipped();
+        return Ok(());
+    };
+
+    let request = &pending.request;
+    let payload = transport::response_payload(
+        frame,
+        request.write_operation,
+        pending.sequence_num,
+        request.group_id,
+        request.command_id,
+    )?
+    .ok_or(ReceiveError::UnexpectedResponse)?;
+
+    pending.response = Some(payload.to_vec());
+    stats.round_trip(
+        request.group_id,
+        request.command_id,
+        pending.sent_at.elapsed(),
+    );
+
+    if let Some(version) = transport::frame_version(frame) {
+        negotiation.response_received(version);
+    }
+
+    Ok(())
+}
+
+/// The longest timeout of the requests that wait for their response.
+pub(super) fn pending_timeout(in_flight: &VecDeque<PendingRequest>) -> Option<Duration> {
+    in_flight
+        .iter()
+        .filter(|pending| pending.response.is_none())
+        .filter_map(|pending| pending.timeout)
+        .max()
+}
+
+impl Inner {
+    /// Sends a request with a new sequence number and the negotiated SMP version.
+    fn send_pending(
+        &mut self,
+        stats: &Stats,
+        pending: &mut PendingRequest,
+    ) -> Result<(), SendError> {
+        pending.version = self.negotiation.version();
+        pending.sequence_num = self.next_seqnum;
+        self.next_seqnum = self.next_seqnum.wrapping_add(1);
+
+        let request = &pending.request;
+        self.transport.send_frame(
+            pending.version,
+            request.write_operation,
+            pending.sequence_num,
+            request.group_id,
+            request.command_id,
+            &request.data,
+        )?;
+        stats.frame_sent(request.data.len());
+        pending.sent_at = Instant::now();
+
+        Ok(())
+    }
+}
+
+/// Keeps multiple requests in flight, to hide the latency of the connection.
+///
+/// All requests must be of commands with the response type `T`.
+/// Responses get matched to their requests by sequence number and are returned
+/// in the order of the requests.
+///
+/// The connection is locked while requests are in flight; other commands
+/// of the same connection block until all responses were received.
+///
+/// Created by [`Connection::pipeline`].
+pub struct Pipeline<'a, T> {
+    connection: &'a Connection,
+    interceptors: InterceptorChain,
+    guard: Option<MutexGuard<'a, Inner>>,
+    window: usize,
+    in_flight: VecDeque<PendingRequest>,
+    retry: u32,
+    renegotiated: bool,
+    cancel: Option<CancellationToken>,
+    _response: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: DeserializeOwned> Pipeline<'a, T> {
+    pub(super) fn new(connection: &'a Connection, window: usize) -> Self {
+        Self {
+            connection,
+            interceptors: connection.interceptors.chain(),
+            guard: None,
+            window: window.max(1),
+            in_flight: VecDeque::new(),
+            retry: 0,
+            renegotiated: false,
+            cancel: None,
+            _response: PhantomData,
+        }
+    }
+
+    /// Makes sending and receiving fail with [`ExecuteError::Cancelled`]
+    /// once `cancel` gets cancelled.
+    pub fn with_cancellation(mut self, cancel: Option<&CancellationToken>) -> Self {
+        self.cancel = cancel.cloned();
+        self
+    }
+
+    /// Checks whether another request fits into the window.
+    pub fn can_send(&self) -> bool {
+        self.in_flight.len() < self.window
+    }
+
+    /// Checks whether no requests are in flight.
+    pub fn is_empty(&self) -> bool {
+        self.in_flight.is_empty()
+    }
+
+    /// Sends a request without waiting for its response.
+    ///
+    /// # Panics
+    ///
+    /// If the window is full, see [`can_send`](Self::can_send).
+    pub fn send<R: McuMgrCommand<Response = T>>(
+        &mut self,
+        request: &R,
+    ) -> Result<(), ExecuteError> {
+        assert!(self.can_send(), "Pipeline window is full");
+
+        check_cancelled(self.cancel.as_ref())?;
+
+        let mut pending = PendingRequest::new(&self.interceptors, request)?;
+
+        let inner = self
+            .guard
+            .get_or_insert_with(|| self.connection.inner.lock().unwrap());
+        pending.timeout = timeout::request_timeout(
+            inner.settings.timeout,
+            inner.timeout_override,
+            request.min_timeout(),
+        );
+        let result = if self.in_flight.is_empty() {
+            inner.ensure_connected()
+        } else {
+            Ok(())
+        }
+        .and_then(|()| {
+            inner
+                .send_pending(&self.connection.stats, &mut pending)
+                .map_err(|e| inner.check_disconnect(e.into()))
+        });
+
+        if let Err(e) = result {
+            return self.interceptors.finish(&pending.request, Err(e));
+        }
+
+        self.in_flight.push_back(pending);
+
+        Ok(())
+    }
+
+    /// Waits for the response of the oldest request in flight.
+    ///
+    /// Retransmits all requests in flight as configured by the [`RetryPolicy`](super::RetryPolicy)
+    /// or if the device rejected their SMP version.
+    ///
+    /// # Panics
+    ///
+    /// If no requests are in flight.
+    pub fn receive(&mut self) -> Result<T, ExecuteError> {
+        assert!(!self.is_empty(), "No requests in flight");
+
+        let inner = self
+            .guard
+            .get_or_insert_with(|| self.connection.inner.lock().unwrap());
+
+        let mut current_wait = None;
+
+        loop {
+            let front = self.in_flight.front_mut().unwrap();
+            if let Some(response) = front.response.take() {
+                let result =
+                    self.interceptors
+                        .response(&front.request, &response, decode_response::<T>);
+
+                if let Err(e) = &result {
+                    if !self.renegotiated && inner.negotiation.request_rejected(front.version, e) {
+                        self.renegotiated = true;
+                        let sent = self.in_flight.iter_mut().try_for_each(|pending| {
+                            pending.response = None;
+                            inner.send_pending(&self.connection.stats, pending)
+                        });
+                        if let Err(e) = sent {
+                            let e = inner.check_disconnect(e.into());
+                            let front = self.in_flight.front().unwrap();
+                            return self.interceptors.finish(&front.request, Err(e));
+                        }
+                        continue;
+                    }
+                }
+
+                let front = self.in_flight.pop_front().unwrap();
+                self.retry = 0;
+
+                if self.in_flight.is_empty() {
+                    self.guard = None;
+                }
+
+                return self.interceptors.finish(&front.request, result);
+            }
+
+            let Inner {
+                transport,
+                transport_buffer,
+                negotiation,
+                settings,
+                ..
+            } = &mut **inner;
+
+            let wait = current_wait.get_or_insert_with(|| {
+                CancellableWait::new(
+                    self.cancel.as_ref(),
+                    settings.timeout,
+                    pending_timeout(&self.in_flight),
+                )
+            });
+
+            let stats = &self.connection.stats;
+            let frame = timeout::recv_raw_frame(
+                transport.as_mut(),
+                transport_buffer,
+                settings.timeout,
+                wait.next_timeout(),
+            );
+            match wait.slice_elapsed(&frame) {
+                Ok(true) => continue,
+                Ok(false) => current_wait = None,
+                Err(e) => {
+                    let front = self.in_flight.front().unwrap();
+                    return self.interceptors.finish(&front.request, Err(e));
+                }
+            }
+            stats.frame_received(&frame);
+            let result = frame
+                .and_then(|frame| store_response(&mut self.in_flight, negotiation, stats, frame))
+                .map_err(ExecuteError::from);
+
+            if let Err(e) = result {
+                let policy = inner.retry_policy;
+                if self.retry >= policy.max_retries || !policy.is_retryable(&e) {
+                    let e = inner.check_disconnect(e);
+                    let front = self.in_flight.front().unwrap();
+                    return self.interceptors.finish(&front.request, Err(e));
+                }
+
+                let delay = policy.backoff_delay(self.retry);
+                self.retry += 1;
+                self.connection.stats.retry();
+                retry::log_retry(&e, self.retry, policy.max_retries);
+                std::thread::sleep(delay);
+
+                let sent = self
+                    .in_flight
+                    .iter_mut()
+                    .filter(|pending| pending.response.is_none())
+                    .try_for_each(|pending| inner.send_pending(&self.connection.stats, pending));
+                if let Err(e) = sent {
+                    let e = inner.check_disconnect(e.into());
+                    let front = self.in_flight.front().unwrap();
+                    return self.interceptors.finish(&front.request, Err(e));
+                }
+            }
+        }
+    }
+}
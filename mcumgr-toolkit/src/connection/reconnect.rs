@@ -0,0 +1,188 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::transport::{
+    ConsoleHandler, ReceiveError, SMP_HEADER_SIZE, SMP_TRANSFER_BUFFER_SIZE, SendError, Transport,
+    serial::SerialTransportConfig,
+};
+
+use super::{ExecuteError, Inner};
+
+/// Reopens the transport after the device disconnected,
+/// see [`Connection::new_reopenable`](super::Connection::new_reopenable).
+pub type Reopen = Box<dyn FnMut() -> io::Result<Box<dyn Transport + Send>> + Send>;
+
+/// Wraps the transport and every reopened one,
+/// see [`Connection::wrap_transport`](super::Connection::wrap_transport).
+pub type TransportWrapper =
+    Box<dyn FnMut(Box<dyn Transport + Send>) -> Box<dyn Transport + Send> + Send>;
+
+/// A console handler that survives reopening the transport.
+pub(super) type SharedConsoleHandler = Arc<Mutex<ConsoleHandler>>;
+
+/// Creates a console handler for a transport that forwards to the shared handler.
+pub(super) fn forward_console(handler: &SharedConsoleHandler) -> ConsoleHandler {
+    let handler = handler.clone();
+    Box::new(move |line| (handler.lock().unwrap())(line))
+}
+
+/// The transport settings that get restored after reopening the transport.
+#[derive(Default)]
+pub(super) struct TransportSettings {
+    pub(super) timeout: Option<Duration>,
+    /// The MTU and whether it gets learned; `None` if the MTU was never configured
+    pub(super) serial: Option<SerialTransportConfig>,
+    pub(super) console_handler: Option<SharedConsoleHandler>,
+}
+
+impl TransportSettings {
+    /// Records a successful [`Transport::set_mtu`].
+    pub(super) fn mtu_changed(&mut self, mtu: Option<usize>) {
+        let serial = self.serial.get_or_insert_with(Default::default);
+        match mtu {
+            Some(mtu) => {
+                serial.mtu = mtu;
+                serial.learn_mtu = false;
+            }
+            None => serial.learn_mtu = true,
+        }
+    }
+
+    fn apply(&self, transport: &mut dyn Transport) -> io::Result<()> {
+        if let Some(timeout) = self.timeout {
+            transport.set_timeout(timeout).map_err(io::Error::other)?;
+        }
+        if let Some(serial) = &self.serial {
+            transport
+                .set_mtu(Some(serial.mtu))
+                .map_err(io::Error::other)?;
+            if serial.learn_mtu {
+                transport.set_mtu(None).map_err(io::Error::other)?;
+            }
+        }
+        if let Some(handler) = &self.console_handler {
+            transport
+                .set_console_handler(Some(forward_console(handler)))
+                .map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+}
+
+/// Takes the place of the transport after the device disconnected,
+/// so that the old transport releases the port until it gets reopened.
+struct Disconnected;
+
+impl Disconnected {
+    fn error() -> io::Error {
+        io::Error::new(io::ErrorKind::NotConnected, "Device disconnected")
+    }
+}
+
+impl Transport for Disconnected {
+    fn send_raw_frame(
+        &mut self,
+        _header: [u8; SMP_HEADER_SIZE],
+        _data: &[u8],
+    ) -> Result<(), SendError> {
+        Err(Self::error().into())
+    }
+
+    fn recv_raw_frame<'a>(
+        &mut self,
+        _buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    ) -> Result<&'a [u8], ReceiveError> {
+        Err(Self::error().into())
+    }
+
+    fn set_timeout(
+        &mut self,
+        _timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn set_mtu(
+        &mut self,
+        _mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn set_console_handler(
+        &mut self,
+        _handler: Option<ConsoleHandler>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+impl Inner {
+    /// Reopens the transport.
+    fn reconnect(&mut self) -> Result<(), ExecuteError> {
+        let Some(reopen) = &mut self.reopen else {
+            return Ok(());
+        };
+
+        let mut transport = reopen().map_err(ExecuteError::DeviceDisconnected)?;
+        if let Some(wrap) = &mut self.wrap {
+            transport = wrap(transport);
+        }
+        self.settings
+            .apply(&mut *transport)
+            .map_err(ExecuteError::DeviceDisconnected)?;
+
+        self.transport = transport;
+        self.disconnected = false;
+        log::info!("Reconnected to device");
+
+        Ok(())
+    }
+
+    /// Wraps the transport and, after the previous wrappers, every reopened one.
+    pub(super) fn wrap_transport(&mut self, mut wrap: TransportWrapper) {
+        let transport = std::mem::replace(&mut self.transport, Box::new(Disconnected));
+        self.transport = wrap(transport);
+
+        self.wrap = Some(match self.wrap.take() {
+            Some(mut previous) => Box::new(move |transport| wrap(previous(transport))),
+            None => wrap,
+        });
+    }
+
+    /// Reopens the transport if the device disconnected earlier.
+    pub(super) fn ensure_connected(&mut self) -> Result<(), ExecuteError> {
+        if self.disconnected {
+            self.reconnect()?;
+        }
+        Ok(())
+    }
+
+    /// Marks the connection as disconnected if the error says so.
+    ///
+    /// Only reopenable connections report [`ExecuteError::DeviceDisconnected`];
+    /// for all others, a disconnect is final and gets reported unchanged.
+    pub(super) fn check_disconnect(&mut self, error: ExecuteError) -> ExecuteError {
+        if self.reopen.is_none() {
+            return error;
+        }
+
+        match error {
+            ExecuteError::SendFailed(SendError::TransportError(e))
+            | ExecuteError::ReceiveFailed(ReceiveError::TransportError(e))
+                if crate::transport::is_disconnect(&e) =>
+            {
+                if !self.disconnected {
+                    log::warn!("Device disconnected: {e}");
+                    self.disconnected = true;
+                    self.transport = Box::new(Disconnected);
+                }
+                ExecuteError::DeviceDisconnected(e)
+            }
+            error => error,
+        }
+    }
+}
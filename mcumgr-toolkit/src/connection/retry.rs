@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use crate::transport::ReceiveError;
+
+use super::ExecuteError;
+
+/// Configures the automatic retransmission of requests.
+///
+/// Every retransmission uses a new sequence number,
+/// so late responses to earlier attempts get ignored.
+///
+/// Be aware that retransmitted requests might get executed twice by the device,
+/// if only the response got lost. Uploads are safe, as they continue at the
+/// offset reported by the device.
+///
+/// ```
+/// # use mcumgr_toolkit::connection::RetryPolicy;
+/// let policy = RetryPolicy {
+///     max_retries: 3,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How often a request gets retransmitted before giving up; `0` disables retransmissions
+    pub max_retries: u32,
+    /// The delay before the first retransmission
+    pub backoff: Duration,
+    /// The factor the delay gets multiplied with after every retransmission
+    pub backoff_factor: u32,
+    /// Retransmit if the device did not respond in time
+    pub retry_on_timeout: bool,
+    /// Retransmit if the response was corrupted, for example because of a CRC error
+    pub retry_on_corruption: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            backoff: Duration::from_millis(100),
+            backoff_factor: 2,
+            retry_on_timeout: true,
+            retry_on_corruption: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Checks whether the given error should trigger a retransmission.
+    pub fn is_retryable(&self, error: &ExecuteError) -> bool {
+        let ExecuteError::ReceiveFailed(error) = error else {
+            return false;
+        };
+
+        match error {
+            ReceiveError::TransportError(_) => self.retry_on_timeout && error.is_timeout(),
+            ReceiveError::UnexpectedResponse
+            | ReceiveError::FrameTooBig
+            | ReceiveError::Base64DecodeError(_)
+            | ReceiveError::ChecksumMismatch => self.retry_on_corruption,
+        }
+    }
+
+    /// The delay before the given retransmission, starting at `0`.
+    pub(super) fn backoff_delay(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(self.backoff_factor.saturating_pow(retry))
+    }
+}
+
+/// Logs a retransmission, including the full chain of causes.
+pub(super) fn log_retry(error: &ExecuteError, retry: u32, max_retries: u32) {
+    let reason = std::iter::successors(Some(error as &dyn std::error::Error), |error| {
+        error.source()
+    })
+    .map(ToString::to_string)
+    .collect::<Vec<_>>()
+    .join(": ");
+
+    log::warn!("{reason}; retransmitting request ({retry}/{max_retries})");
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::transport::{ReceiveError, SMP_HEADER_SIZE};
+
+/// Upper bounds of the buckets of a [`LatencyHistogram`].
+///
+/// Round trips that take longer than the last bound are counted in an additional bucket.
+pub const LATENCY_BUCKETS: [Duration; 12] = [
+    Duration::from_millis(1),
+    Duration::from_millis(2),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(20),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(5),
+];
+
+/// A histogram of request round-trip times.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// The number of round trips per bucket of [`LATENCY_BUCKETS`],
+    /// followed by the number of round trips that took longer than the last bucket
+    pub buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    /// The number of round trips
+    pub count: u64,
+    /// The sum of all round-trip times
+    pub total: Duration,
+    /// The fastest round trip
+    pub min: Duration,
+    /// The slowest round trip
+    pub max: Duration,
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, latency: Duration) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket] += 1;
+
+        self.min = if self.count == 0 {
+            latency
+        } else {
+            self.min.min(latency)
+        };
+        self.max = self.max.max(latency);
+        self.total += latency;
+        self.count += 1;
+    }
+
+    /// The average round-trip time, or `None` if nothing was recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        u32::try_from(self.count)
+            .ok()
+            .filter(|count| *count > 0)
+            .map(|count| self.total / count)
+    }
+
+    /// The upper bound of the bucket that contains the given percentile,
+    /// or `None` if nothing was recorded.
+    ///
+    /// For the bucket above [`LATENCY_BUCKETS`], this is the slowest round trip.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = ((self.count as f64) * percentile / 100.0).ceil().max(1.0) as u64;
+
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(LATENCY_BUCKETS.get(bucket).copied().unwrap_or(self.max));
+            }
+        }
+
+        Some(self.max)
+    }
+}
+
+/// The round-trip times of one command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandLatency {
+    /// The group ID of the command
+    pub group_id: u16,
+    /// The command ID
+    pub command_id: u8,
+    /// The round-trip times of all successful requests of this command
+    pub histogram: LatencyHistogram,
+}
+
+/// Statistics of a connection since it was created.
+///
+/// Byte counts include the SMP header, but not the framing of the transport.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// The number of SMP frames sent
+    pub frames_sent: u64,
+    /// The number of bytes sent
+    pub bytes_sent: u64,
+    /// The number of SMP frames received, including skipped ones
+    pub frames_received: u64,
+    /// The number of bytes received
+    pub bytes_received: u64,
+    /// The number of received frames with a CRC error
+    pub crc_errors: u64,
+    /// The number of received frames that did not belong to a request in flight,
+    /// for example late responses to retransmitted requests
+    pub skipped_frames: u64,
+    /// The number of times the device did not respond in time
+    pub timeouts: u64,
+    /// The number of retransmitted requests
+    pub retries: u64,
+    /// The round-trip times per command, ordered by group and command ID
+    pub latencies: Vec<CommandLatency>,
+}
+
+/// Collects the [`ConnectionStats`] of a connection.
+///
+/// Lives outside of the connection's lock, so that statistics can be
+/// read while a pipeline keeps the connection busy.
+#[derive(Default)]
+pub(super) struct Stats {
+    frames_sent: AtomicU64,
+    bytes_sent: AtomicU64,
+    frames_received: AtomicU64,
+    bytes_received: AtomicU64,
+    crc_errors: AtomicU64,
+    skipped_frames: AtomicU64,
+    timeouts: AtomicU64,
+    retries: AtomicU64,
+    latencies: Mutex<BTreeMap<(u16, u8), LatencyHistogram>>,
+}
+
+impl Stats {
+    pub(super) fn frame_sent(&self, data_len: usize) {
+        self.frames_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent
+            .fetch_add((SMP_HEADER_SIZE + data_len) as u64, Ordering::Relaxed);
+    }
+
+    pub(super) fn frame_received(&self, result: &Result<&[u8], ReceiveError>) {
+        let counter = match result {
+            Ok(frame) => {
+                self.bytes_received
+                    .fetch_add(frame.len() as u64, Ordering::Relaxed);
+                &self.frames_received
+            }
+            Err(ReceiveError::ChecksumMismatch) => &self.crc_errors,
+            Err(e) if e.is_timeout() => &self.timeouts,
+            Err(_) => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn frame_skipped(&self) {
+        self.skipped_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn round_trip(&self, group_id: u16, command_id: u8, latency: Duration) {
+        self.latencies
+            .lock()
+            .unwrap()
+            .entry((group_id, command_id))
+            .or_default()
+            .record(latency);
+    }
+
+    pub(super) fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn snapshot(&self) -> ConnectionStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        ConnectionStats {
+            frames_sent: load(&self.frames_sent),
+            bytes_sent: load(&self.bytes_sent),
+            frames_received: load(&self.frames_received),
+            bytes_received: load(&self.bytes_received),
+            crc_errors: load(&self.crc_errors),
+            skipped_frames: load(&self.skipped_frames),
+            timeouts: load(&self.timeouts),
+            retries: load(&self.retries),
+            latencies: self
+                .latencies
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(&(group_id, command_id), histogram)| CommandLatency {
+                    group_id,
+                    command_id,
+                    histogram: histogram.clone(),
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+#[cfg(feature = "tokio")]
+use crate::transport::AsyncTransport;
+use crate::transport::{ReceiveError, SMP_TRANSFER_BUFFER_SIZE, Transport};
+
+/// The timeout of a request.
+///
+/// The override replaces all timeouts; otherwise commands that take long
+/// get their minimum timeout if the communication timeout is shorter.
+pub(super) fn request_timeout(
+    base: Option<Duration>,
+    timeout_override: Option<Duration>,
+    min_timeout: Option<Duration>,
+) -> Option<Duration> {
+    timeout_override.or(match base {
+        Some(base) => min_timeout.map(|min_timeout| min_timeout.max(base)),
+        None => min_timeout,
+    })
+}
+
+/// The timeout to temporarily configure instead of the communication timeout.
+///
+/// Only possible if the communication timeout is known, so that it can be restored.
+fn temporary_timeout(
+    base: Option<Duration>,
+    timeout: Option<Duration>,
+) -> Option<(Duration, Duration)> {
+    let (base, timeout) = base.zip(timeout)?;
+    (base != timeout).then_some((base, timeout))
+}
+
+/// Receives a frame, waiting up to `timeout` instead of the communication timeout `base`.
+pub(super) fn recv_raw_frame<'a>(
+    transport: &mut (dyn Transport + Send),
+    buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    base: Option<Duration>,
+    timeout: Option<Duration>,
+) -> Result<&'a [u8], ReceiveError> {
+    let Some((base, timeout)) = temporary_timeout(base, timeout) else {
+        return transport.recv_raw_frame(buffer);
+    };
+
+    if let Err(e) = transport.set_timeout(timeout) {
+        log::warn!("Failed to change the timeout to {timeout:?}: {e}");
+        return transport.recv_raw_frame(buffer);
+    }
+
+    let frame = transport.recv_raw_frame(buffer);
+
+    if let Err(e) = transport.set_timeout(base) {
+        log::warn!("Failed to restore the timeout of {base:?}: {e}");
+    }
+
+    frame
+}
+
+/// Async counterpart of [`recv_raw_frame`].
+#[cfg(feature = "tokio")]
+pub(super) async fn recv_raw_frame_async<'a>(
+    transport: &mut (dyn AsyncTransport + Send),
+    buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    base: Option<Duration>,
+    timeout: Option<Duration>,
+) -> Result<&'a [u8], ReceiveError> {
+    let Some((base, timeout)) = temporary_timeout(base, timeout) else {
+        return transport.recv_raw_frame(buffer).await;
+    };
+
+    if let Err(e) = transport.set_timeout(timeout) {
+        log::warn!("Failed to change the timeout to {timeout:?}: {e}");
+        return transport.recv_raw_frame(buffer).await;
+    }
+
+    let frame = transport.recv_raw_frame(buffer).await;
+
+    if let Err(e) = transport.set_timeout(base) {
+        log::warn!("Failed to restore the timeout of {base:?}: {e}");
+    }
+
+    frame
+}
@@ -6,6 +6,8 @@
 
 /// A high-level client for Zephyr's MCUmgr SMP functionality
 pub mod client;
+#[cfg(feature = "tokio")]
+pub use client::AsyncMCUmgrClient;
 pub use client::MCUmgrClient;
 
 mod errno;
@@ -29,6 +31,13 @@ pub mod bootloader;
 /// MCUboot specific algorithms
 pub mod mcuboot;
 
+/// Dependencies of the code generated by the derive macros
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+}
+
 /// See [`enum mcumgr_group_t`](https://docs.zephyrproject.org/latest/doxygen/html/mgmt__defines_8h.html).
 #[derive(strum::FromRepr, strum::Display, Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u16)]
@@ -0,0 +1,282 @@
+use std::{io, time::Duration};
+
+use base64::prelude::*;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use super::{
+    AsyncTransport, ReceiveError, SMP_HEADER_SIZE, SMP_TRANSFER_BUFFER_SIZE, SendError,
+    codec::encode_serial_parts,
+    serial::{SerialMtuTooSmall, SerialTransportConfig, check_serial_mtu},
+};
+
+/// An async transport layer implementation for serial ports.
+///
+/// Uses the same framing as [`SerialTransport`](super::serial::SerialTransport).
+/// Works with any async byte stream, like a `tokio_serial::SerialStream` or a
+/// [`TcpStream`](tokio::net::TcpStream) connected to a serial bridge.
+pub struct AsyncSerialTransport<T> {
+    serial: BufReader<T>,
+    timeout: Duration,
+    crc_algo: crc::Crc<u16>,
+    line_buffer: Vec<u8>,
+    mtu: usize,
+    learn_mtu: bool,
+    max_line_length: usize,
+    frame: Vec<u8>,
+    frame_len: Option<usize>,
+}
+
+impl<T> AsyncSerialTransport<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    /// Create a new [`AsyncSerialTransport`].
+    ///
+    /// # Arguments
+    ///
+    /// * `serial` - An async serial port object.
+    /// * `timeout` - The communication timeout.
+    ///
+    pub fn new(serial: T, timeout: Duration) -> Self {
+        Self::from_checked_config(serial, timeout, SerialTransportConfig::default())
+    }
+
+    /// Create a new [`AsyncSerialTransport`] with custom parameters.
+    ///
+    /// See [`SerialTransport::with_config`](super::serial::SerialTransport::with_config).
+    ///
+    /// # Errors
+    ///
+    /// If `config.mtu` is smaller than [`SERIAL_TRANSPORT_MIN_MTU`](super::serial::SERIAL_TRANSPORT_MIN_MTU).
+    ///
+    pub fn with_config(
+        serial: T,
+        timeout: Duration,
+        config: SerialTransportConfig,
+    ) -> Result<Self, SerialMtuTooSmall> {
+        check_serial_mtu(config.mtu)?;
+        Ok(Self::from_checked_config(serial, timeout, config))
+    }
+
+    fn from_checked_config(serial: T, timeout: Duration, config: SerialTransportConfig) -> Self {
+        let max_line_length = config.read_buffer_size.max(config.mtu);
+
+        Self {
+            serial: BufReader::with_capacity(max_line_length, serial),
+            timeout,
+            crc_algo: crc::Crc::<u16>::new(&crc::CRC_16_XMODEM),
+            line_buffer: Vec::with_capacity(max_line_length),
+            mtu: config.mtu,
+            learn_mtu: config.learn_mtu,
+            max_line_length,
+            frame: Vec::new(),
+            frame_len: None,
+        }
+    }
+
+    /// The current maximum length of a transmitted line.
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    /// Receive an SMP transport chunk and decode it.
+    ///
+    /// Cancel safe: a partially received line is kept in the line buffer,
+    /// and the next call continues with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - whether this is the first chunk of the frame.
+    ///
+    /// # Return
+    ///
+    /// Whether the chunk starts a frame, and the decoded data. Even if a continuation
+    /// is expected, a new frame may start if the rest of the previous one got lost.
+    ///
+    async fn recv_chunk(&mut self, first: bool) -> Result<(bool, Vec<u8>), ReceiveError> {
+        loop {
+            let num_read = self.serial.read_until(0x0a, &mut self.line_buffer).await?;
+
+            if num_read == 0 {
+                return Err(ReceiveError::TransportError(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Serial port unexpectedly returned end-of-file",
+                )));
+            }
+
+            let chunk = self.decode_line(first);
+            self.line_buffer.clear();
+
+            if let Some(chunk) = chunk {
+                return chunk;
+            }
+        }
+    }
+
+    /// Decodes the chunk in the line buffer, or `None` if the line is not an expected chunk.
+    ///
+    /// Only the last chunk start counts, as a partially received line
+    /// may have been interrupted by a new chunk.
+    fn decode_line(&mut self, first: bool) -> Option<Result<(bool, Vec<u8>), ReceiveError>> {
+        let (start, initial) = self.line_buffer.windows(2).enumerate().rev().find_map(
+            |(pos, window)| match window {
+                [6, 9] => Some((pos, true)),
+                [4, 20] if !first => Some((pos, false)),
+                _ => None,
+            },
+        )?;
+
+        let base64_data = self.line_buffer[start + 2..]
+            .strip_suffix(&[0x0a])
+            .unwrap_or(&self.line_buffer[start + 2..]);
+
+        let line_length = base64_data.len() + 3;
+        if line_length > self.max_line_length {
+            return Some(Err(ReceiveError::FrameTooBig));
+        }
+
+        let data = match BASE64_STANDARD.decode(base64_data) {
+            Ok(data) => data,
+            Err(e) => return Some(Err(base64::DecodeSliceError::from(e).into())),
+        };
+
+        log::debug!(
+            "Received Chunk ({}, {} bytes raw, {} bytes decoded)",
+            if initial { "initial" } else { "partial" },
+            base64_data.len(),
+            data.len()
+        );
+
+        if self.learn_mtu && line_length > self.mtu {
+            log::debug!("Learned serial MTU {line_length} from device");
+            self.mtu = line_length;
+        }
+
+        Some(Ok((initial, data)))
+    }
+
+    /// Receive the chunks of an SMP frame into the frame buffer.
+    ///
+    /// Cancel safe: the received chunks are kept, and the next call continues with them.
+    async fn recv_frame(&mut self) -> Result<(), ReceiveError> {
+        loop {
+            let (initial, chunk) = self.recv_chunk(self.frame_len.is_none()).await?;
+            let mut data = chunk.as_slice();
+
+            if initial {
+                if self.frame_len.is_some() {
+                    log::debug!("Discarding incomplete SMP frame");
+                }
+
+                let (len_data, first_data) = data
+                    .split_first_chunk::<2>()
+                    .ok_or(ReceiveError::UnexpectedResponse)?;
+                self.frame_len = Some(u16::from_be_bytes(*len_data).into());
+                self.frame.clear();
+                data = first_data;
+            }
+
+            self.frame.extend_from_slice(data);
+
+            match self.frame_len {
+                Some(frame_len) if self.frame.len() < frame_len => {}
+                Some(frame_len) if self.frame.len() == frame_len => return Ok(()),
+                _ => return Err(ReceiveError::UnexpectedResponse),
+            }
+        }
+    }
+
+    /// Copies the received frame into `buffer` and verifies its checksum.
+    fn take_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    ) -> Result<&'a [u8], ReceiveError> {
+        self.frame_len = None;
+
+        let result_buffer = buffer
+            .get_mut(..self.frame.len())
+            .ok_or(ReceiveError::FrameTooBig)?;
+        result_buffer.copy_from_slice(&self.frame);
+
+        let (data, checksum_data) = result_buffer
+            .split_last_chunk::<2>()
+            .ok_or(ReceiveError::UnexpectedResponse)?;
+
+        let expected_checksum = u16::from_be_bytes(*checksum_data);
+
+        let actual_checksum = self.crc_algo.checksum(data);
+
+        if expected_checksum != actual_checksum {
+            return Err(ReceiveError::ChecksumMismatch);
+        }
+
+        log::debug!("Received SMP Frame ({} bytes)", data.len());
+
+        Ok(data)
+    }
+}
+
+fn timeout_error() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "Operation timed out")
+}
+
+#[async_trait::async_trait]
+impl<T> AsyncTransport for AsyncSerialTransport<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn send_raw_frame(
+        &mut self,
+        header: [u8; SMP_HEADER_SIZE],
+        data: &[u8],
+    ) -> Result<(), SendError> {
+        log::debug!("Sending SMP Frame ({} bytes)", data.len());
+
+        let encoded = encode_serial_parts(&[&header, data], self.mtu)?;
+
+        let serial = self.serial.get_mut();
+        tokio::time::timeout(self.timeout, async {
+            serial.write_all(&encoded).await?;
+            serial.flush().await
+        })
+        .await
+        .map_err(|_| timeout_error())??;
+
+        Ok(())
+    }
+
+    async fn recv_raw_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    ) -> Result<&'a [u8], ReceiveError> {
+        // Keeps the partial frame on timeout, so that the next receive continues with it
+        match tokio::time::timeout(self.timeout, self.recv_frame()).await {
+            Ok(Ok(())) => self.take_frame(buffer),
+            Ok(Err(e)) => {
+                self.frame_len = None;
+                Err(e)
+            }
+            Err(_) => Err(timeout_error().into()),
+        }
+    }
+
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn set_mtu(
+        &mut self,
+        mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(mtu) = mtu {
+            check_serial_mtu(mtu)?;
+            self.mtu = mtu;
+        }
+        self.learn_mtu = mtu.is_none();
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use tokio::net::{ToSocketAddrs, UdpSocket};
+
+use super::{AsyncTransport, ReceiveError, SMP_HEADER_SIZE, SMP_TRANSFER_BUFFER_SIZE, SendError};
+
+/// An async transport layer implementation for UDP sockets.
+///
+/// Async counterpart of [`UdpTransport`](super::udp::UdpTransport).
+pub struct AsyncUdpTransport {
+    socket: UdpSocket,
+    timeout: Duration,
+    send_buffer: Box<[u8]>,
+}
+
+impl AsyncUdpTransport {
+    /// Create a new [`AsyncUdpTransport`] that is connected to the given device address.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the device, for example `192.168.1.42:1337` or `[fe80::1]:1337`.
+    /// * `timeout` - The communication timeout.
+    ///
+    pub async fn new(addr: impl ToSocketAddrs, timeout: Duration) -> io::Result<Self> {
+        let mut last_error = None;
+
+        for addr in tokio::net::lookup_host(addr).await? {
+            let local_addr: SocketAddr = match addr {
+                SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+                SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+            };
+
+            let socket = match UdpSocket::bind(local_addr).await {
+                Ok(socket) => socket,
+                Err(e) => {
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = socket.connect(addr).await {
+                log::debug!("Unable to connect to {addr}: {e}");
+                last_error = Some(e);
+                continue;
+            }
+
+            return Ok(Self::from_socket(socket, timeout));
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Could not resolve to any address",
+            )
+        }))
+    }
+
+    /// Create a new [`AsyncUdpTransport`] from an already connected socket.
+    ///
+    /// The socket must be [connected](UdpSocket::connect) to the device.
+    pub fn from_socket(socket: UdpSocket, timeout: Duration) -> Self {
+        Self {
+            socket,
+            timeout,
+            send_buffer: vec![0u8; SMP_TRANSFER_BUFFER_SIZE].into_boxed_slice(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncTransport for AsyncUdpTransport {
+    async fn send_raw_frame(
+        &mut self,
+        header: [u8; SMP_HEADER_SIZE],
+        data: &[u8],
+    ) -> Result<(), SendError> {
+        log::debug!("Sending SMP Frame ({} bytes)", data.len());
+
+        let frame_size = header.len() + data.len();
+        let frame = self
+            .send_buffer
+            .get_mut(..frame_size)
+            .ok_or(SendError::DataTooBig)?;
+
+        let (frame_header, frame_data) = frame.split_at_mut(header.len());
+        frame_header.copy_from_slice(&header);
+        frame_data.copy_from_slice(data);
+
+        let sent = tokio::time::timeout(self.timeout, self.socket.send(frame))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Sending timed out"))??;
+        if sent != frame_size {
+            return Err(SendError::DataTooBig);
+        }
+
+        Ok(())
+    }
+
+    async fn recv_raw_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    ) -> Result<&'a [u8], ReceiveError> {
+        let len = tokio::time::timeout(self.timeout, self.socket.recv(buffer))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Receiving timed out"))??;
+
+        // A datagram that fills the whole buffer might have been truncated
+        if len >= buffer.len() {
+            return Err(ReceiveError::FrameTooBig);
+        }
+
+        log::debug!("Received SMP Frame ({} bytes)", len);
+
+        Ok(&buffer[..len])
+    }
+
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.timeout = timeout;
+        Ok(())
+    }
+}
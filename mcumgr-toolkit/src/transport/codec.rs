@@ -0,0 +1,394 @@
+use base64::prelude::*;
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::{MCUmgrGroup, commands::ErrResponse, smp_errors::DeviceError};
+
+use super::{
+    SMP_HEADER_SIZE, SendError, SmpHeader,
+    serial::{check_serial_mtu, serial_body_size},
+};
+
+/// The first two bytes of the first serial chunk of a frame.
+const SERIAL_CHUNK_START_INITIAL: [u8; 2] = [6, 9];
+/// The first two bytes of all further serial chunks of a frame.
+const SERIAL_CHUNK_START_CONTINUATION: [u8; 2] = [4, 20];
+
+const CRC: crc::Crc<u16> = crc::Crc::<u16>::new(&crc::CRC_16_XMODEM);
+
+/// Error while decoding an SMP frame
+#[derive(Error, Debug, Diagnostic)]
+pub enum DecodeError {
+    /// The frame is shorter than the SMP header
+    #[error("Frame is shorter than the SMP header")]
+    #[diagnostic(code(mcumgr_toolkit::transport::decode::truncated))]
+    Truncated,
+    /// The payload size does not match the size in the header
+    #[error("Payload has {actual} bytes, but the header announces {expected}")]
+    #[diagnostic(code(mcumgr_toolkit::transport::decode::length_mismatch))]
+    LengthMismatch {
+        /// The size in the header
+        expected: usize,
+        /// The actual size of the payload
+        actual: usize,
+    },
+    /// A serial chunk is not base64 encoded
+    #[error("Failed to decode base64 data")]
+    #[diagnostic(code(mcumgr_toolkit::transport::decode::base64_decode))]
+    Base64DecodeError(#[from] base64::DecodeError),
+    /// The CRC of a serial frame does not match its content
+    #[error("Frame checksum mismatch")]
+    #[diagnostic(code(mcumgr_toolkit::transport::decode::checksum))]
+    ChecksumMismatch,
+    /// A serial frame ended before all of its chunks were received
+    #[error("Frame is incomplete")]
+    #[diagnostic(code(mcumgr_toolkit::transport::decode::incomplete))]
+    Incomplete,
+    /// A serial continuation chunk was received without an initial chunk
+    #[error("Continuation chunk without initial chunk")]
+    #[diagnostic(code(mcumgr_toolkit::transport::decode::unexpected_continuation))]
+    UnexpectedContinuation,
+    /// The payload is not valid CBOR
+    #[error("Failed to decode CBOR payload")]
+    #[diagnostic(code(mcumgr_toolkit::transport::decode::cbor))]
+    CborDecodeError(#[from] ciborium::de::Error<std::io::Error>),
+}
+
+/// A complete SMP frame, consisting of header and payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmpFrame {
+    /// The SMP header
+    pub header: SmpHeader,
+    /// The payload, most likely CBOR encoded
+    pub payload: Vec<u8>,
+}
+
+impl SmpFrame {
+    /// Parses a raw SMP frame, as transmitted over UDP or reassembled from serial chunks.
+    pub fn parse(data: &[u8]) -> Result<Self, DecodeError> {
+        let (header, payload) = data
+            .split_first_chunk::<SMP_HEADER_SIZE>()
+            .ok_or(DecodeError::Truncated)?;
+        let header = SmpHeader::from_bytes(*header);
+
+        if usize::from(header.data_length) != payload.len() {
+            return Err(DecodeError::LengthMismatch {
+                expected: header.data_length.into(),
+                actual: payload.len(),
+            });
+        }
+
+        Ok(Self {
+            header,
+            payload: payload.to_vec(),
+        })
+    }
+
+    /// Encodes the raw SMP frame.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = self.header.to_bytes().to_vec();
+        data.extend_from_slice(&self.payload);
+        data
+    }
+
+    /// The name of the command group, see [`MCUmgrGroup::group_id_to_string`].
+    pub fn group_name(&self) -> String {
+        MCUmgrGroup::group_id_to_string(self.header.group_id)
+    }
+
+    /// The name of the command, as defined by Zephyr, if known.
+    pub fn command_name(&self) -> Option<&'static str> {
+        command_name(self.header.group_id, self.header.command_id)
+    }
+
+    /// Decodes the CBOR payload.
+    pub fn body(&self) -> Result<ciborium::Value, DecodeError> {
+        ciborium::from_reader(self.payload.as_slice()).map_err(Into::into)
+    }
+
+    /// The error reported by the device, if the frame is an error response.
+    pub fn device_error(&self) -> Option<DeviceError> {
+        if !self.header.is_response() {
+            return None;
+        }
+
+        ciborium::from_reader::<ErrResponse, _>(self.payload.as_slice())
+            .ok()?
+            .into_device_error()
+    }
+}
+
+/// The name of a command, as defined by Zephyr, if known.
+pub fn command_name(group_id: u16, command_id: u8) -> Option<&'static str> {
+    let name = match (MCUmgrGroup::from_repr(group_id)?, command_id) {
+        (MCUmgrGroup::MGMT_GROUP_ID_OS, 0) => "OS_MGMT_ID_ECHO",
+        (MCUmgrGroup::MGMT_GROUP_ID_OS, 1) => "OS_MGMT_ID_CONS_ECHO_CTRL",
+        (MCUmgrGroup::MGMT_GROUP_ID_OS, 2) => "OS_MGMT_ID_TASKSTAT",
+        (MCUmgrGroup::MGMT_GROUP_ID_OS, 3) => "OS_MGMT_ID_MPSTAT",
+        (MCUmgrGroup::MGMT_GROUP_ID_OS, 4) => "OS_MGMT_ID_DATETIME_STR",
+        (MCUmgrGroup::MGMT_GROUP_ID_OS, 5) => "OS_MGMT_ID_RESET",
+        (MCUmgrGroup::MGMT_GROUP_ID_OS, 6) => "OS_MGMT_ID_MCUMGR_PARAMS",
+        (MCUmgrGroup::MGMT_GROUP_ID_OS, 7) => "OS_MGMT_ID_INFO",
+        (MCUmgrGroup::MGMT_GROUP_ID_OS, 8) => "OS_MGMT_ID_BOOTLOADER_INFO",
+        (MCUmgrGroup::MGMT_GROUP_ID_IMAGE, 0) => "IMG_MGMT_ID_STATE",
+        (MCUmgrGroup::MGMT_GROUP_ID_IMAGE, 1) => "IMG_MGMT_ID_UPLOAD",
+        (MCUmgrGroup::MGMT_GROUP_ID_IMAGE, 5) => "IMG_MGMT_ID_ERASE",
+        (MCUmgrGroup::MGMT_GROUP_ID_IMAGE, 6) => "IMG_MGMT_ID_SLOT_INFO",
+        (MCUmgrGroup::MGMT_GROUP_ID_STAT, 0) => "STAT_MGMT_ID_SHOW",
+        (MCUmgrGroup::MGMT_GROUP_ID_STAT, 1) => "STAT_MGMT_ID_LIST",
+        (MCUmgrGroup::MGMT_GROUP_ID_SETTINGS, 0) => "SETTINGS_MGMT_ID_READ_WRITE",
+        (MCUmgrGroup::MGMT_GROUP_ID_SETTINGS, 1) => "SETTINGS_MGMT_ID_DELETE",
+        (MCUmgrGroup::MGMT_GROUP_ID_SETTINGS, 2) => "SETTINGS_MGMT_ID_COMMIT",
+        (MCUmgrGroup::MGMT_GROUP_ID_SETTINGS, 3) => "SETTINGS_MGMT_ID_LOAD_SAVE",
+        (MCUmgrGroup::MGMT_GROUP_ID_FS, 0) => "FS_MGMT_ID_FILE",
+        (MCUmgrGroup::MGMT_GROUP_ID_FS, 1) => "FS_MGMT_ID_STAT",
+        (MCUmgrGroup::MGMT_GROUP_ID_FS, 2) => "FS_MGMT_ID_HASH_CHECKSUM",
+        (MCUmgrGroup::MGMT_GROUP_ID_FS, 3) => "FS_MGMT_ID_SUPPORTED_HASH_CHECKSUM",
+        (MCUmgrGroup::MGMT_GROUP_ID_FS, 4) => "FS_MGMT_ID_OPENED_FILE",
+        (MCUmgrGroup::MGMT_GROUP_ID_SHELL, 0) => "SHELL_MGMT_ID_EXEC",
+        (MCUmgrGroup::MGMT_GROUP_ID_ENUM, 0) => "ENUM_MGMT_ID_COUNT",
+        (MCUmgrGroup::MGMT_GROUP_ID_ENUM, 1) => "ENUM_MGMT_ID_LIST",
+        (MCUmgrGroup::MGMT_GROUP_ID_ENUM, 2) => "ENUM_MGMT_ID_SINGLE",
+        (MCUmgrGroup::MGMT_GROUP_ID_ENUM, 3) => "ENUM_MGMT_ID_DETAILS",
+        (MCUmgrGroup::ZEPHYR_MGMT_GRP_BASIC, 0) => "ZEPHYR_MGMT_GRP_BASIC_CMD_ERASE_STORAGE",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+/// Encodes a raw SMP frame for a serial connection.
+///
+/// Adds the length and checksum, and splits the frame into base64 encoded lines
+/// of at most `mtu` bytes, like [`SerialTransport`](super::serial::SerialTransport).
+///
+/// # Errors
+///
+/// If `mtu` is smaller than [`SERIAL_TRANSPORT_MIN_MTU`](super::serial::SERIAL_TRANSPORT_MIN_MTU),
+/// or the frame exceeds 64 KiB.
+///
+pub fn encode_serial_frame(frame: &[u8], mtu: usize) -> Result<Vec<u8>, SendError> {
+    encode_serial_parts(&[frame], mtu)
+}
+
+/// Encodes a raw SMP frame that is split into parts, like header and payload.
+///
+/// See [`encode_serial_frame`].
+pub(super) fn encode_serial_parts(parts: &[&[u8]], mtu: usize) -> Result<Vec<u8>, SendError> {
+    check_serial_mtu(mtu)?;
+
+    let frame_len = parts.iter().map(|part| part.len()).sum::<usize>();
+    let size = u16::try_from(frame_len + 2).map_err(|_| SendError::DataTooBig)?;
+
+    let mut data = Vec::with_capacity(frame_len + 4);
+    data.extend_from_slice(&size.to_be_bytes());
+    let mut digest = CRC.digest();
+    for part in parts {
+        digest.update(part);
+        data.extend_from_slice(part);
+    }
+    data.extend_from_slice(&digest.finalize().to_be_bytes());
+
+    let body_size = serial_body_size(mtu);
+    let mut encoded = Vec::with_capacity(data.len().div_ceil(body_size) * mtu);
+    for (index, chunk) in data.chunks(body_size).enumerate() {
+        encoded.extend_from_slice(if index == 0 {
+            &SERIAL_CHUNK_START_INITIAL
+        } else {
+            &SERIAL_CHUNK_START_CONTINUATION
+        });
+        encoded.extend_from_slice(BASE64_STANDARD.encode(chunk).as_bytes());
+        encoded.push(b'\n');
+    }
+
+    log::debug!(
+        "Encoded {} chunks ({} bytes raw, {} bytes encoded)",
+        data.len().div_ceil(body_size),
+        data.len(),
+        encoded.len()
+    );
+
+    Ok(encoded)
+}
+
+/// An element of decoded serial traffic
+#[derive(Debug)]
+pub enum SerialItem {
+    /// A complete SMP frame
+    Frame(SmpFrame),
+    /// A line that is not part of an SMP frame, like shell output, without line ending
+    Console(Vec<u8>),
+    /// Data that looked like an SMP frame, but could not be decoded
+    Invalid(DecodeError),
+}
+
+/// A frame whose serial chunks are partially received.
+#[derive(Debug)]
+struct PartialFrame {
+    size: usize,
+    data: Vec<u8>,
+}
+
+/// Decodes the raw bytes of a serial connection into SMP frames and console output.
+///
+/// For example to analyze captures of a logic analyzer or a terminal program.
+/// The data can be passed in pieces of arbitrary size.
+#[derive(Debug, Default)]
+pub struct SerialDecoder {
+    line: Vec<u8>,
+    frame: Option<PartialFrame>,
+}
+
+impl SerialDecoder {
+    /// Creates a decoder that expects the start of a line.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes the next piece of data.
+    ///
+    /// # Return
+    ///
+    /// All frames and console lines that got completed by this piece.
+    ///
+    pub fn decode(&mut self, data: &[u8]) -> Vec<SerialItem> {
+        let mut items = vec![];
+
+        for &byte in data {
+            if byte == b'\n' {
+                let line = std::mem::take(&mut self.line);
+                self.decode_line(&line, &mut items);
+            } else {
+                self.line.push(byte);
+            }
+        }
+
+        items
+    }
+
+    /// Decodes the remaining data at the end of the capture.
+    ///
+    /// # Return
+    ///
+    /// The last unterminated line, and an error if a frame is incomplete.
+    ///
+    pub fn finish(mut self) -> Vec<SerialItem> {
+        let mut items = vec![];
+
+        if !self.line.is_empty() {
+            let line = std::mem::take(&mut self.line);
+            self.decode_line(&line, &mut items);
+        }
+
+        if self.frame.is_some() {
+            items.push(SerialItem::Invalid(DecodeError::Incomplete));
+        }
+
+        items
+    }
+
+    fn decode_line(&mut self, line: &[u8], items: &mut Vec<SerialItem>) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        // Like the transports, only the last chunk start of a line counts,
+        // as console output or an interrupted chunk may precede it
+        let mut starts = line
+            .windows(2)
+            .enumerate()
+            .filter_map(|(pos, window)| match window {
+                [6, 9] => Some((pos, true)),
+                [4, 20] if self.frame.is_some() || pos == 0 => Some((pos, false)),
+                _ => None,
+            });
+
+        let Some(first) = starts.next() else {
+            items.push(SerialItem::Console(line.to_vec()));
+            return;
+        };
+        let (start, initial) = starts.next_back().unwrap_or(first);
+
+        if first.0 > 0 {
+            items.push(SerialItem::Console(line[..first.0].to_vec()));
+        }
+
+        let chunk = &line[start + 2..];
+        if initial {
+            if self.frame.take().is_some() {
+                items.push(SerialItem::Invalid(DecodeError::Incomplete));
+            }
+
+            let result = BASE64_STANDARD
+                .decode(chunk)
+                .map_err(Into::into)
+                .and_then(|data| match data.split_first_chunk::<2>() {
+                    Some((size, data)) => Ok(PartialFrame {
+                        size: u16::from_be_bytes(*size).into(),
+                        data: data.to_vec(),
+                    }),
+                    None => Err(DecodeError::Truncated),
+                });
+
+            match result {
+                Ok(frame) => self.frame = Some(frame),
+                Err(e) => items.push(SerialItem::Invalid(e)),
+            }
+        } else {
+            let Some(frame) = &mut self.frame else {
+                items.push(SerialItem::Invalid(DecodeError::UnexpectedContinuation));
+                return;
+            };
+
+            match BASE64_STANDARD.decode(chunk) {
+                Ok(data) => frame.data.extend_from_slice(&data),
+                Err(e) => {
+                    self.frame = None;
+                    items.push(SerialItem::Invalid(e.into()));
+                    return;
+                }
+            }
+        }
+
+        if self
+            .frame
+            .as_ref()
+            .is_some_and(|frame| frame.data.len() >= frame.size)
+        {
+            let frame = self.frame.take().unwrap();
+            items.push(match finish_serial_frame(frame) {
+                Ok(frame) => SerialItem::Frame(frame),
+                Err(e) => SerialItem::Invalid(e),
+            });
+        }
+    }
+}
+
+/// Verifies the checksum of a reassembled serial frame and parses it.
+fn finish_serial_frame(frame: PartialFrame) -> Result<SmpFrame, DecodeError> {
+    if frame.data.len() != frame.size {
+        return Err(DecodeError::LengthMismatch {
+            expected: frame.size,
+            actual: frame.data.len(),
+        });
+    }
+
+    let (data, checksum) = frame
+        .data
+        .split_last_chunk::<2>()
+        .ok_or(DecodeError::Truncated)?;
+
+    if CRC.checksum(data) != u16::from_be_bytes(*checksum) {
+        return Err(DecodeError::ChecksumMismatch);
+    }
+
+    SmpFrame::parse(data)
+}
+
+/// Decodes a complete capture of a serial connection.
+///
+/// See [`SerialDecoder`].
+pub fn decode_serial_capture(data: &[u8]) -> Vec<SerialItem> {
+    let mut decoder = SerialDecoder::new();
+    let mut items = decoder.decode(data);
+    items.extend(decoder.finish());
+    items
+}
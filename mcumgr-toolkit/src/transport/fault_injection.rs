@@ -0,0 +1,384 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    time::Duration,
+};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use super::{
+    ConsoleHandler, ReceiveError, SMP_HEADER_SIZE, SMP_TRANSFER_BUFFER_SIZE, SendError, Transport,
+    serial::ConfigurableTimeout,
+};
+
+/// Probabilities of faults that get injected into frames travelling in one direction.
+///
+/// All values are probabilities per frame and must be within `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameFaults {
+    /// The frame gets lost
+    pub drop: f64,
+    /// The frame arrives twice
+    pub duplicate: f64,
+    /// The frame gets held back until the next frame went through
+    pub reorder: f64,
+    /// A random bit of the frame gets flipped
+    pub corrupt: f64,
+    /// The frame gets delayed by up to [`FaultInjectionConfig::max_delay`]
+    pub delay: f64,
+}
+
+/// Configuration of a [`FaultInjectingTransport`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaultInjectionConfig {
+    /// Seed of the random number generator; the same seed produces the same faults
+    pub seed: u64,
+    /// Faults of frames sent to the device
+    pub send: FrameFaults,
+    /// Faults of frames received from the device
+    pub receive: FrameFaults,
+    /// The maximum delay of delayed frames
+    pub max_delay: Duration,
+}
+
+impl Default for FaultInjectionConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            send: FrameFaults::default(),
+            receive: FrameFaults::default(),
+            max_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+/// A transport wrapper that randomly drops, duplicates, reorders,
+/// corrupts and delays frames.
+///
+/// Meant for testing how the client copes with unreliable connections.
+/// For bit errors on the serial line itself, see [`FaultInjectingSerial`].
+///
+/// ```no_run
+/// # use mcumgr_toolkit::transport::fault_injection::{FaultInjectingTransport, FaultInjectionConfig, FrameFaults};
+/// # use mcumgr_toolkit::transport::udp::UdpTransport;
+/// # fn main() -> std::io::Result<()> {
+/// let udp = UdpTransport::new("127.0.0.1:1337", std::time::Duration::from_secs(1))?;
+///
+/// let transport = FaultInjectingTransport::new(
+///     udp,
+///     FaultInjectionConfig {
+///         seed: 42,
+///         receive: FrameFaults {
+///             drop: 0.1,
+///             ..Default::default()
+///         },
+///         ..Default::default()
+///     },
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct FaultInjectingTransport<T> {
+    transport: T,
+    config: FaultInjectionConfig,
+    rng: StdRng,
+    held_send: Option<Vec<u8>>,
+    held_receive: Option<Vec<u8>>,
+    pending_receive: VecDeque<Vec<u8>>,
+    injected_faults: usize,
+}
+
+impl<T: Transport> FaultInjectingTransport<T> {
+    /// Create a new [`FaultInjectingTransport`].
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - The transport to inject faults into.
+    /// * `config` - The probabilities of the individual faults.
+    ///
+    /// # Panics
+    ///
+    /// If one of the probabilities is not within `0.0..=1.0`.
+    pub fn new(transport: T, config: FaultInjectionConfig) -> Self {
+        for faults in [config.send, config.receive] {
+            for probability in [
+                faults.drop,
+                faults.duplicate,
+                faults.reorder,
+                faults.corrupt,
+                faults.delay,
+            ] {
+                assert!(
+                    (0.0..=1.0).contains(&probability),
+                    "Fault probability {probability} is not within 0.0..=1.0"
+                );
+            }
+        }
+
+        Self {
+            transport,
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            held_send: None,
+            held_receive: None,
+            pending_receive: VecDeque::new(),
+            injected_faults: 0,
+        }
+    }
+
+    /// The number of faults injected so far.
+    pub fn injected_faults(&self) -> usize {
+        self.injected_faults
+    }
+
+    /// Returns the wrapped transport.
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    fn inject(&mut self, probability: f64, fault: &str) -> bool {
+        let inject = self.rng.random_bool(probability);
+        if inject {
+            log::debug!("Injecting fault: {fault}");
+            self.injected_faults += 1;
+        }
+        inject
+    }
+
+    fn maybe_delay(&mut self, probability: f64) {
+        if self.inject(probability, "delay") {
+            let delay = self
+                .rng
+                .random_range(Duration::ZERO..=self.config.max_delay);
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn maybe_corrupt(&mut self, probability: f64, frame: &mut [u8]) {
+        if !frame.is_empty() && self.inject(probability, "corrupt") {
+            let bit = self.rng.random_range(0..frame.len() * 8);
+            frame[bit / 8] ^= 1 << (bit % 8);
+        }
+    }
+
+    fn send_frame_data(&mut self, frame: &[u8]) -> Result<(), SendError> {
+        let (header, data) = frame
+            .split_first_chunk::<SMP_HEADER_SIZE>()
+            .ok_or(SendError::DataTooBig)?;
+        self.transport.send_raw_frame(*header, data)
+    }
+}
+
+impl<T: Transport> Transport for FaultInjectingTransport<T> {
+    fn send_raw_frame(
+        &mut self,
+        header: [u8; SMP_HEADER_SIZE],
+        data: &[u8],
+    ) -> Result<(), SendError> {
+        let faults = self.config.send;
+
+        self.maybe_delay(faults.delay);
+
+        if self.inject(faults.drop, "drop sent frame") {
+            return Ok(());
+        }
+
+        let mut frame = header.iter().chain(data).copied().collect::<Vec<_>>();
+        self.maybe_corrupt(faults.corrupt, &mut frame);
+
+        if self.held_send.is_none() && self.inject(faults.reorder, "reorder sent frame") {
+            self.held_send = Some(frame);
+            return Ok(());
+        }
+
+        self.send_frame_data(&frame)?;
+
+        if self.inject(faults.duplicate, "duplicate sent frame") {
+            self.send_frame_data(&frame)?;
+        }
+
+        if let Some(held) = self.held_send.take() {
+            self.send_frame_data(&held)?;
+        }
+
+        Ok(())
+    }
+
+    fn recv_raw_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    ) -> Result<&'a [u8], ReceiveError> {
+        let faults = self.config.receive;
+
+        let frame = loop {
+            if let Some(frame) = self.pending_receive.pop_front() {
+                break frame;
+            }
+
+            let mut frame = match self.transport.recv_raw_frame(buffer) {
+                Ok(frame) => frame.to_vec(),
+                Err(e) => {
+                    // Held back frames arrive late instead
+                    self.pending_receive.extend(self.held_receive.take());
+                    return Err(e);
+                }
+            };
+
+            if self.inject(faults.drop, "drop received frame") {
+                continue;
+            }
+
+            self.maybe_corrupt(faults.corrupt, &mut frame);
+
+            if self.held_receive.is_none() && self.inject(faults.reorder, "reorder received frame")
+            {
+                self.held_receive = Some(frame);
+                continue;
+            }
+
+            if self.inject(faults.duplicate, "duplicate received frame") {
+                self.pending_receive.push_back(frame.clone());
+            }
+
+            self.pending_receive.extend(self.held_receive.take());
+
+            break frame;
+        };
+
+        self.maybe_delay(faults.delay);
+
+        let result = &mut buffer[..frame.len()];
+        result.copy_from_slice(&frame);
+        Ok(result)
+    }
+
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.transport.set_timeout(timeout)
+    }
+
+    fn set_mtu(
+        &mut self,
+        mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.transport.set_mtu(mtu)
+    }
+
+    fn set_console_handler(
+        &mut self,
+        handler: Option<ConsoleHandler>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.transport.set_console_handler(handler)
+    }
+
+    fn poll_console(&mut self) -> Result<(), ReceiveError> {
+        self.transport.poll_console()
+    }
+}
+
+/// Configuration of a [`FaultInjectingSerial`].
+///
+/// All values are probabilities per transmitted byte and must be within `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SerialFaultConfig {
+    /// Seed of the random number generator; the same seed produces the same faults
+    pub seed: u64,
+    /// A random bit of a byte received from the device gets flipped
+    pub read_bit_flip: f64,
+    /// A random bit of a byte sent to the device gets flipped
+    pub write_bit_flip: f64,
+}
+
+/// A serial port wrapper that randomly flips bits of the transmitted bytes.
+///
+/// Meant for testing how the client copes with transmission errors,
+/// which the serial transport detects through its CRC.
+///
+/// Use it with [`SerialTransport`](super::serial::SerialTransport)
+/// or [`MCUmgrClient::new_from_serial`](crate::MCUmgrClient::new_from_serial).
+pub struct FaultInjectingSerial<T> {
+    serial: T,
+    config: SerialFaultConfig,
+    rng: StdRng,
+    injected_faults: usize,
+}
+
+impl<T> FaultInjectingSerial<T> {
+    /// Create a new [`FaultInjectingSerial`].
+    ///
+    /// # Arguments
+    ///
+    /// * `serial` - The serial port to inject faults into.
+    /// * `config` - The probabilities of bit flips.
+    ///
+    /// # Panics
+    ///
+    /// If one of the probabilities is not within `0.0..=1.0`.
+    pub fn new(serial: T, config: SerialFaultConfig) -> Self {
+        for probability in [config.read_bit_flip, config.write_bit_flip] {
+            assert!(
+                (0.0..=1.0).contains(&probability),
+                "Fault probability {probability} is not within 0.0..=1.0"
+            );
+        }
+
+        Self {
+            serial,
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            injected_faults: 0,
+        }
+    }
+
+    /// The number of flipped bits so far.
+    pub fn injected_faults(&self) -> usize {
+        self.injected_faults
+    }
+
+    /// Returns the wrapped serial port.
+    pub fn into_inner(self) -> T {
+        self.serial
+    }
+
+    fn flip_bits(&mut self, probability: f64, data: &mut [u8]) {
+        for byte in data {
+            if self.rng.random_bool(probability) {
+                log::debug!("Injecting fault: bit flip");
+                self.injected_faults += 1;
+                *byte ^= 1 << self.rng.random_range(0..8);
+            }
+        }
+    }
+}
+
+impl<T: Read> Read for FaultInjectingSerial<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let num_read = self.serial.read(buf)?;
+        self.flip_bits(self.config.read_bit_flip, &mut buf[..num_read]);
+        Ok(num_read)
+    }
+}
+
+impl<T: Write> Write for FaultInjectingSerial<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = buf.to_vec();
+        self.flip_bits(self.config.write_bit_flip, &mut data);
+        self.serial.write_all(&data)?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.serial.flush()
+    }
+}
+
+impl<T: ConfigurableTimeout> ConfigurableTimeout for FaultInjectingSerial<T> {
+    fn set_timeout(
+        &mut self,
+        duration: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.serial.set_timeout(duration)
+    }
+}
@@ -3,22 +3,48 @@ use std::{io, time::Duration};
 use miette::Diagnostic;
 use thiserror::Error;
 
+/// Encoding and decoding of SMP frames, for example to analyze captured traffic
+pub mod codec;
+/// Transport wrappers that inject faults, for robustness testing
+pub mod fault_injection;
+/// Recording and replaying of SMP sessions
+pub mod recording;
+/// RFC 2217 serial port, for use with the serial port based transport
+pub mod rfc2217;
 /// Serial port based transport
 pub mod serial;
+/// UDP based transport
+pub mod udp;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-struct SmpHeader {
-    ver: u8,
-    op: u8,
-    flags: u8,
-    data_length: u16,
-    group_id: u16,
-    sequence_num: u8,
-    command_id: u8,
+/// Async serial port based transport
+#[cfg(feature = "tokio")]
+pub mod async_serial;
+/// Async UDP based transport
+#[cfg(feature = "tokio")]
+pub mod async_udp;
+
+/// The [header](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_protocol.html#smp-frame-header) of an SMP frame
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SmpHeader {
+    /// The SMP protocol version bits
+    pub ver: u8,
+    /// The operation: read, read response, write or write response
+    pub op: u8,
+    /// Flags, currently unused
+    pub flags: u8,
+    /// The size of the payload
+    pub data_length: u16,
+    /// The group ID of the command
+    pub group_id: u16,
+    /// The sequence number that pairs a response with its request
+    pub sequence_num: u8,
+    /// The command ID
+    pub command_id: u8,
 }
 
 impl SmpHeader {
-    fn from_bytes(data: [u8; SMP_HEADER_SIZE]) -> Self {
+    /// Parses a raw SMP header.
+    pub fn from_bytes(data: [u8; SMP_HEADER_SIZE]) -> Self {
         Self {
             ver: (data[0] >> 3) & 0b11,
             op: data[0] & 0b111,
@@ -29,7 +55,8 @@ impl SmpHeader {
             command_id: data[7],
         }
     }
-    fn to_bytes(self) -> [u8; SMP_HEADER_SIZE] {
+    /// Encodes the SMP header.
+    pub fn to_bytes(self) -> [u8; SMP_HEADER_SIZE] {
         let [length_0, length_1] = self.data_length.to_be_bytes();
         let [group_id_0, group_id_1] = self.group_id.to_be_bytes();
         [
@@ -43,10 +70,63 @@ impl SmpHeader {
             self.command_id,
         ]
     }
+
+    /// The SMP protocol version of the frame.
+    pub fn version(&self) -> SmpVersion {
+        SmpVersion::from_header_bits(self.ver)
+    }
+
+    /// Checks whether the frame is a write request or the response to one.
+    pub fn is_write_operation(&self) -> bool {
+        matches!(self.op, smp_op::WRITE | smp_op::WRITE_RSP)
+    }
+
+    /// Checks whether the frame is a response.
+    pub fn is_response(&self) -> bool {
+        matches!(self.op, smp_op::READ_RSP | smp_op::WRITE_RSP)
+    }
+}
+
+/// The version of the SMP protocol, as transmitted in the SMP header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SmpVersion {
+    /// The original protocol, supported by all SMP servers
+    V1,
+    /// The current protocol, with error responses that include the group ID
+    #[default]
+    V2,
+}
+
+impl SmpVersion {
+    fn from_header_bits(bits: u8) -> Self {
+        if bits == 0 { Self::V1 } else { Self::V2 }
+    }
+
+    fn header_bits(self) -> u8 {
+        match self {
+            Self::V1 => 0b00,
+            Self::V2 => 0b01,
+        }
+    }
 }
 
-const SMP_HEADER_SIZE: usize = 8;
-const SMP_TRANSFER_BUFFER_SIZE: usize = u16::MAX as usize;
+impl std::fmt::Display for SmpVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::V1 => f.write_str("SMP v1"),
+            Self::V2 => f.write_str("SMP v2"),
+        }
+    }
+}
+
+/// Receives console output that is not part of an SMP frame, line by line.
+///
+/// See [`Transport::set_console_handler`].
+pub type ConsoleHandler = Box<dyn FnMut(&[u8]) + Send>;
+
+/// The size of an [`SmpHeader`].
+pub const SMP_HEADER_SIZE: usize = 8;
+pub(crate) const SMP_TRANSFER_BUFFER_SIZE: usize = u16::MAX as usize;
 
 mod smp_op {
     pub(super) const READ: u8 = 0;
@@ -66,6 +146,17 @@ pub enum SendError {
     #[error("Given data slice was too big")]
     #[diagnostic(code(mcumgr_toolkit::transport::send::too_big))]
     DataTooBig,
+    /// The serial MTU is too small to transmit data
+    #[error("Serial MTU too small")]
+    #[diagnostic(code(mcumgr_toolkit::transport::send::mtu_too_small))]
+    MtuTooSmall(#[from] serial::SerialMtuTooSmall),
+}
+
+impl SendError {
+    /// Checks if the connection to the device was lost
+    pub fn is_disconnect(&self) -> bool {
+        matches!(self, Self::TransportError(e) if is_disconnect(e))
+    }
 }
 
 /// Error while receiving a command response
@@ -87,6 +178,207 @@ pub enum ReceiveError {
     #[error("Failed to decode base64 data")]
     #[diagnostic(code(mcumgr_toolkit::transport::recv::base64_decode))]
     Base64DecodeError(#[from] base64::DecodeSliceError),
+    /// The CRC of the response we received does not match its content
+    #[error("Response checksum mismatch")]
+    #[diagnostic(code(mcumgr_toolkit::transport::recv::checksum))]
+    ChecksumMismatch,
+}
+
+impl ReceiveError {
+    /// Checks if the device did not respond in time
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self,
+            Self::TransportError(e)
+                if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)
+        )
+    }
+
+    /// Checks if the connection to the device was lost
+    pub fn is_disconnect(&self) -> bool {
+        matches!(self, Self::TransportError(e) if is_disconnect(e))
+    }
+}
+
+/// `EIO`, reported by Linux when reading from a USB serial port that disappeared
+#[cfg(unix)]
+const EIO: i32 = 5;
+
+/// Checks if an I/O error means that the connection to the device was lost,
+/// for example because a USB serial port disappeared.
+pub(crate) fn is_disconnect(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    if error.raw_os_error() == Some(EIO) {
+        return true;
+    }
+
+    matches!(
+        error.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::NotFound
+            | io::ErrorKind::PermissionDenied
+    )
+}
+
+/// Builds the SMP header of a request frame.
+fn request_header(
+    version: SmpVersion,
+    write_operation: bool,
+    sequence_num: u8,
+    group_id: u16,
+    command_id: u8,
+    data: &[u8],
+) -> Result<[u8; SMP_HEADER_SIZE], SendError> {
+    let header = SmpHeader {
+        ver: version.header_bits(),
+        op: if write_operation {
+            smp_op::WRITE
+        } else {
+            smp_op::READ
+        },
+        flags: 0,
+        data_length: data.len().try_into().map_err(|_| SendError::DataTooBig)?,
+        group_id,
+        sequence_num,
+        command_id,
+    };
+
+    Ok(header.to_bytes())
+}
+
+/// Checks whether a received frame is the response to the given request.
+///
+/// # Return
+///
+/// The size of the response payload, or `None` if the frame belongs
+/// to a different request and should be ignored.
+///
+fn check_response_frame(
+    frame: &[u8],
+    write_operation: bool,
+    sequence_num: u8,
+    group_id: u16,
+    command_id: u8,
+) -> Result<Option<usize>, ReceiveError> {
+    let (header_data, data) = frame
+        .split_first_chunk::<SMP_HEADER_SIZE>()
+        .ok_or(ReceiveError::UnexpectedResponse)?;
+
+    let header = SmpHeader::from_bytes(*header_data);
+
+    let expected_op = if write_operation {
+        smp_op::WRITE_RSP
+    } else {
+        smp_op::READ_RSP
+    };
+
+    // Receiving packets with the wrong sequence number is not an error,
+    // they should simply be silently ignored.
+    if header.sequence_num != sequence_num {
+        return Ok(None);
+    }
+
+    if (header.group_id != group_id)
+        || (header.command_id != command_id)
+        || (header.op != expected_op)
+        || (usize::from(header.data_length) != data.len())
+    {
+        return Err(ReceiveError::UnexpectedResponse);
+    }
+
+    Ok(Some(data.len()))
+}
+
+/// Reads the SMP version of a received frame.
+pub(crate) fn frame_version(frame: &[u8]) -> Option<SmpVersion> {
+    frame
+        .split_first_chunk::<SMP_HEADER_SIZE>()
+        .map(|(header, _)| SmpVersion::from_header_bits(SmpHeader::from_bytes(*header).ver))
+}
+
+/// Reads the sequence number of a received frame.
+pub(crate) fn frame_sequence_num(frame: &[u8]) -> Option<u8> {
+    frame
+        .split_first_chunk::<SMP_HEADER_SIZE>()
+        .map(|(header, _)| SmpHeader::from_bytes(*header).sequence_num)
+}
+
+/// Extracts the payload of a received frame, if it is the response to the given request.
+///
+/// See [`check_response_frame`].
+pub(crate) fn response_payload(
+    frame: &[u8],
+    write_operation: bool,
+    sequence_num: u8,
+    group_id: u16,
+    command_id: u8,
+) -> Result<Option<&[u8]>, ReceiveError> {
+    let data_size =
+        check_response_frame(frame, write_operation, sequence_num, group_id, command_id)?;
+
+    Ok(data_size.map(|data_size| &frame[SMP_HEADER_SIZE..SMP_HEADER_SIZE + data_size]))
+}
+
+/// Receives the response to the given request, skipping frames of other requests.
+///
+/// # Return
+///
+/// The SMP version and the payload data of the response.
+///
+pub(crate) fn receive_response<'a, T: Transport + ?Sized>(
+    transport: &mut T,
+    buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    write_operation: bool,
+    sequence_num: u8,
+    group_id: u16,
+    command_id: u8,
+) -> Result<(SmpVersion, &'a [u8]), ReceiveError> {
+    let (version, data_size) = loop {
+        let frame = transport.recv_raw_frame(buffer)?;
+
+        if let Some(data_size) =
+            check_response_frame(frame, write_operation, sequence_num, group_id, command_id)?
+        {
+            break (frame_version(frame).unwrap_or_default(), data_size);
+        }
+    };
+
+    Ok((
+        version,
+        &buffer[SMP_HEADER_SIZE..SMP_HEADER_SIZE + data_size],
+    ))
+}
+
+/// Receives the response to the given request, skipping frames of other requests.
+///
+/// Async counterpart of [`receive_response`].
+#[cfg(feature = "tokio")]
+pub(crate) async fn receive_response_async<'a, T: AsyncTransport + ?Sized>(
+    transport: &mut T,
+    buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    write_operation: bool,
+    sequence_num: u8,
+    group_id: u16,
+    command_id: u8,
+) -> Result<(SmpVersion, &'a [u8]), ReceiveError> {
+    let (version, data_size) = loop {
+        let frame = transport.recv_raw_frame(buffer).await?;
+
+        if let Some(data_size) =
+            check_response_frame(frame, write_operation, sequence_num, group_id, command_id)?
+        {
+            break (frame_version(frame).unwrap_or_default(), data_size);
+        }
+    };
+
+    Ok((
+        version,
+        &buffer[SMP_HEADER_SIZE..SMP_HEADER_SIZE + data_size],
+    ))
 }
 
 /// Defines the API of the SMP transport layer
@@ -114,6 +406,7 @@ pub trait Transport {
     ///
     /// # Arguments
     ///
+    /// * `version` - The SMP protocol version of the frame header.
     /// * `write_operation` - If the frame contains a write or read operation.
     /// * `sequence_num` - A sequence number. Must be different every time this function is called.
     /// * `group_id` - The group ID of the command.
@@ -124,27 +417,21 @@ pub trait Transport {
     ///
     fn send_frame(
         &mut self,
+        version: SmpVersion,
         write_operation: bool,
         sequence_num: u8,
         group_id: u16,
         command_id: u8,
         data: &[u8],
     ) -> Result<(), SendError> {
-        let header = SmpHeader {
-            ver: 0b01,
-            op: if write_operation {
-                smp_op::WRITE
-            } else {
-                smp_op::READ
-            },
-            flags: 0,
-            data_length: data.len().try_into().map_err(|_| SendError::DataTooBig)?,
-            group_id,
+        let header_data = request_header(
+            version,
+            write_operation,
             sequence_num,
+            group_id,
             command_id,
-        };
-
-        let header_data = header.to_bytes();
+            data,
+        )?;
 
         self.send_raw_frame(header_data, data)
     }
@@ -171,39 +458,208 @@ pub trait Transport {
         group_id: u16,
         command_id: u8,
     ) -> Result<&'a [u8], ReceiveError> {
-        let data_size = loop {
-            let frame = self.recv_raw_frame(buffer)?;
+        receive_response(
+            self,
+            buffer,
+            write_operation,
+            sequence_num,
+            group_id,
+            command_id,
+        )
+        .map(|(_, data)| data)
+    }
 
-            let (header_data, data) = frame
-                .split_first_chunk::<SMP_HEADER_SIZE>()
-                .ok_or(ReceiveError::UnexpectedResponse)?;
+    /// Changes the communication timeout.
+    ///
+    /// When the device does not respond to packets within the set
+    /// duration, an error will be raised.
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
 
-            let header = SmpHeader::from_bytes(*header_data);
+    /// Changes the maximum length of a transmitted line.
+    ///
+    /// Only supported by line based transports, like [`SerialTransport`](serial::SerialTransport).
+    ///
+    /// `None` keeps the current MTU, but raises it to the length
+    /// of the longest line received from the device.
+    fn set_mtu(
+        &mut self,
+        mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _ = mtu;
+        Err("Transport does not support configuring the MTU".into())
+    }
 
-            let expected_op = if write_operation {
-                smp_op::WRITE_RSP
-            } else {
-                smp_op::READ_RSP
-            };
+    /// Sets a handler that receives all data that is not part of an SMP frame,
+    /// like the output of a Zephyr shell that shares the UART with SMP.
+    ///
+    /// Only supported by line based transports, like [`SerialTransport`](serial::SerialTransport).
+    fn set_console_handler(
+        &mut self,
+        handler: Option<ConsoleHandler>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _ = handler;
+        Err("Transport does not support console output".into())
+    }
 
-            // Receiving packets with the wrong sequence number is not an error,
-            // they should simply be silently ignored.
-            if header.sequence_num != sequence_num {
-                continue;
-            }
+    /// Reads incoming data without waiting for an SMP response,
+    /// forwarding console output to the console handler.
+    ///
+    /// Returns after the first read or when the communication timeout expires.
+    ///
+    /// Only supported by line based transports, like [`SerialTransport`](serial::SerialTransport).
+    fn poll_console(&mut self) -> Result<(), ReceiveError> {
+        Err(ReceiveError::TransportError(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Transport does not support console output",
+        )))
+    }
+}
 
-            if (header.group_id != group_id)
-                || (header.command_id != command_id)
-                || (header.op != expected_op)
-                || (usize::from(header.data_length) != data.len())
-            {
-                return Err(ReceiveError::UnexpectedResponse);
-            }
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send_raw_frame(
+        &mut self,
+        header: [u8; SMP_HEADER_SIZE],
+        data: &[u8],
+    ) -> Result<(), SendError> {
+        (**self).send_raw_frame(header, data)
+    }
 
-            break data.len();
-        };
+    fn recv_raw_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    ) -> Result<&'a [u8], ReceiveError> {
+        (**self).recv_raw_frame(buffer)
+    }
 
-        Ok(&buffer[SMP_HEADER_SIZE..SMP_HEADER_SIZE + data_size])
+    fn send_frame(
+        &mut self,
+        version: SmpVersion,
+        write_operation: bool,
+        sequence_num: u8,
+        group_id: u16,
+        command_id: u8,
+        data: &[u8],
+    ) -> Result<(), SendError> {
+        (**self).send_frame(
+            version,
+            write_operation,
+            sequence_num,
+            group_id,
+            command_id,
+            data,
+        )
+    }
+
+    fn receive_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+        write_operation: bool,
+        sequence_num: u8,
+        group_id: u16,
+        command_id: u8,
+    ) -> Result<&'a [u8], ReceiveError> {
+        (**self).receive_frame(buffer, write_operation, sequence_num, group_id, command_id)
+    }
+
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        (**self).set_timeout(timeout)
+    }
+
+    fn set_mtu(
+        &mut self,
+        mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        (**self).set_mtu(mtu)
+    }
+
+    fn set_console_handler(
+        &mut self,
+        handler: Option<ConsoleHandler>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        (**self).set_console_handler(handler)
+    }
+
+    fn poll_console(&mut self) -> Result<(), ReceiveError> {
+        (**self).poll_console()
+    }
+}
+
+/// Defines the API of the async SMP transport layer
+///
+/// Async counterpart of [`Transport`].
+#[cfg(feature = "tokio")]
+#[async_trait::async_trait]
+pub trait AsyncTransport: Send {
+    /// Send a raw SMP frame over the bus.
+    ///
+    /// This function must be provided by the implementing struct
+    /// but should not be called directly.
+    async fn send_raw_frame(
+        &mut self,
+        header: [u8; SMP_HEADER_SIZE],
+        data: &[u8],
+    ) -> Result<(), SendError>;
+
+    /// Receive a raw SMP frame from the bus.
+    ///
+    /// This function must be provided by the implementing struct
+    /// but should not be called directly.
+    async fn recv_raw_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    ) -> Result<&'a [u8], ReceiveError>;
+
+    /// Send an SMP frame over the bus.
+    ///
+    /// See [`Transport::send_frame`].
+    async fn send_frame(
+        &mut self,
+        version: SmpVersion,
+        write_operation: bool,
+        sequence_num: u8,
+        group_id: u16,
+        command_id: u8,
+        data: &[u8],
+    ) -> Result<(), SendError> {
+        let header_data = request_header(
+            version,
+            write_operation,
+            sequence_num,
+            group_id,
+            command_id,
+            data,
+        )?;
+
+        self.send_raw_frame(header_data, data).await
+    }
+
+    /// Receive an SMP frame from the bus.
+    ///
+    /// See [`Transport::receive_frame`].
+    async fn receive_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+        write_operation: bool,
+        sequence_num: u8,
+        group_id: u16,
+        command_id: u8,
+    ) -> Result<&'a [u8], ReceiveError> {
+        receive_response_async(
+            self,
+            buffer,
+            write_operation,
+            sequence_num,
+            group_id,
+            command_id,
+        )
+        .await
+        .map(|(_, data)| data)
     }
 
     /// Changes the communication timeout.
@@ -214,4 +670,18 @@ pub trait Transport {
         &mut self,
         timeout: Duration,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Changes the maximum length of a transmitted line.
+    ///
+    /// Only supported by line based transports, like [`AsyncSerialTransport`](async_serial::AsyncSerialTransport).
+    ///
+    /// `None` keeps the current MTU, but raises it to the length
+    /// of the longest line received from the device.
+    fn set_mtu(
+        &mut self,
+        mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _ = mtu;
+        Err("Transport does not support configuring the MTU".into())
+    }
 }
@@ -0,0 +1,438 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ciborium::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::MCUmgrGroup;
+
+use super::{
+    ConsoleHandler, ReceiveError, SMP_HEADER_SIZE, SMP_TRANSFER_BUFFER_SIZE, SendError, SmpHeader,
+    Transport,
+};
+
+/// The position of the sequence number in the SMP header.
+const SEQUENCE_NUM_OFFSET: usize = 6;
+
+/// A single line of a recording file.
+#[derive(Serialize, Deserialize, Debug)]
+struct RecordEntry {
+    /// Microseconds since the start of the recording
+    time_us: u64,
+    #[serde(flatten)]
+    event: RecordEvent,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum RecordEvent {
+    /// A frame was sent to the device
+    Send {
+        #[serde(with = "hex")]
+        frame: Vec<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// A frame was received from the device
+    Receive {
+        #[serde(with = "hex")]
+        frame: Vec<u8>,
+    },
+    /// Receiving a frame failed
+    ReceiveError { message: String, timed_out: bool },
+}
+
+/// A transport wrapper that records all frames to a file.
+///
+/// Every frame gets written as one JSON object per line, including a timestamp
+/// in microseconds since the start of the recording:
+///
+/// ```text
+/// {"time_us":0,"event":"send","frame":"0800000100000a00a0"}
+/// {"time_us":3512,"event":"receive","frame":"0900003600000a00bf..."}
+/// ```
+///
+/// The recording can be played back with [`ReplayTransport`].
+pub struct RecordingTransport<T> {
+    transport: T,
+    recording: Recording,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    /// Create a new [`RecordingTransport`] that writes to the given writer.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - The transport to record.
+    /// * `writer` - Where the recording gets written to.
+    ///
+    pub fn new(transport: T, writer: impl Write + Send + 'static) -> Self {
+        Recording::new(writer).record(transport)
+    }
+
+    /// Create a new [`RecordingTransport`] that writes to the given file.
+    ///
+    /// Overwrites the file if it already exists.
+    pub fn create(transport: T, path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Recording::create(path)?.record(transport))
+    }
+
+    /// Returns the recorded transport.
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+}
+
+/// A recording that multiple [`RecordingTransport`]s write to, for example
+/// the transports of a connection that gets reopened after the device disconnected.
+///
+/// Clones write to the same recording.
+///
+/// ```no_run
+/// # use mcumgr_toolkit::{MCUmgrClient, transport::recording::Recording};
+/// # fn main() -> std::io::Result<()> {
+/// let client = MCUmgrClient::new_from_tcp("192.168.1.42:4000", std::time::Duration::from_secs(1))?;
+///
+/// let recording = Recording::create("session.jsonl")?;
+/// client.wrap_transport(Box::new(move |transport| Box::new(recording.record(transport))));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Recording {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    start: Instant,
+}
+
+impl std::fmt::Debug for Recording {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recording").finish_non_exhaustive()
+    }
+}
+
+impl Recording {
+    /// Create a new [`Recording`] that writes to the given writer.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Arc::new(Mutex::new(Box::new(writer))),
+            start: Instant::now(),
+        }
+    }
+
+    /// Create a new [`Recording`] that writes to the given file.
+    ///
+    /// Overwrites the file if it already exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Records all frames of the given transport.
+    pub fn record<T: Transport>(&self, transport: T) -> RecordingTransport<T> {
+        RecordingTransport {
+            transport,
+            recording: self.clone(),
+        }
+    }
+
+    fn write(&self, event: RecordEvent) -> io::Result<()> {
+        let entry = RecordEntry {
+            time_us: self.start.elapsed().as_micros() as u64,
+            event,
+        };
+
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer(&mut *writer, &entry)?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn send_raw_frame(
+        &mut self,
+        header: [u8; SMP_HEADER_SIZE],
+        data: &[u8],
+    ) -> Result<(), SendError> {
+        let result = self.transport.send_raw_frame(header, data);
+
+        self.recording.write(RecordEvent::Send {
+            frame: header.iter().chain(data).copied().collect(),
+            error: result.as_ref().err().map(ToString::to_string),
+        })?;
+
+        result
+    }
+
+    fn recv_raw_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    ) -> Result<&'a [u8], ReceiveError> {
+        match self.transport.recv_raw_frame(buffer) {
+            Ok(frame) => {
+                let len = frame.len();
+                self.recording.write(RecordEvent::Receive {
+                    frame: frame.to_vec(),
+                })?;
+                Ok(&buffer[..len])
+            }
+            Err(e) => {
+                self.recording.write(RecordEvent::ReceiveError {
+                    message: e.to_string(),
+                    timed_out: e.is_timeout(),
+                })?;
+                Err(e)
+            }
+        }
+    }
+
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.transport.set_timeout(timeout)
+    }
+
+    fn set_mtu(
+        &mut self,
+        mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.transport.set_mtu(mtu)
+    }
+
+    fn set_console_handler(
+        &mut self,
+        handler: Option<ConsoleHandler>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.transport.set_console_handler(handler)
+    }
+
+    fn poll_console(&mut self) -> Result<(), ReceiveError> {
+        self.transport.poll_console()
+    }
+}
+
+/// A transport that plays back a recording of [`RecordingTransport`] as the device.
+///
+/// Every sent frame has to match the next recorded one, except for the sequence number
+/// and the message of echo requests; the responses get adjusted accordingly.
+/// Once the device has nothing more to say, receiving times out.
+///
+/// Timestamps are ignored; the recording gets played back as fast as possible.
+pub struct ReplayTransport {
+    entries: VecDeque<RecordEntry>,
+    sequence_offset: u8,
+    /// The payload of the last sent echo request, if it differs from the recording
+    echo_request: Option<Vec<u8>>,
+}
+
+impl ReplayTransport {
+    /// Create a new [`ReplayTransport`] from a recording.
+    pub fn new(reader: impl BufRead) -> io::Result<Self> {
+        let mut entries = VecDeque::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            entries.push_back(serde_json::from_str(&line)?);
+        }
+
+        Ok(Self {
+            entries,
+            sequence_offset: 0,
+            echo_request: None,
+        })
+    }
+
+    /// Create a new [`ReplayTransport`] from a recording file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+
+    /// Checks whether the entire recording was played back.
+    pub fn is_finished(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send_raw_frame(
+        &mut self,
+        header: [u8; SMP_HEADER_SIZE],
+        data: &[u8],
+    ) -> Result<(), SendError> {
+        // Responses the client did not wait for during recording
+        while let Some(RecordEntry {
+            event: RecordEvent::Receive { .. } | RecordEvent::ReceiveError { .. },
+            ..
+        }) = self.entries.front()
+        {
+            log::debug!("Skipping unconsumed response of recording");
+            self.entries.pop_front();
+        }
+
+        let Some(RecordEntry {
+            event: RecordEvent::Send { frame, error },
+            ..
+        }) = self.entries.pop_front()
+        else {
+            return Err(SendError::TransportError(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "End of recording reached",
+            )));
+        };
+
+        let Some((expected_header, expected_data)) = frame.split_first_chunk::<SMP_HEADER_SIZE>()
+        else {
+            return Err(SendError::TransportError(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Sent frame does not match the recording",
+            )));
+        };
+
+        let mut expected_header = SmpHeader::from_bytes(*expected_header);
+        let actual_header = SmpHeader::from_bytes(header);
+        expected_header.sequence_num = actual_header.sequence_num;
+
+        // Echo messages may differ, like the random ones of `check_connection`
+        self.echo_request = None;
+        let matches = if expected_data == data {
+            expected_header == actual_header
+        } else if is_echo(&actual_header) {
+            expected_header.data_length = actual_header.data_length;
+            self.echo_request = Some(data.to_vec());
+            expected_header == actual_header
+        } else {
+            false
+        };
+
+        if !matches {
+            return Err(SendError::TransportError(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Sent frame does not match the recording",
+            )));
+        }
+
+        self.sequence_offset = header[SEQUENCE_NUM_OFFSET].wrapping_sub(frame[SEQUENCE_NUM_OFFSET]);
+
+        if let Some(error) = error {
+            return Err(SendError::TransportError(io::Error::other(error)));
+        }
+
+        Ok(())
+    }
+
+    fn recv_raw_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    ) -> Result<&'a [u8], ReceiveError> {
+        let event = match self.entries.front() {
+            Some(RecordEntry {
+                event: RecordEvent::Receive { .. } | RecordEvent::ReceiveError { .. },
+                ..
+            }) => self.entries.pop_front().unwrap().event,
+            _ => {
+                return Err(ReceiveError::TransportError(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "No more responses in recording",
+                )));
+            }
+        };
+
+        match event {
+            RecordEvent::Receive { mut frame } => {
+                if let Some(request) = &self.echo_request {
+                    if let Some(response) = replace_echo_message(request, &frame) {
+                        frame = response;
+                    }
+                }
+
+                let result = buffer
+                    .get_mut(..frame.len())
+                    .ok_or(ReceiveError::FrameTooBig)?;
+                result.copy_from_slice(&frame);
+
+                if let Some(sequence_num) = result.get_mut(SEQUENCE_NUM_OFFSET) {
+                    *sequence_num = sequence_num.wrapping_add(self.sequence_offset);
+                }
+
+                Ok(result)
+            }
+            RecordEvent::ReceiveError { message, timed_out } => {
+                Err(ReceiveError::TransportError(if timed_out {
+                    io::Error::new(io::ErrorKind::TimedOut, message)
+                } else {
+                    io::Error::other(message)
+                }))
+            }
+            RecordEvent::Send { .. } => unreachable!(),
+        }
+    }
+
+    fn set_timeout(
+        &mut self,
+        _timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn set_mtu(
+        &mut self,
+        _mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn set_console_handler(
+        &mut self,
+        _handler: Option<ConsoleHandler>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn poll_console(&mut self) -> Result<(), ReceiveError> {
+        Ok(())
+    }
+}
+
+/// Checks whether a frame belongs to the `OS_MGMT_ID_ECHO` command.
+fn is_echo(header: &SmpHeader) -> bool {
+    header.group_id == MCUmgrGroup::MGMT_GROUP_ID_OS as u16 && header.command_id == 0
+}
+
+/// Replaces the message of a recorded echo response with the one of the sent `request` payload.
+///
+/// Returns `None` if `frame` is not a successful echo response.
+fn replace_echo_message(request: &[u8], frame: &[u8]) -> Option<Vec<u8>> {
+    let (header, data) = frame.split_first_chunk::<SMP_HEADER_SIZE>()?;
+    let mut header = SmpHeader::from_bytes(*header);
+    if !header.is_response() || !is_echo(&header) {
+        return None;
+    }
+
+    let field = |value: Value, name: &str| {
+        value
+            .into_map()
+            .ok()?
+            .into_iter()
+            .find(|(key, _)| key.as_text() == Some(name))
+    };
+    let (_, message) = field(ciborium::from_reader(request).ok()?, "d")?;
+    let (key, _) = field(ciborium::from_reader(data).ok()?, "r")?;
+
+    let mut payload = vec![];
+    ciborium::into_writer(&Value::Map(vec![(key, message)]), &mut payload).ok()?;
+    header.data_length = u16::try_from(payload.len()).ok()?;
+
+    let mut response = header.to_bytes().to_vec();
+    response.extend_from_slice(&payload);
+    Some(response)
+}
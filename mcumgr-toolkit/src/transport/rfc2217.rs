@@ -0,0 +1,449 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use serialport::{DataBits, Parity, StopBits};
+
+use super::serial::ConfigurableTimeout;
+
+/// The URL prefix that selects an RFC 2217 port, as in `rfc2217://host:port`.
+pub const RFC2217_URL_PREFIX: &str = "rfc2217://";
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+const OPT_BINARY: u8 = 0;
+const OPT_SUPPRESS_GO_AHEAD: u8 = 3;
+const OPT_COM_PORT: u8 = 44;
+
+const SET_BAUDRATE: u8 = 1;
+const SET_DATASIZE: u8 = 2;
+const SET_PARITY: u8 = 3;
+const SET_STOPSIZE: u8 = 4;
+const SET_CONTROL: u8 = 5;
+
+/// Servers answer client commands with the command code plus 100.
+const SERVER_RESPONSE_OFFSET: u8 = 100;
+
+const CONTROL_NO_FLOW_CONTROL: u8 = 1;
+const CONTROL_DTR_ON: u8 = 8;
+const CONTROL_DTR_OFF: u8 = 9;
+const CONTROL_RTS_ON: u8 = 11;
+const CONTROL_RTS_OFF: u8 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParserState {
+    Data,
+    Iac,
+    Negotiation(u8),
+    Subnegotiation,
+    SubnegotiationIac,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ComPortOption {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+/// The serial port settings that get negotiated with an RFC 2217 server.
+///
+/// The port always uses one stop bit and no flow control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rfc2217Settings {
+    /// The baud rate the port should operate at
+    pub baud_rate: u32,
+    /// The number of data bits per character
+    pub data_bits: DataBits,
+    /// The parity checking mode
+    pub parity: Parity,
+    /// The state of the DTR line, or `None` to keep the state of the server
+    pub dtr: Option<bool>,
+    /// The state of the RTS line, or `None` to keep the state of the server
+    pub rts: Option<bool>,
+}
+
+impl Rfc2217Settings {
+    /// Settings with the given baud rate, 8 data bits, no parity
+    /// and modem lines that are left untouched.
+    pub fn new(baud_rate: u32) -> Self {
+        Self {
+            baud_rate,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            dtr: None,
+            rts: None,
+        }
+    }
+}
+
+/// A serial port behind an [RFC 2217](https://www.rfc-editor.org/rfc/rfc2217) terminal server.
+///
+/// Negotiates the serial port settings via Telnet and can be used
+/// as the serial port of a [`SerialTransport`](super::serial::SerialTransport).
+pub struct Rfc2217Port {
+    stream: TcpStream,
+    timeout: Duration,
+    read_buffer: Box<[u8]>,
+    parser_state: ParserState,
+    subnegotiation: Vec<u8>,
+    received_data: VecDeque<u8>,
+    com_port_option: ComPortOption,
+    responses: HashMap<u8, Vec<u8>>,
+}
+
+impl Rfc2217Port {
+    /// Connects to an RFC 2217 server and configures the serial port.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the server, for example `192.168.1.42:4000`.
+    /// * `settings` - The serial port settings, for example `Rfc2217Settings::new(115200)`.
+    /// * `timeout` - The connection and communication timeout.
+    ///
+    pub fn open(
+        addr: impl ToSocketAddrs,
+        settings: Rfc2217Settings,
+        timeout: Duration,
+    ) -> io::Result<Self> {
+        let mut last_error = None;
+
+        for addr in addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => return Self::from_stream(stream, settings, timeout),
+                Err(e) => {
+                    log::debug!("Unable to connect to {addr}: {e}");
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Could not resolve to any address",
+            )
+        }))
+    }
+
+    /// Configures the serial port behind an already connected RFC 2217 server.
+    ///
+    /// See [`open`](Self::open).
+    pub fn from_stream(
+        stream: TcpStream,
+        settings: Rfc2217Settings,
+        timeout: Duration,
+    ) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+
+        let mut port = Self {
+            stream,
+            timeout,
+            read_buffer: vec![0u8; 4096].into_boxed_slice(),
+            parser_state: ParserState::Data,
+            subnegotiation: vec![],
+            received_data: VecDeque::new(),
+            com_port_option: ComPortOption::Pending,
+            responses: HashMap::new(),
+        };
+        port.apply_timeout()?;
+
+        port.stream.write_all(&[
+            IAC,
+            WILL,
+            OPT_COM_PORT,
+            IAC,
+            WILL,
+            OPT_BINARY,
+            IAC,
+            DO,
+            OPT_BINARY,
+            IAC,
+            DO,
+            OPT_SUPPRESS_GO_AHEAD,
+        ])?;
+
+        let deadline = Instant::now() + port.timeout;
+        while port.com_port_option == ComPortOption::Pending {
+            port.receive_until(deadline)?;
+        }
+        if port.com_port_option == ComPortOption::Rejected {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Server does not support RFC 2217",
+            ));
+        }
+
+        port.set_baud_rate(settings.baud_rate)?;
+        port.set_data_bits(settings.data_bits)?;
+        port.set_parity(settings.parity)?;
+        port.set_stop_bits(StopBits::One)?;
+        port.send_control(CONTROL_NO_FLOW_CONTROL, "flow control")?;
+        if let Some(dtr) = settings.dtr {
+            port.write_data_terminal_ready(dtr)?;
+        }
+        if let Some(rts) = settings.rts {
+            port.write_request_to_send(rts)?;
+        }
+
+        Ok(port)
+    }
+
+    /// Changes the baud rate of the serial port.
+    pub fn set_baud_rate(&mut self, baud_rate: u32) -> io::Result<()> {
+        self.send_setting(SET_BAUDRATE, &baud_rate.to_be_bytes(), "baud rate")
+    }
+
+    /// Changes the number of data bits of the serial port.
+    pub fn set_data_bits(&mut self, data_bits: DataBits) -> io::Result<()> {
+        let value = match data_bits {
+            DataBits::Five => 5,
+            DataBits::Six => 6,
+            DataBits::Seven => 7,
+            DataBits::Eight => 8,
+        };
+        self.send_setting(SET_DATASIZE, &[value], "data bits")
+    }
+
+    /// Changes the parity of the serial port.
+    pub fn set_parity(&mut self, parity: Parity) -> io::Result<()> {
+        let value = match parity {
+            Parity::None => 1,
+            Parity::Odd => 2,
+            Parity::Even => 3,
+        };
+        self.send_setting(SET_PARITY, &[value], "parity")
+    }
+
+    /// Changes the number of stop bits of the serial port.
+    pub fn set_stop_bits(&mut self, stop_bits: StopBits) -> io::Result<()> {
+        let value = match stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        };
+        self.send_setting(SET_STOPSIZE, &[value], "stop bits")
+    }
+
+    /// Sets the state of the DTR (Data Terminal Ready) line.
+    pub fn write_data_terminal_ready(&mut self, level: bool) -> io::Result<()> {
+        let value = if level {
+            CONTROL_DTR_ON
+        } else {
+            CONTROL_DTR_OFF
+        };
+        self.send_control(value, "DTR state")
+    }
+
+    /// Sets the state of the RTS (Request To Send) line.
+    pub fn write_request_to_send(&mut self, level: bool) -> io::Result<()> {
+        let value = if level {
+            CONTROL_RTS_ON
+        } else {
+            CONTROL_RTS_OFF
+        };
+        self.send_control(value, "RTS state")
+    }
+
+    fn send_control(&mut self, value: u8, name: &str) -> io::Result<()> {
+        self.send_setting(SET_CONTROL, &[value], name)
+    }
+
+    /// Sends a COM-PORT-OPTION command and waits until the server confirms it.
+    fn send_setting(&mut self, command: u8, value: &[u8], name: &str) -> io::Result<()> {
+        let response_code = command + SERVER_RESPONSE_OFFSET;
+        self.responses.remove(&response_code);
+
+        let mut message = vec![IAC, SB, OPT_COM_PORT, command];
+        for &byte in value {
+            message.push(byte);
+            if byte == IAC {
+                message.push(IAC);
+            }
+        }
+        message.extend_from_slice(&[IAC, SE]);
+        self.stream.write_all(&message)?;
+
+        let deadline = Instant::now() + self.timeout;
+        let response = loop {
+            if let Some(response) = self.responses.remove(&response_code) {
+                break response;
+            }
+            self.receive_until(deadline)?;
+        };
+
+        log::debug!("RFC 2217: set {name} to {value:?}, server responded {response:?}");
+
+        if response != value {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Server did not accept {name} {value:?}"),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn apply_timeout(&mut self) -> io::Result<()> {
+        self.stream.set_read_timeout(Some(self.timeout))?;
+        self.stream.set_write_timeout(Some(self.timeout))
+    }
+
+    /// Reads from the server once, waiting at most until `deadline`.
+    ///
+    /// Returns `None` if the deadline has already passed.
+    fn receive_before(&mut self, deadline: Instant) -> io::Result<Option<usize>> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.receive().map(Some)
+    }
+
+    /// Receives and processes data from the server, giving up after `deadline`.
+    fn receive_until(&mut self, deadline: Instant) -> io::Result<()> {
+        let Some(num_read) = self.receive_before(deadline)? else {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "RFC 2217 server did not respond",
+            ));
+        };
+
+        if num_read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "RFC 2217 server closed the connection",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Reads from the server once and processes the received bytes.
+    fn receive(&mut self) -> io::Result<usize> {
+        let num_read = self.stream.read(&mut self.read_buffer)?;
+
+        for pos in 0..num_read {
+            let byte = self.read_buffer[pos];
+            self.process_byte(byte)?;
+        }
+
+        Ok(num_read)
+    }
+
+    fn process_byte(&mut self, byte: u8) -> io::Result<()> {
+        self.parser_state = match (self.parser_state, byte) {
+            (ParserState::Data, IAC) => ParserState::Iac,
+            (ParserState::Data, byte) => {
+                self.received_data.push_back(byte);
+                ParserState::Data
+            }
+            (ParserState::Iac, IAC) => {
+                self.received_data.push_back(IAC);
+                ParserState::Data
+            }
+            (ParserState::Iac, WILL | WONT | DO | DONT) => ParserState::Negotiation(byte),
+            (ParserState::Iac, SB) => {
+                self.subnegotiation.clear();
+                ParserState::Subnegotiation
+            }
+            (ParserState::Iac, _) => ParserState::Data,
+            (ParserState::Negotiation(command), option) => {
+                self.process_negotiation(command, option)?;
+                ParserState::Data
+            }
+            (ParserState::Subnegotiation, IAC) => ParserState::SubnegotiationIac,
+            (ParserState::Subnegotiation, byte) => {
+                self.subnegotiation.push(byte);
+                ParserState::Subnegotiation
+            }
+            (ParserState::SubnegotiationIac, IAC) => {
+                self.subnegotiation.push(IAC);
+                ParserState::Subnegotiation
+            }
+            (ParserState::SubnegotiationIac, SE) => {
+                self.process_subnegotiation();
+                ParserState::Data
+            }
+            (ParserState::SubnegotiationIac, _) => ParserState::Data,
+        };
+
+        Ok(())
+    }
+
+    fn process_negotiation(&mut self, command: u8, option: u8) -> io::Result<()> {
+        match (command, option) {
+            (DO, OPT_COM_PORT) => self.com_port_option = ComPortOption::Accepted,
+            (DONT, OPT_COM_PORT) => self.com_port_option = ComPortOption::Rejected,
+            // We already offered these, nothing to answer
+            (DO, OPT_BINARY) | (WILL, OPT_BINARY | OPT_SUPPRESS_GO_AHEAD) => {}
+            (DO, option) => self.stream.write_all(&[IAC, WONT, option])?,
+            (WILL, option) => self.stream.write_all(&[IAC, DONT, option])?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn process_subnegotiation(&mut self) {
+        if let [OPT_COM_PORT, command, value @ ..] = self.subnegotiation.as_slice() {
+            self.responses.insert(*command, value.to_vec());
+        }
+    }
+}
+
+impl Read for Rfc2217Port {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // In-band notifications of the server must not extend the timeout
+        let deadline = Instant::now() + self.timeout;
+        while self.received_data.is_empty() {
+            match self.receive_before(deadline)? {
+                Some(0) => return Ok(0),
+                Some(_) => {}
+                None => return Err(io::ErrorKind::TimedOut.into()),
+            }
+        }
+
+        self.received_data.read(buf)
+    }
+}
+
+impl Write for Rfc2217Port {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut escaped = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            escaped.push(byte);
+            if byte == IAC {
+                escaped.push(IAC);
+            }
+        }
+
+        self.stream.write_all(&escaped)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl ConfigurableTimeout for Rfc2217Port {
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.timeout = timeout;
+        self.apply_timeout().map_err(Into::into)
+    }
+}
@@ -8,34 +8,123 @@ use ringbuf::{
 };
 use serialport::SerialPort;
 
-use super::{ReceiveError, SMP_HEADER_SIZE, SMP_TRANSFER_BUFFER_SIZE, SendError, Transport};
+use super::{
+    ConsoleHandler, ReceiveError, SMP_HEADER_SIZE, SMP_TRANSFER_BUFFER_SIZE, SendError, Transport,
+    codec::encode_serial_parts,
+};
 
 /// A transport layer implementation for serial ports.
 pub struct SerialTransport<T> {
-    transfer_buffer: Box<[u8]>,
-    body_buffer: Box<[u8]>,
+    mtu: usize,
+    receive_buffer: Box<[u8]>,
+    decode_buffer: Box<[u8]>,
+    learn_mtu: bool,
     serial: T,
     crc_algo: crc::Crc<u16>,
     read_buffer: LocalRb<Heap<u8>>,
+    console_handler: Option<ConsoleHandler>,
+    console_line: Vec<u8>,
+    discarding_chunk: bool,
+    frame: Vec<u8>,
+    frame_len: Option<usize>,
+    partial_chunk: Option<PartialChunk>,
 }
 
-fn fill_buffer_with_data<'a, I: Iterator<Item = u8>>(
-    buffer: &'a mut [u8],
-    data_iter: &mut I,
-) -> &'a [u8] {
-    for (pos, val) in buffer.iter_mut().enumerate() {
-        if let Some(next) = data_iter.next() {
-            *val = next;
-        } else {
-            return &buffer[..pos];
+/// A chunk whose reception timed out before its newline arrived.
+#[derive(Clone, Copy)]
+struct PartialChunk {
+    /// The number of bytes in the receive buffer
+    received: usize,
+    /// Whether the chunk starts a frame
+    initial: bool,
+}
+
+/// Configurable parameters for [`SerialTransport`].
+#[derive(Clone, Debug)]
+pub struct SerialTransportConfig {
+    /// Default: `127`
+    ///
+    /// The maximum length of a transmitted line, including frame start and newline.
+    ///
+    /// Must not exceed the device's [`MCUMGR_SERIAL_MAX_FRAME`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/include/zephyr/mgmt/mcumgr/transport/serial.h#L18)
+    /// and must be at least [`SERIAL_TRANSPORT_MIN_MTU`].
+    pub mtu: usize,
+    /// Default: `false`
+    ///
+    /// Raise the MTU to the length of the longest line received from the device.
+    ///
+    /// Zephyr chunks its responses with the same `MCUMGR_SERIAL_MAX_FRAME`
+    /// that limits its requests, so received lines never exceed the device's MTU.
+    pub learn_mtu: bool,
+    /// Default: `4096`
+    ///
+    /// The size of the buffer incoming data gets read into.
+    ///
+    /// Also limits the length of received lines.
+    pub read_buffer_size: usize,
+}
+
+impl Default for SerialTransportConfig {
+    fn default() -> Self {
+        Self {
+            mtu: SERIAL_TRANSPORT_ZEPHYR_MTU,
+            learn_mtu: false,
+            read_buffer_size: 4096,
         }
     }
-
-    buffer
 }
 
 /// See Zephyr's [`MCUMGR_SERIAL_MAX_FRAME`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/include/zephyr/mgmt/mcumgr/transport/serial.h#L18).
-const SERIAL_TRANSPORT_ZEPHYR_MTU: usize = 127;
+pub(super) const SERIAL_TRANSPORT_ZEPHYR_MTU: usize = 127;
+
+/// Console lines longer than this get split.
+const CONSOLE_LINE_MAX_LENGTH: usize = 1024;
+
+/// Checks whether two bytes start an SMP chunk.
+fn is_chunk_start(byte_0: u8, byte_1: u8) -> bool {
+    matches!((byte_0, byte_1), (6, 9) | (4, 20))
+}
+
+/// Maps read timeouts to `Ok(None)`.
+fn ignore_timeout(result: std::io::Result<usize>) -> std::io::Result<Option<usize>> {
+    match result {
+        Ok(num_read) => Ok(Some(num_read)),
+        Err(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// The smallest possible MTU of a serial transport.
+///
+/// Fits the frame start, a single base64 block and the newline.
+pub const SERIAL_TRANSPORT_MIN_MTU: usize = 7;
+
+/// The number of raw bytes that fit into a line of the given length.
+pub(super) fn serial_body_size(mtu: usize) -> usize {
+    ((mtu - 3) / 4) * 3
+}
+
+/// The configured MTU is smaller than [`SERIAL_TRANSPORT_MIN_MTU`].
+#[derive(thiserror::Error, miette::Diagnostic, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("MTU must be at least {SERIAL_TRANSPORT_MIN_MTU} bytes, got {0}")]
+#[diagnostic(code(mcumgr_toolkit::transport::serial::mtu_too_small))]
+pub struct SerialMtuTooSmall(pub usize);
+
+/// Checks that the given MTU can hold at least one byte of data.
+pub(super) fn check_serial_mtu(mtu: usize) -> Result<(), SerialMtuTooSmall> {
+    if mtu < SERIAL_TRANSPORT_MIN_MTU {
+        Err(SerialMtuTooSmall(mtu))
+    } else {
+        Ok(())
+    }
+}
 
 impl<T> SerialTransport<T>
 where
@@ -48,72 +137,119 @@ where
     /// * `serial` - A serial port object, like [`serialport::SerialPort`].
     ///
     pub fn new(serial: T) -> Self {
-        let mtu = SERIAL_TRANSPORT_ZEPHYR_MTU;
-        Self {
-            serial,
-            transfer_buffer: vec![0u8; mtu].into_boxed_slice(),
-            body_buffer: vec![0u8; ((mtu - 3) / 4) * 3].into_boxed_slice(),
-            crc_algo: crc::Crc::<u16>::new(&crc::CRC_16_XMODEM),
-            read_buffer: LocalRb::new(4096),
-        }
+        Self::from_checked_config(serial, SerialTransportConfig::default())
     }
 
-    /// Take a raw data stream, split it into SMP transport frames and transmit them.
+    /// Create a new [`SerialTransport`] with custom parameters.
     ///
     /// # Arguments
     ///
-    /// * `data_iter` - An iterator that produces the binary data of the message to send.
+    /// * `serial` - A serial port object, like [`serialport::SerialPort`].
+    /// * `config` - The line length and buffer sizes.
     ///
-    fn send_chunked<I: Iterator<Item = u8>>(&mut self, mut data_iter: I) -> Result<(), SendError> {
-        self.transfer_buffer[0] = 6;
-        self.transfer_buffer[1] = 9;
-
-        loop {
-            let body = fill_buffer_with_data(&mut self.body_buffer, &mut data_iter);
+    /// # Errors
+    ///
+    /// If `config.mtu` is smaller than [`SERIAL_TRANSPORT_MIN_MTU`].
+    ///
+    pub fn with_config(
+        serial: T,
+        config: SerialTransportConfig,
+    ) -> Result<Self, SerialMtuTooSmall> {
+        check_serial_mtu(config.mtu)?;
+        Ok(Self::from_checked_config(serial, config))
+    }
 
-            if body.is_empty() {
-                break Ok(());
-            }
+    fn from_checked_config(serial: T, config: SerialTransportConfig) -> Self {
+        let receive_size = config.read_buffer_size.max(config.mtu);
 
-            let base64_len = BASE64_STANDARD
-                .encode_slice(body, &mut self.transfer_buffer[2..])
-                .expect("Transfer buffer overflow; this is a bug. Please report.");
+        Self {
+            serial,
+            mtu: config.mtu,
+            receive_buffer: vec![0u8; receive_size].into_boxed_slice(),
+            decode_buffer: vec![0u8; receive_size.div_ceil(4) * 3].into_boxed_slice(),
+            learn_mtu: config.learn_mtu,
+            crc_algo: crc::Crc::<u16>::new(&crc::CRC_16_XMODEM),
+            read_buffer: LocalRb::new(receive_size),
+            console_handler: None,
+            console_line: Vec::new(),
+            discarding_chunk: false,
+            frame: Vec::new(),
+            frame_len: None,
+            partial_chunk: None,
+        }
+    }
 
-            self.transfer_buffer[base64_len + 2] = 0x0a;
+    /// Sets a handler that receives all data that is not part of an SMP frame,
+    /// like the output of a Zephyr shell that shares the UART with SMP.
+    ///
+    /// The data is split into lines; line endings are removed.
+    /// Without a handler, this data gets discarded.
+    ///
+    /// Note that data only gets read while waiting for a response
+    /// or in [`Transport::poll_console`].
+    pub fn with_console_handler(mut self, handler: impl FnMut(&[u8]) + Send + 'static) -> Self {
+        self.console_handler = Some(Box::new(handler));
+        self
+    }
 
-            self.serial
-                .write_all(&self.transfer_buffer[..base64_len + 3])?;
+    /// Forwards a byte that is not part of an SMP frame to the console handler.
+    fn handle_console_byte(&mut self, byte: u8) {
+        let Some(handler) = &mut self.console_handler else {
+            return;
+        };
 
-            log::debug!(
-                "Sent Chunk ({}, {} bytes raw, {} bytes encoded)",
-                if self.transfer_buffer[0] == 6 {
-                    "initial"
-                } else {
-                    "partial"
-                },
-                body.len(),
-                base64_len,
-            );
+        if byte == b'\n' {
+            let line = self
+                .console_line
+                .strip_suffix(b"\r")
+                .unwrap_or(&self.console_line);
+            handler(line);
+            self.console_line.clear();
+        } else {
+            self.console_line.push(byte);
+            if self.console_line.len() >= CONSOLE_LINE_MAX_LENGTH {
+                handler(&self.console_line);
+                self.console_line.clear();
+            }
+        }
+    }
 
-            self.transfer_buffer[0] = 4;
-            self.transfer_buffer[1] = 20;
+    /// Forwards a byte that was read while not inside of an SMP chunk.
+    ///
+    /// Skips the remainder of SMP chunks that we did not expect.
+    fn handle_unexpected_byte(&mut self, byte: u8, next: Option<u8>) -> bool {
+        if self.discarding_chunk {
+            if byte == b'\n' {
+                self.discarding_chunk = false;
+            }
+            false
+        } else if next.is_some_and(|next| is_chunk_start(byte, next)) {
+            log::debug!("Discarding unexpected SMP chunk");
+            self.discarding_chunk = true;
+            true
+        } else {
+            self.handle_console_byte(byte);
+            false
         }
     }
 
-    /// Receive an SMP transport frame and decode it.
+    /// The current maximum length of a transmitted line.
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    /// Waits for the start of an SMP transport chunk.
     ///
     /// # Arguments
     ///
-    /// * `first` - whether this is the first first frame of the message.
+    /// * `first` - whether this is the first chunk of the frame.
     ///
     /// # Return
     ///
-    /// The received data
+    /// Whether the chunk starts a frame. Even if a continuation is expected,
+    /// a new frame may start if the rest of the previous one got lost.
     ///
-    fn recv_chunk(&mut self, first: bool) -> Result<&[u8], ReceiveError> {
-        let expected_header_0 = if first { 6 } else { 4 };
-        let expected_header_1 = if first { 9 } else { 20 };
-
+    fn recv_chunk_start(&mut self, first: bool) -> Result<bool, ReceiveError> {
         loop {
             while self.read_buffer.occupied_len() < 2 {
                 let num_read = self
@@ -130,57 +266,149 @@ where
             }
 
             let current = self.read_buffer.try_pop().unwrap();
-            let next = self.read_buffer.try_peek().unwrap();
-            if current == expected_header_0 && *next == expected_header_1 {
+            let next = *self.read_buffer.try_peek().unwrap();
+            if !self.discarding_chunk {
+                match (current, next) {
+                    (6, 9) => {
+                        self.read_buffer.try_pop().unwrap();
+                        return Ok(true);
+                    }
+                    (4, 20) if !first => {
+                        self.read_buffer.try_pop().unwrap();
+                        return Ok(false);
+                    }
+                    _ => {}
+                }
+            }
+
+            if self.handle_unexpected_byte(current, Some(next)) {
                 self.read_buffer.try_pop().unwrap();
-                break;
             }
         }
+    }
 
-        let mut base64_data = None;
-        for (pos, elem) in self.transfer_buffer.iter_mut().enumerate() {
+    /// Receive an SMP transport chunk and decode it into the decode buffer.
+    ///
+    /// If reading times out, the received part of the chunk is kept
+    /// and the next call continues with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - whether this is the first chunk of the frame.
+    ///
+    /// # Return
+    ///
+    /// Whether the chunk starts a frame, and the length of the decoded data.
+    ///
+    fn recv_chunk(&mut self, first: bool) -> Result<(bool, usize), ReceiveError> {
+        let PartialChunk { received, initial } = match self.partial_chunk.take() {
+            Some(partial_chunk) => partial_chunk,
+            None => PartialChunk {
+                received: 0,
+                initial: self.recv_chunk_start(first)?,
+            },
+        };
+
+        let mut base64_len = None;
+        for pos in received..self.receive_buffer.len() {
             let data = loop {
-                if let Some(e) = self.read_buffer.try_pop() {
+                if let Some(&e) = self.read_buffer.try_peek() {
                     break e;
-                } else {
-                    let num_read = self
-                        .read_buffer
-                        .read_from(&mut self.serial, None)
-                        .unwrap()?;
+                }
 
-                    if num_read == 0 {
+                match self.read_buffer.read_from(&mut self.serial, None).unwrap() {
+                    Ok(0) => {
                         return Err(ReceiveError::TransportError(std::io::Error::new(
                             std::io::ErrorKind::UnexpectedEof,
                             "Serial port unexpectedly returned end-of-file",
                         )));
                     }
+                    Ok(_) => {}
+                    Err(e) => {
+                        let e = ReceiveError::from(e);
+                        if e.is_timeout() {
+                            self.partial_chunk = Some(PartialChunk {
+                                received: pos,
+                                initial,
+                            });
+                        }
+                        return Err(e);
+                    }
                 }
             };
 
+            // Not part of the base64 alphabet, so another chunk started
+            if data == 6 || data == 4 {
+                log::debug!("Discarding incomplete SMP chunk");
+                return self.recv_chunk(first);
+            }
+
+            self.read_buffer.try_pop().unwrap();
+
             if data == 0x0a {
-                base64_data = Some(&self.transfer_buffer[..pos]);
+                base64_len = Some(pos);
                 break;
             }
 
-            *elem = data;
+            self.receive_buffer[pos] = data;
         }
 
         if let Some(0x0a) = self.read_buffer.try_peek() {
-            base64_data = Some(&self.transfer_buffer);
+            base64_len = Some(self.receive_buffer.len());
         }
 
-        if let Some(base64_data) = base64_data {
-            let len = BASE64_STANDARD.decode_slice(base64_data, &mut self.body_buffer)?;
+        let Some(base64_len) = base64_len else {
+            return Err(ReceiveError::FrameTooBig);
+        };
 
-            log::debug!(
-                "Received Chunk ({}, {} bytes raw, {} bytes decoded)",
-                if first { "initial" } else { "partial" },
-                base64_data.len(),
-                len
-            );
-            Ok(&self.body_buffer[..len])
-        } else {
-            Err(ReceiveError::FrameTooBig)
+        let base64_data = &self.receive_buffer[..base64_len];
+        let len = BASE64_STANDARD.decode_slice(base64_data, &mut self.decode_buffer)?;
+
+        log::debug!(
+            "Received Chunk ({}, {} bytes raw, {} bytes decoded)",
+            if initial { "initial" } else { "partial" },
+            base64_len,
+            len
+        );
+
+        let line_length = base64_len + 3;
+        if self.learn_mtu && line_length > self.mtu() {
+            log::debug!("Learned serial MTU {line_length} from device");
+            self.mtu = line_length;
+        }
+
+        Ok((initial, len))
+    }
+
+    /// Receive the chunks of an SMP frame into the frame buffer.
+    ///
+    /// If reading times out, the received chunks are kept
+    /// and the next call continues with them.
+    fn recv_frame(&mut self) -> Result<(), ReceiveError> {
+        loop {
+            let (initial, len) = self.recv_chunk(self.frame_len.is_none())?;
+            let mut data = &self.decode_buffer[..len];
+
+            if initial {
+                if self.frame_len.is_some() {
+                    log::debug!("Discarding incomplete SMP frame");
+                }
+
+                let (len_data, first_data) = data
+                    .split_first_chunk::<2>()
+                    .ok_or(ReceiveError::UnexpectedResponse)?;
+                self.frame_len = Some(u16::from_be_bytes(*len_data).into());
+                self.frame.clear();
+                data = first_data;
+            }
+
+            self.frame.extend_from_slice(data);
+
+            match self.frame_len {
+                Some(frame_len) if self.frame.len() < frame_len => {}
+                Some(frame_len) if self.frame.len() == frame_len => return Ok(()),
+                _ => return Err(ReceiveError::UnexpectedResponse),
+            }
         }
     }
 }
@@ -196,59 +424,26 @@ where
     ) -> Result<(), SendError> {
         log::debug!("Sending SMP Frame ({} bytes)", data.len());
 
-        let checksum = {
-            let mut digest = self.crc_algo.digest();
-            digest.update(&header);
-            digest.update(data);
-            digest.finalize().to_be_bytes()
-        };
+        let encoded = encode_serial_parts(&[&header, data], self.mtu)?;
+        self.serial.write_all(&encoded)?;
 
-        let size = u16::try_from(header.len() + data.len() + checksum.len())
-            .map_err(|_| SendError::DataTooBig)?
-            .to_be_bytes();
-
-        self.send_chunked(
-            size.into_iter()
-                .chain(header)
-                .chain(data.iter().copied())
-                .chain(checksum),
-        )
+        Ok(())
     }
 
     fn recv_raw_frame<'a>(
         &mut self,
         buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
     ) -> Result<&'a [u8], ReceiveError> {
-        let first_chunk = self.recv_chunk(true)?;
-
-        let (len, first_data) =
-            if let Some((len_data, first_data)) = first_chunk.split_first_chunk::<2>() {
-                (u16::from_be_bytes(*len_data), first_data)
-            } else {
-                return Err(ReceiveError::UnexpectedResponse);
-            };
+        let result = self.recv_frame();
+        if !result.as_ref().is_err_and(ReceiveError::is_timeout) {
+            self.frame_len = None;
+        }
+        result?;
 
         let result_buffer = buffer
-            .split_at_mut_checked(len.into())
-            .ok_or(ReceiveError::FrameTooBig)?
-            .0;
-
-        let (first_result_buffer, mut leftover_result_buffer) = result_buffer
-            .split_at_mut_checked(first_data.len())
-            .ok_or(ReceiveError::UnexpectedResponse)?;
-
-        first_result_buffer.copy_from_slice(first_data);
-
-        while !leftover_result_buffer.is_empty() {
-            let next_chunk = self.recv_chunk(false)?;
-
-            let current_result_buffer;
-            (current_result_buffer, leftover_result_buffer) = leftover_result_buffer
-                .split_at_mut_checked(next_chunk.len())
-                .ok_or(ReceiveError::UnexpectedResponse)?;
-
-            current_result_buffer.copy_from_slice(next_chunk);
-        }
+            .get_mut(..self.frame.len())
+            .ok_or(ReceiveError::FrameTooBig)?;
+        result_buffer.copy_from_slice(&self.frame);
 
         let (data, checksum_data) = result_buffer
             .split_last_chunk::<2>()
@@ -259,7 +454,7 @@ where
         let actual_checksum = self.crc_algo.checksum(data);
 
         if expected_checksum != actual_checksum {
-            return Err(ReceiveError::UnexpectedResponse);
+            return Err(ReceiveError::ChecksumMismatch);
         }
 
         log::debug!("Received SMP Frame ({} bytes)", data.len());
@@ -273,6 +468,53 @@ where
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         ConfigurableTimeout::set_timeout(&mut self.serial, timeout)
     }
+
+    fn set_mtu(
+        &mut self,
+        mtu: Option<usize>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(mtu) = mtu {
+            check_serial_mtu(mtu)?;
+            self.mtu = mtu;
+        }
+        self.learn_mtu = mtu.is_none();
+        Ok(())
+    }
+
+    fn set_console_handler(
+        &mut self,
+        handler: Option<ConsoleHandler>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.console_handler = handler;
+        self.console_line.clear();
+        Ok(())
+    }
+
+    fn poll_console(&mut self) -> Result<(), ReceiveError> {
+        let num_read = ignore_timeout(self.read_buffer.read_from(&mut self.serial, None).unwrap())?;
+
+        if num_read == Some(0) {
+            return Err(ReceiveError::TransportError(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Serial port unexpectedly returned end-of-file",
+            )));
+        }
+
+        while let Some(&current) = self.read_buffer.try_peek() {
+            // Keep a trailing byte that might turn out to be the start of a chunk
+            if (current == 6 || current == 4) && self.read_buffer.occupied_len() < 2 {
+                break;
+            }
+
+            self.read_buffer.try_pop().unwrap();
+            let next = self.read_buffer.try_peek().copied();
+            if self.handle_unexpected_byte(current, next) {
+                self.read_buffer.try_pop().unwrap();
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// Specifies that the serial transport has a configurable timeout
@@ -287,7 +529,7 @@ pub trait ConfigurableTimeout {
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
 }
 
-impl<T: AsMut<dyn SerialPort> + ?Sized> ConfigurableTimeout for T {
+impl ConfigurableTimeout for Box<dyn SerialPort> {
     fn set_timeout(
         &mut self,
         timeout: Duration,
@@ -295,3 +537,48 @@ impl<T: AsMut<dyn SerialPort> + ?Sized> ConfigurableTimeout for T {
         SerialPort::set_timeout(self.as_mut(), timeout).map_err(Into::into)
     }
 }
+
+#[cfg(unix)]
+impl ConfigurableTimeout for serialport::TTYPort {
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        SerialPort::set_timeout(self, timeout).map_err(Into::into)
+    }
+}
+
+#[cfg(windows)]
+impl ConfigurableTimeout for serialport::COMPort {
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        SerialPort::set_timeout(self, timeout).map_err(Into::into)
+    }
+}
+
+/// For serial bridges like [ser2net](https://github.com/cminyard/ser2net).
+impl ConfigurableTimeout for std::net::TcpStream {
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.set_read_timeout(Some(timeout))?;
+        self.set_write_timeout(Some(timeout))?;
+        Ok(())
+    }
+}
+
+/// For example Zephyr's `native_sim` UART exposed as a socket.
+#[cfg(unix)]
+impl ConfigurableTimeout for std::os::unix::net::UnixStream {
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.set_read_timeout(Some(timeout))?;
+        self.set_write_timeout(Some(timeout))?;
+        Ok(())
+    }
+}
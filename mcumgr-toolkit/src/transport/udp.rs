@@ -0,0 +1,127 @@
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    time::Duration,
+};
+
+use super::{ReceiveError, SMP_HEADER_SIZE, SMP_TRANSFER_BUFFER_SIZE, SendError, Transport};
+
+/// The default port of Zephyr's UDP transport.
+///
+/// See [`MCUMGR_TRANSPORT_UDP_PORT`](https://github.com/zephyrproject-rtos/zephyr/blob/v4.2.1/subsys/mgmt/mcumgr/transport/Kconfig.udp).
+pub const ZEPHYR_DEFAULT_UDP_PORT: u16 = 1337;
+
+/// A transport layer implementation for UDP sockets.
+///
+/// Every SMP frame is sent as a single datagram, matching
+/// Zephyr's [UDP transport](https://docs.zephyrproject.org/latest/services/device_mgmt/smp_transport.html#udp-ip).
+pub struct UdpTransport {
+    socket: UdpSocket,
+    send_buffer: Box<[u8]>,
+}
+
+impl UdpTransport {
+    /// Create a new [`UdpTransport`] that is connected to the given device address.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the device, for example `192.168.1.42:1337` or `[fe80::1]:1337`.
+    /// * `timeout` - The communication timeout.
+    ///
+    pub fn new(addr: impl ToSocketAddrs, timeout: Duration) -> io::Result<Self> {
+        let mut last_error = None;
+
+        for addr in addr.to_socket_addrs()? {
+            let local_addr: SocketAddr = match addr {
+                SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+                SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+            };
+
+            let socket = match UdpSocket::bind(local_addr).and_then(|socket| {
+                socket.connect(addr)?;
+                Ok(socket)
+            }) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    log::debug!("Unable to connect to {addr}: {e}");
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+
+            socket.set_read_timeout(Some(timeout))?;
+
+            return Ok(Self::from_socket(socket));
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Could not resolve to any address",
+            )
+        }))
+    }
+
+    /// Create a new [`UdpTransport`] from an already connected socket.
+    ///
+    /// The socket must be [connected](UdpSocket::connect) to the device
+    /// and should have a read timeout configured.
+    pub fn from_socket(socket: UdpSocket) -> Self {
+        Self {
+            socket,
+            send_buffer: vec![0u8; SMP_TRANSFER_BUFFER_SIZE].into_boxed_slice(),
+        }
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send_raw_frame(
+        &mut self,
+        header: [u8; SMP_HEADER_SIZE],
+        data: &[u8],
+    ) -> Result<(), SendError> {
+        log::debug!("Sending SMP Frame ({} bytes)", data.len());
+
+        let frame_size = header.len() + data.len();
+        let frame = self
+            .send_buffer
+            .get_mut(..frame_size)
+            .ok_or(SendError::DataTooBig)?;
+
+        let (frame_header, frame_data) = frame.split_at_mut(header.len());
+        frame_header.copy_from_slice(&header);
+        frame_data.copy_from_slice(data);
+
+        let sent = self.socket.send(frame)?;
+        if sent != frame_size {
+            return Err(SendError::DataTooBig);
+        }
+
+        Ok(())
+    }
+
+    fn recv_raw_frame<'a>(
+        &mut self,
+        buffer: &'a mut [u8; SMP_TRANSFER_BUFFER_SIZE],
+    ) -> Result<&'a [u8], ReceiveError> {
+        let len = self.socket.recv(buffer)?;
+
+        // A datagram that fills the whole buffer might have been truncated
+        if len >= buffer.len() {
+            return Err(ReceiveError::FrameTooBig);
+        }
+
+        log::debug!("Received SMP Frame ({} bytes)", len);
+
+        Ok(&buffer[..len])
+    }
+
+    fn set_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.socket
+            .set_read_timeout(Some(timeout))
+            .map_err(Into::into)
+    }
+}
@@ -0,0 +1,107 @@
+mod common;
+use common::FakeUdpDevice;
+
+use std::{
+    net::{TcpListener, UdpSocket},
+    time::Duration,
+};
+
+use mcumgr_toolkit::{
+    AsyncMCUmgrClient,
+    client::MCUmgrClientError,
+    connection::ExecuteError,
+    transport::{ReceiveError, SendError},
+};
+
+#[tokio::test]
+async fn echo_serial() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream(stream, 2);
+    });
+
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let client = AsyncMCUmgrClient::new_from_serial(stream, Duration::from_secs(5));
+
+    assert_eq!(
+        client.os_echo("Hello world!").await.unwrap(),
+        "Hello world!"
+    );
+
+    let long_message = "a".repeat(1000);
+    assert_eq!(client.os_echo(&long_message).await.unwrap(), long_message);
+
+    device.join().unwrap();
+}
+
+#[tokio::test]
+async fn serial_timeout() {
+    let (stream, _device) = tokio::io::duplex(4096);
+    let client = AsyncMCUmgrClient::new_from_serial(stream, Duration::from_millis(100));
+
+    let err = client.os_echo("Hello world!").await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MCUmgrClientError::ExecuteError(ExecuteError::ReceiveFailed(ReceiveError::TransportError(ref e)))
+                if e.kind() == std::io::ErrorKind::TimedOut
+        ),
+        "{err:?}"
+    );
+}
+
+#[tokio::test]
+async fn serial_closed_by_device() {
+    let (stream, device) = tokio::io::duplex(4096);
+    drop(device);
+    let client = AsyncMCUmgrClient::new_from_serial(stream, Duration::from_secs(5));
+
+    let err = client.os_echo("Hello world!").await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MCUmgrClientError::ExecuteError(ExecuteError::SendFailed(SendError::TransportError(_)))
+        ),
+        "{err:?}"
+    );
+}
+
+#[tokio::test]
+async fn echo_udp() {
+    let device = FakeUdpDevice::spawn(|request| {
+        let message = request.field("d").unwrap().clone();
+        Some(ciborium::Value::Map(vec![("r".into(), message)]))
+    });
+
+    let client = AsyncMCUmgrClient::new_from_udp(device.addr(), Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(
+        client.os_echo("Hello world!").await.unwrap(),
+        "Hello world!"
+    );
+
+    assert_eq!(device.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn udp_timeout() {
+    let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = device.local_addr().unwrap();
+
+    let client = AsyncMCUmgrClient::new_from_udp(addr, Duration::from_millis(100))
+        .await
+        .unwrap();
+
+    let err = client.os_echo("Hello world!").await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MCUmgrClientError::ExecuteError(ExecuteError::ReceiveFailed(ReceiveError::TransportError(ref e)))
+                if e.kind() == std::io::ErrorKind::TimedOut
+        ),
+        "{err:?}"
+    );
+}
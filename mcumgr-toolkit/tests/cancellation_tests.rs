@@ -0,0 +1,243 @@
+mod common;
+use common::{EchoSerial, FakeUdpDevice};
+
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+use ciborium::Value;
+use mcumgr_toolkit::{
+    AsyncMCUmgrClient, MCUmgrClient,
+    client::{FirmwareUpdateError, FirmwareUpdateParams, MCUmgrClientError},
+    connection::CancellationToken,
+    transport::{
+        SmpHeader,
+        codec::{SerialDecoder, SerialItem, SmpFrame, encode_serial_frame},
+    },
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const CANCEL_DELAY: Duration = Duration::from_millis(200);
+
+/// The pause in the middle of the response of [`serve_slow_download`].
+const RESPONSE_PAUSE: Duration = Duration::from_millis(250);
+const SLOW_TIMEOUT: Duration = Duration::from_secs(2);
+const SLOW_FILE_SIZE: usize = 600;
+
+/// Spawns a fake device that accepts file uploads, but never answers file downloads.
+fn spawn_device() -> FakeUdpDevice {
+    FakeUdpDevice::spawn(|request| match request.command() {
+        // File upload/download
+        (8, 0) => match (request.field("off"), request.field("data")) {
+            (Some(Value::Integer(off)), Some(Value::Bytes(data))) => {
+                let end = u64::try_from(*off).unwrap() + data.len() as u64;
+                Some(Value::Map(vec![("off".into(), end.into())]))
+            }
+            _ => None,
+        },
+        // File close
+        (8, 4) => Some(Value::Map(vec![])),
+        (group_id, command_id) => panic!("Unexpected command ({group_id}, {command_id})"),
+    })
+}
+
+/// Answers a file download over a serial stream, pausing in the middle of the response.
+fn serve_slow_download(mut stream: TcpStream) {
+    let mut decoder = SerialDecoder::new();
+    let request = loop {
+        let mut buffer = [0u8; 256];
+        let len = stream.read(&mut buffer).unwrap();
+        assert_ne!(len, 0);
+        if let Some(SerialItem::Frame(frame)) = decoder.decode(&buffer[..len]).pop() {
+            break frame;
+        }
+    };
+    assert_eq!((request.header.group_id, request.header.command_id), (8, 0));
+
+    let response = Value::Map(vec![
+        ("off".into(), 0.into()),
+        ("data".into(), Value::Bytes(vec![0x42; SLOW_FILE_SIZE])),
+        ("len".into(), (SLOW_FILE_SIZE as u64).into()),
+    ]);
+    let mut payload = vec![];
+    ciborium::into_writer(&response, &mut payload).unwrap();
+
+    let response = SmpFrame {
+        header: SmpHeader {
+            op: request.header.op | 1,
+            data_length: payload.len() as u16,
+            ..request.header
+        },
+        payload,
+    };
+
+    let encoded = encode_serial_frame(&response.to_bytes(), 127).unwrap();
+    let (first_half, second_half) = encoded.split_at(encoded.len() / 2);
+    stream.write_all(first_half).unwrap();
+    std::thread::sleep(RESPONSE_PAUSE);
+    stream.write_all(second_half).unwrap();
+}
+
+fn spawn_slow_device() -> (std::thread::JoinHandle<()>, SocketAddr) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let device = std::thread::spawn(move || serve_slow_download(listener.accept().unwrap().0));
+
+    (device, addr)
+}
+
+fn cancel_later(token: &CancellationToken) {
+    let token = token.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(CANCEL_DELAY);
+        token.cancel();
+    });
+}
+
+#[test]
+fn cancel_pending_receive() {
+    let device = spawn_device();
+    let client = MCUmgrClient::new_from_udp(device.addr(), TIMEOUT).unwrap();
+
+    let cancel = CancellationToken::new();
+    cancel_later(&cancel);
+
+    let start = Instant::now();
+    let mut data = vec![];
+    let result = client.fs_file_download("/file", &mut data, None, Some(&cancel));
+
+    assert!(
+        matches!(result, Err(MCUmgrClientError::Cancelled)),
+        "{result:?}"
+    );
+    assert!(start.elapsed() < TIMEOUT / 2, "{:?}", start.elapsed());
+
+    // The file got closed, and the cancelled receive is not counted as timeout
+    assert_eq!(device.commands(), [(8, 0), (8, 4)]);
+    assert_eq!(client.stats().timeouts, 0);
+}
+
+#[test]
+fn cancellable_receive_of_paused_response() {
+    let (device, addr) = spawn_slow_device();
+    let client = MCUmgrClient::new_from_tcp(addr, SLOW_TIMEOUT).unwrap();
+
+    // Waiting in slices must not lose the first half of the response
+    let cancel = CancellationToken::new();
+    let mut data = vec![];
+    client
+        .fs_file_download("/file", &mut data, None, Some(&cancel))
+        .unwrap();
+    assert_eq!(data, [0x42; SLOW_FILE_SIZE]);
+
+    device.join().unwrap();
+}
+
+#[test]
+fn cancel_between_chunks() {
+    let device = spawn_device();
+    let client = MCUmgrClient::new_from_udp(device.addr(), TIMEOUT).unwrap();
+
+    let cancel = CancellationToken::new();
+    let mut chunks = 0;
+    let mut progress = |current, _total| {
+        if current > 0 {
+            chunks += 1;
+            if chunks == 2 {
+                cancel.cancel();
+            }
+        }
+        true
+    };
+
+    let file = vec![0x42; 2000];
+    let result = client.fs_file_upload(
+        "/file",
+        file.as_slice(),
+        file.len() as u64,
+        Some(&mut progress),
+        Some(&cancel),
+    );
+
+    assert!(
+        matches!(result, Err(MCUmgrClientError::Cancelled)),
+        "{result:?}"
+    );
+    assert_eq!(device.commands(), [(8, 0), (8, 0), (8, 4)]);
+}
+
+#[test]
+fn cancelled_token_sends_nothing() {
+    let client = MCUmgrClient::new_from_serial(EchoSerial::default());
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    assert!(cancel.is_cancelled());
+
+    let result = client.image_upload([0; 1000], None, None, false, None, Some(&cancel));
+    assert!(
+        matches!(result, Err(MCUmgrClientError::Cancelled)),
+        "{result:?}"
+    );
+    assert_eq!(client.stats().frames_sent, 0);
+
+    let result = client.firmware_update(
+        [0; 1000],
+        None,
+        FirmwareUpdateParams::default(),
+        None,
+        Some(&cancel),
+    );
+    assert!(
+        matches!(result, Err(FirmwareUpdateError::Cancelled)),
+        "{result:?}"
+    );
+    assert_eq!(client.stats().frames_sent, 0);
+
+    // Other commands are not affected
+    assert_eq!(client.os_echo("Hello").unwrap(), "Hello");
+}
+
+#[tokio::test]
+async fn cancel_pending_receive_async() {
+    let device = spawn_device();
+    let client = AsyncMCUmgrClient::new_from_udp(device.addr(), TIMEOUT)
+        .await
+        .unwrap();
+
+    let cancel = CancellationToken::new();
+    cancel_later(&cancel);
+
+    let start = Instant::now();
+    let mut data = vec![];
+    let result = client
+        .fs_file_download("/file", &mut data, None, Some(&cancel))
+        .await;
+
+    assert!(
+        matches!(result, Err(MCUmgrClientError::Cancelled)),
+        "{result:?}"
+    );
+    assert!(start.elapsed() < TIMEOUT / 2, "{:?}", start.elapsed());
+    assert_eq!(device.commands(), [(8, 0), (8, 4)]);
+}
+
+#[tokio::test]
+async fn cancellable_receive_of_paused_response_async() {
+    let (device, addr) = spawn_slow_device();
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let client = AsyncMCUmgrClient::new_from_serial(stream, SLOW_TIMEOUT);
+
+    let cancel = CancellationToken::new();
+    let mut data = vec![];
+    client
+        .fs_file_download("/file", &mut data, None, Some(&cancel))
+        .await
+        .unwrap();
+    assert_eq!(data, [0x42; SLOW_FILE_SIZE]);
+
+    device.join().unwrap();
+}
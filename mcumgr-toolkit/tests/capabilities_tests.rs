@@ -0,0 +1,157 @@
+mod common;
+use common::FakeUdpDevice;
+
+use std::time::Duration;
+
+use ciborium::{Value, cbor};
+use mcumgr_toolkit::{
+    AsyncMCUmgrClient, MCUmgrClient, MCUmgrGroup,
+    client::{DeviceCapabilities, GroupCapabilities},
+};
+
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// `MGMT_ERR_ENOTSUP`
+const ENOTSUP: i32 = 8;
+
+/// How much of the enumeration group a fake device implements.
+#[derive(Clone, Copy)]
+enum Enumeration {
+    Full,
+    ListOnly,
+    Missing,
+}
+
+/// Spawns a fake device that supports the OS and the file system group.
+fn spawn_device(enumeration: Enumeration) -> FakeUdpDevice {
+    FakeUdpDevice::spawn(move |request| Some(respond(enumeration, request.command())))
+}
+
+fn respond(enumeration: Enumeration, command: (u16, u8)) -> Value {
+    let not_supported = cbor!({ "rc" => ENOTSUP }).unwrap();
+
+    match command {
+        (0, 0) => cbor!({ "r" => "" }).unwrap(),
+        // File not found
+        (8, 1) => cbor!({ "err" => { "group" => 8, "rc" => 2 } }).unwrap(),
+        (10, 1) => match enumeration {
+            Enumeration::Missing => not_supported,
+            _ => cbor!({ "groups" => [8, 0, 10] }).unwrap(),
+        },
+        (10, 3) => match enumeration {
+            Enumeration::Full => cbor!({ "groups" => [
+                { "group" => 0, "name" => "os", "handlers" => 9 },
+                { "group" => 8, "name" => "fs", "handlers" => 5 },
+                { "group" => 10, "name" => "enum", "handlers" => 4 },
+            ]})
+            .unwrap(),
+            _ => not_supported,
+        },
+        _ => not_supported,
+    }
+}
+
+fn group(group_id: u16, name: Option<&str>, handlers: Option<u32>) -> GroupCapabilities {
+    GroupCapabilities {
+        group_id,
+        name: name.map(ToString::to_string),
+        handlers,
+    }
+}
+
+#[test]
+fn capabilities_from_enumeration() {
+    let device = spawn_device(Enumeration::Full);
+    let client = MCUmgrClient::new_from_udp(device.addr(), TIMEOUT).unwrap();
+
+    assert_eq!(
+        client.capabilities().unwrap(),
+        DeviceCapabilities {
+            probed: false,
+            groups: vec![
+                group(0, Some("os"), Some(9)),
+                group(8, Some("fs"), Some(5)),
+                group(10, Some("enum"), Some(4)),
+            ],
+        }
+    );
+}
+
+#[test]
+fn capabilities_without_details() {
+    let device = spawn_device(Enumeration::ListOnly);
+    let client = MCUmgrClient::new_from_udp(device.addr(), TIMEOUT).unwrap();
+
+    let capabilities = client.capabilities().unwrap();
+    assert_eq!(
+        capabilities,
+        DeviceCapabilities {
+            probed: false,
+            groups: vec![
+                group(0, None, None),
+                group(8, None, None),
+                group(10, None, None)
+            ],
+        }
+    );
+    assert!(capabilities.supports(MCUmgrGroup::MGMT_GROUP_ID_FS));
+    assert!(!capabilities.supports(MCUmgrGroup::MGMT_GROUP_ID_IMAGE));
+}
+
+#[test]
+fn capabilities_from_probing() {
+    let device = spawn_device(Enumeration::Missing);
+    let client = MCUmgrClient::new_from_udp(device.addr(), TIMEOUT).unwrap();
+
+    assert_eq!(
+        client.capabilities().unwrap(),
+        DeviceCapabilities {
+            probed: true,
+            groups: vec![group(0, None, None), group(8, None, None)],
+        }
+    );
+
+    let requests = device
+        .requests()
+        .iter()
+        .map(|request| {
+            (
+                request.header.op,
+                request.header.group_id,
+                request.header.command_id,
+            )
+        })
+        .collect::<Vec<_>>();
+    // Every group is only probed until it is found
+    assert_eq!(
+        requests.iter().filter(|(_, group, _)| *group == 0).count(),
+        1
+    );
+    assert_eq!(
+        requests.iter().filter(|(_, group, _)| *group == 8).count(),
+        1
+    );
+    // Erasing the storage is never used as probe
+    assert!(requests.iter().all(|(_, group, _)| *group != 63));
+    // The only write is the empty shell command line
+    assert!(
+        requests
+            .iter()
+            .filter(|(op, _, _)| *op == 2)
+            .all(|(_, group, command)| (*group, *command) == (9, 0))
+    );
+}
+
+#[tokio::test]
+async fn capabilities_async() {
+    let device = spawn_device(Enumeration::Missing);
+    let client = AsyncMCUmgrClient::new_from_udp(device.addr(), TIMEOUT)
+        .await
+        .unwrap();
+
+    let capabilities = client.capabilities().await.unwrap();
+    assert!(capabilities.probed);
+    assert!(capabilities.supports(MCUmgrGroup::MGMT_GROUP_ID_OS));
+    assert!(capabilities.supports_group_id(8));
+    assert!(!capabilities.supports(MCUmgrGroup::MGMT_GROUP_ID_SHELL));
+}
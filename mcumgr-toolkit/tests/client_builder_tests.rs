@@ -0,0 +1,224 @@
+mod common;
+
+use std::{net::TcpListener, time::Duration};
+
+use mcumgr_toolkit::{
+    client::{ClientBuilder, ClientBuilderError, ConnectionUrl, ConnectionUrlError},
+    connection::RetryPolicy,
+    transport::recording::Recording,
+};
+use serialport::{DataBits, Parity};
+
+#[test]
+fn parse_connection_urls() {
+    let cases = [
+        (
+            "serial:///dev/ttyUSB0?baud=115200",
+            ConnectionUrl::Serial {
+                port: "/dev/ttyUSB0".to_string(),
+                baud_rate: Some(115200),
+            },
+        ),
+        (
+            "serial://COM3",
+            ConnectionUrl::Serial {
+                port: "COM3".to_string(),
+                baud_rate: None,
+            },
+        ),
+        (
+            "usb://2fe3:0100:1",
+            ConnectionUrl::UsbSerial {
+                identifier: "2fe3:0100:1".to_string(),
+                baud_rate: None,
+            },
+        ),
+        (
+            "rfc2217://192.168.1.42:4000?baud=9600",
+            ConnectionUrl::Rfc2217 {
+                addr: "192.168.1.42:4000".to_string(),
+                baud_rate: Some(9600),
+                data_bits: None,
+                parity: None,
+                dtr: None,
+                rts: None,
+            },
+        ),
+        (
+            "rfc2217://host:4000?data_bits=7&parity=even&dtr=off&rts=on",
+            ConnectionUrl::Rfc2217 {
+                addr: "host:4000".to_string(),
+                baud_rate: None,
+                data_bits: Some(DataBits::Seven),
+                parity: Some(Parity::Even),
+                dtr: Some(false),
+                rts: Some(true),
+            },
+        ),
+        (
+            "udp://[fe80::1]:1337",
+            ConnectionUrl::Udp {
+                addr: "[fe80::1]:1337".to_string(),
+            },
+        ),
+        (
+            "tcp://localhost:4000",
+            ConnectionUrl::Tcp {
+                addr: "localhost:4000".to_string(),
+            },
+        ),
+        (
+            "unix:///tmp/uart.sock",
+            ConnectionUrl::Unix {
+                path: "/tmp/uart.sock".into(),
+            },
+        ),
+        (
+            "replay://session.jsonl",
+            ConnectionUrl::Replay {
+                path: "session.jsonl".into(),
+            },
+        ),
+    ];
+
+    for (s, expected) in cases {
+        let url = s.parse::<ConnectionUrl>().unwrap();
+        assert_eq!(url, expected);
+        assert_eq!(url.to_string(), s);
+    }
+}
+
+#[test]
+fn parse_invalid_connection_urls() {
+    assert!(matches!(
+        "/dev/ttyUSB0".parse::<ConnectionUrl>(),
+        Err(ConnectionUrlError::MissingScheme(_))
+    ));
+    assert!(matches!(
+        "ble://device".parse::<ConnectionUrl>(),
+        Err(ConnectionUrlError::UnknownScheme(scheme)) if scheme == "ble"
+    ));
+    assert!(matches!(
+        "serial://".parse::<ConnectionUrl>(),
+        Err(ConnectionUrlError::MissingTarget)
+    ));
+    assert!(matches!(
+        "serial://COM3?baud=fast".parse::<ConnectionUrl>(),
+        Err(ConnectionUrlError::InvalidBaudRate(_))
+    ));
+    assert!(matches!(
+        "udp://127.0.0.1:1337?baud=115200".parse::<ConnectionUrl>(),
+        Err(ConnectionUrlError::UnknownParameter(param)) if param == "baud=115200"
+    ));
+    assert!(matches!(
+        "serial://COM3?parity=even".parse::<ConnectionUrl>(),
+        Err(ConnectionUrlError::UnknownParameter(param)) if param == "parity=even"
+    ));
+    assert!(matches!(
+        "rfc2217://host:4000?data_bits=9".parse::<ConnectionUrl>(),
+        Err(ConnectionUrlError::InvalidParameter(param)) if param == "data_bits=9"
+    ));
+    assert!(matches!(
+        "rfc2217://host:4000?dtr=1".parse::<ConnectionUrl>(),
+        Err(ConnectionUrlError::InvalidParameter(param)) if param == "dtr=1"
+    ));
+}
+
+#[test]
+fn serial_name_selects_rfc2217() {
+    assert_eq!(
+        ConnectionUrl::serial("rfc2217://host:4000?parity=odd").unwrap(),
+        ConnectionUrl::Rfc2217 {
+            addr: "host:4000".to_string(),
+            baud_rate: None,
+            data_bits: None,
+            parity: Some(Parity::Odd),
+            dtr: None,
+            rts: None,
+        }
+    );
+    assert_eq!(
+        ConnectionUrl::serial("COM3").unwrap(),
+        ConnectionUrl::Serial {
+            port: "COM3".to_string(),
+            baud_rate: None,
+        }
+    );
+}
+
+#[test]
+fn build_tcp_client() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream(stream, 1);
+    });
+
+    let client = ClientBuilder::from_url(&format!("tcp://{addr}"))
+        .unwrap()
+        .timeout(Duration::from_secs(5))
+        .frame_size(1024)
+        .retry_policy(RetryPolicy {
+            max_retries: 2,
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+
+    assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+
+    device.join().unwrap();
+}
+
+#[test]
+fn build_records_and_replays() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream(stream, 1);
+    });
+
+    let dir = std::env::temp_dir().join(format!(
+        "mcumgr-toolkit-builder-test-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("session.jsonl");
+
+    let client = ClientBuilder::from_url(&format!("tcp://{addr}"))
+        .unwrap()
+        .timeout(Duration::from_secs(5))
+        .record(Recording::create(&path).unwrap())
+        .build()
+        .unwrap();
+    assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+    device.join().unwrap();
+
+    let client = ClientBuilder::new(ConnectionUrl::Replay { path })
+        .build()
+        .unwrap();
+    assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+}
+
+#[test]
+fn build_fails_if_unreachable() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let err = ClientBuilder::new(ConnectionUrl::Tcp {
+        addr: addr.to_string(),
+    })
+    .build()
+    .err()
+    .unwrap();
+
+    assert!(
+        matches!(&err, ClientBuilderError::ConnectFailed(url, _) if *url == format!("tcp://{addr}")),
+        "{err:?}"
+    );
+}
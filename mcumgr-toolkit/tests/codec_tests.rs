@@ -0,0 +1,237 @@
+use std::{
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use ciborium::cbor;
+use mcumgr_toolkit::{
+    smp_errors::DeviceError,
+    transport::{
+        SendError, SmpHeader, SmpVersion, Transport,
+        codec::{DecodeError, SerialItem, SmpFrame, decode_serial_capture, encode_serial_frame},
+        serial::{
+            ConfigurableTimeout, SERIAL_TRANSPORT_MIN_MTU, SerialMtuTooSmall, SerialTransport,
+        },
+    },
+};
+
+/// A serial port that records everything written to it.
+#[derive(Clone, Default)]
+struct CaptureSerial(Arc<Mutex<Vec<u8>>>);
+
+impl Read for CaptureSerial {
+    fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::TimedOut.into())
+    }
+}
+
+impl Write for CaptureSerial {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ConfigurableTimeout for CaptureSerial {
+    fn set_timeout(&mut self, _: Duration) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+fn frame(op: u8, group_id: u16, command_id: u8, body: ciborium::Value) -> SmpFrame {
+    let mut payload = vec![];
+    ciborium::into_writer(&body, &mut payload).unwrap();
+
+    SmpFrame {
+        header: SmpHeader {
+            ver: 1,
+            op,
+            flags: 0,
+            data_length: payload.len() as u16,
+            group_id,
+            sequence_num: 42,
+            command_id,
+        },
+        payload,
+    }
+}
+
+#[test]
+fn encoding_matches_serial_transport() {
+    let serial = CaptureSerial::default();
+    let mut transport = SerialTransport::new(serial.clone());
+
+    let data = [0x5a; 300];
+    transport
+        .send_frame(SmpVersion::V2, true, 7, 8, 0, &data)
+        .unwrap();
+
+    let header = SmpHeader {
+        ver: 1,
+        op: 2,
+        flags: 0,
+        data_length: data.len() as u16,
+        group_id: 8,
+        sequence_num: 7,
+        command_id: 0,
+    };
+    let mut frame = header.to_bytes().to_vec();
+    frame.extend_from_slice(&data);
+
+    assert_eq!(
+        *serial.0.lock().unwrap(),
+        encode_serial_frame(&frame, 127).unwrap()
+    );
+}
+
+#[test]
+fn decode_conversation() {
+    let request = frame(0, 0, 0, cbor!({ "d" => "Hello" }).unwrap());
+    let response = frame(1, 0, 0, cbor!({ "r" => "Hello" }).unwrap());
+
+    let mut capture = b"uart:~$ \r\n".to_vec();
+    capture.extend(encode_serial_frame(&request.to_bytes(), 127).unwrap());
+    capture.extend(b"shell output\r\n");
+    capture.extend(encode_serial_frame(&response.to_bytes(), 20).unwrap());
+    capture.extend(b"prompt");
+
+    let items = decode_serial_capture(&capture);
+    assert_eq!(items.len(), 5, "{items:?}");
+
+    assert!(matches!(&items[0], SerialItem::Console(line) if line == b"uart:~$ "));
+    assert!(matches!(&items[1], SerialItem::Frame(frame) if *frame == request));
+    assert!(matches!(&items[2], SerialItem::Console(line) if line == b"shell output"));
+    assert!(matches!(&items[3], SerialItem::Frame(frame) if *frame == response));
+    assert!(matches!(&items[4], SerialItem::Console(line) if line == b"prompt"));
+
+    let SerialItem::Frame(frame) = &items[3] else {
+        unreachable!()
+    };
+    assert!(frame.header.is_response());
+    assert!(!frame.header.is_write_operation());
+    assert_eq!(frame.header.version(), SmpVersion::V2);
+    assert_eq!(frame.group_name(), "MGMT_GROUP_ID_OS");
+    assert_eq!(frame.command_name(), Some("OS_MGMT_ID_ECHO"));
+    assert_eq!(frame.body().unwrap(), cbor!({ "r" => "Hello" }).unwrap());
+    assert_eq!(frame.device_error(), None);
+}
+
+#[test]
+fn decode_device_errors() {
+    let v1 = frame(3, 8, 0, cbor!({ "rc" => 5, "rsn" => "oops" }).unwrap());
+    let v2 = frame(
+        3,
+        8,
+        0,
+        cbor!({ "err" => { "group" => 8, "rc" => 2 } }).unwrap(),
+    );
+    let request = frame(2, 8, 0, cbor!({ "rc" => 5 }).unwrap());
+
+    assert_eq!(
+        v1.device_error(),
+        Some(DeviceError::V1 {
+            rc: 5,
+            rsn: Some("oops".to_string())
+        })
+    );
+    assert_eq!(v2.device_error(), Some(DeviceError::V2 { group: 8, rc: 2 }));
+    assert_eq!(request.device_error(), None);
+}
+
+#[test]
+fn decode_corrupted_frames() {
+    let frame = frame(0, 0, 0, cbor!({ "d" => "Hello world" }).unwrap()).to_bytes();
+    let encoded = encode_serial_frame(&frame, 20).unwrap();
+    let lines = encoded.split_inclusive(|&b| b == b'\n').collect::<Vec<_>>();
+    assert!(lines.len() > 2);
+
+    // Missing chunk, followed by a new frame
+    let mut capture = lines[0].to_vec();
+    capture.extend(&encoded);
+    // Continuation without start
+    capture.extend(lines[1]);
+    // Corrupted data
+    let mut corrupted = encoded.clone();
+    let pos = lines[0].len() + 3;
+    corrupted[pos] = if corrupted[pos] == b'A' { b'B' } else { b'A' };
+    capture.extend(&corrupted);
+    // Truncated at the end
+    capture.extend(lines[0]);
+
+    let items = decode_serial_capture(&capture);
+    assert!(
+        matches!(
+            items.as_slice(),
+            [
+                SerialItem::Invalid(DecodeError::Incomplete),
+                SerialItem::Frame(_),
+                SerialItem::Invalid(DecodeError::UnexpectedContinuation),
+                SerialItem::Invalid(DecodeError::ChecksumMismatch),
+                SerialItem::Invalid(DecodeError::Incomplete),
+            ]
+        ),
+        "{items:?}"
+    );
+}
+
+#[test]
+fn decode_chunks_after_console_output() {
+    let request = frame(0, 0, 0, cbor!({ "d" => "Hello" }).unwrap());
+    let response = frame(1, 0, 0, cbor!({ "r" => "Hello" }).unwrap());
+    let encoded_response = encode_serial_frame(&response.to_bytes(), 127).unwrap();
+
+    // Console output without line ending in front of a chunk
+    let mut capture = b"uart:~$ ".to_vec();
+    capture.extend(encode_serial_frame(&request.to_bytes(), 127).unwrap());
+    // A chunk that got interrupted by a new frame
+    capture.extend(&encoded_response[..10]);
+    capture.extend(&encoded_response);
+
+    let items = decode_serial_capture(&capture);
+    assert!(
+        matches!(
+            items.as_slice(),
+            [
+                SerialItem::Console(line),
+                SerialItem::Frame(first),
+                SerialItem::Frame(second),
+            ] if line == b"uart:~$ " && *first == request && *second == response
+        ),
+        "{items:?}"
+    );
+}
+
+#[test]
+fn encode_invalid_frames() {
+    let frame = frame(0, 0, 0, cbor!({ "d" => "Hello" }).unwrap()).to_bytes();
+
+    assert!(matches!(
+        encode_serial_frame(&frame, SERIAL_TRANSPORT_MIN_MTU - 1),
+        Err(SendError::MtuTooSmall(SerialMtuTooSmall(6)))
+    ));
+    assert!(encode_serial_frame(&frame, SERIAL_TRANSPORT_MIN_MTU).is_ok());
+    assert!(matches!(
+        encode_serial_frame(&[0; u16::MAX as usize - 1], 127),
+        Err(SendError::DataTooBig)
+    ));
+}
+
+#[test]
+fn parse_raw_frame() {
+    let frame = frame(2, 1, 1, cbor!({ "off" => 0 }).unwrap());
+    let data = frame.to_bytes();
+
+    assert_eq!(SmpFrame::parse(&data).unwrap(), frame);
+    assert!(matches!(
+        SmpFrame::parse(&data[..4]),
+        Err(DecodeError::Truncated)
+    ));
+    assert!(matches!(
+        SmpFrame::parse(&data[..data.len() - 1]),
+        Err(DecodeError::LengthMismatch { .. })
+    ));
+}
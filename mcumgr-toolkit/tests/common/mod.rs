@@ -3,9 +3,19 @@
 use std::{
     collections::VecDeque,
     io::{Read, Write},
+    net::{SocketAddr, UdpSocket},
+    sync::{Arc, Mutex},
 };
 
-use mcumgr_toolkit::transport::serial::ConfigurableTimeout;
+use ciborium::Value;
+use mcumgr_toolkit::{
+    MCUmgrClient,
+    transport::{
+        SMP_HEADER_SIZE, SmpHeader,
+        fault_injection::{FaultInjectingTransport, FaultInjectionConfig, FrameFaults},
+        serial::{ConfigurableTimeout, SerialTransport},
+    },
+};
 
 #[derive(Default)]
 pub(crate) struct LoopbackSerial {
@@ -49,6 +59,7 @@ impl Drop for LoopbackSerial {
 pub(crate) struct EchoSerial {
     input_buffer: VecDeque<u8>,
     output_buffer: VecDeque<u8>,
+    mtu: Option<usize>,
 }
 
 const FRAME_START_1: u8 = 6;
@@ -58,6 +69,17 @@ const FRAME_START_CONT_2: u8 = 20;
 const FRAME_END: u8 = 0x0a;
 
 impl EchoSerial {
+    /// Behaves like a device with the given `MCUMGR_SERIAL_MAX_FRAME`.
+    ///
+    /// Panics on longer request lines and sends response lines of that length.
+    pub(crate) fn with_mtu(mtu: usize) -> Self {
+        Self {
+            input_buffer: VecDeque::new(),
+            output_buffer: VecDeque::new(),
+            mtu: Some(mtu),
+        }
+    }
+
     fn process_input_data(&mut self) {
         let mut data = vec![];
 
@@ -65,12 +87,21 @@ impl EchoSerial {
         assert_eq!(Some(FRAME_START_2), self.input_buffer.pop_front());
 
         loop {
+            let mut line_length = 3;
             loop {
                 let next = self.input_buffer.pop_front().unwrap();
                 if next == FRAME_END {
                     break;
                 }
                 data.push(next);
+                line_length += 1;
+            }
+
+            if let Some(mtu) = self.mtu {
+                assert!(
+                    line_length <= mtu,
+                    "Line of {line_length} bytes exceeds MTU {mtu}"
+                );
             }
 
             if self.input_buffer.is_empty() {
@@ -85,7 +116,8 @@ impl EchoSerial {
 
         self.output_buffer.push_back(FRAME_START_1);
         self.output_buffer.push_back(FRAME_START_2);
-        for chunk in data.chunks(4) {
+        let chunk_size = self.mtu.map_or(4, |mtu| ((mtu - 3) / 4) * 4);
+        for chunk in data.chunks(chunk_size) {
             for elem in chunk {
                 self.output_buffer.push_back(*elem);
             }
@@ -148,6 +180,11 @@ impl Read for EchoSerial {
             self.process_input_data();
         }
 
+        // Like a real serial port, time out if the device has nothing to say
+        if self.output_buffer.is_empty() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+
         self.output_buffer.read(buf)
     }
 }
@@ -181,3 +218,212 @@ impl Drop for EchoSerial {
         }
     }
 }
+
+/// Creates a client that talks to an [`EchoSerial`], with `receive` faults injected into the responses.
+pub(crate) fn faulty_echo_client(receive: FrameFaults, seed: u64) -> MCUmgrClient {
+    MCUmgrClient::new_from_transport(FaultInjectingTransport::new(
+        SerialTransport::new(EchoSerial::default()),
+        FaultInjectionConfig {
+            seed,
+            receive,
+            ..Default::default()
+        },
+    ))
+}
+
+/// Answers `num_requests` SMP serial requests arriving on `stream` through an [`EchoSerial`].
+///
+/// Useful for testing stream sockets like TCP or Unix sockets.
+pub(crate) fn serve_echo_stream<S: Read + Write>(stream: S, num_requests: usize) {
+    serve_echo_stream_with(stream, num_requests, EchoSerial::default);
+}
+
+/// Like [`serve_echo_stream`], but through an [`EchoSerial::with_mtu`].
+///
+/// Returns the length of the longest request line.
+pub(crate) fn serve_echo_stream_with_mtu<S: Read + Write>(
+    stream: S,
+    num_requests: usize,
+    mtu: usize,
+) -> usize {
+    serve_echo_stream_with(stream, num_requests, || EchoSerial::with_mtu(mtu))
+}
+
+fn serve_echo_stream_with<S: Read + Write>(
+    stream: S,
+    num_requests: usize,
+    echo_serial: impl Fn() -> EchoSerial,
+) -> usize {
+    use base64::prelude::*;
+    use std::io::BufRead;
+
+    let mut stream = std::io::BufReader::new(stream);
+    let mut longest_line = 0;
+
+    for _ in 0..num_requests {
+        let mut raw_request = vec![];
+        let mut base64_data = vec![];
+
+        loop {
+            let mut line = vec![];
+            stream.read_until(FRAME_END, &mut line).unwrap();
+            assert_eq!(line.last(), Some(&FRAME_END));
+            longest_line = longest_line.max(line.len());
+
+            base64_data.extend_from_slice(&line[2..line.len() - 1]);
+            raw_request.extend_from_slice(&line);
+
+            let data = BASE64_STANDARD.decode(&base64_data).unwrap_or_default();
+            if let Some((len, data)) = data.split_first_chunk() {
+                if u16::from_be_bytes(*len) as usize == data.len() {
+                    break;
+                }
+            }
+        }
+
+        let mut echo = echo_serial();
+        echo.write_all(&raw_request).unwrap();
+        let mut response = vec![];
+        let mut buffer = [0u8; 256];
+        while let Ok(num_read) = echo.read(&mut buffer) {
+            response.extend_from_slice(&buffer[..num_read]);
+        }
+
+        stream.get_mut().write_all(&response).unwrap();
+    }
+
+    longest_line
+}
+
+/// A request received by a [`FakeUdpDevice`].
+#[derive(Clone, Debug)]
+pub(crate) struct UdpRequest {
+    pub(crate) header: SmpHeader,
+    pub(crate) body: Value,
+    /// The size of the whole frame
+    pub(crate) len: usize,
+}
+
+impl UdpRequest {
+    /// The group and command ID.
+    pub(crate) fn command(&self) -> (u16, u8) {
+        (self.header.group_id, self.header.command_id)
+    }
+
+    /// An entry of the CBOR map in the body.
+    pub(crate) fn field(&self, name: &str) -> Option<&Value> {
+        self.body
+            .as_map()
+            .unwrap()
+            .iter()
+            .find(|(key, _)| key.as_text() == Some(name))
+            .map(|(_, value)| value)
+    }
+}
+
+/// Sends responses to a request of a [`FakeUdpDevice`].
+pub(crate) struct UdpResponder {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    header: SmpHeader,
+}
+
+impl UdpResponder {
+    /// Sends `body` as response to the request.
+    pub(crate) fn send(&self, body: &Value) {
+        self.send_with_header(body, |_| {});
+    }
+
+    /// Sends `body` as response to the request, after applying `modify` to the response header.
+    pub(crate) fn send_with_header(&self, body: &Value, modify: impl FnOnce(&mut SmpHeader)) {
+        let mut payload = vec![];
+        ciborium::into_writer(body, &mut payload).unwrap();
+
+        let mut header = SmpHeader {
+            op: self.header.op | 1,
+            data_length: payload.len() as u16,
+            ..self.header
+        };
+        modify(&mut header);
+
+        let mut frame = header.to_bytes().to_vec();
+        frame.extend_from_slice(&payload);
+        self.socket.send_to(&frame, self.peer).unwrap();
+    }
+}
+
+/// A fake device that answers SMP requests over UDP.
+///
+/// Records all requests.
+pub(crate) struct FakeUdpDevice {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<UdpRequest>>>,
+}
+
+impl FakeUdpDevice {
+    /// Spawns a device on a local port that answers every request with the body returned by `respond`,
+    /// or not at all for `None`.
+    pub(crate) fn spawn(
+        mut respond: impl FnMut(&UdpRequest) -> Option<Value> + Send + 'static,
+    ) -> Self {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        Self::spawn_with(socket, move |request, responder| {
+            if let Some(body) = respond(request) {
+                responder.send(&body);
+            }
+        })
+    }
+
+    /// Spawns a device on `socket` that answers requests through the given [`UdpResponder`].
+    pub(crate) fn spawn_with(
+        socket: UdpSocket,
+        mut handle: impl FnMut(&UdpRequest, UdpResponder) + Send + 'static,
+    ) -> Self {
+        let addr = socket.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        std::thread::spawn({
+            let requests = requests.clone();
+            move || {
+                let mut buffer = [0u8; u16::MAX as usize];
+                loop {
+                    let (len, peer) = socket.recv_from(&mut buffer).unwrap();
+                    let (header, data) = buffer[..len]
+                        .split_first_chunk::<SMP_HEADER_SIZE>()
+                        .unwrap();
+
+                    let request = UdpRequest {
+                        header: SmpHeader::from_bytes(*header),
+                        body: ciborium::from_reader(data).unwrap(),
+                        len,
+                    };
+                    requests.lock().unwrap().push(request.clone());
+
+                    let responder = UdpResponder {
+                        socket: socket.try_clone().unwrap(),
+                        peer,
+                        header: request.header,
+                    };
+                    handle(&request, responder);
+                }
+            }
+        });
+
+        Self { addr, requests }
+    }
+
+    /// The address of the device.
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// All requests received so far.
+    pub(crate) fn requests(&self) -> Vec<UdpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The group and command IDs of all requests received so far.
+    pub(crate) fn commands(&self) -> Vec<(u16, u8)> {
+        self.requests().iter().map(UdpRequest::command).collect()
+    }
+}
@@ -0,0 +1,93 @@
+use ciborium::cbor;
+use mcumgr_toolkit::{
+    MCUmgrGroup,
+    commands::{
+        DeserializeFromEmptyMap, LONG_OPERATION_TIMEOUT, McuMgrCommand, SerializeAsEmptyMap,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, McuMgrCommand)]
+#[mcumgr(write, group = 64, id = 3, response = SetLedResponse)]
+struct SetLed<'a> {
+    name: &'a str,
+    on: bool,
+}
+
+#[derive(Debug, PartialEq, DeserializeFromEmptyMap)]
+struct SetLedResponse;
+
+#[derive(SerializeAsEmptyMap, McuMgrCommand)]
+#[mcumgr(read, group = MCUmgrGroup::MGMT_GROUP_ID_PERUSER, id = 4, response = GetLedsResponse)]
+struct GetLeds;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct GetLedsResponse {
+    leds: Vec<String>,
+}
+
+#[derive(SerializeAsEmptyMap, McuMgrCommand)]
+#[mcumgr(write, group = 65, id = 0, response = SetLedResponse, long_operation)]
+struct Calibrate;
+
+fn encode(value: &impl Serialize) -> Vec<u8> {
+    let mut data = vec![];
+    ciborium::into_writer(value, &mut data).unwrap();
+    data
+}
+
+fn decode<T: for<'a> Deserialize<'a>>(value: ciborium::Value) -> T {
+    ciborium::from_reader(encode(&value).as_slice()).unwrap()
+}
+
+#[test]
+fn command_properties() {
+    let set_led = SetLed {
+        name: "red",
+        on: true,
+    };
+    assert!(set_led.is_write_operation());
+    assert_eq!(set_led.group_id(), 64);
+    assert_eq!(set_led.command_id(), 3);
+    assert_eq!(set_led.min_timeout(), None);
+
+    assert!(!GetLeds.is_write_operation());
+    assert_eq!(GetLeds.group_id(), 64);
+    assert_eq!(GetLeds.command_id(), 4);
+
+    assert_eq!(Calibrate.group_id(), 65);
+    assert_eq!(Calibrate.min_timeout(), Some(LONG_OPERATION_TIMEOUT));
+}
+
+#[test]
+fn payload_encoding() {
+    let set_led = SetLed {
+        name: "red",
+        on: true,
+    };
+    assert_eq!(
+        encode(set_led.data()),
+        encode(&cbor!({"name" => "red", "on" => true}).unwrap())
+    );
+    assert_eq!(encode(GetLeds.data()), encode(&cbor!({}).unwrap()));
+}
+
+#[test]
+fn response_decoding() {
+    let response: <SetLed as McuMgrCommand>::Response = decode(cbor!({}).unwrap());
+    assert_eq!(response, SetLedResponse);
+    let () = response.into();
+
+    // Unknown entries get ignored
+    let response: SetLedResponse = decode(cbor!({"extra" => 5}).unwrap());
+    assert_eq!(response, SetLedResponse);
+
+    let response: <GetLeds as McuMgrCommand>::Response =
+        decode(cbor!({"leds" => ["red", "green"]}).unwrap());
+    assert_eq!(
+        response,
+        GetLedsResponse {
+            leds: vec!["red".to_string(), "green".to_string()]
+        }
+    );
+}
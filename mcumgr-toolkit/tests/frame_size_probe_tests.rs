@@ -0,0 +1,111 @@
+mod common;
+use common::FakeUdpDevice;
+
+use std::time::Duration;
+
+use ciborium::Value;
+use mcumgr_toolkit::{AsyncMCUmgrClient, MCUmgrClient, client::MCUmgrClientError};
+
+const TIMEOUT: Duration = Duration::from_millis(200);
+
+/// `MGMT_ERR_ENOMEM`
+const ENOMEM: i32 = 2;
+
+/// How a fake device reacts to frames that exceed its buffer.
+#[derive(Clone, Copy)]
+enum Overflow {
+    Drop,
+    Enomem,
+}
+
+/// Spawns a fake device that only answers echo requests up to a given frame size.
+fn spawn_device(max_frame_size: usize, overflow: Overflow) -> FakeUdpDevice {
+    FakeUdpDevice::spawn(move |request| {
+        assert_eq!(request.command(), (0, 0), "Unexpected command");
+
+        if request.len <= max_frame_size {
+            let message = request.field("d").unwrap().clone();
+            Some(Value::Map(vec![("r".into(), message)]))
+        } else {
+            match overflow {
+                Overflow::Drop => None,
+                Overflow::Enomem => Some(Value::Map(vec![("rc".into(), ENOMEM.into())])),
+            }
+        }
+    })
+}
+
+fn assert_close_below(probed: usize, limit: usize) {
+    assert!(probed <= limit, "{probed} > {limit}");
+    assert!(
+        probed >= limit - limit / 16,
+        "{probed} too far below {limit}"
+    );
+}
+
+#[test]
+fn probe_with_enomem() {
+    let device = spawn_device(1000, Overflow::Enomem);
+    let client = MCUmgrClient::new_from_udp(device.addr(), TIMEOUT).unwrap();
+
+    let frame_size = client.probe_frame_size(4096).unwrap();
+    assert_close_below(frame_size, 1000);
+
+    // Probes never exceed the given maximum
+    assert!(device.requests().iter().all(|request| request.len <= 4096));
+}
+
+#[test]
+fn probe_with_dropped_frames() {
+    let device = spawn_device(700, Overflow::Drop);
+    let client = MCUmgrClient::new_from_udp(device.addr(), TIMEOUT).unwrap();
+
+    let frame_size = client.probe_frame_size(4096).unwrap();
+    assert_close_below(frame_size, 700);
+
+    // Timeouts are only counted, the client stays usable
+    assert!(client.stats().timeouts > 0);
+    assert_eq!(client.os_echo("Hello").unwrap(), "Hello");
+    assert!(device.requests().len() < 20);
+}
+
+#[test]
+fn probe_below_default() {
+    let device = spawn_device(100, Overflow::Enomem);
+    let client = MCUmgrClient::new_from_udp(device.addr(), TIMEOUT).unwrap();
+
+    let frame_size = client.probe_frame_size(4096).unwrap();
+    assert_close_below(frame_size, 100);
+}
+
+#[test]
+fn probe_limited_by_max_frame_size() {
+    let device = spawn_device(4000, Overflow::Enomem);
+    let client = MCUmgrClient::new_from_udp(device.addr(), TIMEOUT).unwrap();
+
+    assert_eq!(client.probe_frame_size(1500).unwrap(), 1500);
+    assert!(device.requests().iter().all(|request| request.len <= 1500));
+}
+
+#[test]
+fn probe_fails_if_nothing_is_accepted() {
+    let device = spawn_device(0, Overflow::Enomem);
+    let client = MCUmgrClient::new_from_udp(device.addr(), TIMEOUT).unwrap();
+
+    let result = client.probe_frame_size(4096);
+    assert!(
+        matches!(result, Err(MCUmgrClientError::ExecuteError(_))),
+        "{result:?}"
+    );
+}
+
+#[tokio::test]
+async fn probe_async() {
+    let device = spawn_device(1000, Overflow::Drop);
+    let client = AsyncMCUmgrClient::new_from_udp(device.addr(), TIMEOUT)
+        .await
+        .unwrap();
+
+    let frame_size = client.probe_frame_size(4096).await.unwrap();
+    assert_close_below(frame_size, 1000);
+}
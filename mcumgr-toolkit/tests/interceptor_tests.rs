@@ -0,0 +1,176 @@
+mod common;
+use common::EchoSerial;
+
+use std::sync::{Arc, Mutex};
+
+use ciborium::Value;
+use mcumgr_toolkit::{
+    MCUmgrClient,
+    client::MCUmgrClientError,
+    commands::{os::Echo, zephyr::EraseStorage},
+    connection::{
+        CommandInterceptor, Connection, ExecuteError, InterceptError, InterceptedRequest,
+    },
+    transport::serial::SerialTransport,
+};
+
+fn cbor(value: &Value) -> Vec<u8> {
+    let mut data = vec![];
+    ciborium::into_writer(value, &mut data).unwrap();
+    data
+}
+
+fn text_map(key: &str, value: &str) -> Value {
+    Value::Map(vec![(Value::Text(key.into()), Value::Text(value.into()))])
+}
+
+type AuditEntry = (u16, u8, Result<Value, String>);
+
+/// Records every result it observes.
+#[derive(Default, Clone)]
+struct AuditLog(Arc<Mutex<Vec<AuditEntry>>>);
+
+impl CommandInterceptor for AuditLog {
+    fn on_result(&self, request: &InterceptedRequest, result: Result<&Value, &ExecuteError>) {
+        self.0.lock().unwrap().push((
+            request.group_id,
+            request.command_id,
+            result.cloned().map_err(|e| e.to_string()),
+        ));
+    }
+}
+
+struct Block<R>(R);
+
+impl<R: mcumgr_toolkit::commands::McuMgrCommand + Send + Sync> CommandInterceptor for Block<R> {
+    fn on_request(&self, request: &mut InterceptedRequest) -> Result<(), InterceptError> {
+        if request.is(&self.0) {
+            return Err("blocked".into());
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn observe_results() {
+    let client = MCUmgrClient::new_from_serial(EchoSerial::default());
+    let log = AuditLog::default();
+    client.add_interceptor(log.clone());
+
+    assert_eq!(client.os_echo("Hello").unwrap(), "Hello");
+
+    let log = log.0.lock().unwrap();
+    assert_eq!(*log, [(0, 0, Ok(text_map("r", "Hello")))]);
+}
+
+#[test]
+fn reject_request() {
+    let client = MCUmgrClient::new_from_serial(EchoSerial::default());
+    let log = AuditLog::default();
+    client.add_interceptor(Block(EraseStorage));
+    client.add_interceptor(log.clone());
+
+    let err = client.zephyr_erase_storage().unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            MCUmgrClientError::ExecuteError(ExecuteError::Rejected(e)) if e.to_string() == "blocked"
+        ),
+        "{err:?}"
+    );
+    assert_eq!(client.stats().frames_sent, 0);
+
+    // The rejection is reported to all interceptors
+    assert_eq!(
+        *log.0.lock().unwrap(),
+        [(63, 0, Err("Rejected by interceptor".to_string()))]
+    );
+
+    // Other commands pass
+    assert_eq!(client.os_echo("Hello").unwrap(), "Hello");
+
+    client.clear_interceptors();
+    client.zephyr_erase_storage().ok();
+    assert_eq!(client.stats().frames_sent, 2);
+}
+
+#[test]
+fn rewrite_request_and_response() {
+    struct Rewrite;
+
+    impl CommandInterceptor for Rewrite {
+        fn on_request(&self, request: &mut InterceptedRequest) -> Result<(), InterceptError> {
+            request.data = cbor(&text_map("d", "Rewritten request"));
+            Ok(())
+        }
+
+        fn on_response(
+            &self,
+            request: &InterceptedRequest,
+            response: &mut Vec<u8>,
+        ) -> Result<(), InterceptError> {
+            assert!(request.is(&Echo { d: "" }));
+            let value: Value = ciborium::from_reader(response.as_slice())?;
+            assert_eq!(value, text_map("r", "Rewritten request"));
+            *response = cbor(&text_map("r", "Rewritten response"));
+            Ok(())
+        }
+    }
+
+    let client = MCUmgrClient::new_from_serial(EchoSerial::default());
+    let log = AuditLog::default();
+    client.add_interceptor(Rewrite);
+    client.add_interceptor(log.clone());
+
+    assert_eq!(client.os_echo("Hello").unwrap(), "Rewritten response");
+    assert_eq!(
+        *log.0.lock().unwrap(),
+        [(0, 0, Ok(text_map("r", "Rewritten response")))]
+    );
+}
+
+#[test]
+fn reject_response() {
+    struct RejectResponse;
+
+    impl CommandInterceptor for RejectResponse {
+        fn on_response(
+            &self,
+            _request: &InterceptedRequest,
+            _response: &mut Vec<u8>,
+        ) -> Result<(), InterceptError> {
+            Err("unexpected response".into())
+        }
+    }
+
+    let client = MCUmgrClient::new_from_serial(EchoSerial::default());
+    client.add_interceptor(RejectResponse);
+
+    assert!(matches!(
+        client.os_echo("Hello"),
+        Err(MCUmgrClientError::ExecuteError(ExecuteError::Rejected(_)))
+    ));
+}
+
+#[test]
+fn intercept_raw_commands() {
+    let connection = Connection::new(SerialTransport::new(EchoSerial::default()));
+    let log = AuditLog::default();
+    connection.add_interceptor(Arc::new(log.clone()));
+    connection.add_interceptor(Arc::new(Block(EraseStorage)));
+
+    let response = connection
+        .execute_raw_command(false, 0, 0, &cbor(&text_map("d", "Raw")))
+        .unwrap();
+    assert_eq!(*response, *cbor(&text_map("r", "Raw")));
+
+    assert!(matches!(
+        connection.execute_raw_command(true, 63, 0, &cbor(&Value::Map(vec![]))),
+        Err(ExecuteError::Rejected(_))
+    ));
+
+    let log = log.0.lock().unwrap();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0], (0, 0, Ok(text_map("r", "Raw"))));
+    assert!(matches!(log[1], (63, 0, Err(_))));
+}
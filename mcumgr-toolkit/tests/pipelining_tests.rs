@@ -0,0 +1,348 @@
+mod common;
+use common::{FakeUdpDevice, UdpRequest, UdpResponder};
+
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use ciborium::Value;
+use mcumgr_toolkit::{
+    AsyncMCUmgrClient, MCUmgrClient,
+    connection::{CommandInterceptor, ExecuteError, InterceptedRequest, RetryPolicy},
+    transport::{ReceiveError, SMP_HEADER_SIZE, SendError, Transport, udp::UdpTransport},
+};
+
+const LATENCY: Duration = Duration::from_millis(10);
+const DOWNLOAD_CHUNK_SIZE: usize = 128;
+const BUF_COUNT: u32 = 4;
+
+/// A fake device with a single file and a single image slot.
+///
+/// Every response is sent after [`LATENCY`], minus a few milliseconds depending
+/// on the sequence number, so that responses overtake each other.
+#[derive(Default)]
+struct FakeDevice {
+    file: Mutex<Vec<u8>>,
+    image: Mutex<Vec<u8>>,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+impl FakeDevice {
+    fn spawn(file: Vec<u8>) -> (Arc<Self>, SocketAddr) {
+        let device = Arc::new(Self {
+            file: Mutex::new(file),
+            ..Default::default()
+        });
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let udp_device = FakeUdpDevice::spawn_with(socket, {
+            let device = device.clone();
+            move |request, responder| device.respond(request, responder)
+        });
+
+        (device, udp_device.addr())
+    }
+
+    fn respond(self: &Arc<Self>, request: &UdpRequest, responder: UdpResponder) {
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+
+        let response = self.process(request);
+
+        let delay = LATENCY - Duration::from_millis(u64::from(request.header.sequence_num % 3) * 2);
+        let device = self.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(delay);
+            device.in_flight.fetch_sub(1, Ordering::SeqCst);
+            responder.send(&response);
+        });
+    }
+
+    fn process(&self, request: &UdpRequest) -> Value {
+        let field = |name: &str| request.field(name);
+        let offset = || usize::try_from(field("off").unwrap().as_integer().unwrap()).unwrap();
+        let data = || field("data").unwrap().as_bytes().unwrap().as_slice();
+
+        match request.command() {
+            // MCUmgr parameters
+            (0, 6) => Value::Map(vec![
+                ("buf_size".into(), 384.into()),
+                ("buf_count".into(), BUF_COUNT.into()),
+            ]),
+            // Image upload
+            (1, 1) => {
+                let mut image = self.image.lock().unwrap();
+                assert_eq!(image.len(), offset(), "Image chunks out of order");
+                image.extend_from_slice(data());
+                Value::Map(vec![("off".into(), (image.len() as u64).into())])
+            }
+            // File upload/download
+            (8, 0) => {
+                let mut file = self.file.lock().unwrap();
+                if field("data").is_some() {
+                    if offset() == 0 {
+                        file.clear();
+                    }
+                    assert_eq!(file.len(), offset(), "File chunks out of order");
+                    file.extend_from_slice(data());
+                    Value::Map(vec![("off".into(), (file.len() as u64).into())])
+                } else {
+                    let start = offset().min(file.len());
+                    let end = (start + DOWNLOAD_CHUNK_SIZE).min(file.len());
+                    let mut response = vec![
+                        ("off".into(), (start as u64).into()),
+                        ("data".into(), Value::Bytes(file[start..end].to_vec())),
+                    ];
+                    if start == 0 {
+                        response.push(("len".into(), (file.len() as u64).into()));
+                    }
+                    Value::Map(response)
+                }
+            }
+            (group_id, command_id) => panic!("Unexpected command ({group_id}, {command_id})"),
+        }
+    }
+}
+
+fn test_data(size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i * 7 % 251) as u8).collect()
+}
+
+fn download_duration(window_size: usize) -> Duration {
+    let (device, addr) = FakeDevice::spawn(test_data(32 * DOWNLOAD_CHUNK_SIZE));
+    let client = MCUmgrClient::new_from_udp(addr, Duration::from_secs(5)).unwrap();
+    client.set_window_size(window_size);
+
+    let mut data = vec![];
+    let start = Instant::now();
+    client
+        .fs_file_download("/file", &mut data, None, None)
+        .unwrap();
+    let duration = start.elapsed();
+
+    assert_eq!(data, *device.file.lock().unwrap());
+    duration
+}
+
+#[test]
+fn pipelined_file_download() {
+    let file = test_data(20 * DOWNLOAD_CHUNK_SIZE + 17);
+    let (device, addr) = FakeDevice::spawn(file.clone());
+    let client = MCUmgrClient::new_from_udp(addr, Duration::from_secs(5)).unwrap();
+    client.set_window_size(4);
+
+    let mut data = vec![];
+    let mut last_progress = 0;
+    let mut progress = |current, total| {
+        assert!(current >= last_progress);
+        assert_eq!(total, file.len() as u64);
+        last_progress = current;
+        true
+    };
+    client
+        .fs_file_download("/file", &mut data, Some(&mut progress), None)
+        .unwrap();
+
+    assert_eq!(data, file);
+    assert_eq!(last_progress, file.len() as u64);
+    assert_eq!(device.max_in_flight.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn pipelined_file_upload() {
+    let (device, addr) = FakeDevice::spawn(vec![]);
+    let client = MCUmgrClient::new_from_udp(addr, Duration::from_secs(5)).unwrap();
+    client.set_window_size(3);
+
+    let file = test_data(5000);
+    client
+        .fs_file_upload("/file", file.as_slice(), file.len() as u64, None, None)
+        .unwrap();
+
+    assert_eq!(*device.file.lock().unwrap(), file);
+    assert_eq!(device.max_in_flight.load(Ordering::SeqCst), 3);
+
+    let stats = client.stats();
+    assert_eq!(stats.frames_received, stats.frames_sent);
+    assert_eq!(stats.skipped_frames, 0);
+    assert_eq!(stats.latencies.len(), 1);
+    assert_eq!(stats.latencies[0].group_id, 8);
+    assert_eq!(stats.latencies[0].histogram.count, stats.frames_sent);
+}
+
+#[test]
+fn intercept_pipelined_requests() {
+    /// Counts the chunks written to the device.
+    #[derive(Default, Clone)]
+    struct ChunkCounter(Arc<AtomicUsize>);
+
+    impl CommandInterceptor for ChunkCounter {
+        fn on_result(&self, request: &InterceptedRequest, result: Result<&Value, &ExecuteError>) {
+            assert!(request.write_operation);
+            assert!(result.is_ok());
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let (_device, addr) = FakeDevice::spawn(vec![]);
+    let client = MCUmgrClient::new_from_udp(addr, Duration::from_secs(5)).unwrap();
+    client.set_window_size(3);
+
+    let counter = ChunkCounter::default();
+    client.add_interceptor(counter.clone());
+
+    let file = test_data(5000);
+    client
+        .fs_file_upload("/file", file.as_slice(), file.len() as u64, None, None)
+        .unwrap();
+
+    assert_eq!(
+        counter.0.load(Ordering::SeqCst) as u64,
+        client.stats().frames_sent
+    );
+}
+
+#[test]
+fn intercept_failed_retransmission() {
+    /// Lets the first pipelined request through, then loses all responses
+    /// and fails all retransmissions.
+    struct FailingTransport {
+        inner: UdpTransport,
+        sends: usize,
+    }
+
+    impl Transport for FailingTransport {
+        fn send_raw_frame(
+            &mut self,
+            header: [u8; SMP_HEADER_SIZE],
+            data: &[u8],
+        ) -> Result<(), SendError> {
+            self.sends += 1;
+            if self.sends > 2 {
+                return Err(io::Error::from(io::ErrorKind::BrokenPipe).into());
+            }
+            self.inner.send_raw_frame(header, data)
+        }
+
+        fn recv_raw_frame<'a>(
+            &mut self,
+            buffer: &'a mut [u8; u16::MAX as usize],
+        ) -> Result<&'a [u8], ReceiveError> {
+            if self.sends > 1 {
+                return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+            }
+            self.inner.recv_raw_frame(buffer)
+        }
+
+        fn set_timeout(
+            &mut self,
+            timeout: Duration,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.inner.set_timeout(timeout)
+        }
+    }
+
+    /// Counts the failed download requests.
+    #[derive(Default, Clone)]
+    struct FailureCounter(Arc<AtomicUsize>);
+
+    impl CommandInterceptor for FailureCounter {
+        fn on_result(&self, request: &InterceptedRequest, result: Result<&Value, &ExecuteError>) {
+            if (request.group_id, request.command_id) == (8, 0) && result.is_err() {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    let (_device, addr) = FakeDevice::spawn(test_data(4 * DOWNLOAD_CHUNK_SIZE));
+    let client = MCUmgrClient::new_from_transport(FailingTransport {
+        inner: UdpTransport::new(addr, Duration::from_secs(5)).unwrap(),
+        sends: 0,
+    });
+    client.set_window_size(1);
+    client.set_retry_policy(RetryPolicy {
+        max_retries: 1,
+        backoff: Duration::ZERO,
+        ..Default::default()
+    });
+
+    let counter = FailureCounter::default();
+    client.add_interceptor(counter.clone());
+
+    let mut data = vec![];
+    client
+        .fs_file_download("/file", &mut data, None, None)
+        .unwrap_err();
+
+    assert_eq!(data.len(), DOWNLOAD_CHUNK_SIZE);
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn pipelined_image_upload() {
+    let (device, addr) = FakeDevice::spawn(vec![]);
+    let client = MCUmgrClient::new_from_udp(addr, Duration::from_secs(5)).unwrap();
+    client.use_auto_window_size().unwrap();
+
+    let image = test_data(5000);
+    client
+        .image_upload(&image, None, None, false, None, None)
+        .unwrap();
+
+    assert_eq!(*device.image.lock().unwrap(), image);
+    assert_eq!(
+        device.max_in_flight.load(Ordering::SeqCst),
+        BUF_COUNT as usize
+    );
+}
+
+#[tokio::test]
+async fn async_pipelined_transfers() {
+    let file = test_data(10 * DOWNLOAD_CHUNK_SIZE);
+    let (device, addr) = FakeDevice::spawn(file.clone());
+    let client = AsyncMCUmgrClient::new_from_udp(addr, Duration::from_secs(5))
+        .await
+        .unwrap();
+    client.use_auto_window_size().await.unwrap();
+
+    let mut data = vec![];
+    client
+        .fs_file_download("/file", &mut data, None, None)
+        .await
+        .unwrap();
+    assert_eq!(data, file);
+
+    let file = test_data(3000);
+    client
+        .fs_file_upload("/file", file.as_slice(), file.len() as u64, None, None)
+        .await
+        .unwrap();
+    assert_eq!(*device.file.lock().unwrap(), file);
+
+    client
+        .image_upload(&file, None, None, false, None, None)
+        .await
+        .unwrap();
+    assert_eq!(*device.image.lock().unwrap(), file);
+}
+
+#[test]
+fn pipelining_benchmark() {
+    let sequential = download_duration(1);
+    let pipelined = download_duration(BUF_COUNT as usize);
+
+    println!("Sequential download: {sequential:?}");
+    println!("Pipelined download:  {pipelined:?}");
+
+    assert!(
+        pipelined < sequential / 2,
+        "Pipelining brought no gain: {pipelined:?} vs {sequential:?}"
+    );
+}
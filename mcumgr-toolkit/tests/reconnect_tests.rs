@@ -0,0 +1,133 @@
+mod common;
+
+use std::{net::TcpListener, time::Duration};
+
+use mcumgr_toolkit::{MCUmgrClient, client::MCUmgrClientError};
+
+#[test]
+fn reconnect_after_device_reset() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream(stream, 1);
+
+        // Reset; the connection drops and the device comes back
+        std::thread::sleep(Duration::from_millis(200));
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream(stream, 2);
+    });
+
+    let client = MCUmgrClient::new_from_tcp(addr, Duration::from_secs(5)).unwrap();
+    assert_eq!(client.os_echo("Before reset").unwrap(), "Before reset");
+
+    let err = client.os_echo("During reset").unwrap_err();
+    assert!(
+        matches!(err, MCUmgrClientError::DeviceDisconnected(_)),
+        "{err:?}"
+    );
+
+    client.wait_for_reconnect(Duration::from_secs(5)).unwrap();
+    assert_eq!(client.os_echo("After reset").unwrap(), "After reset");
+
+    device.join().unwrap();
+}
+
+#[test]
+fn reconnect_keeps_learning_mtu() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream_with_mtu(stream, 1, 256);
+
+        std::thread::sleep(Duration::from_millis(200));
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream_with_mtu(stream, 3, 256)
+    });
+
+    let client = MCUmgrClient::new_from_tcp(addr, Duration::from_secs(5)).unwrap();
+    client.use_auto_serial_mtu().unwrap();
+
+    let err = client.os_echo("During reset").unwrap_err();
+    assert!(
+        matches!(err, MCUmgrClientError::DeviceDisconnected(_)),
+        "{err:?}"
+    );
+    client.wait_for_reconnect(Duration::from_secs(5)).unwrap();
+
+    // The first response teaches the reopened transport the MTU again
+    let request = "a".repeat(1000);
+    assert_eq!(client.os_echo(&request).unwrap(), request);
+    assert_eq!(client.os_echo(&request).unwrap(), request);
+
+    let longest_line = device.join().unwrap();
+    assert!(longest_line > 127, "Longest line: {longest_line}");
+}
+
+#[test]
+fn wait_for_reconnect_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream(stream, 1);
+    });
+
+    let client = MCUmgrClient::new_from_tcp(addr, Duration::from_secs(5)).unwrap();
+    assert_eq!(client.os_echo("Hello").unwrap(), "Hello");
+    device.join().unwrap();
+
+    let err = client
+        .wait_for_reconnect(Duration::from_millis(300))
+        .unwrap_err();
+    assert!(
+        matches!(err, MCUmgrClientError::DeviceDisconnected(_)),
+        "{err:?}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn reconnect_to_recreated_socket() {
+    use std::os::unix::net::UnixListener;
+
+    let dir = std::env::temp_dir().join(format!(
+        "mcumgr-toolkit-reconnect-test-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("device.sock");
+    let _ = std::fs::remove_file(&path);
+
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let device = std::thread::spawn({
+        let path = path.clone();
+        move || {
+            let (stream, _) = listener.accept().unwrap();
+            common::serve_echo_stream(stream, 1);
+
+            // Like a USB device that disappears and gets enumerated again
+            drop(listener);
+            std::fs::remove_file(&path).unwrap();
+            std::thread::sleep(Duration::from_millis(300));
+
+            let listener = UnixListener::bind(&path).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            common::serve_echo_stream(stream, 2);
+        }
+    });
+
+    let client = MCUmgrClient::new_from_unix(&path, Duration::from_secs(5)).unwrap();
+    assert_eq!(client.os_echo("Before reset").unwrap(), "Before reset");
+
+    client.wait_for_reconnect(Duration::from_secs(5)).unwrap();
+    assert_eq!(client.os_echo("After reset").unwrap(), "After reset");
+
+    device.join().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+}
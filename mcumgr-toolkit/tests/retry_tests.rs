@@ -0,0 +1,97 @@
+mod common;
+use common::EchoSerial;
+
+use std::time::Duration;
+
+use mcumgr_toolkit::{
+    MCUmgrClient,
+    client::MCUmgrClientError,
+    connection::{ExecuteError, RetryPolicy},
+    transport::{
+        fault_injection::{
+            FaultInjectingSerial, FaultInjectingTransport, FaultInjectionConfig, FrameFaults,
+            SerialFaultConfig,
+        },
+        serial::SerialTransport,
+    },
+};
+
+const RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_retries: 10,
+    backoff: Duration::ZERO,
+    backoff_factor: 2,
+    retry_on_timeout: true,
+    retry_on_corruption: true,
+};
+
+fn lossy_echo_client(drop: f64) -> MCUmgrClient {
+    MCUmgrClient::new_from_transport(FaultInjectingTransport::new(
+        SerialTransport::new(EchoSerial::default()),
+        FaultInjectionConfig {
+            seed: 42,
+            receive: FrameFaults {
+                drop,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    ))
+}
+
+#[test]
+fn retransmit_lost_responses() {
+    let client = lossy_echo_client(0.3);
+    client.set_retry_policy(RETRY_POLICY);
+
+    for _ in 0..20 {
+        assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+    }
+
+    assert!(client.retry_count() > 0);
+}
+
+#[test]
+fn retransmit_corrupted_responses() {
+    let client = MCUmgrClient::new_from_serial(FaultInjectingSerial::new(
+        EchoSerial::default(),
+        SerialFaultConfig {
+            seed: 42,
+            read_bit_flip: 0.005,
+            ..Default::default()
+        },
+    ));
+    client.set_retry_policy(RETRY_POLICY);
+
+    for _ in 0..20 {
+        assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+    }
+
+    assert!(client.retry_count() > 0);
+}
+
+#[test]
+fn give_up_after_max_retries() {
+    let client = lossy_echo_client(1.0);
+    client.set_retry_policy(RetryPolicy {
+        max_retries: 3,
+        ..RETRY_POLICY
+    });
+
+    let err = client.os_echo("Hello world!").unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MCUmgrClientError::ExecuteError(ExecuteError::ReceiveFailed(_))
+        ),
+        "{err:?}"
+    );
+    assert_eq!(client.retry_count(), 3);
+}
+
+#[test]
+fn no_retransmissions_by_default() {
+    let client = lossy_echo_client(1.0);
+
+    assert!(client.os_echo("Hello world!").is_err());
+    assert_eq!(client.retry_count(), 0);
+}
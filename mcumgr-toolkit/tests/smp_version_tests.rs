@@ -0,0 +1,195 @@
+mod common;
+use common::FakeUdpDevice;
+
+use std::{net::UdpSocket, time::Duration};
+
+use ciborium::Value;
+use mcumgr_toolkit::{
+    AsyncMCUmgrClient, MCUmgrClient,
+    client::MCUmgrClientError,
+    connection::ExecuteError,
+    smp_errors::{DeviceError, MCUmgrErr},
+    transport::SmpVersion,
+};
+
+/// How a fake device reacts to the version in the SMP header.
+#[derive(Clone, Copy)]
+enum Server {
+    /// Zephyr with SMP v2 and the original protocol enabled
+    Zephyr,
+    /// Zephyr without `CONFIG_MCUMGR_SMP_SUPPORT_ORIGINAL_PROTOCOL`
+    ZephyrV2Only,
+    /// SMP v1 server that rejects newer versions
+    V1Only,
+    /// SMP v1 server that ignores the version bits and always responds with v1 headers
+    Legacy,
+}
+
+/// Spawns a fake device that answers echo and file upload requests.
+fn spawn_device(server: Server) -> FakeUdpDevice {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    FakeUdpDevice::spawn_with(socket, move |request, responder| {
+        let version = request.header.version();
+        let rc = match (server, version) {
+            (Server::ZephyrV2Only, SmpVersion::V1) => Some(MCUmgrErr::MGMT_ERR_UNSUPPORTED_TOO_OLD),
+            (Server::V1Only, SmpVersion::V2) => Some(MCUmgrErr::MGMT_ERR_UNSUPPORTED_TOO_NEW),
+            _ => None,
+        };
+
+        let field = |name: &str| request.field(name).unwrap().clone();
+        let response = if let Some(rc) = rc {
+            Value::Map(vec![("rc".into(), (rc as i32).into())])
+        } else if request.header.group_id == 8 {
+            let off = field("off").as_integer().unwrap();
+            let len = field("data").as_bytes().unwrap().len();
+            Value::Map(vec![(
+                "off".into(),
+                (u64::try_from(off).unwrap() + len as u64).into(),
+            )])
+        } else {
+            Value::Map(vec![("r".into(), field("d"))])
+        };
+
+        responder.send_with_header(&response, |header| {
+            if matches!(server, Server::Legacy | Server::V1Only) {
+                header.ver = 0;
+            }
+        });
+    })
+}
+
+/// The SMP versions of all requests received by `device`.
+fn versions(device: &FakeUdpDevice) -> Vec<SmpVersion> {
+    device
+        .requests()
+        .iter()
+        .map(|request| request.header.version())
+        .collect()
+}
+
+fn connect(device: &FakeUdpDevice) -> MCUmgrClient {
+    MCUmgrClient::new_from_udp(device.addr(), Duration::from_secs(5)).unwrap()
+}
+
+fn assert_device_error(err: MCUmgrClientError, expected: MCUmgrErr) {
+    assert!(
+        matches!(
+            err,
+            MCUmgrClientError::ExecuteError(ExecuteError::ErrorResponse(DeviceError::V1 { rc, .. }))
+                if rc == expected as i32
+        ),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn zephyr_uses_v2() {
+    let device = spawn_device(Server::Zephyr);
+    let client = connect(&device);
+
+    client.check_connection().unwrap();
+    client.check_connection().unwrap();
+
+    assert_eq!(client.smp_version(), SmpVersion::V2);
+    assert_eq!(versions(&device), [SmpVersion::V2; 2]);
+}
+
+#[test]
+fn fall_back_if_device_rejects_v2() {
+    let device = spawn_device(Server::V1Only);
+    let client = connect(&device);
+
+    client.check_connection().unwrap();
+    client.check_connection().unwrap();
+
+    assert_eq!(client.smp_version(), SmpVersion::V1);
+    assert_eq!(
+        versions(&device),
+        [SmpVersion::V2, SmpVersion::V1, SmpVersion::V1]
+    );
+}
+
+#[test]
+fn fall_back_if_device_responds_with_v1() {
+    let device = spawn_device(Server::Legacy);
+    let client = connect(&device);
+
+    client.check_connection().unwrap();
+    client.check_connection().unwrap();
+
+    assert_eq!(client.smp_version(), SmpVersion::V1);
+    assert_eq!(versions(&device), [SmpVersion::V2, SmpVersion::V1]);
+}
+
+#[test]
+fn fall_back_during_pipelined_upload() {
+    let device = spawn_device(Server::V1Only);
+    let client = connect(&device);
+    client.set_window_size(3);
+
+    let file = vec![42u8; 2000];
+    client
+        .fs_file_upload("/file", file.as_slice(), file.len() as u64, None, None)
+        .unwrap();
+
+    assert_eq!(client.smp_version(), SmpVersion::V1);
+    let versions = versions(&device);
+    assert_eq!(versions[..3], [SmpVersion::V2; 3]);
+    assert!(
+        versions[3..]
+            .iter()
+            .all(|version| *version == SmpVersion::V1)
+    );
+}
+
+#[test]
+fn explicit_version() {
+    let device = spawn_device(Server::Zephyr);
+    let client = connect(&device);
+    client.set_smp_version(Some(SmpVersion::V1));
+
+    client.check_connection().unwrap();
+
+    assert_eq!(client.smp_version(), SmpVersion::V1);
+    assert_eq!(versions(&device), [SmpVersion::V1]);
+}
+
+#[test]
+fn explicit_version_disables_fallback() {
+    let device = spawn_device(Server::V1Only);
+    let client = connect(&device);
+    client.set_smp_version(Some(SmpVersion::V2));
+
+    let err = client.check_connection().unwrap_err();
+    assert_device_error(err, MCUmgrErr::MGMT_ERR_UNSUPPORTED_TOO_NEW);
+
+    assert_eq!(client.smp_version(), SmpVersion::V2);
+    assert_eq!(versions(&device), [SmpVersion::V2]);
+}
+
+#[test]
+fn explicit_v1_rejected_by_v2_only_device() {
+    let device = spawn_device(Server::ZephyrV2Only);
+    let client = connect(&device);
+    client.set_smp_version(Some(SmpVersion::V1));
+
+    let err = client.check_connection().unwrap_err();
+    assert_device_error(err, MCUmgrErr::MGMT_ERR_UNSUPPORTED_TOO_OLD);
+
+    client.set_smp_version(None);
+    client.check_connection().unwrap();
+    assert_eq!(client.smp_version(), SmpVersion::V2);
+}
+
+#[tokio::test]
+async fn async_fall_back_if_device_rejects_v2() {
+    let device = spawn_device(Server::V1Only);
+    let client = AsyncMCUmgrClient::new_from_udp(device.addr(), Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    client.check_connection().await.unwrap();
+
+    assert_eq!(client.smp_version().await, SmpVersion::V1);
+    assert_eq!(versions(&device), [SmpVersion::V2, SmpVersion::V1]);
+}
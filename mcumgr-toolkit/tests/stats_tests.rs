@@ -0,0 +1,123 @@
+mod common;
+use common::{EchoSerial, faulty_echo_client};
+
+use mcumgr_toolkit::{
+    MCUmgrClient,
+    connection::{LATENCY_BUCKETS, RetryPolicy},
+    transport::fault_injection::{FaultInjectingSerial, FrameFaults, SerialFaultConfig},
+};
+
+/// SMP header plus the CBOR map `{"d": "Hello"}` or `{"r": "Hello"}`
+const ECHO_FRAME_SIZE: u64 = 8 + 9;
+
+#[test]
+fn count_frames_and_bytes() {
+    let client = faulty_echo_client(FrameFaults::default(), 0);
+
+    for _ in 0..5 {
+        assert_eq!(client.os_echo("Hello").unwrap(), "Hello");
+    }
+
+    let stats = client.stats();
+    assert_eq!(stats.frames_sent, 5);
+    assert_eq!(stats.bytes_sent, 5 * ECHO_FRAME_SIZE);
+    assert_eq!(stats.frames_received, 5);
+    assert_eq!(stats.bytes_received, 5 * ECHO_FRAME_SIZE);
+    assert_eq!(stats.crc_errors, 0);
+    assert_eq!(stats.skipped_frames, 0);
+    assert_eq!(stats.timeouts, 0);
+    assert_eq!(stats.retries, 0);
+
+    assert_eq!(stats.latencies.len(), 1);
+    let latency = &stats.latencies[0];
+    assert_eq!((latency.group_id, latency.command_id), (0, 0));
+    assert_eq!(latency.histogram.count, 5);
+    assert_eq!(latency.histogram.buckets.iter().sum::<u64>(), 5);
+    assert!(latency.histogram.min <= latency.histogram.mean().unwrap());
+    assert!(latency.histogram.mean().unwrap() <= latency.histogram.max);
+}
+
+#[test]
+fn count_skipped_frames() {
+    let client = faulty_echo_client(
+        FrameFaults {
+            duplicate: 1.0,
+            ..Default::default()
+        },
+        0,
+    );
+
+    for _ in 0..10 {
+        assert_eq!(client.os_echo("Hello").unwrap(), "Hello");
+    }
+
+    let stats = client.stats();
+    assert_eq!(stats.frames_sent, 10);
+    assert_eq!(stats.skipped_frames, 9);
+    assert_eq!(stats.frames_received, 10 + stats.skipped_frames);
+    assert_eq!(stats.latencies[0].histogram.count, 10);
+}
+
+#[test]
+fn count_timeouts_and_retries() {
+    let client = faulty_echo_client(
+        FrameFaults {
+            drop: 1.0,
+            ..Default::default()
+        },
+        0,
+    );
+    client.set_retry_policy(RetryPolicy {
+        max_retries: 2,
+        backoff: std::time::Duration::ZERO,
+        ..Default::default()
+    });
+
+    assert!(client.os_echo("Hello").is_err());
+
+    let stats = client.stats();
+    assert_eq!(stats.frames_sent, 3);
+    assert_eq!(stats.frames_received, 0);
+    assert_eq!(stats.timeouts, 3);
+    assert_eq!(stats.retries, 2);
+    assert_eq!(client.retry_count(), 2);
+    assert!(stats.latencies.is_empty());
+}
+
+#[test]
+fn count_crc_errors() {
+    let client = MCUmgrClient::new_from_serial(FaultInjectingSerial::new(
+        EchoSerial::default(),
+        SerialFaultConfig {
+            seed: 42,
+            read_bit_flip: 0.005,
+            ..Default::default()
+        },
+    ));
+
+    let failures = (0..50)
+        .filter(|_| client.os_echo("Hello world!").is_err())
+        .count();
+
+    let stats = client.stats();
+    assert!(stats.crc_errors > 0);
+    assert!(stats.crc_errors <= failures as u64);
+    assert_eq!(stats.latencies[0].histogram.count, 50 - failures as u64);
+}
+
+#[test]
+fn latency_percentiles() {
+    let client = faulty_echo_client(FrameFaults::default(), 0);
+    assert!(client.stats().latencies.is_empty());
+
+    for _ in 0..3 {
+        client.os_echo("Hello").unwrap();
+    }
+
+    let histogram = &client.stats().latencies[0].histogram;
+    let p50 = histogram.percentile(50.0).unwrap();
+    let p100 = histogram.percentile(100.0).unwrap();
+    assert!(p50 <= p100);
+    assert!(histogram.max <= p100);
+    assert!(LATENCY_BUCKETS.contains(&p100) || p100 == histogram.max);
+}
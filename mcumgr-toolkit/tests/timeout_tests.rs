@@ -0,0 +1,111 @@
+mod common;
+use common::EchoSerial;
+
+use std::{
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use mcumgr_toolkit::{
+    MCUmgrClient, commands::LONG_OPERATION_TIMEOUT, transport::serial::ConfigurableTimeout,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Records the timeouts that get configured on the serial port.
+#[derive(Default)]
+struct TimeoutRecorder {
+    serial: EchoSerial,
+    timeouts: Arc<Mutex<Vec<Duration>>>,
+}
+
+impl Read for TimeoutRecorder {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.serial.read(buf)
+    }
+}
+
+impl Write for TimeoutRecorder {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.serial.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.serial.flush()
+    }
+}
+
+impl ConfigurableTimeout for TimeoutRecorder {
+    fn set_timeout(
+        &mut self,
+        duration: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.timeouts.lock().unwrap().push(duration);
+        Ok(())
+    }
+}
+
+fn recording_client() -> (MCUmgrClient, Arc<Mutex<Vec<Duration>>>) {
+    let serial = TimeoutRecorder::default();
+    let timeouts = serial.timeouts.clone();
+
+    let client = MCUmgrClient::new_from_serial(serial);
+    client.set_timeout(TIMEOUT).unwrap();
+    timeouts.lock().unwrap().clear();
+
+    (client, timeouts)
+}
+
+#[test]
+fn long_operations_extend_the_timeout() {
+    let (client, timeouts) = recording_client();
+
+    client.os_echo("Hello").unwrap();
+    assert!(timeouts.lock().unwrap().is_empty());
+
+    client.zephyr_erase_storage().unwrap();
+    assert_eq!(*timeouts.lock().unwrap(), [LONG_OPERATION_TIMEOUT, TIMEOUT]);
+}
+
+#[test]
+fn long_operations_keep_longer_timeouts() {
+    let (client, timeouts) = recording_client();
+
+    let long_timeout = LONG_OPERATION_TIMEOUT * 2;
+    client.set_timeout(long_timeout).unwrap();
+    timeouts.lock().unwrap().clear();
+
+    client.zephyr_erase_storage().unwrap();
+    assert!(timeouts.lock().unwrap().is_empty());
+}
+
+#[test]
+fn override_timeout() {
+    let (client, timeouts) = recording_client();
+
+    let short_timeout = Duration::from_millis(100);
+    let response = client.with_timeout(short_timeout, || {
+        client.zephyr_erase_storage().unwrap();
+        client.os_echo("Hello").unwrap()
+    });
+    assert_eq!(response, "Hello");
+    assert_eq!(
+        *timeouts.lock().unwrap(),
+        [short_timeout, TIMEOUT, short_timeout, TIMEOUT]
+    );
+
+    timeouts.lock().unwrap().clear();
+    client.os_echo("Hello").unwrap();
+    assert!(timeouts.lock().unwrap().is_empty());
+}
+
+#[test]
+fn unknown_timeout_is_not_changed() {
+    let serial = TimeoutRecorder::default();
+    let timeouts = serial.timeouts.clone();
+    let client = MCUmgrClient::new_from_serial(serial);
+
+    client.zephyr_erase_storage().unwrap();
+    assert!(timeouts.lock().unwrap().is_empty());
+}
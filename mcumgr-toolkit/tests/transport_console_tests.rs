@@ -0,0 +1,163 @@
+mod common;
+use common::EchoSerial;
+
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+};
+
+use mcumgr_toolkit::{MCUmgrClient, transport::serial::ConfigurableTimeout};
+
+/// An [`EchoSerial`] that shares its UART with a chatty console.
+///
+/// Emits one console line after every line of the SMP response,
+/// and the remaining lines once the response is done.
+#[derive(Default)]
+struct ChattySerial {
+    echo: EchoSerial,
+    console: VecDeque<Vec<u8>>,
+    output: VecDeque<u8>,
+}
+
+impl ChattySerial {
+    fn new(console: &[&[u8]]) -> Self {
+        Self {
+            console: console.iter().map(|line| line.to_vec()).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Read for ChattySerial {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.output.is_empty() {
+            let mut echo_data = [0u8; 256];
+            let num_read = match self.echo.read(&mut echo_data) {
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => 0,
+                result => result?,
+            };
+
+            for &byte in &echo_data[..num_read] {
+                self.output.push_back(byte);
+                if byte == b'\n' {
+                    if let Some(line) = self.console.pop_front() {
+                        self.output.extend(line);
+                    }
+                }
+            }
+
+            if num_read == 0 {
+                if let Some(line) = self.console.pop_front() {
+                    self.output.extend(line);
+                }
+            }
+        }
+
+        if self.output.is_empty() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+
+        self.output.read(buf)
+    }
+}
+
+impl Write for ChattySerial {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.echo.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.echo.flush()
+    }
+}
+
+impl ConfigurableTimeout for ChattySerial {
+    fn set_timeout(
+        &mut self,
+        _: std::time::Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+fn collect_console(client: &MCUmgrClient) -> Arc<Mutex<Vec<String>>> {
+    let lines = Arc::new(Mutex::new(vec![]));
+    let lines_handle = lines.clone();
+    client
+        .set_console_handler(Some(Box::new(move |line| {
+            lines_handle
+                .lock()
+                .unwrap()
+                .push(String::from_utf8_lossy(line).into_owned())
+        })))
+        .unwrap();
+    lines
+}
+
+#[test]
+fn console_output_between_chunks() {
+    let client = MCUmgrClient::new_from_serial(ChattySerial::new(&[
+        b"*** Booting Zephyr OS ***\r\n",
+        b"[00:00:01.000] <inf> app: ",
+        b"started\r\n",
+        b"uart:~$ \n",
+    ]));
+    let lines = collect_console(&client);
+
+    let request = "a".repeat(100);
+    assert_eq!(client.os_echo(&request).unwrap(), request);
+
+    assert_eq!(
+        *lines.lock().unwrap(),
+        [
+            "*** Booting Zephyr OS ***",
+            "[00:00:01.000] <inf> app: started",
+            "uart:~$ ",
+        ]
+    );
+}
+
+#[test]
+fn console_output_without_handler() {
+    let client =
+        MCUmgrClient::new_from_serial(ChattySerial::new(&[b"hello\n", b"\x06 \x04 world\n"]));
+
+    let request = "a".repeat(100);
+    assert_eq!(client.os_echo(&request).unwrap(), request);
+}
+
+#[test]
+fn poll_console() {
+    let client = MCUmgrClient::new_from_serial(ChattySerial::new(&[
+        b"first\r\n",
+        b"\x06\x09AAAA\n",
+        b"sec",
+        b"ond\n",
+        b"\x04",
+        b"\x14BBBB\n\x04 third\n",
+    ]));
+    let lines = collect_console(&client);
+
+    for _ in 0..10 {
+        client.poll_console().unwrap();
+    }
+
+    assert_eq!(*lines.lock().unwrap(), ["first", "second", "\x04 third"]);
+
+    // Commands still work afterwards
+    assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+}
+
+#[test]
+fn console_not_supported_on_udp() {
+    let device = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let client = MCUmgrClient::new_from_udp(
+        device.local_addr().unwrap(),
+        std::time::Duration::from_millis(100),
+    )
+    .unwrap();
+
+    assert!(client.set_console_handler(None).is_err());
+    assert!(client.poll_console().is_err());
+}
@@ -0,0 +1,114 @@
+mod common;
+use common::{EchoSerial, faulty_echo_client};
+
+use mcumgr_toolkit::{
+    MCUmgrClient,
+    client::MCUmgrClientError,
+    connection::ExecuteError,
+    transport::fault_injection::{FaultInjectingSerial, FrameFaults, SerialFaultConfig},
+};
+
+#[test]
+fn no_faults() {
+    let client = faulty_echo_client(FrameFaults::default(), 0);
+
+    for _ in 0..10 {
+        assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+    }
+}
+
+#[test]
+fn duplicated_responses_are_ignored() {
+    let client = faulty_echo_client(
+        FrameFaults {
+            duplicate: 1.0,
+            ..Default::default()
+        },
+        0,
+    );
+
+    for _ in 0..10 {
+        assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+    }
+}
+
+#[test]
+fn dropped_response() {
+    let client = faulty_echo_client(
+        FrameFaults {
+            drop: 1.0,
+            ..Default::default()
+        },
+        0,
+    );
+
+    let err = client.os_echo("Hello world!").unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MCUmgrClientError::ExecuteError(ExecuteError::ReceiveFailed(_))
+        ),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn late_response_is_ignored() {
+    let client = faulty_echo_client(
+        FrameFaults {
+            reorder: 1.0,
+            ..Default::default()
+        },
+        0,
+    );
+
+    // Every response is held back until after the next receive attempt
+    assert!(client.os_echo("first").is_err());
+
+    // The late response to "first" must not be mistaken for this one
+    assert!(client.os_echo("second").is_err());
+}
+
+#[test]
+fn faults_are_reproducible() {
+    let run = |seed| {
+        let client = faulty_echo_client(
+            FrameFaults {
+                drop: 0.3,
+                corrupt: 0.3,
+                ..Default::default()
+            },
+            seed,
+        );
+
+        (0..20)
+            .map(|_| client.os_echo("Hello world!").is_ok())
+            .collect::<Vec<_>>()
+    };
+
+    let results = run(1234);
+    assert_eq!(results, run(1234));
+    assert!(results.contains(&true));
+    assert!(results.contains(&false));
+}
+
+#[test]
+fn serial_bit_flips_are_detected() {
+    let client = MCUmgrClient::new_from_serial(FaultInjectingSerial::new(
+        EchoSerial::default(),
+        SerialFaultConfig {
+            seed: 42,
+            read_bit_flip: 0.005,
+            ..Default::default()
+        },
+    ));
+
+    let results = (0..20)
+        .map(|_| client.os_echo("Hello world!"))
+        .collect::<Vec<_>>();
+
+    assert!(results.iter().any(Result::is_err));
+    for result in results.into_iter().flatten() {
+        assert_eq!(result, "Hello world!");
+    }
+}
@@ -0,0 +1,206 @@
+mod common;
+use common::EchoSerial;
+
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use mcumgr_toolkit::{
+    MCUmgrClient,
+    client::MCUmgrClientError,
+    connection::ExecuteError,
+    transport::{
+        ReceiveError, SendError,
+        recording::{Recording, RecordingTransport, ReplayTransport},
+        serial::{ConfigurableTimeout, SerialTransport},
+    },
+};
+
+/// A recording destination that can still be read after the client is gone.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A device that never responds.
+struct SilentSerial;
+
+impl Read for SilentSerial {
+    fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::TimedOut.into())
+    }
+}
+
+impl Write for SilentSerial {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ConfigurableTimeout for SilentSerial {
+    fn set_timeout(
+        &mut self,
+        _: std::time::Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+fn record_echo_session(messages: &[&str]) -> Vec<u8> {
+    let recording = SharedBuffer::default();
+
+    let client = MCUmgrClient::new_from_transport(RecordingTransport::new(
+        SerialTransport::new(EchoSerial::default()),
+        recording.clone(),
+    ));
+    for message in messages {
+        assert_eq!(client.os_echo(message).unwrap(), *message);
+    }
+
+    recording.0.lock().unwrap().clone()
+}
+
+#[test]
+fn record_and_replay() {
+    let recording = record_echo_session(&["Hello world!", &"a".repeat(1000)]);
+
+    let lines = String::from_utf8(recording.clone()).unwrap();
+    let events = lines
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["event"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(events, ["send", "receive", "send", "receive"]);
+
+    let client = MCUmgrClient::new_from_transport(ReplayTransport::new(&recording[..]).unwrap());
+    assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+    assert_eq!(client.os_echo("a".repeat(1000)).unwrap(), "a".repeat(1000));
+
+    let err = client.os_echo("Hello world!").unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MCUmgrClientError::ExecuteError(ExecuteError::SendFailed(SendError::TransportError(ref e)))
+                if e.kind() == std::io::ErrorKind::UnexpectedEof
+        ),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn replay_mismatching_request() {
+    let recording = record_echo_session(&["Hello world!"]);
+
+    let client = MCUmgrClient::new_from_transport(ReplayTransport::new(&recording[..]).unwrap());
+
+    let err = client.os_get_datetime().unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MCUmgrClientError::ExecuteError(ExecuteError::SendFailed(SendError::TransportError(ref e)))
+                if e.kind() == std::io::ErrorKind::InvalidData
+        ),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn replay_different_echo_message() {
+    let recording = record_echo_session(&["Hello world!", "Hello world!"]);
+
+    let client = MCUmgrClient::new_from_transport(ReplayTransport::new(&recording[..]).unwrap());
+    assert_eq!(client.os_echo("Hello moon!").unwrap(), "Hello moon!");
+    client.check_connection().unwrap();
+}
+
+#[test]
+fn replay_timeout() {
+    let recording = SharedBuffer::default();
+
+    let client = MCUmgrClient::new_from_transport(RecordingTransport::new(
+        SerialTransport::new(SilentSerial),
+        recording.clone(),
+    ));
+    assert!(client.os_echo("Hello world!").is_err());
+
+    let recording = recording.0.lock().unwrap().clone();
+    let replay = ReplayTransport::new(&recording[..]).unwrap();
+    assert!(!replay.is_finished());
+
+    let client = MCUmgrClient::new_from_transport(replay);
+    let err = client.os_echo("Hello world!").unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MCUmgrClientError::ExecuteError(ExecuteError::ReceiveFailed(ReceiveError::TransportError(ref e)))
+                if e.kind() == std::io::ErrorKind::TimedOut
+        ),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn record_across_reconnect() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream(stream, 1);
+
+        std::thread::sleep(Duration::from_millis(200));
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream(stream, 2);
+    });
+
+    let buffer = SharedBuffer::default();
+    let client = MCUmgrClient::new_from_tcp(addr, Duration::from_secs(5)).unwrap();
+    let recording = Recording::new(buffer.clone());
+    client.wrap_transport(Box::new(move |transport| {
+        Box::new(recording.record(transport))
+    }));
+
+    assert_eq!(client.os_echo("Before reset").unwrap(), "Before reset");
+    assert!(matches!(
+        client.os_echo("During reset").unwrap_err(),
+        MCUmgrClientError::DeviceDisconnected(_)
+    ));
+    client.wait_for_reconnect(Duration::from_secs(5)).unwrap();
+    assert_eq!(client.os_echo("After reset").unwrap(), "After reset");
+    device.join().unwrap();
+
+    let lines = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let events = lines
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["event"].clone())
+        .collect::<Vec<_>>();
+    // The frames of the reopened transport follow in the same recording
+    assert_eq!(events.iter().filter(|event| *event == "send").count(), 4);
+    assert_eq!(events[..2], ["send", "receive"]);
+    assert_eq!(
+        events[events.len() - 4..],
+        ["send", "receive", "send", "receive"]
+    );
+
+    // The random echo of the connection check gets replayed as well
+    let replay = ReplayTransport::new(&buffer.0.lock().unwrap()[..]).unwrap();
+    let client = MCUmgrClient::new_from_transport(replay);
+    assert_eq!(client.os_echo("Before reset").unwrap(), "Before reset");
+    assert!(client.os_echo("During reset").is_err());
+    client.wait_for_reconnect(Duration::from_secs(5)).unwrap();
+    assert_eq!(client.os_echo("After reset").unwrap(), "After reset");
+}
@@ -0,0 +1,259 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use mcumgr_toolkit::transport::{
+    Transport,
+    rfc2217::{Rfc2217Port, Rfc2217Settings},
+    serial::{ConfigurableTimeout, SerialTransport},
+};
+use serialport::{DataBits, Parity};
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const COM_PORT_OPTION: u8 = 44;
+const NOTIFY_MODEMSTATE: u8 = 107;
+
+/// A minimal RFC 2217 server that confirms all settings and loops back all data.
+///
+/// If `notify_interval` is given, the server also keeps sending modem state notifications.
+///
+/// Returns the received COM-PORT-OPTION commands once the client disconnects.
+fn spawn_fake_server(
+    listener: TcpListener,
+    support_com_port: bool,
+    notify_interval: Option<Duration>,
+) -> JoinHandle<Vec<Vec<u8>>> {
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut writer = stream.try_clone().unwrap();
+
+        if let Some(interval) = notify_interval {
+            let mut notifier = stream.try_clone().unwrap();
+            std::thread::spawn(move || {
+                for _ in 0..50 {
+                    std::thread::sleep(interval);
+                    let notification = [IAC, SB, COM_PORT_OPTION, NOTIFY_MODEMSTATE, 0, IAC, SE];
+                    if notifier.write_all(&notification).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        let mut commands = vec![];
+        let mut buffer = [0u8; 1024];
+        let mut pending = vec![];
+
+        loop {
+            let num_read = stream.read(&mut buffer).unwrap();
+            if num_read == 0 {
+                break commands;
+            }
+            pending.extend_from_slice(&buffer[..num_read]);
+
+            let mut data = vec![];
+            let mut pos = 0;
+            while pos < pending.len() {
+                match pending[pos..] {
+                    [IAC, IAC, ..] => {
+                        data.extend_from_slice(&[IAC, IAC]);
+                        pos += 2;
+                    }
+                    [IAC, WILL, option, ..] => {
+                        let answer = if option == COM_PORT_OPTION && !support_com_port {
+                            DONT
+                        } else {
+                            DO
+                        };
+                        writer.write_all(&[IAC, answer, option]).unwrap();
+                        pos += 3;
+                    }
+                    [IAC, DO, option, ..] => {
+                        writer.write_all(&[IAC, WILL, option]).unwrap();
+                        pos += 3;
+                    }
+                    [IAC, WONT | DONT, _, ..] => pos += 3,
+                    [IAC, SB, ..] => {
+                        let Some(end) = pending[pos..].windows(2).position(|w| w == [IAC, SE])
+                        else {
+                            break;
+                        };
+                        let subnegotiation = pending[pos + 2..pos + end].to_vec();
+                        assert_eq!(subnegotiation[0], COM_PORT_OPTION);
+
+                        let mut answer = vec![IAC, SB, COM_PORT_OPTION, subnegotiation[1] + 100];
+                        answer.extend_from_slice(&subnegotiation[2..]);
+                        answer.extend_from_slice(&[IAC, SE]);
+                        writer.write_all(&answer).unwrap();
+
+                        commands.push(subnegotiation[1..].to_vec());
+                        pos += end + 2;
+                    }
+                    [IAC] | [IAC, WILL | WONT | DO | DONT] => break,
+                    [byte, ..] => {
+                        data.push(byte);
+                        pos += 1;
+                    }
+                    [] => unreachable!(),
+                }
+            }
+            pending.drain(..pos);
+
+            writer.write_all(&data).unwrap();
+        }
+    })
+}
+
+fn connect(support_com_port: bool) -> (std::io::Result<Rfc2217Port>, JoinHandle<Vec<Vec<u8>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = spawn_fake_server(listener, support_com_port, None);
+
+    let port = Rfc2217Port::open(addr, Rfc2217Settings::new(115200), Duration::from_secs(5));
+
+    (port, server)
+}
+
+#[test]
+fn negotiates_settings() {
+    let (port, server) = connect(true);
+    let mut port = port.unwrap();
+
+    port.set_baud_rate(1000000).unwrap();
+    port.write_data_terminal_ready(false).unwrap();
+    port.write_request_to_send(true).unwrap();
+    port.set_parity(serialport::Parity::Even).unwrap();
+    drop(port);
+
+    assert_eq!(
+        server.join().unwrap(),
+        vec![
+            vec![1, 0x00, 0x01, 0xc2, 0x00],
+            vec![2, 8],
+            vec![3, 1],
+            vec![4, 1],
+            vec![5, 1],
+            vec![1, 0x00, 0x0f, 0x42, 0x40],
+            vec![5, 9],
+            vec![5, 11],
+            vec![3, 3],
+        ]
+    );
+}
+
+#[test]
+fn negotiates_initial_settings() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = spawn_fake_server(listener, true, None);
+
+    let settings = Rfc2217Settings {
+        data_bits: DataBits::Seven,
+        parity: Parity::Even,
+        dtr: Some(false),
+        rts: Some(true),
+        ..Rfc2217Settings::new(9600)
+    };
+    let port = Rfc2217Port::open(addr, settings, Duration::from_secs(5)).unwrap();
+    drop(port);
+
+    assert_eq!(
+        server.join().unwrap(),
+        vec![
+            vec![1, 0x00, 0x00, 0x25, 0x80],
+            vec![2, 7],
+            vec![3, 3],
+            vec![4, 1],
+            vec![5, 1],
+            vec![5, 9],
+            vec![5, 11],
+        ]
+    );
+}
+
+#[test]
+fn notifications_do_not_extend_read_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = spawn_fake_server(listener, true, Some(Duration::from_millis(50)));
+
+    let mut port =
+        Rfc2217Port::open(addr, Rfc2217Settings::new(115200), Duration::from_secs(5)).unwrap();
+    port.set_timeout(Duration::from_millis(300)).unwrap();
+
+    let start = Instant::now();
+    let err = port.read(&mut [0u8; 16]).unwrap_err();
+    assert!(
+        matches!(
+            err.kind(),
+            std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+        ),
+        "{err:?}"
+    );
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    drop(port);
+    server.join().unwrap();
+}
+
+#[test]
+fn server_without_rfc2217_support() {
+    let (port, server) = connect(false);
+
+    let err = port.err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+
+    assert_eq!(server.join().unwrap(), Vec::<Vec<u8>>::new());
+}
+
+#[test]
+fn escapes_data() {
+    let (port, server) = connect(true);
+    let mut port = port.unwrap();
+
+    let data = (0..=255).chain([IAC, IAC, 0, IAC]).collect::<Vec<u8>>();
+    port.write_all(&data).unwrap();
+
+    let mut received = vec![0u8; data.len()];
+    port.read_exact(&mut received).unwrap();
+    assert_eq!(received, data);
+
+    drop(port);
+    server.join().unwrap();
+}
+
+#[test]
+fn serial_transport_over_rfc2217() {
+    let (port, server) = connect(true);
+    let mut transport = SerialTransport::new(port.unwrap());
+
+    let header = [1, 2, 3, 4, 5, 6, 7, 8];
+    let data = vec![IAC; 1000];
+    transport.send_raw_frame(header, &data).unwrap();
+
+    let mut buffer = [0u8; u16::MAX as usize];
+    let received = transport.recv_raw_frame(&mut buffer).unwrap();
+    assert_eq!(&received[..8], &header);
+    assert_eq!(&received[8..], &data);
+
+    drop(transport);
+    server.join().unwrap();
+}
+
+#[test]
+fn connection_refused() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    assert!(Rfc2217Port::open(addr, Rfc2217Settings::new(115200), Duration::from_secs(5)).is_err());
+}
@@ -1,7 +1,22 @@
 mod common;
-use common::LoopbackSerial;
+use common::{EchoSerial, LoopbackSerial};
 
-use mcumgr_toolkit::transport::{Transport, serial::SerialTransport};
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+};
+
+use mcumgr_toolkit::{
+    MCUmgrClient,
+    transport::{
+        SmpVersion, Transport,
+        serial::{
+            ConfigurableTimeout, SERIAL_TRANSPORT_MIN_MTU, SerialMtuTooSmall, SerialTransport,
+            SerialTransportConfig,
+        },
+    },
+};
 use proptest::prelude::*;
 
 fn create_loopback_transport() -> Box<dyn Transport> {
@@ -59,3 +74,174 @@ fn test_chunking_upper_limit() {
         data.len()
     );
 }
+
+#[test]
+fn test_echo_at_several_mtus() {
+    let request: String = (0..3000)
+        .map(|i| char::from(b'a' + (i % 26) as u8))
+        .collect();
+
+    for mtu in [SERIAL_TRANSPORT_MIN_MTU, 8, 20, 127, 128, 512, 4096, 8192] {
+        let client = MCUmgrClient::new_from_serial_with_config(
+            EchoSerial::with_mtu(mtu),
+            SerialTransportConfig {
+                mtu,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let response = client.os_echo(&request).unwrap();
+        assert_eq!(request, response, "Echo failed at MTU {mtu}");
+    }
+}
+
+#[test]
+fn test_learn_mtu_from_device() {
+    let mut transport = SerialTransport::with_config(
+        EchoSerial::with_mtu(512),
+        SerialTransportConfig {
+            learn_mtu: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(transport.mtu(), 127);
+
+    let mut request = vec![];
+    ciborium::into_writer(
+        &ciborium::Value::Map(vec![("d".into(), "a".repeat(1000).into())]),
+        &mut request,
+    )
+    .unwrap();
+
+    let mut buffer = [0u8; u16::MAX as usize];
+    transport
+        .send_frame(SmpVersion::V2, false, 42, 0, 0, &request)
+        .unwrap();
+    transport
+        .receive_frame(&mut buffer, false, 42, 0, 0)
+        .unwrap();
+
+    // Longest multiple of a base64 block that fits, plus frame start and newline
+    assert_eq!(transport.mtu(), 511);
+
+    transport
+        .send_frame(SmpVersion::V2, false, 43, 0, 0, &request)
+        .unwrap();
+    transport
+        .receive_frame(&mut buffer, false, 43, 0, 0)
+        .unwrap();
+    assert_eq!(transport.mtu(), 511);
+}
+
+#[test]
+fn test_use_auto_serial_mtu() {
+    let client = MCUmgrClient::new_from_serial(EchoSerial::with_mtu(256));
+
+    // Sending with the learned MTU must not exceed what the device accepts
+    client.use_auto_serial_mtu().unwrap();
+    let request = "a".repeat(3000);
+    assert_eq!(client.os_echo(&request).unwrap(), request);
+}
+
+#[test]
+fn test_set_mtu() {
+    let mut transport = SerialTransport::new(EchoSerial::with_mtu(64));
+
+    assert!(
+        transport
+            .set_mtu(Some(SERIAL_TRANSPORT_MIN_MTU - 1))
+            .is_err()
+    );
+    assert_eq!(transport.mtu(), 127);
+
+    transport.set_mtu(Some(64)).unwrap();
+    assert_eq!(transport.mtu(), 64);
+}
+
+#[test]
+fn test_config_mtu_too_small() {
+    let config = SerialTransportConfig {
+        mtu: SERIAL_TRANSPORT_MIN_MTU - 1,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        SerialTransport::with_config(EchoSerial::with_mtu(64), config.clone()).err(),
+        Some(SerialMtuTooSmall(SERIAL_TRANSPORT_MIN_MTU - 1))
+    );
+    assert!(MCUmgrClient::new_from_serial_with_config(EchoSerial::with_mtu(64), config).is_err());
+}
+
+/// A loopback whose data can be withheld, and that times out while it is empty.
+#[derive(Clone, Default)]
+struct PausingLoopback(Arc<Mutex<VecDeque<u8>>>);
+
+impl PausingLoopback {
+    /// Withholds everything but the first half of the data.
+    fn pause(&self) -> Vec<u8> {
+        let mut data = self.0.lock().unwrap();
+        let half = data.len() / 2;
+        data.split_off(half).into()
+    }
+
+    fn resume(&self, rest: Vec<u8>) {
+        self.0.lock().unwrap().extend(rest);
+    }
+}
+
+impl Read for PausingLoopback {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut data = self.0.lock().unwrap();
+        if data.is_empty() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        data.read(buf)
+    }
+}
+
+impl Write for PausingLoopback {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ConfigurableTimeout for PausingLoopback {
+    fn set_timeout(
+        &mut self,
+        _: std::time::Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_resume_frame_after_timeout() {
+    let serial = PausingLoopback::default();
+    let mut transport = SerialTransport::new(serial.clone());
+    let mut buffer = [0u8; u16::MAX as usize];
+
+    let header = [1, 2, 3, 4, 5, 6, 7, 8];
+    let data = vec![0x42; 1000];
+    let frame = [header.as_slice(), &data].concat();
+
+    // The received part of the frame survives the timeout
+    transport.send_raw_frame(header, &data).unwrap();
+    let rest = serial.pause();
+    let err = transport.recv_raw_frame(&mut buffer).unwrap_err();
+    assert!(err.is_timeout(), "{err:?}");
+    serial.resume(rest);
+    assert_eq!(transport.recv_raw_frame(&mut buffer).unwrap(), frame);
+
+    // A new frame replaces a frame whose rest got lost
+    transport.send_raw_frame([8; 8], &[0x17; 500]).unwrap();
+    serial.pause();
+    assert!(transport.recv_raw_frame(&mut buffer).is_err());
+    transport.send_raw_frame(header, &data).unwrap();
+    assert_eq!(transport.recv_raw_frame(&mut buffer).unwrap(), frame);
+}
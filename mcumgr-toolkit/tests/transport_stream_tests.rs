@@ -0,0 +1,73 @@
+mod common;
+
+use std::{net::TcpListener, time::Duration};
+
+use mcumgr_toolkit::MCUmgrClient;
+
+#[test]
+fn echo_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream(stream, 2);
+    });
+
+    let client = MCUmgrClient::new_from_tcp(addr, Duration::from_secs(5)).unwrap();
+
+    assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+
+    let long_message = "a".repeat(10000);
+    assert_eq!(client.os_echo(&long_message).unwrap(), long_message);
+
+    device.join().unwrap();
+}
+
+#[test]
+fn tcp_connection_refused() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    assert!(MCUmgrClient::new_from_tcp(addr, Duration::from_secs(5)).is_err());
+}
+
+#[test]
+fn tcp_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = MCUmgrClient::new_from_tcp(addr, Duration::from_millis(100)).unwrap();
+    assert!(client.os_echo("Hello world!").is_err());
+
+    drop(listener);
+}
+
+#[cfg(unix)]
+#[test]
+fn echo_unix() {
+    use std::os::unix::net::UnixListener;
+
+    let dir = std::env::temp_dir().join(format!("mcumgr-toolkit-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("echo_unix.sock");
+    let _ = std::fs::remove_file(&path);
+
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let device = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        common::serve_echo_stream(stream, 2);
+    });
+
+    let client = MCUmgrClient::new_from_unix(&path, Duration::from_secs(5)).unwrap();
+
+    assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+
+    let long_message = "a".repeat(10000);
+    assert_eq!(client.os_echo(&long_message).unwrap(), long_message);
+
+    device.join().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+}
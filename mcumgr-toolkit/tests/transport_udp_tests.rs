@@ -0,0 +1,117 @@
+mod common;
+use common::FakeUdpDevice;
+
+use std::{net::UdpSocket, time::Duration};
+
+use ciborium::Value;
+use mcumgr_toolkit::{
+    MCUmgrClient,
+    client::MCUmgrClientError,
+    connection::ExecuteError,
+    transport::{ReceiveError, Transport, udp::UdpTransport},
+};
+
+/// Spawns a fake device on `socket` that answers echo requests.
+///
+/// If `send_stale_response` is set, every response is preceded by
+/// a copy with a wrong sequence number.
+fn spawn_echo_device(socket: UdpSocket, send_stale_response: bool) -> FakeUdpDevice {
+    FakeUdpDevice::spawn_with(socket, move |request, responder| {
+        let message = request.field("d").unwrap().clone();
+        let response = Value::Map(vec![("r".into(), message)]);
+
+        if send_stale_response {
+            responder.send_with_header(&response, |header| {
+                header.sequence_num = header.sequence_num.wrapping_sub(1);
+            });
+        }
+
+        responder.send(&response);
+    })
+}
+
+#[test]
+fn echo() {
+    let device = spawn_echo_device(UdpSocket::bind("127.0.0.1:0").unwrap(), false);
+
+    let client = MCUmgrClient::new_from_udp(device.addr(), Duration::from_secs(5)).unwrap();
+
+    assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+
+    let long_message = "a".repeat(10000);
+    assert_eq!(client.os_echo(&long_message).unwrap(), long_message);
+
+    assert_eq!(device.requests().len(), 2);
+}
+
+#[test]
+fn echo_ipv6() {
+    let Ok(socket) = UdpSocket::bind("[::1]:0") else {
+        // IPv6 is not available on this host
+        return;
+    };
+    let device = spawn_echo_device(socket, false);
+
+    let client = MCUmgrClient::new_from_udp(device.addr(), Duration::from_secs(5)).unwrap();
+    assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+}
+
+#[test]
+fn mismatched_sequence_numbers_get_skipped() {
+    let device = spawn_echo_device(UdpSocket::bind("127.0.0.1:0").unwrap(), true);
+
+    let client = MCUmgrClient::new_from_udp(device.addr(), Duration::from_secs(5)).unwrap();
+
+    for _ in 0..3 {
+        assert_eq!(client.os_echo("Hello world!").unwrap(), "Hello world!");
+    }
+
+    assert_eq!(device.requests().len(), 3);
+}
+
+#[test]
+fn timeout() {
+    let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = device.local_addr().unwrap();
+
+    let client = MCUmgrClient::new_from_udp(addr, Duration::from_secs(5)).unwrap();
+    client.set_timeout(Duration::from_millis(100)).unwrap();
+
+    let err = client.os_echo("Hello world!").unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MCUmgrClientError::ExecuteError(ExecuteError::ReceiveFailed(
+                ReceiveError::TransportError(_)
+            ))
+        ),
+        "Unexpected error: {err:?}"
+    );
+
+    drop(device);
+}
+
+#[test]
+fn one_frame_per_datagram() {
+    let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = device.local_addr().unwrap();
+
+    let mut transport = UdpTransport::new(addr, Duration::from_secs(5)).unwrap();
+
+    let header = [1, 2, 3, 4, 5, 6, 7, 8];
+    let data = vec![42u8; 5000];
+    transport.send_raw_frame(header, &data).unwrap();
+
+    let mut buffer = [0u8; u16::MAX as usize];
+    let (len, peer) = device.recv_from(&mut buffer).unwrap();
+    assert_eq!(len, header.len() + data.len());
+    assert_eq!(&buffer[..8], &header);
+    assert_eq!(&buffer[8..len], &data);
+
+    device.send_to(&buffer[..len], peer).unwrap();
+
+    let mut recv_buffer = [0u8; u16::MAX as usize];
+    let received = transport.recv_raw_frame(&mut recv_buffer).unwrap();
+    assert_eq!(&received[..8], &header);
+    assert_eq!(&received[8..], &data);
+}